@@ -1,9 +1,23 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
 use std::process::Command;
+use std::sync::Mutex;
+
+mod error;
+use error::AppError;
 
 // ─── Types ───────────────────────────────────────────────────────────────────
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub version: Option<String>,
+    pub install_hint: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioFileInfo {
     pub path: String,
@@ -29,6 +43,8 @@ pub struct ConvertOptions {
     pub bitrate: Option<String>,
     pub sample_rate: Option<u32>,
     pub channels: Option<u32>,
+    pub bit_depth: Option<u32>,
+    pub sample_fmt: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +55,28 @@ pub struct EditOptions {
     pub start_time: Option<f64>,
     pub end_time: Option<f64>,
     pub fade_duration: Option<f64>,
+    /// From analyze_loudness; lets "normalize" skip its own measuring pass
+    #[serde(default)]
+    pub measured: Option<LoudnessReport>,
+    /// "trim_silence": level below which audio counts as silence (default -50 dB)
+    #[serde(default)]
+    pub silence_threshold_db: Option<f64>,
+    /// "trim_silence": shortest stretch that gets removed, in seconds (default 0.5)
+    #[serde(default)]
+    pub silence_min_duration: Option<f64>,
+    /// "tempo"/"pitch": 1.5 plays 50% faster or a fifth higher
+    #[serde(default)]
+    pub factor: Option<f64>,
+}
+
+/// Integrated loudness (LUFS), loudness range (LU), true peak (dBTP) and
+/// gating threshold (LUFS) as measured by loudnorm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessReport {
+    pub input_i: f64,
+    pub input_lra: f64,
+    pub input_tp: f64,
+    pub input_thresh: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +87,11 @@ pub struct MetadataUpdate {
     pub album: Option<String>,
     pub year: Option<String>,
     pub genre: Option<String>,
+    #[serde(default)]
+    pub track: Option<String>,
+    /// JPEG or PNG to embed as front cover, replacing any existing one
+    #[serde(default)]
+    pub cover_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,9 +108,72 @@ pub struct OpResult {
     pub output_path: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatSupport {
+    pub format: String,
+    pub available: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub ffmpeg_path: String,
+    pub formats: Vec<FormatSupport>,
+    #[serde(skip)]
+    encoders: HashSet<String>,
+    #[serde(skip)]
+    muxers: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    ffmpeg_path: Option<String>,
+}
+
 // ─── Helpers ─────────────────────────────────────────────────────────────────
 
+fn dirs_next() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join("Library/Application Support/cz.core.audio-converter"))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var("APPDATA").ok().map(|a| PathBuf::from(a).join("CORE Audio Converter"))
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config/core-audio-converter"))
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs_next().map(|d| d.join("settings.json"))
+}
+
+fn load_settings() -> Settings {
+    settings_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) -> Result<(), AppError> {
+    let path = settings_path().ok_or("Cannot determine settings folder")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| AppError::io("Failed to create settings folder", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| AppError::io("Failed to save settings", e))
+}
+
 fn find_ffmpeg() -> String {
+    // User-configured binary wins if it still exists
+    if let Some(path) = load_settings().ffmpeg_path {
+        if Path::new(&path).exists() {
+            return path;
+        }
+    }
     // Try common paths
     for path in &["/opt/homebrew/bin/ffmpeg", "/usr/local/bin/ffmpeg", "/usr/bin/ffmpeg"] {
         if Path::new(path).exists() {
@@ -78,6 +184,14 @@ fn find_ffmpeg() -> String {
 }
 
 fn find_ffprobe() -> String {
+    // ffprobe ships next to ffmpeg, so prefer the sibling of the configured binary
+    if let Some(path) = load_settings().ffmpeg_path {
+        let name = if cfg!(windows) { "ffprobe.exe" } else { "ffprobe" };
+        let sibling = Path::new(&path).with_file_name(name);
+        if sibling.exists() {
+            return sibling.to_string_lossy().to_string();
+        }
+    }
     for path in &["/opt/homebrew/bin/ffprobe", "/usr/local/bin/ffprobe", "/usr/bin/ffprobe"] {
         if Path::new(path).exists() {
             return path.to_string();
@@ -86,10 +200,169 @@ fn find_ffprobe() -> String {
     "ffprobe".to_string()
 }
 
+fn ffmpeg_version(bin: &str) -> Result<String, AppError> {
+    let output = Command::new(bin)
+        .arg("-version")
+        .output()
+        .map_err(|e| AppError::io(format!("Cannot run {}", bin), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next().unwrap_or("");
+    if !output.status.success() || !first_line.starts_with("ffmpeg version") {
+        return Err(AppError::invalid(format!("{} is not an ffmpeg binary", bin)));
+    }
+    Ok(first_line.to_string())
+}
+
+fn install_hint(tool: &str) -> String {
+    let hint = match tool {
+        "ffmpeg" | "ffprobe" => {
+            if cfg!(target_os = "macos") {
+                "brew install ffmpeg"
+            } else if cfg!(windows) {
+                "choco install ffmpeg"
+            } else {
+                "sudo apt install ffmpeg"
+            }
+        }
+        _ => return format!("Install {} and make sure it is on your PATH", tool),
+    };
+    format!("Install it with: {}", hint)
+}
+
+// A missing binary gets the install hint instead of a bare "not found"
+fn spawn_error(tool: &str, e: std::io::Error) -> AppError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        AppError::ToolNotFound {
+            tool: tool.to_string(),
+            hint: install_hint(tool),
+        }
+    } else {
+        AppError::io(format!("Failed to start {}", tool), e)
+    }
+}
+
+fn tool_status(name: &str, bin: &str) -> ToolStatus {
+    let version = Command::new(bin)
+        .arg("-version")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8_lossy(&o.stdout).lines().next().map(String::from));
+    ToolStatus {
+        name: name.to_string(),
+        found: version.is_some(),
+        version,
+        install_hint: install_hint(name),
+    }
+}
+
+// Target format → (encoder ffmpeg picks by default, muxer it writes with)
+const FORMAT_REQUIREMENTS: &[(&str, &str, &str)] = &[
+    ("mp3", "libmp3lame", "mp3"),
+    ("wav", "pcm_s16le", "wav"),
+    ("flac", "flac", "flac"),
+    ("aac", "aac", "adts"),
+    ("ogg", "libvorbis", "ogg"),
+    ("wma", "wmav2", "asf"),
+    ("aiff", "pcm_s16be", "aiff"),
+    ("m4a", "aac", "ipod"),
+];
+
+// Probed once per ffmpeg binary; a different path means a fresh probe
+static CAPABILITIES: Mutex<Option<Capabilities>> = Mutex::new(None);
+
+// Names from `ffmpeg -encoders` / `-formats`: the listing starts after the
+// dashed separator, each line being "<flags> <name[,name…]> <description>"
+fn parse_listing(stdout: &str, flag: Option<char>) -> HashSet<String> {
+    stdout
+        .lines()
+        .skip_while(|l| !l.trim().chars().all(|c| c == '-') || l.trim().is_empty())
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            let names = parts.next()?;
+            flag.map_or(true, |f| flags.contains(f)).then_some(names)
+        })
+        .flat_map(|names| names.split(',').map(String::from))
+        .collect()
+}
+
+fn probe_capabilities(ffmpeg: &str) -> Result<Capabilities, AppError> {
+    let run = |arg: &str| {
+        Command::new(ffmpeg)
+            .args(["-hide_banner", arg])
+            .output()
+            .map_err(|e| spawn_error("ffmpeg", e))
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+    };
+    let encoders = parse_listing(&run("-encoders")?, None);
+    let muxers = parse_listing(&run("-formats")?, Some('E'));
+
+    let formats = FORMAT_REQUIREMENTS
+        .iter()
+        .map(|(format, encoder, muxer)| {
+            let reason = if !encoders.contains(*encoder) {
+                Some(format!("Your ffmpeg lacks the {} encoder needed for {}", encoder, format.to_uppercase()))
+            } else if !muxers.contains(*muxer) {
+                Some(format!("Your ffmpeg cannot write {} files (no {} muxer)", format.to_uppercase(), muxer))
+            } else {
+                None
+            };
+            FormatSupport {
+                format: format.to_string(),
+                available: reason.is_none(),
+                reason,
+            }
+        })
+        .collect();
+
+    Ok(Capabilities {
+        ffmpeg_path: ffmpeg.to_string(),
+        formats,
+        encoders,
+        muxers,
+    })
+}
+
+fn capabilities() -> Result<Capabilities, AppError> {
+    let ffmpeg = find_ffmpeg();
+    let mut cache = CAPABILITIES.lock().unwrap();
+    if let Some(caps) = cache.as_ref().filter(|c| c.ffmpeg_path == ffmpeg) {
+        return Ok(caps.clone());
+    }
+    let caps = probe_capabilities(&ffmpeg)?;
+    *cache = Some(caps.clone());
+    Ok(caps)
+}
+
+// Friendly explanation when the installed ffmpeg can't produce `format`.
+// Stream copies only need the muxer. Unknown formats are left to ffmpeg.
+fn unsupported_reason(format: &str, reencode: bool) -> Option<String> {
+    let format = format.to_lowercase();
+    let (_, encoder, muxer) = FORMAT_REQUIREMENTS.iter().find(|(f, _, _)| *f == format)?;
+    let caps = capabilities().ok()?;
+    if reencode && !caps.encoders.contains(*encoder) {
+        return Some(format!("Your ffmpeg lacks the {} encoder needed for {}", encoder, format.to_uppercase()));
+    }
+    if !caps.muxers.contains(*muxer) {
+        return Some(format!("Your ffmpeg cannot write {} files (no {} muxer)", format.to_uppercase(), muxer));
+    }
+    None
+}
+
+fn unsupported(message: String) -> OpResult {
+    OpResult {
+        success: false,
+        message,
+        output_path: None,
+    }
+}
+
 // ─── Commands ────────────────────────────────────────────────────────────────
 
 #[tauri::command]
-fn probe_file(path: String) -> Result<AudioFileInfo, String> {
+fn probe_file(path: String) -> Result<AudioFileInfo, AppError> {
     let ffprobe = find_ffprobe();
     let output = Command::new(&ffprobe)
         .args([
@@ -100,10 +373,10 @@ fn probe_file(path: String) -> Result<AudioFileInfo, String> {
             &path,
         ])
         .output()
-        .map_err(|e| format!("ffprobe error: {}", e))?;
+        .map_err(|e| spawn_error("ffprobe", e))?;
 
     if !output.status.success() {
-        return Err(format!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr)));
+        return Err(AppError::process_failed("ffprobe", &output));
     }
 
     let json: serde_json::Value = serde_json::from_slice(&output.stdout)
@@ -112,7 +385,7 @@ fn probe_file(path: String) -> Result<AudioFileInfo, String> {
     let format = &json["format"];
     let stream = json["streams"].as_array()
         .and_then(|s| s.iter().find(|s| s["codec_type"] == "audio"))
-        .ok_or("No audio stream found")?;
+        .ok_or_else(|| AppError::invalid("No audio stream found"))?;
 
     let filename = Path::new(&path)
         .file_name()
@@ -139,8 +412,110 @@ fn probe_file(path: String) -> Result<AudioFileInfo, String> {
     })
 }
 
+const SAMPLE_FORMATS: &[&str] = &["u8", "s16", "s32", "s64", "flt", "dbl", "u8p", "s16p", "s32p", "s64p", "fltp", "dblp"];
+
+// ffmpeg arguments for the requested bit depth / sample format. PCM containers
+// pick the matching PCM encoder, FLAC stores 24-bit in s32 samples, and lossy
+// formats have no bit depth to choose.
+fn sample_format_args(format: &str, bit_depth: Option<u32>, sample_fmt: Option<&str>) -> Result<Vec<String>, AppError> {
+    let format = format.to_lowercase();
+    let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    if bit_depth.is_some() && sample_fmt.is_some() {
+        return Err(AppError::invalid("Set either a bit depth or a sample format, not both"));
+    }
+    // ffmpeg has no s24 sample format; it's 24-bit in s32 containers
+    if sample_fmt == Some("s24") {
+        return sample_format_args(&format, Some(24), None);
+    }
+
+    if let Some(depth) = bit_depth {
+        let codec = match (format.as_str(), depth) {
+            ("wav", 8) => "pcm_u8",
+            ("wav", 16) => "pcm_s16le",
+            ("wav", 24) => "pcm_s24le",
+            ("wav", 32) => "pcm_s32le",
+            ("aiff", 8) => "pcm_s8",
+            ("aiff", 16) => "pcm_s16be",
+            ("aiff", 24) => "pcm_s24be",
+            ("aiff", 32) => "pcm_s32be",
+            ("flac", 16) => return Ok(args(&["-sample_fmt", "s16"])),
+            ("flac", 24) => return Ok(args(&["-sample_fmt", "s32", "-bits_per_raw_sample", "24"])),
+            ("wav" | "aiff" | "flac", _) => {
+                return Err(AppError::invalid(format!("{}-bit isn't supported for {}", depth, format.to_uppercase())));
+            }
+            _ => {
+                return Err(AppError::invalid(format!(
+                    "{} is a lossy format without a fixed bit depth — use WAV, AIFF or FLAC",
+                    format.to_uppercase()
+                )));
+            }
+        };
+        return Ok(args(&["-c:a", codec]));
+    }
+
+    let Some(fmt) = sample_fmt else {
+        return Ok(vec![]);
+    };
+    if !SAMPLE_FORMATS.contains(&fmt) {
+        return Err(AppError::invalid(format!("Unknown sample format: {}", fmt)));
+    }
+    // For PCM the sample format is the codec
+    let pcm = match (format.as_str(), fmt) {
+        ("wav", "u8") => Some("pcm_u8"),
+        ("wav", "s16") => Some("pcm_s16le"),
+        ("wav", "s32") => Some("pcm_s32le"),
+        ("wav", "flt") => Some("pcm_f32le"),
+        ("wav", "dbl") => Some("pcm_f64le"),
+        ("aiff", "s16") => Some("pcm_s16be"),
+        ("aiff", "s32") => Some("pcm_s32be"),
+        ("aiff", "flt") => Some("pcm_f32be"),
+        ("aiff", "dbl") => Some("pcm_f64be"),
+        ("wav" | "aiff", _) => return Err(AppError::invalid(format!("{} can't store {} samples", format.to_uppercase(), fmt))),
+        ("flac", "s16" | "s32") => None,
+        ("flac", _) => return Err(AppError::invalid("FLAC only stores s16 or s32 samples")),
+        _ => None,
+    };
+    Ok(match pcm {
+        Some(codec) => args(&["-c:a", codec]),
+        None => args(&["-sample_fmt", fmt]),
+    })
+}
+
+// "pcm_s24le, 24-bit, 48000 Hz, 2 ch" for the first audio stream of `path`
+fn describe_output(path: &str) -> Option<String> {
+    let output = Command::new(find_ffprobe())
+        .args(["-v", "quiet", "-print_format", "json", "-select_streams", "a:0", "-show_streams", path])
+        .output()
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let stream = json["streams"].as_array()?.first()?;
+
+    let mut parts = vec![stream["codec_name"].as_str()?.to_string()];
+    let bits = stream["bits_per_raw_sample"]
+        .as_str()
+        .and_then(|b| b.parse::<u32>().ok())
+        .or(stream["bits_per_sample"].as_u64().map(|b| b as u32))
+        .filter(|b| *b > 0);
+    match (bits, stream["sample_fmt"].as_str()) {
+        (Some(bits), _) => parts.push(format!("{}-bit", bits)),
+        (None, Some(fmt)) => parts.push(fmt.to_string()),
+        _ => {}
+    }
+    if let Some(rate) = stream["sample_rate"].as_str() {
+        parts.push(format!("{} Hz", rate));
+    }
+    if let Some(channels) = stream["channels"].as_u64() {
+        parts.push(format!("{} ch", channels));
+    }
+    Some(parts.join(", "))
+}
+
 #[tauri::command]
-fn convert_audio(opts: ConvertOptions) -> Result<OpResult, String> {
+fn convert_audio(opts: ConvertOptions) -> Result<OpResult, AppError> {
+    if let Some(reason) = unsupported_reason(&opts.format, true) {
+        return Ok(unsupported(reason));
+    }
     let ffmpeg = find_ffmpeg();
     let mut args = vec![
         "-y".to_string(),
@@ -160,17 +535,22 @@ fn convert_audio(opts: ConvertOptions) -> Result<OpResult, String> {
         args.push("-ac".to_string());
         args.push(ch.to_string());
     }
+    args.extend(sample_format_args(&opts.format, opts.bit_depth, opts.sample_fmt.as_deref())?);
     args.push(opts.output_path.clone());
 
     let output = Command::new(&ffmpeg)
         .args(&args)
         .output()
-        .map_err(|e| format!("ffmpeg error: {}", e))?;
+        .map_err(|e| spawn_error("ffmpeg", e))?;
 
     if output.status.success() {
+        let message = match describe_output(&opts.output_path) {
+            Some(desc) => format!("Conversion complete ({})", desc),
+            None => "Conversion complete".to_string(),
+        };
         Ok(OpResult {
             success: true,
-            message: "Conversion complete".to_string(),
+            message,
             output_path: Some(opts.output_path),
         })
     } else {
@@ -182,8 +562,39 @@ fn convert_audio(opts: ConvertOptions) -> Result<OpResult, String> {
     }
 }
 
+const LOUDNORM_TARGET: &str = "loudnorm=I=-16:LRA=11:TP=-1.5";
+
+// Measures without writing anything; loudnorm prints its figures as a JSON
+// block at the end of stderr
+#[tauri::command]
+fn analyze_loudness(path: String) -> Result<LoudnessReport, AppError> {
+    let output = Command::new(find_ffmpeg())
+        .args(["-hide_banner", "-nostats", "-i", &path, "-af", "loudnorm=print_format=json", "-f", "null", "-"])
+        .output()
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+    if !output.status.success() {
+        return Err(AppError::process_failed("ffmpeg", &output));
+    }
+    parse_loudnorm(&String::from_utf8_lossy(&output.stderr))
+        .ok_or_else(|| AppError::invalid("ffmpeg didn't report a loudness measurement"))
+}
+
+// The figures come as strings ("-23.54"); silence measures "-inf"
+fn parse_loudnorm(stderr: &str) -> Option<LoudnessReport> {
+    let start = stderr.rfind('{')?;
+    let end = start + stderr[start..].find('}')?;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..=end]).ok()?;
+    let field = |name: &str| json[name].as_str()?.trim().parse::<f64>().ok();
+    Some(LoudnessReport {
+        input_i: field("input_i")?,
+        input_lra: field("input_lra")?,
+        input_tp: field("input_tp")?,
+        input_thresh: field("input_thresh")?,
+    })
+}
+
 #[tauri::command]
-fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
+fn edit_audio(opts: EditOptions) -> Result<OpResult, AppError> {
     let ffmpeg = find_ffmpeg();
     let mut args = vec!["-y".to_string(), "-i".to_string(), opts.input_path.clone()];
 
@@ -213,7 +624,55 @@ fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
         }
         "normalize" => {
             args.push("-af".to_string());
-            args.push("loudnorm=I=-16:LRA=11:TP=-1.5".to_string());
+            // Second pass of a two-pass normalize: a linear gain from the
+            // measurement instead of loudnorm's dynamic estimate. Silence
+            // measures -inf, which loudnorm won't take.
+            let measured = opts
+                .measured
+                .as_ref()
+                .filter(|m| [m.input_i, m.input_lra, m.input_tp, m.input_thresh].iter().all(|v| v.is_finite()));
+            args.push(match measured {
+                Some(m) => format!(
+                    "{}:measured_I={}:measured_LRA={}:measured_TP={}:measured_thresh={}:linear=true",
+                    LOUDNORM_TARGET, m.input_i, m.input_lra, m.input_tp, m.input_thresh
+                ),
+                None => LOUDNORM_TARGET.to_string(),
+            });
+        }
+        "trim_silence" => {
+            let threshold = opts.silence_threshold_db.unwrap_or(-50.0);
+            let duration = opts.silence_min_duration.unwrap_or(0.5);
+            if !threshold.is_finite() || threshold > 0.0 {
+                return Err(AppError::invalid("Silence threshold must be 0 dB or lower"));
+            }
+            if !duration.is_finite() || duration < 0.0 {
+                return Err(AppError::invalid("Minimum silence duration can't be negative"));
+            }
+            args.push("-af".to_string());
+            args.push(trim_silence_filter(threshold, duration));
+        }
+        "tempo" | "pitch" => {
+            let factor = opts
+                .factor
+                .filter(|f| f.is_finite() && *f > 0.0)
+                .ok_or_else(|| AppError::invalid("Factor must be a number greater than 0"))?;
+            let filter = if opts.operation == "tempo" {
+                atempo_chain(factor)
+            } else {
+                // Resampling at a different rate shifts pitch and speed
+                // together; atempo then restores the original duration
+                let rate = probe_stream_params(&opts.input_path)
+                    .and_then(|p| p.sample_rate.parse::<u32>().ok())
+                    .unwrap_or(44100);
+                format!(
+                    "asetrate={},aresample={},{}",
+                    (rate as f64 * factor).round(),
+                    rate,
+                    atempo_chain(1.0 / factor)
+                )
+            };
+            args.push("-af".to_string());
+            args.push(filter);
         }
         "split_silence" => {
             args.push("-af".to_string());
@@ -225,7 +684,7 @@ fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
             let output = Command::new(&ffmpeg)
                 .args(&args)
                 .output()
-                .map_err(|e| format!("ffmpeg error: {}", e))?;
+                .map_err(|e| spawn_error("ffmpeg", e))?;
 
             return Ok(OpResult {
                 success: output.status.success(),
@@ -233,7 +692,16 @@ fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
                 output_path: None,
             });
         }
-        _ => return Err(format!("Unknown operation: {}", opts.operation)),
+        _ => return Err(AppError::invalid(format!("Unknown operation: {}", opts.operation))),
+    }
+
+    let ext = Path::new(&opts.output_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    if let Some(reason) = unsupported_reason(&ext, opts.operation != "trim") {
+        return Ok(unsupported(reason));
     }
 
     args.push(opts.output_path.clone());
@@ -241,7 +709,7 @@ fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
     let output = Command::new(&ffmpeg)
         .args(&args)
         .output()
-        .map_err(|e| format!("ffmpeg error: {}", e))?;
+        .map_err(|e| spawn_error("ffmpeg", e))?;
 
     Ok(OpResult {
         success: output.status.success(),
@@ -254,10 +722,175 @@ fn edit_audio(opts: EditOptions) -> Result<OpResult, String> {
     })
 }
 
+// Codec parameters of a file's first audio stream
+#[derive(Debug, Clone, PartialEq)]
+struct StreamParams {
+    codec: String,
+    sample_rate: String,
+    channels: u64,
+    channel_layout: String,
+}
+
+// A single atempo only goes from 0.5 to 2.0, so larger changes are chained
+// Only the leading and trailing runs go; pauses in between are kept. The
+// stop_* options of silenceremove would end the output at the first pause,
+// so the tail is trimmed as the start of the reversed audio instead.
+fn trim_silence_filter(threshold_db: f64, min_duration: f64) -> String {
+    let leading = format!(
+        "silenceremove=start_periods=1:start_duration={}:start_threshold={}dB",
+        min_duration, threshold_db
+    );
+    format!("{0},areverse,{0},areverse", leading)
+}
+
+fn atempo_chain(mut factor: f64) -> String {
+    let mut steps = Vec::new();
+    while factor > 2.0 {
+        steps.push("atempo=2.0".to_string());
+        factor /= 2.0;
+    }
+    while factor < 0.5 {
+        steps.push("atempo=0.5".to_string());
+        factor /= 0.5;
+    }
+    steps.push(format!("atempo={}", factor));
+    steps.join(",")
+}
+
+fn probe_stream_params(path: &str) -> Option<StreamParams> {
+    let output = Command::new(find_ffprobe())
+        .args([
+            "-v", "quiet",
+            "-print_format", "json",
+            "-select_streams", "a:0",
+            "-show_streams",
+            path,
+        ])
+        .output()
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    let stream = json["streams"].as_array()?.first()?;
+    let channels = stream["channels"].as_u64().unwrap_or(0);
+    Some(StreamParams {
+        codec: stream["codec_name"].as_str()?.to_string(),
+        sample_rate: stream["sample_rate"].as_str().unwrap_or("").to_string(),
+        channels,
+        channel_layout: stream["channel_layout"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("{}c", channels)),
+    })
+}
+
+fn extension_of(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+// The concat demuxer can only stream-copy inputs with identical codec
+// parameters into a container of the same kind
+fn can_stream_copy(input_paths: &[String], output_path: &str) -> bool {
+    let out_ext = extension_of(output_path);
+    if input_paths.iter().any(|p| extension_of(p) != out_ext) {
+        return false;
+    }
+    let params: Vec<Option<StreamParams>> = input_paths.iter().map(|p| probe_stream_params(p)).collect();
+    match params.first() {
+        Some(Some(first)) => params.iter().all(|p| p.as_ref() == Some(first)),
+        _ => false,
+    }
+}
+
+// `reencode` of None picks stream copy when the inputs allow it. A crossfade
+// always re-encodes, overlapping each pair of tracks with acrossfade.
 #[tauri::command]
-fn merge_audio(input_paths: Vec<String>, output_path: String) -> Result<OpResult, String> {
-    let ffmpeg = find_ffmpeg();
+fn merge_audio(
+    input_paths: Vec<String>,
+    output_path: String,
+    reencode: Option<bool>,
+    crossfade_seconds: Option<f64>,
+) -> Result<OpResult, AppError> {
+    if input_paths.len() < 2 {
+        return Err(AppError::invalid("Merging needs at least two files"));
+    }
+    let crossfade = crossfade_seconds.filter(|d| *d > 0.0);
+    if crossfade.is_some() && reencode == Some(false) {
+        return Err(AppError::invalid("A crossfade can't be applied with stream copy"));
+    }
+    let reencode = crossfade.is_some() || reencode.unwrap_or_else(|| !can_stream_copy(&input_paths, &output_path));
+
+    if !reencode {
+        return merge_copy(&input_paths, output_path);
+    }
+    if let Some(reason) = unsupported_reason(&extension_of(&output_path), true) {
+        return Ok(unsupported(reason));
+    }
+
+    // Every input is conformed to the first one's rate and layout so the
+    // filters see matching streams
+    let first = probe_stream_params(&input_paths[0]);
+    let conform = match &first {
+        Some(p) if !p.sample_rate.is_empty() => {
+            format!("aformat=sample_rates={}:channel_layouts={}", p.sample_rate, p.channel_layout)
+        }
+        _ => "anull".to_string(),
+    };
+
+    let mut args = vec!["-y".to_string()];
+    for path in &input_paths {
+        args.extend(["-i".to_string(), path.clone()]);
+    }
+
+    let mut graph: Vec<String> = (0..input_paths.len())
+        .map(|i| format!("[{}:a]{}[a{}]", i, conform, i))
+        .collect();
+    match crossfade {
+        Some(d) => {
+            let mut last = "a0".to_string();
+            for i in 1..input_paths.len() {
+                let out = format!("x{}", i);
+                graph.push(format!("[{}][a{}]acrossfade=d={}[{}]", last, i, d, out));
+                last = out;
+            }
+            graph.push(format!("[{}]anull[out]", last));
+        }
+        None => {
+            let inputs: String = (0..input_paths.len()).map(|i| format!("[a{}]", i)).collect();
+            graph.push(format!("{}concat=n={}:v=0:a=1[out]", inputs, input_paths.len()));
+        }
+    }
+    args.extend([
+        "-filter_complex".to_string(),
+        graph.join(";"),
+        "-map".to_string(),
+        "[out]".to_string(),
+        output_path.clone(),
+    ]);
+
+    let output = Command::new(find_ffmpeg())
+        .args(&args)
+        .output()
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+
+    let method = match crossfade {
+        Some(d) => format!("re-encoded with {}s crossfade", d),
+        None => "re-encoded".to_string(),
+    };
+    Ok(OpResult {
+        success: output.status.success(),
+        message: if output.status.success() {
+            format!("Merge complete ({})", method)
+        } else {
+            String::from_utf8_lossy(&output.stderr).to_string()
+        },
+        output_path: if output.status.success() { Some(output_path) } else { None },
+    })
+}
 
+fn merge_copy(input_paths: &[String], output_path: String) -> Result<OpResult, AppError> {
     // Create concat file content
     let list_content: String = input_paths
         .iter()
@@ -267,19 +900,20 @@ fn merge_audio(input_paths: Vec<String>, output_path: String) -> Result<OpResult
 
     let tmp_list = format!("{}.txt", &output_path);
     std::fs::write(&tmp_list, &list_content)
-        .map_err(|e| format!("Failed to write concat list: {}", e))?;
+        .map_err(|e| AppError::io("Failed to write concat list", e))?;
 
-    let output = Command::new(&ffmpeg)
+    let output = Command::new(find_ffmpeg())
         .args(["-y", "-f", "concat", "-safe", "0", "-i", &tmp_list, "-c", "copy", &output_path])
-        .output()
-        .map_err(|e| format!("ffmpeg error: {}", e))?;
+        .output();
 
+    // The list goes whether or not ffmpeg could even be started
     let _ = std::fs::remove_file(&tmp_list);
+    let output = output.map_err(|e| spawn_error("ffmpeg", e))?;
 
     Ok(OpResult {
         success: output.status.success(),
         message: if output.status.success() {
-            "Merge complete".to_string()
+            "Merge complete (stream copy)".to_string()
         } else {
             String::from_utf8_lossy(&output.stderr).to_string()
         },
@@ -287,8 +921,201 @@ fn merge_audio(input_paths: Vec<String>, output_path: String) -> Result<OpResult
     })
 }
 
+// Chapter titles become file names, so drop anything a filesystem would reject
+fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    cleaned.chars().take(120).collect()
+}
+
+#[tauri::command]
+fn split_by_chapters(input: String, output_dir: String) -> Result<OpResult, AppError> {
+    let ffprobe = find_ffprobe();
+    let output = Command::new(&ffprobe)
+        .args(["-v", "quiet", "-print_format", "json", "-show_chapters", &input])
+        .output()
+        .map_err(|e| spawn_error("ffprobe", e))?;
+
+    if !output.status.success() {
+        return Err(AppError::process_failed("ffprobe", &output));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("JSON parse error: {}", e))?;
+    let chapters = json["chapters"].as_array().cloned().unwrap_or_default();
+    if chapters.is_empty() {
+        return Ok(OpResult {
+            success: false,
+            message: "This file has no embedded chapters to split by".to_string(),
+            output_path: None,
+        });
+    }
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| AppError::io("Failed to create output folder", e))?;
+
+    let ffmpeg = find_ffmpeg();
+    let ext = Path::new(&input)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let total = chapters.len();
+    let width = total.to_string().len().max(2);
+    let mut failed = Vec::new();
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let track = i + 1;
+        let start = chapter["start_time"].as_str().unwrap_or("0");
+        let end = chapter["end_time"].as_str().unwrap_or("0");
+        let title = chapter["tags"]["title"]
+            .as_str()
+            .or(chapter["tags"]["TITLE"].as_str())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| format!("Chapter {}", track));
+
+        let mut name = sanitize_filename(&title);
+        if name.is_empty() {
+            name = format!("Chapter {}", track);
+        }
+        let out_path = Path::new(&output_dir)
+            .join(format!("{:0width$} - {}.{}", track, name, ext, width = width))
+            .to_string_lossy()
+            .to_string();
+
+        let result = Command::new(&ffmpeg)
+            .args([
+                "-y", "-i", &input,
+                "-ss", start, "-to", end,
+                "-map_chapters", "-1",
+                "-metadata", &format!("title={}", title),
+                "-metadata", &format!("track={}/{}", track, total),
+                "-c", "copy",
+                &out_path,
+            ])
+            .output()
+            .map_err(|e| spawn_error("ffmpeg", e))?;
+
+        if !result.status.success() {
+            failed.push(title);
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(OpResult {
+            success: true,
+            message: format!("Split into {} chapters", total),
+            output_path: Some(output_dir),
+        })
+    } else {
+        Ok(OpResult {
+            success: false,
+            message: format!("{} of {} chapters failed: {}", failed.len(), total, failed.join(", ")),
+            output_path: Some(output_dir),
+        })
+    }
+}
+
+#[tauri::command]
+fn update_metadata(meta: MetadataUpdate) -> Result<OpResult, AppError> {
+    write_metadata(meta)
+}
+
+// One failing file is reported in its own result and doesn't stop the rest
+#[tauri::command]
+fn batch_update_metadata(entries: Vec<MetadataUpdate>) -> Vec<OpResult> {
+    entries
+        .into_iter()
+        .map(|meta| {
+            let path = meta.path.clone();
+            write_metadata(meta).unwrap_or_else(|e| OpResult {
+                success: false,
+                message: e.to_string(),
+                output_path: Some(path),
+            })
+        })
+        .collect()
+}
+
+// Pull tags out of a file name with a pattern like "{track} - {artist} - {title}".
+// Placeholders: track, artist, title, album, year, genre, and `_` to skip a part.
 #[tauri::command]
-fn update_metadata(meta: MetadataUpdate) -> Result<OpResult, String> {
+fn infer_metadata_from_filename(path: String, pattern: String) -> Result<MetadataUpdate, AppError> {
+    let stem = Path::new(&path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let mut meta = MetadataUpdate {
+        path: path.clone(),
+        title: None,
+        artist: None,
+        album: None,
+        year: None,
+        genre: None,
+        track: None,
+        cover_path: None,
+    };
+
+    let mismatch = || AppError::invalid("File name doesn't match the pattern");
+    let mut rest = stem.as_str();
+    let mut pat = pattern.as_str();
+    while !pat.is_empty() {
+        let Some(open) = pat.find('{') else {
+            // Trailing literal
+            if rest != pat {
+                return Err(mismatch());
+            }
+            break;
+        };
+        let literal = &pat[..open];
+        rest = rest.strip_prefix(literal).ok_or_else(mismatch)?;
+
+        let close = pat[open..]
+            .find('}')
+            .ok_or_else(|| AppError::invalid("Unclosed '{' in pattern"))?
+            + open;
+        let field = &pat[open + 1..close];
+        pat = &pat[close + 1..];
+
+        // The value runs up to the next literal (or to the end)
+        let next_literal = &pat[..pat.find('{').unwrap_or(pat.len())];
+        if next_literal.is_empty() && !pat.is_empty() {
+            return Err(AppError::invalid("Placeholders must be separated by text"));
+        }
+        let end = if pat.is_empty() {
+            rest.len()
+        } else if pat.find('{').is_none() {
+            rest.rfind(next_literal).ok_or_else(mismatch)?
+        } else {
+            rest.find(next_literal).ok_or_else(mismatch)?
+        };
+        let value = rest[..end].trim().to_string();
+        rest = &rest[end..];
+
+        let value = (!value.is_empty()).then_some(value);
+        match field {
+            "track" => meta.track = value,
+            "artist" => meta.artist = value,
+            "title" => meta.title = value,
+            "album" => meta.album = value,
+            "year" => meta.year = value,
+            "genre" => meta.genre = value,
+            "_" => {}
+            other => return Err(AppError::invalid(format!("Unknown placeholder: {{{}}}", other))),
+        }
+    }
+
+    Ok(meta)
+}
+
+// Tags are written to a temp file that replaces the original only on success
+fn write_metadata(meta: MetadataUpdate) -> Result<OpResult, AppError> {
     let ffmpeg = find_ffmpeg();
     let ext = Path::new(&meta.path)
         .extension()
@@ -298,23 +1125,30 @@ fn update_metadata(meta: MetadataUpdate) -> Result<OpResult, String> {
     let tmp_out = format!("{}_meta_tmp.{}", &meta.path, &ext);
 
     let mut args = vec!["-y".to_string(), "-i".to_string(), meta.path.clone()];
+    let mut cover_map = Vec::new();
+    if let Some(cover) = &meta.cover_path {
+        cover_map = cover_args(&ext, cover)?;
+        args.extend(["-i".to_string(), cover.clone()]);
+    }
 
     if let Some(v) = &meta.title { args.extend(["-metadata".to_string(), format!("title={}", v)]); }
     if let Some(v) = &meta.artist { args.extend(["-metadata".to_string(), format!("artist={}", v)]); }
     if let Some(v) = &meta.album { args.extend(["-metadata".to_string(), format!("album={}", v)]); }
     if let Some(v) = &meta.year { args.extend(["-metadata".to_string(), format!("date={}", v)]); }
     if let Some(v) = &meta.genre { args.extend(["-metadata".to_string(), format!("genre={}", v)]); }
+    if let Some(v) = &meta.track { args.extend(["-metadata".to_string(), format!("track={}", v)]); }
 
+    args.extend(cover_map);
     args.extend(["-c".to_string(), "copy".to_string(), tmp_out.clone()]);
 
     let output = Command::new(&ffmpeg)
         .args(&args)
         .output()
-        .map_err(|e| format!("ffmpeg error: {}", e))?;
+        .map_err(|e| spawn_error("ffmpeg", e))?;
 
     if output.status.success() {
         std::fs::rename(&tmp_out, &meta.path)
-            .map_err(|e| format!("Failed to replace file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to replace file", e))?;
         Ok(OpResult {
             success: true,
             message: "Metadata updated".to_string(),
@@ -330,8 +1164,43 @@ fn update_metadata(meta: MetadataUpdate) -> Result<OpResult, String> {
     }
 }
 
+// Maps the audio of input 0 and the image of input 1 as an attached picture.
+// Only containers with a cover-art slot ffmpeg can write are accepted.
+fn cover_args(ext: &str, cover: &str) -> Result<Vec<String>, AppError> {
+    let cover_ext = Path::new(cover)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    if !matches!(cover_ext.as_str(), "jpg" | "jpeg" | "png") {
+        return Err(AppError::invalid("Cover art must be a JPEG or PNG image"));
+    }
+    if !Path::new(cover).is_file() {
+        return Err(AppError::invalid(format!("Cover image not found: {}", cover)));
+    }
+
+    let mut args: Vec<String> = ["-map", "0:a", "-map", "1:v", "-disposition:v", "attached_pic"]
+        .map(String::from)
+        .to_vec();
+    match ext.to_lowercase().as_str() {
+        // ID3v2.3 APIC frames are what most players read; the comment sets the picture type
+        "mp3" => args.extend(
+            ["-id3v2_version", "3", "-metadata:s:v", "title=Album cover", "-metadata:s:v", "comment=Cover (front)"]
+                .map(String::from),
+        ),
+        "flac" | "m4a" => {}
+        other => {
+            return Err(AppError::invalid(format!(
+                "Cover art can't be embedded in {} files — use MP3, FLAC or M4A",
+                other.to_uppercase()
+            )))
+        }
+    }
+    Ok(args)
+}
+
 #[tauri::command]
-fn get_waveform_data(path: String, num_peaks: u32) -> Result<WaveformData, String> {
+fn get_waveform_data(path: String, num_peaks: u32) -> Result<WaveformData, AppError> {
     let ffprobe = find_ffprobe();
     let ffmpeg = find_ffmpeg();
 
@@ -339,7 +1208,7 @@ fn get_waveform_data(path: String, num_peaks: u32) -> Result<WaveformData, Strin
     let probe_out = Command::new(&ffprobe)
         .args(["-v", "quiet", "-show_entries", "format=duration", "-of", "csv=p=0", &path])
         .output()
-        .map_err(|e| format!("ffprobe error: {}", e))?;
+        .map_err(|e| spawn_error("ffprobe", e))?;
 
     let duration: f64 = String::from_utf8_lossy(&probe_out.stdout)
         .trim()
@@ -356,10 +1225,10 @@ fn get_waveform_data(path: String, num_peaks: u32) -> Result<WaveformData, Strin
             "-"
         ])
         .output()
-        .map_err(|e| format!("ffmpeg waveform error: {}", e))?;
+        .map_err(|e| spawn_error("ffmpeg", e))?;
 
     if !output.status.success() {
-        return Err(format!("FFmpeg waveform extraction failed"));
+        return Err(AppError::process_failed("ffmpeg", &output));
     }
 
     let samples: Vec<i16> = output.stdout
@@ -384,6 +1253,98 @@ fn get_waveform_data(path: String, num_peaks: u32) -> Result<WaveformData, Strin
     })
 }
 
+#[tauri::command]
+fn check_capabilities() -> Result<Capabilities, AppError> {
+    capabilities()
+}
+
+fn temp_png(kind: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!("core-audio-{}-{}-{}.png", kind, std::process::id(), nanos))
+}
+
+// Run ffmpeg with `args` writing a PNG to `out`, then hand it back as a data URL
+fn render_png(args: &[&str], out: &Path) -> Result<String, AppError> {
+    let output = Command::new(find_ffmpeg())
+        .args(args)
+        .arg(out)
+        .output()
+        .map_err(|e| spawn_error("ffmpeg", e));
+
+    let result = output.and_then(|output| {
+        if !output.status.success() {
+            return Err(AppError::process_failed("ffmpeg", &output));
+        }
+        std::fs::read(out).map_err(|e| AppError::io("Failed to read image", e))
+    });
+    let _ = std::fs::remove_file(out);
+
+    Ok(format!("data:image/png;base64,{}", BASE64.encode(result?)))
+}
+
+#[tauri::command]
+fn get_spectrogram(path: String, width: u32, height: u32) -> Result<String, AppError> {
+    let width = width.clamp(256, 4096);
+    let height = height.clamp(128, 2048);
+    let out = temp_png("spectrogram");
+    render_png(
+        &[
+            "-y", "-i", &path,
+            "-lavfi", &format!("showspectrumpic=s={}x{}:legend=1", width, height),
+            "-frames:v", "1",
+        ],
+        &out,
+    )
+}
+
+// ebur128 draws its graph frame by frame; the last frame covers the whole file
+#[tauri::command]
+fn get_loudness_graph(path: String, width: u32, height: u32) -> Result<String, AppError> {
+    let width = width.clamp(640, 4096);
+    let height = height.clamp(480, 2048);
+    let out = temp_png("loudness");
+    render_png(
+        &[
+            "-y", "-i", &path,
+            "-filter_complex", &format!("[0:a]ebur128=video=1:size={}x{}[v][a];[a]anullsink", width, height),
+            "-map", "[v]",
+            "-update", "1",
+            "-f", "image2",
+        ],
+        &out,
+    )
+}
+
+#[tauri::command]
+fn get_ffmpeg_path() -> Result<String, AppError> {
+    Ok(find_ffmpeg())
+}
+
+#[tauri::command]
+fn check_all_tools() -> Vec<ToolStatus> {
+    vec![tool_status("ffmpeg", &find_ffmpeg()), tool_status("ffprobe", &find_ffprobe())]
+}
+
+// An empty path clears the setting and falls back to auto-detection
+#[tauri::command]
+fn set_ffmpeg_path(path: String) -> Result<String, AppError> {
+    let path = path.trim().to_string();
+    let mut settings = load_settings();
+    *CAPABILITIES.lock().unwrap() = None;
+    if path.is_empty() {
+        settings.ffmpeg_path = None;
+        save_settings(&settings)?;
+        return ffmpeg_version(&find_ffmpeg());
+    }
+    let version = ffmpeg_version(&path)?;
+    settings.ffmpeg_path = Some(path);
+    save_settings(&settings)?;
+    Ok(version)
+}
+
 // ─── App ─────────────────────────────────────────────────────────────────────
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -395,10 +1356,45 @@ pub fn run() {
             probe_file,
             convert_audio,
             edit_audio,
+            analyze_loudness,
             merge_audio,
+            split_by_chapters,
             update_metadata,
+            batch_update_metadata,
+            infer_metadata_from_filename,
             get_waveform_data,
+            get_spectrogram,
+            get_loudness_graph,
+            get_ffmpeg_path,
+            set_ffmpeg_path,
+            check_capabilities,
+            check_all_tools,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trim_silence_keeps_pauses_in_between() {
+        let filter = trim_silence_filter(-50.0, 0.5);
+        assert!(!filter.contains("stop_periods"));
+
+        // 0.5 s silence, 1 s tone, 1 s pause, 1 s tone, 1 s silence
+        let source = "aevalsrc='if(between(t,0.5,1.5)+between(t,2.5,3.5),0.5*sin(2*PI*440*t),0)':s=8000:d=4.5";
+        let Ok(output) = Command::new(find_ffmpeg())
+            .args(["-v", "error", "-f", "lavfi", "-i", source, "-af", &filter])
+            .args(["-f", "s16le", "-ac", "1", "-ar", "8000", "-"])
+            .output()
+        else {
+            // No ffmpeg on this machine
+            return;
+        };
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let seconds = output.stdout.len() as f64 / 2.0 / 8000.0;
+        assert!((2.9..3.1).contains(&seconds), "trimmed to {} s", seconds);
+    }
+}
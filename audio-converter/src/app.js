@@ -16,6 +16,7 @@ let selStart = null;
 let selEnd = null;
 let mergeFiles = [];
 let batchFiles = [];
+let tagFiles = [];
 
 // ─── DOM ─────────────────────────────────────────────────────────────────────
 const $ = (s) => document.querySelector(s);
@@ -91,6 +92,7 @@ async function loadFile(path) {
     // Enable buttons
     $('#btnConvert').disabled = false;
     $('#btnEdit').disabled = false;
+    $('#btnSplitChapters').disabled = false;
     $('#btnSaveMeta').disabled = false;
 
     // Fill metadata fields
@@ -99,6 +101,7 @@ async function loadFile(path) {
     $('#metaAlbum').value = fileInfo.album || '';
     $('#metaYear').value = fileInfo.year || '';
     $('#metaGenre').value = fileInfo.genre || '';
+    $('#metaCover').value = '';
 
     // Set edit end time
     $('#editEnd').placeholder = fileInfo.duration.toFixed(1);
@@ -107,7 +110,7 @@ async function loadFile(path) {
     await loadAudioForPlayback(path);
     setStatus('File loaded');
   } catch (err) {
-    setStatus(`Error: ${err}`);
+    setStatus(errorText(err));
   }
 }
 
@@ -121,8 +124,10 @@ $('#btnRemoveFile').addEventListener('click', () => {
   fileInfoBar.classList.add('hidden');
   waveformContainer.classList.add('hidden');
   transport.classList.add('hidden');
+  $('#analysisView').classList.add('hidden');
   $('#btnConvert').disabled = true;
   $('#btnEdit').disabled = true;
+  $('#btnSplitChapters').disabled = true;
   $('#btnSaveMeta').disabled = true;
   setStatus('Ready');
 });
@@ -133,7 +138,7 @@ async function loadWaveform(path) {
     waveformData = await invoke('get_waveform_data', { path, numPeaks: 2000 });
     drawWaveform();
   } catch (e) {
-    console.error('Waveform error:', e);
+    console.error('Waveform error:', errorText(e));
   }
 }
 
@@ -227,6 +232,42 @@ canvas.addEventListener('mousemove', (e) => {
 
 canvas.addEventListener('mouseup', () => { isDragging = false; });
 
+// ─── Spectrogram / Loudness ──────────────────────────────────────────────────
+async function showAnalysis(command, label) {
+  if (!currentFile) return;
+  setStatus(`Rendering ${label}...`);
+  try {
+    const width = Math.round(waveformContainer.getBoundingClientRect().width * (window.devicePixelRatio || 1));
+    $('#analysisImage').src = await invoke(command, { path: currentFile, width, height: Math.round(width / 2) });
+    $('#analysisImage').alt = label;
+    $('#analysisView').classList.remove('hidden');
+    setStatus(`${label} ready`);
+  } catch (e) {
+    setStatus(errorText(e));
+  }
+}
+
+$('#btnSpectrogram').addEventListener('click', () => showAnalysis('get_spectrogram', 'Spectrogram'));
+$('#btnLoudness').addEventListener('click', () => showAnalysis('get_loudness_graph', 'Loudness graph'));
+$('#btnCloseAnalysis').addEventListener('click', () => $('#analysisView').classList.add('hidden'));
+
+// Kept for the file it was measured on so Normalize can reuse it
+let loudness = null;
+
+$('#btnLufs').addEventListener('click', async () => {
+  if (!currentFile) return;
+  const path = currentFile;
+  setStatus('Measuring loudness...');
+  try {
+    const report = await invoke('analyze_loudness', { path });
+    loudness = { path, report };
+    const fmt = (v) => (v === null ? '−∞' : v.toFixed(1));
+    setStatus(`${fmt(report.input_i)} LUFS · LRA ${fmt(report.input_lra)} LU · peak ${fmt(report.input_tp)} dBTP`);
+  } catch (e) {
+    setStatus(errorText(e));
+  }
+});
+
 // ─── Playback (Web Audio API) ────────────────────────────────────────────────
 async function loadAudioForPlayback(path) {
   try {
@@ -322,12 +363,14 @@ $('#btnConvert').addEventListener('click', async () => {
         bitrate: $('#convertBitrate').value || null,
         sample_rate: $('#convertSampleRate').value ? parseInt($('#convertSampleRate').value) : null,
         channels: $('#convertChannels').value ? parseInt($('#convertChannels').value) : null,
+        bit_depth: $('#convertBitDepth').value ? parseInt($('#convertBitDepth').value) : null,
+        sample_fmt: null,
       }
     });
     setProgress(100);
-    setStatus(result.success ? `Converted → ${outputPath}` : `Error: ${result.message}`);
+    setStatus(result.success ? `${result.message} → ${outputPath}` : `Error: ${result.message}`);
   } catch (e) {
-    setStatus(`Error: ${e}`);
+    setStatus(errorText(e));
   }
   setTimeout(() => showProgress(false), 2000);
 });
@@ -339,6 +382,9 @@ $('#editOp').addEventListener('change', () => {
   $('#trimStartGroup').style.display = ['trim','fade_out'].includes(op) ? '' : 'none';
   $('#trimEndGroup').style.display = op === 'trim' ? '' : 'none';
   $('#fadeDurGroup').style.display = ['fade_in','fade_out'].includes(op) ? '' : 'none';
+  $('#silenceThresholdGroup').style.display = op === 'trim_silence' ? '' : 'none';
+  $('#silenceDurGroup').style.display = op === 'trim_silence' ? '' : 'none';
+  $('#factorGroup').style.display = ['tempo','pitch'].includes(op) ? '' : 'none';
 });
 
 $('#btnEdit').addEventListener('click', async () => {
@@ -363,12 +409,19 @@ $('#btnEdit').addEventListener('click', async () => {
         start_time: $('#editStart').value ? parseFloat($('#editStart').value) : null,
         end_time: $('#editEnd').value ? parseFloat($('#editEnd').value) : null,
         fade_duration: $('#fadeDur').value ? parseFloat($('#fadeDur').value) : null,
+        silence_threshold_db: $('#silenceThreshold').value ? parseFloat($('#silenceThreshold').value) : null,
+        silence_min_duration: $('#silenceDur').value ? parseFloat($('#silenceDur').value) : null,
+        factor: $('#editFactor').value ? parseFloat($('#editFactor').value) : null,
+        // Silence measures -inf, which arrives as null and can't be reused
+        measured: loudness?.path === currentFile && Object.values(loudness.report).every(Number.isFinite)
+          ? loudness.report
+          : null,
       }
     });
     setProgress(100);
     setStatus(result.success ? `Edit complete → ${outputPath}` : `Error: ${result.message}`);
   } catch (e) {
-    setStatus(`Error: ${e}`);
+    setStatus(errorText(e));
   }
   setTimeout(() => showProgress(false), 2000);
 });
@@ -410,16 +463,47 @@ $('#btnMerge').addEventListener('click', async () => {
   showProgress(true);
   setStatus('Merging files...');
   try {
-    const result = await invoke('merge_audio', { inputPaths: mergeFiles, outputPath });
+    const mode = $('#mergeMode').value;
+    const result = await invoke('merge_audio', {
+      inputPaths: mergeFiles,
+      outputPath,
+      reencode: mode === 'auto' ? null : mode === 'reencode',
+      crossfadeSeconds: $('#mergeCrossfade').value ? parseFloat($('#mergeCrossfade').value) : null,
+    });
+    setProgress(100);
+    setStatus(result.success ? `${result.message} → ${outputPath}` : `Error: ${result.message}`);
+  } catch (e) {
+    setStatus(errorText(e));
+  }
+  setTimeout(() => showProgress(false), 2000);
+});
+
+// ─── Split by Chapters ───────────────────────────────────────────────────────
+$('#btnSplitChapters').addEventListener('click', async () => {
+  if (!currentFile) return;
+  const outputDir = await open({ directory: true, multiple: false });
+  if (!outputDir) return;
+  showProgress(true);
+  setStatus('Splitting by chapters...');
+  try {
+    const result = await invoke('split_by_chapters', { input: currentFile, outputDir });
     setProgress(100);
-    setStatus(result.success ? `Merged → ${outputPath}` : `Error: ${result.message}`);
+    setStatus(result.success ? `${result.message} → ${outputDir}` : `Error: ${result.message}`);
   } catch (e) {
-    setStatus(`Error: ${e}`);
+    setStatus(errorText(e));
   }
   setTimeout(() => showProgress(false), 2000);
 });
 
 // ─── Metadata ────────────────────────────────────────────────────────────────
+$('#btnMetaCover').addEventListener('click', async () => {
+  const path = await open({
+    filters: [{ name: 'Image', extensions: ['jpg', 'jpeg', 'png'] }],
+    multiple: false,
+  });
+  if (path) $('#metaCover').value = path;
+});
+
 $('#btnSaveMeta').addEventListener('click', async () => {
   if (!currentFile) return;
   setStatus('Saving metadata...');
@@ -432,12 +516,101 @@ $('#btnSaveMeta').addEventListener('click', async () => {
         album: $('#metaAlbum').value || null,
         year: $('#metaYear').value || null,
         genre: $('#metaGenre').value || null,
+        cover_path: $('#metaCover').value || null,
       }
     });
     setStatus(result.success ? 'Metadata saved' : `Error: ${result.message}`);
   } catch (e) {
-    setStatus(`Error: ${e}`);
+    setStatus(errorText(e));
+  }
+});
+
+// ─── Tag Multiple Files ──────────────────────────────────────────────────────
+$('#btnTagAdd').addEventListener('click', async () => {
+  const paths = await open({
+    filters: [{ name: 'Audio', extensions: ['mp3','wav','flac','aac','ogg','wma','aiff','m4a'] }],
+    multiple: true,
+  });
+  if (!paths) return;
+  const arr = Array.isArray(paths) ? paths : [paths];
+  arr.forEach(p => tagFiles.push({ path: p, status: 'pending', meta: null, error: null }));
+  await inferTags();
+});
+
+$('#tagPattern').addEventListener('change', inferTags);
+
+// Preview what the pattern pulls out of each name before anything is written
+async function inferTags() {
+  const pattern = $('#tagPattern').value;
+  for (const f of tagFiles) {
+    try {
+      f.meta = await invoke('infer_metadata_from_filename', { path: f.path, pattern });
+      f.error = null;
+    } catch (e) {
+      f.meta = null;
+      f.error = errorText(e);
+    }
+    f.status = 'pending';
+  }
+  renderTagList();
+}
+
+function renderTagList() {
+  const list = $('#tagList');
+  if (tagFiles.length === 0) {
+    list.innerHTML = '<p class="placeholder">Add files to tag them from their names.</p>';
+    $('#btnTagAll').disabled = true;
+    return;
+  }
+  list.innerHTML = tagFiles.map((f, i) => {
+    const m = f.meta;
+    const preview = m
+      ? [m.track && `#${m.track}`, m.artist, m.title].filter(Boolean).join(' · ')
+      : (f.error || '');
+    return `<div class="batch-item">
+      <span>${f.path.split('/').pop()}</span>
+      <span class="placeholder">${preview}</span>
+      <span class="status ${f.status}" title="${f.error || ''}">${f.status === 'done' ? '✓' : f.status === 'error' ? '✗' : '⏳'}</span>
+      <button class="icon-btn" onclick="removeTag(${i})">✕</button>
+    </div>`;
+  }).join('');
+  $('#btnTagAll').disabled = false;
+}
+window.removeTag = (i) => { tagFiles.splice(i, 1); renderTagList(); };
+
+$('#btnTagAll').addEventListener('click', async () => {
+  if (tagFiles.length === 0) return;
+  const shared = {
+    album: $('#tagAlbum').value || null,
+    year: $('#tagYear').value || null,
+    genre: $('#tagGenre').value || null,
+  };
+  const entries = tagFiles.map(f => ({
+    path: f.path,
+    title: f.meta?.title ?? null,
+    artist: f.meta?.artist ?? null,
+    album: shared.album ?? f.meta?.album ?? null,
+    year: shared.year ?? f.meta?.year ?? null,
+    genre: shared.genre ?? f.meta?.genre ?? null,
+    track: f.meta?.track ?? null,
+  }));
+
+  showProgress(true);
+  setStatus(`Tagging ${entries.length} files...`);
+  try {
+    const results = await invoke('batch_update_metadata', { entries });
+    results.forEach((r, i) => {
+      tagFiles[i].status = r.success ? 'done' : 'error';
+      tagFiles[i].error = r.success ? null : r.message;
+    });
+    const failed = results.filter(r => !r.success).length;
+    setProgress(100);
+    setStatus(failed ? `Tagged ${results.length - failed} files, ${failed} failed` : `Tagged ${results.length} files`);
+  } catch (e) {
+    setStatus(errorText(e));
   }
+  renderTagList();
+  setTimeout(() => showProgress(false), 2000);
 });
 
 // ─── Batch ───────────────────────────────────────────────────────────────────
@@ -490,6 +663,8 @@ $('#btnBatchConvert').addEventListener('click', async () => {
           bitrate: null,
           sample_rate: null,
           channels: null,
+          bit_depth: null,
+          sample_fmt: null,
         }
       });
       f.status = result.success ? 'done' : 'error';
@@ -518,11 +693,61 @@ function formatSize(bytes) {
 }
 
 function setStatus(msg) { statusText.textContent = msg; }
+
+// Commands reject with { kind, message, ... }; say what to do about each kind
+function errorText(e) {
+  if (!e || typeof e !== 'object') return `Error: ${e}`;
+  switch (e.kind) {
+    case 'tool_not_found': return e.message;
+    case 'invalid_input': return `Can't do that: ${e.message}`;
+    case 'process_failed':
+      console.error(`${e.tool} output:\n${e.stderr_tail}`);
+      return `${e.message} (details in the console)`;
+    default: return `Error: ${e.message}`;
+  }
+}
+
 function showProgress(show) { progressBar.classList.toggle('hidden', !show); }
 function setProgress(pct) {
   progressFill.style.width = `${pct}%`;
   progressText.textContent = `${pct}%`;
 }
 
+// ─── Capabilities ────────────────────────────────────────────────────────────
+// Gray out formats the installed ffmpeg can't write
+async function loadCapabilities() {
+  try {
+    const caps = await invoke('check_capabilities');
+    for (const f of caps.formats) {
+      $$(`#convertFormat option[value="${f.format}"], #batchFormat option[value="${f.format}"]`).forEach(opt => {
+        opt.disabled = !f.available;
+        opt.title = f.reason || '';
+      });
+    }
+    for (const sel of [$('#convertFormat'), $('#batchFormat')]) {
+      if (sel.selectedOptions[0]?.disabled) {
+        const usable = [...sel.options].find(o => !o.disabled);
+        if (usable) sel.value = usable.value;
+      }
+    }
+  } catch (e) {
+    console.warn('Capability check failed:', e);
+  }
+}
+
+// Say up front what's missing and how to install it
+async function checkTools() {
+  try {
+    const missing = (await invoke('check_all_tools')).filter(t => !t.found);
+    if (missing.length) {
+      setStatus(missing.map(t => `${t.name} not found. ${t.install_hint}`).join(' · '));
+    }
+  } catch (e) {
+    console.warn('Tool check failed:', e);
+  }
+}
+
 // Init edit field visibility
 $('#editOp').dispatchEvent(new Event('change'));
+checkTools();
+loadCapabilities();
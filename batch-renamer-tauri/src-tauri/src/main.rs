@@ -4,7 +4,7 @@
 use chrono::Local;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
@@ -59,6 +59,40 @@ struct RenameResult {
     errors: Vec<String>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+struct PlannedRename {
+    index: usize,
+    original: String,
+    renamed: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RenameConflict {
+    index: usize,
+    original: String,
+    renamed: String,
+    kind: String, // "invalid", "duplicate", "exists", "cycle"
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RenamePlan {
+    operations: Vec<PlannedRename>,
+    conflicts: Vec<RenameConflict>,
+}
+
+#[derive(Debug, Serialize)]
+struct RenameError {
+    message: String,
+    plan: Option<RenamePlan>,
+}
+
+impl From<String> for RenameError {
+    fn from(message: String) -> Self {
+        RenameError { message, plan: None }
+    }
+}
+
 // ── Helpers ────────────────────────────────────────────────────────────────
 
 fn apply_rename(name: &str, mode: &RenameMode, index: usize) -> String {
@@ -129,6 +163,212 @@ fn apply_rename(name: &str, mode: &RenameMode, index: usize) -> String {
     }
 }
 
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) => ma.dev() == mb.dev() && ma.ino() == mb.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+}
+
+// Computes every rename up front and collects all conflicts instead of
+// stopping at the first one.
+fn build_plan(dir: &Path, files: &[String], mode: &RenameMode) -> RenamePlan {
+    let targets: Vec<String> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| apply_rename(f, mode, i))
+        .collect();
+
+    // Names that get vacated by the batch itself
+    let moving: HashSet<&str> = files
+        .iter()
+        .zip(&targets)
+        .filter(|(f, t)| f != t)
+        .map(|(f, _)| f.as_str())
+        .collect();
+
+    // Final name -> every file that ends up with it (unchanged files included)
+    let mut claims: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, t) in targets.iter().enumerate() {
+        claims.entry(t.as_str()).or_default().push(i);
+    }
+
+    let mut operations = Vec::new();
+    let mut conflicts = Vec::new();
+    for (i, (f, t)) in files.iter().zip(&targets).enumerate() {
+        if f == t {
+            continue;
+        }
+        let conflict = |kind: &str, message: String| RenameConflict {
+            index: i,
+            original: f.clone(),
+            renamed: t.clone(),
+            kind: kind.to_string(),
+            message,
+        };
+        let target_path = dir.join(t);
+        if t.is_empty() || t == "." || t == ".." || t.contains('/') || t.contains('\\') {
+            conflicts.push(conflict("invalid", format!("'{}' is not a valid file name", t)));
+        } else if claims[t.as_str()].len() > 1 {
+            let others: Vec<&str> = claims[t.as_str()]
+                .iter()
+                .filter(|&&j| j != i)
+                .map(|&j| files[j].as_str())
+                .collect();
+            conflicts.push(conflict(
+                "duplicate",
+                format!("'{}' and '{}' would both become '{}'", f, others.join("', '"), t),
+            ));
+        } else if target_path.exists()
+            && !moving.contains(t.as_str())
+            && !is_same_file(&dir.join(f), &target_path)
+        {
+            conflicts.push(conflict("exists", format!("'{}' already exists", t)));
+        }
+        operations.push(PlannedRename {
+            index: i,
+            original: f.clone(),
+            renamed: t.clone(),
+        });
+    }
+
+    for k in find_cycles(&operations) {
+        let op = &operations[k];
+        if conflicts.iter().any(|c| c.index == op.index) {
+            continue;
+        }
+        conflicts.push(RenameConflict {
+            index: op.index,
+            original: op.original.clone(),
+            renamed: op.renamed.clone(),
+            kind: "cycle".to_string(),
+            message: format!("'{}' → '{}' is part of a swap", op.original, op.renamed),
+        });
+    }
+    conflicts.sort_by_key(|c| c.index);
+
+    RenamePlan {
+        operations,
+        conflicts,
+    }
+}
+
+// Positions (into `ops`) of renames that form cycles, e.g. a → b, b → a
+fn find_cycles(ops: &[PlannedRename]) -> Vec<usize> {
+    let by_source: HashMap<&str, usize> = ops
+        .iter()
+        .enumerate()
+        .map(|(k, op)| (op.original.as_str(), k))
+        .collect();
+    let mut state = vec![0u8; ops.len()]; // 0 = unvisited, 1 = on current path, 2 = done
+    let mut in_cycle = Vec::new();
+
+    for start in 0..ops.len() {
+        let mut path = Vec::new();
+        let mut k = start;
+        loop {
+            if state[k] == 2 {
+                break;
+            }
+            if state[k] == 1 {
+                let pos = path.iter().position(|&p| p == k).unwrap_or(0);
+                in_cycle.extend_from_slice(&path[pos..]);
+                break;
+            }
+            state[k] = 1;
+            path.push(k);
+            match by_source.get(ops[k].renamed.as_str()) {
+                Some(&next) => k = next,
+                None => break,
+            }
+        }
+        for p in path {
+            state[p] = 2;
+        }
+    }
+    in_cycle
+}
+
+// Orders chained renames (a → b, b → c) so each target is vacated before it
+// is reused. Assumes the plan has no cycles.
+fn order_operations(ops: &[PlannedRename]) -> Vec<usize> {
+    let by_source: HashMap<&str, usize> = ops
+        .iter()
+        .enumerate()
+        .map(|(k, op)| (op.original.as_str(), k))
+        .collect();
+    let mut done = vec![false; ops.len()];
+    let mut order = Vec::with_capacity(ops.len());
+
+    for start in 0..ops.len() {
+        let mut chain = Vec::new();
+        let mut k = start;
+        while !done[k] {
+            done[k] = true;
+            chain.push(k);
+            match by_source.get(ops[k].renamed.as_str()) {
+                Some(&next) => k = next,
+                None => break,
+            }
+        }
+        order.extend(chain.into_iter().rev());
+    }
+    order
+}
+
+fn temp_name(dir: &Path, index: usize) -> PathBuf {
+    let mut attempt = 0;
+    loop {
+        let candidate = dir.join(format!(".~rename-{}-{}-{}", std::process::id(), index, attempt));
+        if !candidate.exists() {
+            return candidate;
+        }
+        attempt += 1;
+    }
+}
+
+// Two-phase moves through temporary names, so swaps and cycles work
+fn moves_via_temp(dir: &Path, pairs: &[(PathBuf, PathBuf)]) -> Vec<(PathBuf, PathBuf)> {
+    let temps: Vec<PathBuf> = (0..pairs.len()).map(|i| temp_name(dir, i)).collect();
+    let mut moves = Vec::with_capacity(pairs.len() * 2);
+    for ((from, _), tmp) in pairs.iter().zip(&temps) {
+        moves.push((from.clone(), tmp.clone()));
+    }
+    for ((_, to), tmp) in pairs.iter().zip(&temps) {
+        moves.push((tmp.clone(), to.clone()));
+    }
+    moves
+}
+
+// Runs the moves as one transaction: if any step fails, every completed
+// step is rolled back in reverse.
+fn run_moves(moves: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    for (done, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            let rollback_failures = moves[..done]
+                .iter()
+                .rev()
+                .filter(|(f, t)| fs::rename(t, f).is_err())
+                .count();
+            let mut msg = format!("{}: {}", from.display(), e);
+            if rollback_failures > 0 {
+                msg.push_str(&format!(" ({} step(s) could not be rolled back)", rollback_failures));
+            } else {
+                msg.push_str(" (no files were renamed)");
+            }
+            return Err(msg);
+        }
+    }
+    Ok(())
+}
+
 // ── Commands ───────────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -175,74 +415,92 @@ fn execute_rename(
     directory: String,
     files: Vec<String>,
     mode_json: String,
+    allow_temp_swap: Option<bool>,
     state: State<AppState>,
-) -> Result<RenameResult, String> {
+) -> Result<RenameResult, RenameError> {
     let mode: RenameMode = serde_json::from_str(&mode_json).map_err(|e| e.to_string())?;
     let dir = PathBuf::from(&directory);
+    let allow_temp_swap = allow_temp_swap.unwrap_or(false);
 
-    let mut success = 0u32;
-    let mut failed = 0u32;
-    let mut errors = Vec::new();
-    let mut records = Vec::new();
-
-    // Check for conflicts first
-    let mut targets: HashMap<String, String> = HashMap::new();
-    for (i, f) in files.iter().enumerate() {
-        let new_name = apply_rename(f, &mode, i);
-        if let Some(existing) = targets.get(&new_name) {
-            return Err(format!(
-                "Conflict: '{}' and '{}' would both become '{}'",
-                existing, f, new_name
-            ));
-        }
-        targets.insert(new_name, f.clone());
+    let plan = build_plan(&dir, &files, &mode);
+    let has_cycles = plan.conflicts.iter().any(|c| c.kind == "cycle");
+    let blocking = plan
+        .conflicts
+        .iter()
+        .filter(|c| !(allow_temp_swap && c.kind == "cycle"))
+        .count();
+    if blocking > 0 {
+        return Err(RenameError {
+            message: format!(
+                "{} conflict{} — nothing was renamed",
+                blocking,
+                if blocking == 1 { "" } else { "s" }
+            ),
+            plan: Some(plan),
+        });
     }
 
-    for (i, f) in files.iter().enumerate() {
-        let new_name = apply_rename(f, &mode, i);
-        if new_name == *f {
-            continue;
-        }
-        let old_path = dir.join(f);
-        let new_path = dir.join(&new_name);
-
-        match fs::rename(&old_path, &new_path) {
-            Ok(_) => {
-                success += 1;
-                records.push(RenameRecord {
-                    old_path: old_path.to_string_lossy().to_string(),
-                    new_path: new_path.to_string_lossy().to_string(),
-                });
-            }
-            Err(e) => {
-                failed += 1;
-                errors.push(format!("{}: {}", f, e));
-            }
-        }
+    let pairs: Vec<(PathBuf, PathBuf)> = order_operations(&plan.operations)
+        .into_iter()
+        .map(|k| {
+            let op = &plan.operations[k];
+            (dir.join(&op.original), dir.join(&op.renamed))
+        })
+        .collect();
+    let moves = if has_cycles {
+        moves_via_temp(&dir, &pairs)
+    } else {
+        pairs.clone()
+    };
+
+    if let Err(message) = run_moves(&moves) {
+        return Err(RenameError {
+            message,
+            plan: Some(plan),
+        });
     }
 
+    let records: Vec<RenameRecord> = pairs
+        .iter()
+        .map(|(old_path, new_path)| RenameRecord {
+            old_path: old_path.to_string_lossy().to_string(),
+            new_path: new_path.to_string_lossy().to_string(),
+        })
+        .collect();
+    let success = records.len() as u32;
     if !records.is_empty() {
         state.undo_stack.lock().unwrap().push(records);
     }
 
     Ok(RenameResult {
         success,
-        failed,
-        errors,
+        failed: 0,
+        errors: Vec::new(),
     })
 }
 
 #[tauri::command]
 fn undo_rename(state: State<AppState>) -> Result<u32, String> {
     let mut stack = state.undo_stack.lock().unwrap();
-    let records = stack.pop().ok_or("Nothing to undo")?;
-    let mut count = 0u32;
-    for rec in records.iter().rev() {
-        if let Err(e) = fs::rename(&rec.new_path, &rec.old_path) {
-            return Err(format!("Undo failed at {}: {}", rec.new_path, e));
-        }
-        count += 1;
+    let records = stack.last().ok_or("Nothing to undo")?;
+    let pairs: Vec<(PathBuf, PathBuf)> = records
+        .iter()
+        .rev()
+        .map(|rec| (PathBuf::from(&rec.new_path), PathBuf::from(&rec.old_path)))
+        .collect();
+    let vacated: HashSet<&PathBuf> = pairs.iter().map(|(from, _)| from).collect();
+    if let Some((_, to)) = pairs.iter().find(|(_, to)| to.exists() && !vacated.contains(to)) {
+        return Err(format!("Undo failed: {} already exists", to.display()));
     }
+    let dir = pairs
+        .first()
+        .and_then(|(p, _)| p.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    // Undo may reverse a swap, so always go through temporary names
+    run_moves(&moves_via_temp(&dir, &pairs)).map_err(|e| format!("Undo failed: {}", e))?;
+    let count = pairs.len() as u32;
+    stack.pop();
     Ok(count)
 }
 
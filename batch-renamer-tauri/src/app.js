@@ -21,6 +21,7 @@ const previewBody = $('#previewTable tbody');
 const progressBar = $('#progressBar');
 const progressFill = $('#progressFill');
 const statusEl = $('#status');
+const allowSwap = $('#allowSwap');
 
 // ── Mode tabs ──────────────────────────────────────────────
 $$('.tab').forEach(tab => {
@@ -114,7 +115,12 @@ renameBtn.addEventListener('click', async () => {
 
   try {
     progressFill.style.width = '70%';
-    const result = await invoke('execute_rename', { directory: currentDir, files, modeJson: getModeJson() });
+    const result = await invoke('execute_rename', {
+      directory: currentDir,
+      files,
+      modeJson: getModeJson(),
+      allowTempSwap: allowSwap.checked,
+    });
     progressFill.style.width = '100%';
 
     let msg = `✓ Renamed ${result.success} file${result.success !== 1 ? 's' : ''}`;
@@ -131,7 +137,8 @@ renameBtn.addEventListener('click', async () => {
       updateUndoCount();
     }, 400);
   } catch (e) {
-    showStatus(e, 'error');
+    showStatus(e.message || e, 'error');
+    if (e.plan) highlightConflicts(e.plan.conflicts);
     progressBar.classList.add('hidden');
     renameBtn.disabled = false;
   }
@@ -160,6 +167,16 @@ async function updateUndoCount() {
 }
 
 // ── Helpers ────────────────────────────────────────────────
+function highlightConflicts(conflicts) {
+  const rows = previewBody.querySelectorAll('tr');
+  conflicts.forEach(c => {
+    const tr = rows[c.index];
+    if (!tr) return;
+    tr.className = 'conflict';
+    tr.title = c.message;
+  });
+}
+
 function showStatus(msg, type) {
   if (!msg) { statusEl.classList.add('hidden'); return; }
   statusEl.textContent = msg;
@@ -2,6 +2,7 @@
 use tauri::Manager;
 use chrono::Local;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -42,6 +43,9 @@ pub enum RenameMode {
         suffix: String,
         start: usize,
         padding: usize,
+        // "name", "natural", "size" or "mtime"; list order when absent
+        #[serde(default)]
+        sort_by: Option<String>,
     },
     #[serde(rename = "date_stamp")]
     DateStamp {
@@ -59,6 +63,17 @@ pub enum RenameMode {
         replacement: String,
         apply_to: String,
     },
+    #[serde(rename = "sanitize")]
+    Sanitize {
+        // None keeps spaces as they are
+        replace_spaces_with: Option<String>,
+        strip_diacritics: bool,
+        // Extra characters to keep besides letters, digits, '-', '_' and '.'
+        #[serde(default)]
+        allowed: String,
+        #[serde(default)]
+        lowercase: bool,
+    },
 }
 
 // ─── Rename Logic ────────────────────────────────────────────────────────────
@@ -80,11 +95,8 @@ fn apply_rename(filename: &str, mode: &RenameMode, index: usize) -> String {
                 return filename.to_string();
             }
             if *use_regex {
-                match regex::Regex::new(find) {
-                    Ok(re) => {
-                        let new_name = re.replace_all(name, replace.as_str());
-                        format!("{}{}", new_name, ext)
-                    }
+                match regex_replace(find, replace, name) {
+                    Ok(new_name) => format!("{}{}", new_name, ext),
                     Err(_) => filename.to_string(),
                 }
             } else {
@@ -97,6 +109,7 @@ fn apply_rename(filename: &str, mode: &RenameMode, index: usize) -> String {
             suffix,
             start,
             padding,
+            ..
         } => {
             let num = format!("{:0>width$}", start + index, width = *padding);
             format!("{}{}{}{}", prefix, num, suffix, ext)
@@ -152,21 +165,434 @@ fn apply_rename(filename: &str, mode: &RenameMode, index: usize) -> String {
             if pattern.is_empty() {
                 return filename.to_string();
             }
-            match regex::Regex::new(pattern) {
-                Ok(re) => {
-                    if apply_to == "full" {
-                        re.replace_all(filename, replacement.as_str()).to_string()
-                    } else {
-                        let new_name = re.replace_all(name, replacement.as_str());
-                        format!("{}{}", new_name, ext)
+            if apply_to == "full" {
+                regex_replace(pattern, replacement, filename).unwrap_or_else(|_| filename.to_string())
+            } else {
+                match regex_replace(pattern, replacement, name) {
+                    Ok(new_name) => format!("{}{}", new_name, ext),
+                    Err(_) => filename.to_string(),
+                }
+            }
+        }
+        RenameMode::Sanitize {
+            replace_spaces_with,
+            strip_diacritics,
+            allowed,
+            lowercase,
+        } => {
+            let clean = sanitize_stem(
+                name,
+                replace_spaces_with.as_deref(),
+                *strip_diacritics,
+                allowed,
+                *lowercase,
+            );
+            if clean.is_empty() {
+                filename.to_string()
+            } else {
+                format!("{}{}", clean, ext)
+            }
+        }
+    }
+}
+
+// ─── Regex Templates ─────────────────────────────────────────────────────────
+
+// Replacement templates support $1 / ${1} / $name / ${name} group references,
+// $$ for a literal dollar, and Perl-style case transforms: \U and \L switch
+// case until \E, \u and \l change only the next character.
+#[derive(Debug)]
+enum TemplatePart {
+    Literal(String),
+    Group(String),
+    Upper,
+    Lower,
+    UpperNext,
+    LowerNext,
+    EndCase,
+}
+
+fn parse_template(template: &str, re: &regex::Regex) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => {
+                let group = match chars.peek().copied() {
+                    Some('$') => {
+                        chars.next();
+                        literal.push('$');
+                        continue;
+                    }
+                    Some('{') => {
+                        chars.next();
+                        let mut group = String::new();
+                        loop {
+                            match chars.next() {
+                                Some('}') => break,
+                                Some(ch) => group.push(ch),
+                                None => return Err(format!("Unclosed '${{{}' in replacement", group)),
+                            }
+                        }
+                        group
+                    }
+                    // $12 is always a number, so "$1_x" means group 1 followed by "_x"
+                    Some(ch) if ch.is_ascii_digit() => {
+                        let mut group = String::new();
+                        while let Some(d) = chars.peek().copied().filter(|d| d.is_ascii_digit()) {
+                            group.push(d);
+                            chars.next();
+                        }
+                        group
+                    }
+                    Some(ch) if ch.is_alphabetic() || ch == '_' => {
+                        let mut group = String::new();
+                        while let Some(d) = chars.peek().copied().filter(|d| d.is_alphanumeric() || *d == '_') {
+                            group.push(d);
+                            chars.next();
+                        }
+                        group
+                    }
+                    _ => {
+                        literal.push('$');
+                        continue;
+                    }
+                };
+
+                let known = match group.parse::<usize>() {
+                    Ok(i) => i < re.captures_len(),
+                    Err(_) => re.capture_names().flatten().any(|n| n == group),
+                };
+                if !known {
+                    return Err(format!("Replacement refers to unknown group '{}'", group));
+                }
+                if !literal.is_empty() {
+                    parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(TemplatePart::Group(group));
+            }
+            '\\' => {
+                let marker = match chars.peek().copied() {
+                    Some('U') => TemplatePart::Upper,
+                    Some('L') => TemplatePart::Lower,
+                    Some('u') => TemplatePart::UpperNext,
+                    Some('l') => TemplatePart::LowerNext,
+                    Some('E') => TemplatePart::EndCase,
+                    Some('\\') => {
+                        chars.next();
+                        literal.push('\\');
+                        continue;
                     }
+                    _ => {
+                        literal.push('\\');
+                        continue;
+                    }
+                };
+                chars.next();
+                if !literal.is_empty() {
+                    parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(marker);
+            }
+            _ => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+struct TemplateReplacer<'a> {
+    parts: &'a [TemplatePart],
+}
+
+impl regex::Replacer for TemplateReplacer<'_> {
+    fn replace_append(&mut self, caps: &regex::Captures<'_>, dst: &mut String) {
+        let mut mode: Option<bool> = None; // Some(true) = upper, Some(false) = lower
+        let mut next: Option<bool> = None;
+
+        for part in self.parts {
+            let text = match part {
+                TemplatePart::Literal(s) => s.as_str(),
+                TemplatePart::Group(g) => match g.parse::<usize>() {
+                    Ok(i) => caps.get(i).map_or("", |m| m.as_str()),
+                    Err(_) => caps.name(g).map_or("", |m| m.as_str()),
+                },
+                TemplatePart::Upper => {
+                    mode = Some(true);
+                    continue;
+                }
+                TemplatePart::Lower => {
+                    mode = Some(false);
+                    continue;
+                }
+                TemplatePart::UpperNext => {
+                    next = Some(true);
+                    continue;
+                }
+                TemplatePart::LowerNext => {
+                    next = Some(false);
+                    continue;
+                }
+                TemplatePart::EndCase => {
+                    mode = None;
+                    continue;
+                }
+            };
+            for ch in text.chars() {
+                match next.take().or(mode) {
+                    Some(true) => dst.extend(ch.to_uppercase()),
+                    Some(false) => dst.extend(ch.to_lowercase()),
+                    None => dst.push(ch),
                 }
-                Err(_) => filename.to_string(),
             }
         }
     }
 }
 
+fn regex_replace(pattern: &str, template: &str, text: &str) -> Result<String, String> {
+    let re = regex::Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+    let parts = parse_template(template, &re)?;
+    Ok(re
+        .replace_all(text, TemplateReplacer { parts: &parts })
+        .to_string())
+}
+
+// Surfaces bad patterns and templates up front instead of silently leaving
+// every name unchanged.
+fn validate_mode(mode: &RenameMode) -> Result<(), String> {
+    match mode {
+        RenameMode::FindReplace {
+            find,
+            replace,
+            use_regex: true,
+        } if !find.is_empty() => regex_replace(find, replace, "").map(|_| ()),
+        RenameMode::Regex {
+            pattern,
+            replacement,
+            ..
+        } if !pattern.is_empty() => regex_replace(pattern, replacement, "").map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+// ─── Sanitize ────────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+const ILLEGAL_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+#[cfg(target_os = "macos")]
+const ILLEGAL_CHARS: &[char] = &['/', ':'];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const ILLEGAL_CHARS: &[char] = &['/'];
+
+#[cfg(target_os = "windows")]
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+#[cfg(not(target_os = "windows"))]
+const RESERVED_NAMES: &[&str] = &[];
+
+fn transliterate(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ď' | 'đ' | 'ð' => "d",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĥ' | 'ħ' => "h",
+        'Ĥ' | 'Ħ' => "H",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ĵ' => "j",
+        'Ĵ' => "J",
+        'ķ' => "k",
+        'Ķ' => "K",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'Ţ' | 'Ť' | 'Ŧ' => "T",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ŵ' => "w",
+        'Ŵ' => "W",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'Ý' | 'Ÿ' | 'Ŷ' => "Y",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+fn sanitize_stem(
+    stem: &str,
+    replace_spaces_with: Option<&str>,
+    strip_diacritics: bool,
+    allowed: &str,
+    lowercase: bool,
+) -> String {
+    // Illegal characters become the space replacement, or '_' when spaces are kept
+    let sep = match replace_spaces_with {
+        Some(r) if !r.is_empty() => r,
+        _ => "_",
+    };
+    let is_sep = |c: char| c == ' ' || c == '_' || c == '-' || c == '.' || sep.contains(c);
+
+    let mut out = String::new();
+    for c in stem.chars() {
+        if c.is_whitespace() {
+            match replace_spaces_with {
+                Some(r) => out.push_str(r),
+                None => out.push(' '),
+            }
+        } else if ILLEGAL_CHARS.contains(&c) || c.is_control() {
+            out.push_str(sep);
+        } else if let Some(ascii) = transliterate(c).filter(|_| strip_diacritics) {
+            out.push_str(ascii);
+        } else if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || allowed.contains(c) {
+            out.push(c);
+        } else if c.is_ascii_punctuation() {
+            out.push_str(sep);
+        }
+        // Anything else (emoji, symbols) is dropped
+    }
+
+    // Collapse runs of separators to their first character and trim the ends
+    let mut collapsed = String::with_capacity(out.len());
+    let mut prev_sep = false;
+    for c in out.chars() {
+        if is_sep(c) {
+            if !prev_sep {
+                collapsed.push(c);
+            }
+            prev_sep = true;
+        } else {
+            collapsed.push(c);
+            prev_sep = false;
+        }
+    }
+    let mut result = collapsed.trim_matches(is_sep).to_string();
+
+    if lowercase {
+        result = result.to_lowercase();
+    }
+    if RESERVED_NAMES.contains(&result.to_uppercase().as_str()) {
+        result.push('_');
+    }
+    result
+}
+
+// Case-insensitive comparison that orders digit runs by numeric value,
+// so "file2" sorts before "file10".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let mut num_a = String::new();
+                while let Some(c) = a_chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+                    num_a.push(c);
+                    a_chars.next();
+                }
+                let mut num_b = String::new();
+                while let Some(c) = b_chars.peek().copied().filter(|c| c.is_ascii_digit()) {
+                    num_b.push(c);
+                    b_chars.next();
+                }
+                let trimmed_a = num_a.trim_start_matches('0');
+                let trimmed_b = num_b.trim_start_matches('0');
+                let ord = trimmed_a
+                    .len()
+                    .cmp(&trimmed_b.len())
+                    .then_with(|| trimmed_a.cmp(trimmed_b));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+// Numbering index for each file, in list order. When the numbering mode
+// has a `sort_by` key the index is the file's rank under that key instead.
+// Preview and execute both go through here so they always agree.
+fn numbering_indices(files: &[FileEntry], mode: &RenameMode) -> Result<Vec<usize>, String> {
+    let sort_by = match mode {
+        RenameMode::Numbering {
+            sort_by: Some(key), ..
+        } => key.as_str(),
+        _ => return Ok((0..files.len()).collect()),
+    };
+
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    match sort_by {
+        "name" => order.sort_by_key(|&i| files[i].name.to_lowercase()),
+        "natural" => order.sort_by(|&a, &b| natural_cmp(&files[a].name, &files[b].name)),
+        "size" => {
+            let sizes: Vec<u64> = files
+                .iter()
+                .map(|f| fs::metadata(&f.path).map(|m| m.len()).unwrap_or(0))
+                .collect();
+            order.sort_by_key(|&i| sizes[i]);
+        }
+        "mtime" => {
+            let mtimes: Vec<_> = files
+                .iter()
+                .map(|f| fs::metadata(&f.path).and_then(|m| m.modified()).ok())
+                .collect();
+            order.sort_by_key(|&i| mtimes[i]);
+        }
+        other => {
+            return Err(format!(
+                "Unknown sort key '{}', expected one of: name, natural, size, mtime",
+                other
+            ))
+        }
+    }
+
+    let mut indices = vec![0; files.len()];
+    for (rank, &i) in order.iter().enumerate() {
+        indices[i] = rank;
+    }
+    Ok(indices)
+}
+
 // ─── Commands ────────────────────────────────────────────────────────────────
 
 #[tauri::command]
@@ -214,12 +640,14 @@ fn validate_paths(paths: Vec<String>) -> Vec<FileEntry> {
 }
 
 #[tauri::command]
-fn preview_rename(files: Vec<FileEntry>, mode: RenameMode) -> Vec<PreviewItem> {
-    files
+fn preview_rename(files: Vec<FileEntry>, mode: RenameMode) -> Result<Vec<PreviewItem>, String> {
+    validate_mode(&mode)?;
+    let indices = numbering_indices(&files, &mode)?;
+    Ok(files
         .iter()
         .enumerate()
         .map(|(i, f)| {
-            let new_name = apply_rename(&f.name, &mode, i);
+            let new_name = apply_rename(&f.name, &mode, indices[i]);
             let changed = new_name != f.name;
             PreviewItem {
                 path: f.path.clone(),
@@ -228,7 +656,7 @@ fn preview_rename(files: Vec<FileEntry>, mode: RenameMode) -> Vec<PreviewItem> {
                 changed,
             }
         })
-        .collect()
+        .collect())
 }
 
 #[tauri::command]
@@ -236,11 +664,18 @@ fn execute_rename(files: Vec<FileEntry>, mode: RenameMode) -> RenameResult {
     let mut renamed = 0;
     let mut errors = Vec::new();
 
+    let indices = match validate_mode(&mode).and_then(|_| numbering_indices(&files, &mode)) {
+        Ok(indices) => indices,
+        Err(e) => {
+            errors.push(e);
+            return RenameResult { renamed, errors };
+        }
+    };
     let previews: Vec<_> = files
         .iter()
         .enumerate()
         .map(|(i, f)| {
-            let new_name = apply_rename(&f.name, &mode, i);
+            let new_name = apply_rename(&f.name, &mode, indices[i]);
             (f, new_name)
         })
         .collect();
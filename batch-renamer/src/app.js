@@ -102,6 +102,7 @@ function buildMode() {
         suffix: $('#numSuffix').value,
         start: parseInt($('#numStart').value) || 1,
         padding: parseInt($('#numPadding').value) || 3,
+        sort_by: document.querySelector('input[name="numSort"]:checked')?.value || null,
       };
     case 'date_stamp':
       return {
@@ -127,6 +128,14 @@ function buildMode() {
         replacement: $('#regReplace').value,
         apply_to: document.querySelector('input[name="regScope"]:checked')?.value || 'name',
       };
+    case 'sanitize':
+      return {
+        mode: 'sanitize',
+        replace_spaces_with: $('#sanSpaces').value || null,
+        strip_diacritics: $('#sanDiacritics').checked,
+        allowed: $('#sanAllowed').value,
+        lowercase: $('#sanLower').checked,
+      };
   }
 }
 
@@ -194,7 +203,13 @@ async function doRename() {
   if (files.length === 0) return;
 
   const mode = buildMode();
-  const items = await invoke('preview_rename', { files, mode });
+  let items;
+  try {
+    items = await invoke('preview_rename', { files, mode });
+  } catch (err) {
+    showToast(`${err}`, true);
+    return;
+  }
   const changedCount = items.filter(i => i.changed).length;
 
   if (changedCount === 0) {
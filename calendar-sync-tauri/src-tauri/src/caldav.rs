@@ -2,6 +2,24 @@ use reqwest::blocking::Client;
 use crate::models::CalendarEvent;
 use crate::ics;
 
+/// One `<response>` of a WebDAV multistatus reply
+#[derive(Debug, Clone)]
+pub struct DavResource {
+    pub href: String,
+    pub etag: Option<String>,
+    pub calendar_data: Option<String>,
+}
+
+/// Result of a conditional DELETE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    Deleted,
+    /// 404/410 — someone else already removed it
+    AlreadyGone,
+    /// 412 — the event changed on the server since we saw it
+    Conflict,
+}
+
 /// CalDAV client for fetching calendars
 pub struct CalDavClient {
     url: String,
@@ -48,14 +66,30 @@ impl CalDavClient {
         Ok(hrefs)
     }
 
-    /// Fetch events from a calendar via REPORT
-    pub fn fetch_events(&self, calendar_path: &str, source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
-        let url = if calendar_path.starts_with("http") {
+    fn calendar_url(&self, calendar_path: &str) -> String {
+        if calendar_path.starts_with("http") {
             calendar_path.to_string()
         } else {
             format!("{}{}", self.url.trim_end_matches('/'), calendar_path)
-        };
+        }
+    }
 
+    fn report(&self, url: &str, body: String) -> Result<Vec<DavResource>, Box<dyn std::error::Error>> {
+        let response = self.client
+            .request(reqwest::Method::from_bytes(b"REPORT").unwrap(), url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml")
+            .body(body)
+            .send()?
+            .error_for_status()?;
+
+        let text = response.text()?;
+        Ok(parse_multistatus(&text))
+    }
+
+    /// Fetch events from a calendar via REPORT
+    pub fn fetch_events(&self, calendar_path: &str, source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
         let body = r#"<?xml version="1.0" encoding="utf-8"?>
 <c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
   <d:prop>
@@ -69,57 +103,213 @@ impl CalDavClient {
   </c:filter>
 </c:calendar-query>"#;
 
-        let response = self.client
-            .request(reqwest::Method::from_bytes(b"REPORT").unwrap(), &url)
-            .basic_auth(&self.username, Some(&self.password))
-            .header("Depth", "1")
-            .header("Content-Type", "application/xml")
-            .body(body)
-            .send()?;
+        let resources = self.report(&self.calendar_url(calendar_path), body.to_string())?;
+        Ok(events_from(resources, source_id))
+    }
 
-        let text = response.text()?;
-        let mut all_events = Vec::new();
-
-        // Extract calendar-data from response
-        for segment in text.split("calendar-data>").skip(1).step_by(2) {
-            if let Some(ics_data) = segment.split("</").next() {
-                let decoded = ics_data
-                    .replace("&lt;", "<")
-                    .replace("&gt;", ">")
-                    .replace("&amp;", "&");
-                if let Ok(events) = ics::parse_ics(&decoded, source_id) {
-                    all_events.extend(events);
-                }
-            }
-        }
+    /// List every event resource with its current ETag, without calendar data
+    pub fn list_etags(&self, calendar_path: &str) -> Result<Vec<DavResource>, Box<dyn std::error::Error>> {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT"/>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
 
-        Ok(all_events)
+        let resources = self.report(&self.calendar_url(calendar_path), body.to_string())?;
+        // The collection itself can show up in the listing
+        Ok(resources
+            .into_iter()
+            .filter(|r| r.etag.as_deref().is_some_and(|e| !e.is_empty()))
+            .collect())
     }
 
-    /// Upload an event to CalDAV server
-    pub fn put_event(&self, calendar_path: &str, event: &CalendarEvent) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("{}{}/{}.ics",
+    /// Fetch only the given resources via calendar-multiget
+    pub fn fetch_hrefs(&self, calendar_path: &str, hrefs: &[String], source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+        let href_list: String = hrefs
+            .iter()
+            .map(|h| format!("  <d:href>{}</d:href>\n", xml_escape(h)))
+            .collect();
+        let body = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+{}</c:calendar-multiget>"#, href_list);
+
+        let resources = self.report(&self.calendar_url(calendar_path), body)?;
+        Ok(events_from(resources, source_id))
+    }
+
+    fn event_url(&self, calendar_path: &str, uid: &str) -> String {
+        format!("{}{}/{}.ics",
             self.url.trim_end_matches('/'),
             calendar_path,
-            event.uid
-        );
+            uid
+        )
+    }
 
-        let ics_content = format!(
-            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:{}\r\nSUMMARY:{}\r\nDTSTART:{}\r\n{}{}\r\nEND:VEVENT\r\nEND:VCALENDAR",
-            event.uid,
-            event.summary,
-            event.dtstart,
-            event.dtend.as_ref().map(|d| format!("DTEND:{}\r\n", d)).unwrap_or_default(),
-            event.description.as_ref().map(|d| format!("DESCRIPTION:{}\r\n", d)).unwrap_or_default(),
-        );
+    /// Full URL of a resource from the href the server listed it under.
+    /// Hrefs are usually absolute paths and needn't match the event's UID.
+    fn resource_url(&self, href: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let base = reqwest::Url::parse(&format!("{}/", self.url.trim_end_matches('/')))?;
+        Ok(base.join(href)?.to_string())
+    }
+
+    /// Upload an event to CalDAV server. An event that already exists there
+    /// is written to its href; a new one is created as `<uid>.ics`.
+    /// Returns the new ETag if the server sent one.
+    pub fn put_event(&self, calendar_path: &str, event: &CalendarEvent) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let url = match &event.href {
+            Some(href) => self.resource_url(href)?,
+            None => self.event_url(calendar_path, &event.uid),
+        };
 
-        self.client
+        let ics_content = ics::write_ics(std::slice::from_ref(event));
+
+        let response = self.client
             .put(&url)
             .basic_auth(&self.username, Some(&self.password))
             .header("Content-Type", "text/calendar")
             .body(ics_content)
-            .send()?;
+            .send()?
+            .error_for_status()?;
+
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        Ok(etag)
+    }
+
+    /// Delete the resource at `href` from the CalDAV server. With an ETag the
+    /// server only deletes it if nobody edited the event since we last fetched it.
+    pub fn delete_event(&self, href: &str, etag: Option<&str>) -> Result<DeleteOutcome, Box<dyn std::error::Error>> {
+        let url = self.resource_url(href)?;
+
+        let mut request = self.client
+            .delete(&url)
+            .basic_auth(&self.username, Some(&self.password));
+        if let Some(etag) = etag {
+            request = request.header("If-Match", etag);
+        }
+        let response = request.send()?;
+
+        match response.status().as_u16() {
+            200..=299 => Ok(DeleteOutcome::Deleted),
+            404 | 410 => Ok(DeleteOutcome::AlreadyGone),
+            412 => Ok(DeleteOutcome::Conflict),
+            code => Err(format!("DELETE {} failed with HTTP {}", url, code).into()),
+        }
+    }
+}
+
+/// Parse each resource's calendar data, tagging events with where they came from
+fn events_from(resources: Vec<DavResource>, source_id: &str) -> Vec<CalendarEvent> {
+    let mut all_events = Vec::new();
+    for resource in resources {
+        let Some(data) = resource.calendar_data else {
+            continue;
+        };
+        if let Ok(events) = ics::parse_ics(&data, source_id) {
+            all_events.extend(events.into_iter().map(|mut event| {
+                event.href = Some(resource.href.clone());
+                event.etag = resource.etag.clone();
+                event
+            }));
+        }
+    }
+    all_events
+}
+
+// Simple multistatus parsing (production would use proper XML parsing).
+// Servers pick their own namespace prefixes (d:, D:, none), so elements are
+// matched on their local name.
+fn parse_multistatus(xml: &str) -> Vec<DavResource> {
+    elements(xml, "response")
+        .into_iter()
+        .filter_map(|response| {
+            let href = elements(response, "href").first().map(|h| xml_unescape(h.trim()))?;
+            Some(DavResource {
+                href,
+                etag: elements(response, "getetag").first().map(|e| xml_unescape(e.trim())),
+                calendar_data: elements(response, "calendar-data")
+                    .first()
+                    .map(|d| xml_unescape(d))
+                    .filter(|d| !d.trim().is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Contents of every `<prefix:name>…</prefix:name>` element
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = xml[pos..].find('<') {
+        let start = pos + offset + 1;
+        let Some(len) = xml[start..].find('>') else {
+            break;
+        };
+        let tag = &xml[start..start + len];
+        pos = start + len + 1;
+
+        let tag_name = tag.split_whitespace().next().unwrap_or("").trim_end_matches('/');
+        if tag_name.starts_with('/') || tag_name.rsplit(':').next() != Some(name) {
+            continue;
+        }
+        if tag.ends_with('/') {
+            found.push("");
+            continue;
+        }
+        let close = format!("</{}>", tag_name);
+        if let Some(end) = xml[pos..].find(&close) {
+            found.push(&xml[pos..pos + end]);
+            pos += end + close.len();
+        }
+    }
+    found
+}
 
-        Ok(())
+fn xml_unescape(s: &str) -> String {
+    let s = s.trim_start_matches("<![CDATA[").trim_end_matches("]]>");
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_url_uses_server_href() {
+        let client = CalDavClient::new("https://caldav.example.com/cal/user/events", "u", "p");
+        assert_eq!(
+            client.resource_url("/cal/user/events/abc123.ics").unwrap(),
+            "https://caldav.example.com/cal/user/events/abc123.ics"
+        );
+        assert_eq!(
+            client.resource_url("abc123.ics").unwrap(),
+            "https://caldav.example.com/cal/user/events/abc123.ics"
+        );
+        assert_eq!(
+            client.resource_url("https://p01.example.net/1/events/x.ics").unwrap(),
+            "https://p01.example.net/1/events/x.ics"
+        );
     }
 }
@@ -1,9 +1,11 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, params, params_from_iter};
 use std::sync::Mutex;
 use std::path::PathBuf;
 use once_cell::sync::Lazy;
+use chrono::{DateTime, Utc};
 
-use crate::models::{CalendarSource, LogEntry, CalendarEvent};
+use crate::models::{CalendarSource, LogEntry, CalendarEvent, DeletedEvent, SyncConflict};
+use crate::{ics, recurrence};
 
 fn db_path() -> PathBuf {
     let mut path = dirs_next().unwrap_or_else(|| PathBuf::from("."));
@@ -43,14 +45,30 @@ pub fn init() -> Result<(), Box<dyn std::error::Error>> {
             url TEXT
         );
         CREATE TABLE IF NOT EXISTS events (
-            uid TEXT PRIMARY KEY,
+            uid TEXT NOT NULL,
             summary TEXT NOT NULL,
             description TEXT,
             dtstart TEXT NOT NULL,
             dtend TEXT,
             location TEXT,
             source_id TEXT NOT NULL,
-            last_modified TEXT NOT NULL
+            last_modified TEXT NOT NULL,
+            PRIMARY KEY (uid, source_id)
+        );
+        CREATE TABLE IF NOT EXISTS deleted_events (
+            uid TEXT NOT NULL,
+            source_id TEXT NOT NULL,
+            deleted_at TEXT NOT NULL,
+            PRIMARY KEY (uid, source_id)
+        );
+        CREATE TABLE IF NOT EXISTS pending_conflicts (
+            uid TEXT PRIMARY KEY,
+            conflict TEXT NOT NULL,
+            detected_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
         );
         CREATE TABLE IF NOT EXISTS log (
             id TEXT PRIMARY KEY,
@@ -60,60 +78,347 @@ pub fn init() -> Result<(), Box<dyn std::error::Error>> {
             level TEXT NOT NULL DEFAULT 'info'
         );"
     )?;
+    add_column_if_missing(&conn, "events", "href", "TEXT")?;
+    add_column_if_missing(&conn, "events", "etag", "TEXT")?;
+    add_column_if_missing(&conn, "events", "rrule", "TEXT")?;
+    add_column_if_missing(&conn, "events", "alarms", "TEXT")?;
+    add_column_if_missing(&conn, "sources", "last_synced", "TEXT")?;
+    add_column_if_missing(&conn, "events", "tzid", "TEXT")?;
+    add_column_if_missing(&conn, "events", "all_day", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "events", "start_utc", "TEXT")?;
+    add_column_if_missing(&conn, "events", "end_utc", "TEXT")?;
+    add_column_if_missing(&conn, "sources", "color", "TEXT")?;
+    add_column_if_missing(&conn, "sources", "enabled", "INTEGER NOT NULL DEFAULT 1")?;
+    key_events_by_source(&conn)?;
+    backfill_utc(&conn)?;
+    Ok(())
+}
+
+// Older databases keyed events on UID alone, so a copy of an event in a
+// second source replaced the original's row along with its href and ETag.
+// SQLite can't change a primary key in place; the table is rebuilt instead.
+fn key_events_by_source(conn: &Connection) -> rusqlite::Result<()> {
+    let keyed_by_source = conn
+        .prepare("PRAGMA table_info(events)")?
+        .query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, i64>(5)?)))?
+        .filter_map(|r| r.ok())
+        .any(|(name, pk)| name == "source_id" && pk > 0);
+    if keyed_by_source {
+        return Ok(());
+    }
+    conn.execute_batch(&format!(
+        "BEGIN;
+        ALTER TABLE events RENAME TO events_by_uid;
+        CREATE TABLE events (
+            uid TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            description TEXT,
+            dtstart TEXT NOT NULL,
+            dtend TEXT,
+            location TEXT,
+            source_id TEXT NOT NULL,
+            last_modified TEXT NOT NULL,
+            href TEXT,
+            etag TEXT,
+            rrule TEXT,
+            alarms TEXT,
+            tzid TEXT,
+            all_day INTEGER NOT NULL DEFAULT 0,
+            start_utc TEXT,
+            end_utc TEXT,
+            PRIMARY KEY (uid, source_id)
+        );
+        INSERT INTO events ({0}) SELECT {0} FROM events_by_uid;
+        DROP TABLE events_by_uid;
+        COMMIT;",
+        EVENT_COLUMNS
+    ))
+}
+
+// Events stored before times were normalized. Their TZID wasn't kept, so
+// local times are read as floating.
+fn backfill_utc(conn: &Connection) -> rusqlite::Result<()> {
+    let rows: Vec<(String, String, String, Option<String>)> = conn
+        .prepare("SELECT uid, source_id, dtstart, dtend FROM events WHERE start_utc IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for (uid, source_id, dtstart, dtend) in rows {
+        conn.execute(
+            "UPDATE events SET all_day = ?3, start_utc = ?4, end_utc = ?5 WHERE uid = ?1 AND source_id = ?2",
+            params![
+                uid,
+                source_id,
+                ics::is_date(&dtstart),
+                ics::to_utc(&dtstart, None).map(|t| t.to_rfc3339()),
+                dtend.as_deref().and_then(|d| ics::to_utc(d, None)).map(|t| t.to_rfc3339()),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// CREATE TABLE IF NOT EXISTS leaves databases from older versions alone,
+// so columns added later have to be patched in
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let exists = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
     Ok(())
 }
 
 pub fn insert_source(source: &CalendarSource) -> Result<(), Box<dyn std::error::Error>> {
     let conn = DB.lock().unwrap();
     conn.execute(
-        "INSERT INTO sources (id, source_type, name, config, added_at, url) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![source.id, source.source_type, source.name, source.config, source.added_at, source.url],
+        "INSERT INTO sources (id, source_type, name, config, added_at, url, color, enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![source.id, source.source_type, source.name, source.config, source.added_at, source.url, source.color, source.enabled],
     )?;
     Ok(())
 }
 
+pub fn update_source(source: &CalendarSource) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute(
+        "UPDATE sources SET source_type = ?2, name = ?3, config = ?4, url = ?5, color = ?6, enabled = ?7 WHERE id = ?1",
+        params![source.id, source.source_type, source.name, source.config, source.url, source.color, source.enabled],
+    )?;
+    Ok(())
+}
+
+const SOURCE_COLUMNS: &str = "id, source_type, name, config, added_at, url, last_synced, color, enabled";
+
+fn source_from_row(row: &rusqlite::Row) -> rusqlite::Result<CalendarSource> {
+    Ok(CalendarSource {
+        id: row.get(0)?,
+        source_type: row.get(1)?,
+        name: row.get(2)?,
+        config: row.get(3)?,
+        added_at: row.get(4)?,
+        url: row.get(5)?,
+        last_synced: row.get(6)?,
+        color: row.get(7)?,
+        enabled: row.get(8)?,
+    })
+}
+
 pub fn get_sources() -> Result<Vec<CalendarSource>, Box<dyn std::error::Error>> {
     let conn = DB.lock().unwrap();
-    let mut stmt = conn.prepare("SELECT id, source_type, name, config, added_at, url FROM sources")?;
-    let sources = stmt.query_map([], |row| {
-        Ok(CalendarSource {
-            id: row.get(0)?,
-            source_type: row.get(1)?,
-            name: row.get(2)?,
-            config: row.get(3)?,
-            added_at: row.get(4)?,
-            url: row.get(5)?,
-        })
-    })?.filter_map(|r| r.ok()).collect();
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sources", SOURCE_COLUMNS))?;
+    let sources = stmt.query_map([], source_from_row)?.filter_map(|r| r.ok()).collect();
     Ok(sources)
 }
 
+pub fn get_source(id: &str) -> Result<Option<CalendarSource>, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sources WHERE id = ?1", SOURCE_COLUMNS))?;
+    let source = stmt.query_map(params![id], source_from_row)?.filter_map(|r| r.ok()).next();
+    Ok(source)
+}
+
+pub fn set_last_synced(source_id: &str, timestamp: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute("UPDATE sources SET last_synced = ?1 WHERE id = ?2", params![timestamp, source_id])?;
+    Ok(())
+}
+
 pub fn insert_event(event: &CalendarEvent) -> Result<(), Box<dyn std::error::Error>> {
+    let alarms = serde_json::to_string(&event.alarms)?;
     let conn = DB.lock().unwrap();
     conn.execute(
-        "INSERT OR REPLACE INTO events (uid, summary, description, dtstart, dtend, location, source_id, last_modified)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![event.uid, event.summary, event.description, event.dtstart, event.dtend, event.location, event.source_id, event.last_modified],
+        "INSERT OR REPLACE INTO events (uid, summary, description, dtstart, dtend, location, source_id, last_modified, href, etag, rrule, alarms, tzid, all_day, start_utc, end_utc)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            event.uid, event.summary, event.description, event.dtstart, event.dtend, event.location, event.source_id, event.last_modified, event.href, event.etag, event.rrule, alarms,
+            event.tzid, event.all_day, event.start_utc.map(|t| t.to_rfc3339()), event.end_utc.map(|t| t.to_rfc3339()),
+        ],
     )?;
     Ok(())
 }
 
+const EVENT_COLUMNS: &str = "uid, summary, description, dtstart, dtend, location, source_id, last_modified, href, etag, rrule, alarms, tzid, all_day, start_utc, end_utc";
+
+fn utc_column(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    Ok(row
+        .get::<_, Option<String>>(index)?
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc)))
+}
+
+fn event_from_row(row: &rusqlite::Row) -> rusqlite::Result<CalendarEvent> {
+    Ok(CalendarEvent {
+        uid: row.get(0)?,
+        summary: row.get(1)?,
+        description: row.get(2)?,
+        dtstart: row.get(3)?,
+        dtend: row.get(4)?,
+        location: row.get(5)?,
+        source_id: row.get(6)?,
+        last_modified: row.get(7)?,
+        href: row.get(8)?,
+        etag: row.get(9)?,
+        rrule: row.get(10)?,
+        // Stored as JSON
+        alarms: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        tzid: row.get(12)?,
+        all_day: row.get(13)?,
+        start_utc: utc_column(row, 14)?,
+        end_utc: utc_column(row, 15)?,
+    })
+}
+
 pub fn get_events_by_source(source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
     let conn = DB.lock().unwrap();
-    let mut stmt = conn.prepare("SELECT uid, summary, description, dtstart, dtend, location, source_id, last_modified FROM events WHERE source_id = ?1")?;
-    let events = stmt.query_map(params![source_id], |row| {
-        Ok(CalendarEvent {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM events WHERE source_id = ?1", EVENT_COLUMNS))?;
+    let events = stmt.query_map(params![source_id], event_from_row)?.filter_map(|r| r.ok()).collect();
+    Ok(events)
+}
+
+pub fn get_all_events() -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM events ORDER BY start_utc", EVENT_COLUMNS))?;
+    let events = stmt.query_map([], event_from_row)?.filter_map(|r| r.ok()).collect();
+    Ok(events)
+}
+
+/// Events starting in [from, to), with recurring events expanded into one
+/// entry per occurrence, sorted by start. `text` is matched against summary
+/// and description.
+pub fn query_events(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    source_ids: Option<&[String]>,
+    text: Option<&str>,
+) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+    let mut sql = format!("SELECT {} FROM events WHERE 1 = 1", EVENT_COLUMNS);
+    let mut args: Vec<String> = Vec::new();
+    if let Some(ids) = source_ids {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        sql.push_str(&format!(" AND source_id IN ({})", vec!["?"; ids.len()].join(", ")));
+        args.extend(ids.iter().cloned());
+    }
+    if let Some(text) = text.map(str::trim).filter(|t| !t.is_empty()) {
+        let pattern = format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        sql.push_str(" AND (summary LIKE ? ESCAPE '\\' OR description LIKE ? ESCAPE '\\')");
+        args.push(pattern.clone());
+        args.push(pattern);
+    }
+
+    let events: Vec<CalendarEvent> = {
+        let conn = DB.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), event_from_row)?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    // Recurring events can only be matched against the range once expanded
+    let mut found: Vec<CalendarEvent> = events
+        .iter()
+        .flat_map(|event| recurrence::expand(event, from, to))
+        .collect();
+    found.sort_by_key(|event| event.start_utc);
+    Ok(found)
+}
+
+/// Remove an event, with its copies in every source, locally and remember the
+/// deletion so the next two-way sync can remove it from the servers.
+/// Returns false if it didn't exist.
+pub fn delete_event(uid: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let source_id: Option<String> = conn
+        .query_row("SELECT source_id FROM events WHERE uid = ?1", params![uid], |row| row.get(0))
+        .ok();
+    let Some(source_id) = source_id else {
+        return Ok(false);
+    };
+
+    conn.execute("DELETE FROM events WHERE uid = ?1", params![uid])?;
+    conn.execute(
+        "INSERT OR REPLACE INTO deleted_events (uid, source_id, deleted_at) VALUES (?1, ?2, ?3)",
+        params![uid, source_id, chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()],
+    )?;
+    Ok(true)
+}
+
+/// Remove a source's copy of an event that disappeared from it, without a tombstone
+pub fn remove_event(uid: &str, source_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute("DELETE FROM events WHERE uid = ?1 AND source_id = ?2", params![uid, source_id])?;
+    Ok(())
+}
+
+pub fn get_deleted_events() -> Result<Vec<DeletedEvent>, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT uid, source_id, deleted_at FROM deleted_events")?;
+    let deleted = stmt.query_map([], |row| {
+        Ok(DeletedEvent {
             uid: row.get(0)?,
-            summary: row.get(1)?,
-            description: row.get(2)?,
-            dtstart: row.get(3)?,
-            dtend: row.get(4)?,
-            location: row.get(5)?,
-            source_id: row.get(6)?,
-            last_modified: row.get(7)?,
+            source_id: row.get(1)?,
+            deleted_at: row.get(2)?,
         })
     })?.filter_map(|r| r.ok()).collect();
-    Ok(events)
+    Ok(deleted)
+}
+
+pub fn clear_deleted_event(uid: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute("DELETE FROM deleted_events WHERE uid = ?1", params![uid])?;
+    Ok(())
+}
+
+/// Park a conflict until the user picks a side. A newer conflict on the
+/// same event replaces the older one.
+pub fn save_pending_conflict(conflict: &SyncConflict) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string(conflict)?;
+    let conn = DB.lock().unwrap();
+    conn.execute(
+        "INSERT OR REPLACE INTO pending_conflicts (uid, conflict, detected_at) VALUES (?1, ?2, ?3)",
+        params![conflict.event_uid, json, chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()],
+    )?;
+    Ok(())
+}
+
+pub fn get_pending_conflicts() -> Result<Vec<SyncConflict>, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT conflict FROM pending_conflicts ORDER BY detected_at")?;
+    let conflicts = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    Ok(conflicts)
+}
+
+pub fn clear_pending_conflict(uid: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute("DELETE FROM pending_conflicts WHERE uid = ?1", params![uid])?;
+    Ok(())
+}
+
+pub fn get_setting(key: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    let value = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+        .ok();
+    Ok(value)
+}
+
+pub fn set_setting(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let conn = DB.lock().unwrap();
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+    Ok(())
 }
 
 pub fn insert_log(entry: &LogEntry) -> Result<(), Box<dyn std::error::Error>> {
@@ -145,3 +450,51 @@ pub fn clear_log() -> Result<(), Box<dyn std::error::Error>> {
     conn.execute("DELETE FROM log", [])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_events_by_source_keeps_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events (
+                uid TEXT PRIMARY KEY,
+                summary TEXT NOT NULL,
+                description TEXT,
+                dtstart TEXT NOT NULL,
+                dtend TEXT,
+                location TEXT,
+                source_id TEXT NOT NULL,
+                last_modified TEXT NOT NULL
+            );
+            INSERT INTO events VALUES ('u1', 'Standup', NULL, '20240101T090000Z', NULL, NULL, 'a', '20240101T000000Z');",
+        )
+        .unwrap();
+        for (column, decl) in [("href", "TEXT"), ("etag", "TEXT"), ("rrule", "TEXT"), ("alarms", "TEXT"), ("tzid", "TEXT"),
+            ("all_day", "INTEGER NOT NULL DEFAULT 0"), ("start_utc", "TEXT"), ("end_utc", "TEXT")]
+        {
+            add_column_if_missing(&conn, "events", column, decl).unwrap();
+        }
+        conn.execute("UPDATE events SET href = '/cal/u1.ics', etag = '\"1\"'", []).unwrap();
+
+        key_events_by_source(&conn).unwrap();
+        // Running it again on the new table is a no-op
+        key_events_by_source(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO events (uid, summary, dtstart, source_id, last_modified) VALUES ('u1', 'Standup', '20240101T090000Z', 'b', '20240101T000000Z')",
+            [],
+        )
+        .unwrap();
+        let rows: Vec<(String, Option<String>)> = conn
+            .prepare("SELECT source_id, href FROM events WHERE uid = 'u1' ORDER BY source_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, [("a".to_string(), Some("/cal/u1.ics".to_string())), ("b".to_string(), None)]);
+    }
+}
@@ -1,6 +1,9 @@
-use icalendar::{Calendar, Component, EventLike};
-use crate::models::CalendarEvent;
-use chrono::Utc;
+use icalendar::{Calendar, Component, Event, EventLike, Property};
+use crate::models::{Alarm, CalendarEvent};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+const PRODID: &str = "-//CORE Tools//Calendar Sync//EN";
 
 /// Parse ICS content string into CalendarEvents
 pub fn parse_ics(content: &str, source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
@@ -19,9 +22,21 @@ pub fn parse_ics(content: &str, source_id: &str) -> Result<Vec<CalendarEvent>, B
                 .to_string();
             let dtend = event.property_value("DTEND")
                 .map(String::from);
+            let tzid = param(event, "DTSTART", "TZID");
+            let end_tzid = param(event, "DTEND", "TZID").or_else(|| tzid.clone());
+            let all_day = is_date(&dtstart) || param(event, "DTSTART", "VALUE").as_deref() == Some("DATE");
+            let start_utc = to_utc(&dtstart, tzid.as_deref());
+            let end_utc = dtend.as_deref().and_then(|d| to_utc(d, end_tzid.as_deref()));
             let last_modified = event.property_value("LAST-MODIFIED")
                 .map(String::from)
                 .unwrap_or_else(|| Utc::now().format("%Y%m%dT%H%M%SZ").to_string());
+            let rrule = event.property_value("RRULE")
+                .map(String::from);
+            let alarms = event.components()
+                .iter()
+                .filter(|c| c.component_kind() == "VALARM")
+                .filter_map(parse_alarm)
+                .collect();
 
             events.push(CalendarEvent {
                 uid,
@@ -32,6 +47,14 @@ pub fn parse_ics(content: &str, source_id: &str) -> Result<Vec<CalendarEvent>, B
                 location,
                 source_id: source_id.to_string(),
                 last_modified,
+                href: None,
+                etag: None,
+                rrule,
+                alarms,
+                tzid,
+                all_day,
+                start_utc,
+                end_utc,
             });
         }
     }
@@ -39,6 +62,159 @@ pub fn parse_ics(content: &str, source_id: &str) -> Result<Vec<CalendarEvent>, B
     Ok(events)
 }
 
+fn param(event: &Event, property: &str, name: &str) -> Option<String> {
+    event
+        .properties()
+        .get(property)?
+        .params()
+        .get(name)
+        .map(|p| p.value().trim_matches('"').to_string())
+}
+
+fn parse_alarm(valarm: &impl Component) -> Option<Alarm> {
+    let trigger = valarm.properties().get("TRIGGER")?;
+    Some(Alarm {
+        trigger: trigger.value().to_string(),
+        related: trigger.params().get("RELATED").map(|p| p.value().to_string()),
+        action: valarm.property_value("ACTION").unwrap_or("DISPLAY").to_string(),
+        description: valarm.property_value("DESCRIPTION").map(String::from),
+    })
+}
+
+/// Serialize events into a single VCALENDAR. icalendar takes care of text
+/// escaping and line folding.
+pub fn write_ics(events: &[CalendarEvent]) -> String {
+    let mut calendar = Calendar::empty();
+    calendar
+        .append_property(("VERSION", "2.0"))
+        .append_property(("PRODID", PRODID))
+        .append_property(("CALSCALE", "GREGORIAN"));
+
+    for event in events {
+        calendar.push(to_vevent(event));
+    }
+
+    calendar.to_string()
+}
+
+fn to_vevent(event: &CalendarEvent) -> Event {
+    let mut vevent = Event::new();
+    vevent
+        .uid(&event.uid)
+        .summary(&event.summary)
+        .append_property(date_property("DTSTART", &event.dtstart, event.tzid.as_deref()))
+        .add_property("LAST-MODIFIED", &event.last_modified);
+    if let Some(dtend) = &event.dtend {
+        vevent.append_property(date_property("DTEND", dtend, event.tzid.as_deref()));
+    }
+    if let Some(description) = &event.description {
+        vevent.description(description);
+    }
+    if let Some(location) = &event.location {
+        vevent.location(location);
+    }
+    if let Some(rrule) = &event.rrule {
+        vevent.add_property("RRULE", rrule);
+    }
+    for alarm in &event.alarms {
+        vevent.alarm(to_valarm(alarm));
+    }
+    vevent.done()
+}
+
+fn to_valarm(alarm: &Alarm) -> icalendar::Alarm {
+    let mut trigger = Property::new("TRIGGER", &alarm.trigger);
+    if let Some(related) = &alarm.related {
+        trigger.add_parameter("RELATED", related);
+    }
+    // Offsets look like "-PT15M"; anything else is an absolute time
+    if !alarm.trigger.starts_with(['-', '+', 'P']) {
+        trigger.add_parameter("VALUE", "DATE-TIME");
+    }
+
+    let mut valarm = match (&alarm.description, alarm.action.as_str()) {
+        (None, "AUDIO") => icalendar::Alarm::audio(chrono::Duration::zero()),
+        // DISPLAY and EMAIL alarms require a DESCRIPTION
+        (description, _) => icalendar::Alarm::display(
+            description.as_deref().unwrap_or("Reminder"),
+            chrono::Duration::zero(),
+        ),
+    };
+    valarm
+        .add_property("ACTION", &alarm.action)
+        .append_property(trigger.done());
+    valarm.done()
+}
+
+// All-day events are stored as a bare YYYYMMDD; local times keep their TZID
+fn date_property(key: &str, value: &str, tzid: Option<&str>) -> Property {
+    let mut property = Property::new(key, value);
+    if is_date(value) {
+        property.add_parameter("VALUE", "DATE");
+    } else if let Some(tzid) = tzid.filter(|_| !value.ends_with('Z')) {
+        property.add_parameter("TZID", tzid);
+    }
+    property.done()
+}
+
+pub fn is_date(value: &str) -> bool {
+    value.len() == 8 && value.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Resolve a stored DTSTART/DTEND to UTC. Local times are looked up in
+/// `tzid`; floating times, and zones chrono-tz doesn't know (such as
+/// Windows names), are read as UTC. All-day dates map to midnight UTC.
+pub fn to_utc(value: &str, tzid: Option<&str>) -> Option<DateTime<Utc>> {
+    let parsed = parse_datetime(value)?;
+    if value.ends_with('Z') || is_date(value) {
+        return Some(parsed);
+    }
+    let Some(tz) = tzid.and_then(|t| t.parse::<Tz>().ok()) else {
+        return Some(parsed);
+    };
+    let wall = parsed.naive_utc();
+    let local = match tz.from_local_datetime(&wall) {
+        // A repeated hour when clocks go back: take the first
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => Some(dt),
+        // A skipped hour when clocks go forward: shift past the gap
+        LocalResult::None => tz.from_local_datetime(&(wall + Duration::hours(1))).earliest(),
+    };
+    Some(local.map(|dt| dt.with_timezone(&Utc)).unwrap_or(parsed))
+}
+
+/// Parse a stored DTSTART/DTEND or a query bound: iCalendar basic format
+/// ("20240101T100000Z", "20240101T100000", "20240101") or ISO 8601.
+/// Floating times are read as UTC and bare dates as midnight.
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let value = value.trim_end_matches('Z');
+    for format in ["%Y%m%dT%H%M%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(dt.and_utc());
+        }
+    }
+    for format in ["%Y%m%d", "%Y-%m-%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc());
+        }
+    }
+    None
+}
+
+/// Write `dt` the same way `original` was written (date, UTC or floating)
+pub fn format_like(original: &str, dt: DateTime<Utc>) -> String {
+    if is_date(original) {
+        dt.format("%Y%m%d").to_string()
+    } else if original.ends_with('Z') {
+        dt.format("%Y%m%dT%H%M%SZ").to_string()
+    } else {
+        dt.format("%Y%m%dT%H%M%S").to_string()
+    }
+}
+
 /// Read and parse an ICS file
 pub fn parse_ics_file(path: &str, source_id: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
     let content = std::fs::read_to_string(path)?;
@@ -65,4 +241,74 @@ END:VCALENDAR"#;
         assert_eq!(events.len(), 1);
         assert_eq!(events[0].summary, "Test Event");
     }
+
+    #[test]
+    fn test_write_ics_roundtrip() {
+        let ics = r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+UID:weekly-1
+SUMMARY:Standup\, daily\; short
+DESCRIPTION:A description long enough that it has to be folded across more than one content line when written back out
+DTSTART:20240101T090000Z
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR
+END:VEVENT
+BEGIN:VEVENT
+UID:allday-1
+SUMMARY:Holiday
+DTSTART;VALUE=DATE:20240704
+END:VEVENT
+END:VCALENDAR"#;
+
+        let events = parse_ics(ics, "test-source").unwrap();
+        let output = write_ics(&events);
+        assert!(output.contains(PRODID));
+        assert!(output.lines().all(|l| l.len() <= 75));
+
+        let reparsed = parse_ics(&output, "test-source").unwrap();
+        assert_eq!(reparsed.len(), 2);
+        let weekly = reparsed.iter().find(|e| e.uid == "weekly-1").unwrap();
+        assert_eq!(weekly.summary, "Standup, daily; short");
+        assert_eq!(weekly.rrule.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,WE,FR"));
+        assert_eq!(weekly.description, events[0].description);
+    }
+
+    #[test]
+    fn test_parse_ics_normalizes_to_utc() {
+        let ics = r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+UID:winter
+SUMMARY:Winter
+DTSTART;TZID=America/New_York:20240101T100000
+DTEND;TZID=America/New_York:20240101T110000
+END:VEVENT
+BEGIN:VEVENT
+UID:summer
+SUMMARY:Summer
+DTSTART;TZID=America/New_York:20240701T100000
+END:VEVENT
+BEGIN:VEVENT
+UID:holiday
+SUMMARY:Holiday
+DTSTART;VALUE=DATE:20240704
+END:VEVENT
+END:VCALENDAR"#;
+
+        let events = parse_ics(ics, "test-source").unwrap();
+        let utc = |uid: &str| {
+            let event = events.iter().find(|e| e.uid == uid).unwrap();
+            (event.start_utc.unwrap().to_rfc3339(), event.all_day)
+        };
+        assert_eq!(utc("winter"), ("2024-01-01T15:00:00+00:00".to_string(), false));
+        assert_eq!(utc("summer"), ("2024-07-01T14:00:00+00:00".to_string(), false));
+        assert_eq!(utc("holiday"), ("2024-07-04T00:00:00+00:00".to_string(), true));
+        assert_eq!(events[0].dtstart, "20240101T100000");
+        assert_eq!(events[0].tzid.as_deref(), Some("America/New_York"));
+
+        let output = write_ics(&events);
+        assert!(output.contains("DTSTART;TZID=America/New_York:20240101T100000"));
+        let reparsed = parse_ics(&output, "test-source").unwrap();
+        assert_eq!(reparsed[0].end_utc, events[0].end_utc);
+    }
 }
@@ -3,8 +3,15 @@ mod caldav;
 mod ics;
 mod sync_engine;
 mod models;
+mod scheduler;
+mod recurrence;
+mod overlap;
+mod subscription;
 
-use models::{CalendarSource, LogEntry};
+use models::{CalendarEvent, CalendarSource, LogEntry, Overlap, SyncOptions, SyncReport, SyncStatus};
+use scheduler::Scheduler;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
 // Tauri commands
 
 #[tauri::command]
@@ -26,6 +33,21 @@ async fn add_caldav_source(url: String, username: String, password: String) -> R
     Ok("CalDAV source added".into())
 }
 
+#[tauri::command]
+async fn add_ics_url_source(url: String, name: String) -> Result<CalendarSource, String> {
+    let url = subscription::normalize_url(&url);
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Not an http(s) or webcal URL: {}", url));
+    }
+    let config = serde_json::json!({ "url": url }).to_string();
+    let mut source = CalendarSource::new("ics_url", &config);
+    if !name.trim().is_empty() {
+        source.name = name.trim().to_string();
+    }
+    db::insert_source(&source).map_err(|e| e.to_string())?;
+    Ok(source)
+}
+
 #[tauri::command]
 async fn import_ics_file() -> Result<String, String> {
     // In real usage, tauri-plugin-dialog would open a file picker
@@ -38,12 +60,142 @@ async fn list_sources() -> Result<Vec<CalendarSource>, String> {
 }
 
 #[tauri::command]
-async fn sync_now(two_way: bool, dedup: bool, conflict_strategy: String) -> Result<String, String> {
+async fn set_source_enabled(id: String, enabled: bool) -> Result<CalendarSource, String> {
+    let mut source = db::get_source(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No source with id {}", id))?;
+    source.enabled = enabled;
+    db::update_source(&source).map_err(|e| e.to_string())?;
+    Ok(source)
+}
+
+#[tauri::command]
+async fn set_source_color(id: String, color: Option<String>) -> Result<CalendarSource, String> {
+    let mut source = db::get_source(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No source with id {}", id))?;
+    source.color = color.filter(|c| !c.is_empty());
+    db::update_source(&source).map_err(|e| e.to_string())?;
+    Ok(source)
+}
+
+// Syncing talks to servers through reqwest::blocking, which panics when
+// called on the async runtime, so the work runs on a blocking thread
+#[tauri::command]
+async fn sync_now(
+    app: AppHandle,
+    two_way: bool,
+    dedup: bool,
+    conflict_strategy: String,
+) -> Result<SyncReport, String> {
+    let options = SyncOptions { two_way, dedup, conflict_strategy };
+    scheduler::save_options(&options).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || app.state::<Scheduler>().sync(&app, &options, "manual"))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn set_auto_sync(scheduler: State<'_, Scheduler>, enabled: bool, interval_minutes: u64) -> Result<SyncStatus, String> {
+    scheduler.configure(enabled, interval_minutes).map_err(|e| e.to_string())?;
+    Ok(scheduler.status())
+}
+
+#[tauri::command]
+async fn get_sync_status(scheduler: State<'_, Scheduler>) -> Result<SyncStatus, String> {
+    Ok(scheduler.status())
+}
+
+#[tauri::command]
+async fn resolve_conflict_manual(uid: String, keep: String) -> Result<String, String> {
+    let conflict = db::get_pending_conflicts()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|c| c.event_uid == uid)
+        .ok_or_else(|| format!("No pending conflict for {}", uid))?;
     let sources = db::get_sources().map_err(|e| e.to_string())?;
-    let result = sync_engine::run_sync(&sources, two_way, dedup, &conflict_strategy)
+
+    let summary = conflict.source_version.summary.clone();
+
+    // Uploads the kept version; blocking for the same reason as sync_now
+    let action = tauri::async_runtime::spawn_blocking(move || {
+        sync_engine::apply_resolution(&conflict, &keep, &sources).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    db::clear_pending_conflict(&uid).map_err(|e| e.to_string())?;
+    let detail = format!("Conflict on '{}' ({}): {}", summary, uid, action);
+    db::insert_log(&LogEntry::conflict("conflict", &detail)).map_err(|e| e.to_string())?;
+    Ok(detail)
+}
+
+#[tauri::command]
+async fn delete_event(uid: String) -> Result<String, String> {
+    let deleted = db::delete_event(&uid).map_err(|e| e.to_string())?;
+    if !deleted {
+        return Err(format!("No event with UID {}", uid));
+    }
+    db::insert_log(&LogEntry::info("delete", &format!("Deleted '{}' locally", uid))).map_err(|e| e.to_string())?;
+    Ok("Event deleted; it will be removed from other sources on the next two-way sync".into())
+}
+
+#[tauri::command]
+async fn get_events(
+    from: String,
+    to: String,
+    source_ids: Option<Vec<String>>,
+    text_query: Option<String>,
+) -> Result<Vec<CalendarEvent>, String> {
+    let start = ics::parse_datetime(&from).ok_or_else(|| format!("Invalid date: {}", from))?;
+    let end = ics::parse_datetime(&to).ok_or_else(|| format!("Invalid date: {}", to))?;
+    if end <= start {
+        return Err("The end of the range must be after its start".into());
+    }
+    db::query_events(start, end, source_ids.as_deref(), text_query.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn find_overlaps(from: String, to: String) -> Result<Vec<Overlap>, String> {
+    let start = ics::parse_datetime(&from).ok_or_else(|| format!("Invalid date: {}", from))?;
+    let end = ics::parse_datetime(&to).ok_or_else(|| format!("Invalid date: {}", to))?;
+    if end <= start {
+        return Err("The end of the range must be after its start".into());
+    }
+    let events = db::get_all_events().map_err(|e| e.to_string())?;
+    Ok(overlap::find_overlaps(&events, start, end))
+}
+
+#[tauri::command]
+async fn export_ics(output_path: String, source_ids: Option<Vec<String>>) -> Result<usize, String> {
+    let events = match source_ids {
+        Some(ids) => {
+            let mut events = Vec::new();
+            for id in ids {
+                events.extend(db::get_events_by_source(&id).map_err(|e| e.to_string())?);
+            }
+            events
+        }
+        None => db::get_all_events().map_err(|e| e.to_string())?,
+    };
+
+    // Same UID from several sources: keep the most recently modified copy
+    let mut by_uid: HashMap<String, CalendarEvent> = HashMap::new();
+    for event in events {
+        match by_uid.get(&event.uid) {
+            Some(existing) if existing.last_modified >= event.last_modified => {}
+            _ => {
+                by_uid.insert(event.uid.clone(), event);
+            }
+        }
+    }
+    let mut merged: Vec<CalendarEvent> = by_uid.into_values().collect();
+    merged.sort_by_key(|e| e.start_key());
+
+    std::fs::write(&output_path, ics::write_ics(&merged))
+        .map_err(|e| format!("Failed to write {}: {}", output_path, e))?;
+    db::insert_log(&LogEntry::info("export", &format!("Exported {} events to {}", merged.len(), output_path)))
         .map_err(|e| e.to_string())?;
-    db::insert_log(&LogEntry::info("sync", &result)).map_err(|e| e.to_string())?;
-    Ok(result)
+    Ok(merged.len())
 }
 
 #[tauri::command]
@@ -70,12 +222,26 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            app.manage(Scheduler::start(app.handle().clone()));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             add_source,
             add_caldav_source,
+            add_ics_url_source,
             import_ics_file,
             list_sources,
+            set_source_enabled,
+            set_source_color,
             sync_now,
+            set_auto_sync,
+            get_sync_status,
+            resolve_conflict_manual,
+            delete_event,
+            get_events,
+            find_overlaps,
+            export_ics,
             preview_sync,
             get_log,
             clear_log,
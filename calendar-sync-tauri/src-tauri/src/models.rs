@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +10,18 @@ pub struct CalendarSource {
     pub config: String,
     pub added_at: String,
     pub url: Option<String>,
+    #[serde(default)]
+    pub last_synced: Option<String>,
+    /// User-picked display color, e.g. "#4f8cff"
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Disabled sources are kept but left out of sync
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 impl CalendarSource {
@@ -24,6 +36,9 @@ impl CalendarSource {
             config: config.to_string(),
             added_at: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
             url,
+            last_synced: None,
+            color: None,
+            enabled: true,
         }
     }
 }
@@ -38,6 +53,68 @@ pub struct CalendarEvent {
     pub location: Option<String>,
     pub source_id: String,
     pub last_modified: String,
+    /// Server resource and its ETag, for events fetched over CalDAV
+    #[serde(default)]
+    pub href: Option<String>,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub rrule: Option<String>,
+    #[serde(default)]
+    pub alarms: Vec<Alarm>,
+    /// TZID that dtstart/dtend are given in; they're kept as written
+    #[serde(default)]
+    pub tzid: Option<String>,
+    /// Date-only event: dtstart/dtend are YYYYMMDD days, not instants
+    #[serde(default)]
+    pub all_day: bool,
+    /// dtstart/dtend resolved to UTC. All-day events get midnight UTC of
+    /// their dates, since a day means the same day in every zone.
+    #[serde(default)]
+    pub start_utc: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub end_utc: Option<DateTime<Utc>>,
+}
+
+impl CalendarEvent {
+    /// The start as a comparable string, the same whatever zone it was written in
+    pub fn start_key(&self) -> String {
+        match self.start_utc {
+            Some(start) => start.format("%Y%m%dT%H%M%SZ").to_string(),
+            None => self.dtstart.clone(),
+        }
+    }
+}
+
+/// A VALARM reminder attached to an event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alarm {
+    /// Offset such as "-PT15M", or an absolute UTC time
+    pub trigger: String,
+    /// "END" when the offset is relative to the event's end
+    #[serde(default)]
+    pub related: Option<String>,
+    pub action: String,
+    pub description: Option<String>,
+}
+
+/// Two events from different calendars that take up the same time.
+/// `start`/`end` bound the shared part, in UTC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Overlap {
+    pub first: CalendarEvent,
+    pub second: CalendarEvent,
+    pub start: String,
+    pub end: String,
+}
+
+/// Tombstone for an event removed locally, kept until sync has removed it
+/// from the other sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedEvent {
+    pub uid: String,
+    pub source_id: String,
+    pub deleted_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,3 +155,38 @@ pub struct SyncConflict {
     pub target_version: CalendarEvent,
     pub resolution: Option<String>,
 }
+
+/// What a sync run did, plus any conflicts left for the user to decide
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub summary: String,
+    pub pending_conflicts: Vec<SyncConflict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOptions {
+    pub two_way: bool,
+    pub dedup: bool,
+    pub conflict_strategy: String,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            two_way: true,
+            dedup: true,
+            conflict_strategy: "newest".to_string(),
+        }
+    }
+}
+
+/// Payload of the `sync-status` event and `get_sync_status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub state: String, // "idle", "running", "error"
+    pub auto_sync: bool,
+    pub interval_minutes: u64,
+    pub last_synced: Option<String>,
+    pub next_sync: Option<String>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,102 @@
+use crate::models::{CalendarEvent, Overlap};
+use crate::recurrence;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Pairs of events from different calendars whose times intersect inside
+/// [from, to), ordered by when the overlap begins. Copies of one event in
+/// several calendars, and the exact duplicates dedup reports, are not counted.
+pub fn find_overlaps(events: &[CalendarEvent], from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Overlap> {
+    let mut spans: Vec<(DateTime<Utc>, DateTime<Utc>, CalendarEvent)> = Vec::new();
+    for event in events {
+        let Some((start, end)) = recurrence::span(event) else { continue };
+        // An occurrence that began before the window can still run into it
+        for occurrence in recurrence::expand(event, from - (end - start), to) {
+            match recurrence::span(&occurrence) {
+                Some((start, end)) if end > start && end > from => spans.push((start, end, occurrence)),
+                _ => {}
+            }
+        }
+    }
+    spans.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut overlaps = Vec::new();
+    // Spans that haven't ended yet at the current start
+    let mut active: Vec<usize> = Vec::new();
+    for (i, (start, end, event)) in spans.iter().enumerate() {
+        active.retain(|&j| spans[j].1 > *start);
+        for &j in &active {
+            let (other_start, other_end, other) = &spans[j];
+            if other.source_id == event.source_id
+                || other.uid == event.uid
+                || (other.summary == event.summary && other_start == start)
+            {
+                continue;
+            }
+            overlaps.push(Overlap {
+                first: other.clone(),
+                second: event.clone(),
+                start: (*start.max(other_start).max(&from)).to_rfc3339_opts(SecondsFormat::Secs, true),
+                end: (*end.min(other_end).min(&to)).to_rfc3339_opts(SecondsFormat::Secs, true),
+            });
+        }
+        active.push(i);
+    }
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ics;
+
+    fn event(uid: &str, source_id: &str, dtstart: &str, dtend: Option<&str>) -> CalendarEvent {
+        CalendarEvent {
+            uid: uid.into(),
+            summary: uid.into(),
+            description: None,
+            dtstart: dtstart.into(),
+            dtend: dtend.map(String::from),
+            location: None,
+            source_id: source_id.into(),
+            last_modified: "20240101T000000Z".into(),
+            href: None,
+            etag: None,
+            rrule: None,
+            alarms: Vec::new(),
+            tzid: None,
+            all_day: dtstart.len() == 8,
+            start_utc: ics::to_utc(dtstart, None),
+            end_utc: dtend.and_then(|d| ics::to_utc(d, None)),
+        }
+    }
+
+    #[test]
+    fn test_find_overlaps_across_calendars() {
+        let events = vec![
+            event("meeting", "work", "20240102T090000Z", Some("20240102T100000Z")),
+            event("dentist", "home", "20240102T093000Z", Some("20240102T103000Z")),
+            event("lunch", "work", "20240102T093000Z", Some("20240102T094500Z")),
+            event("trip", "home", "20240103", None),
+            event("call", "work", "20240103T230000Z", Some("20240104T010000Z")),
+            event("meeting", "home", "20240102T090000Z", Some("20240102T100000Z")),
+        ];
+        let overlaps = find_overlaps(
+            &events,
+            ics::parse_datetime("20240101").unwrap(),
+            ics::parse_datetime("20240110").unwrap(),
+        );
+        let pairs: Vec<(&str, &str, &str, &str)> = overlaps
+            .iter()
+            .map(|o| (o.first.uid.as_str(), o.second.uid.as_str(), o.start.as_str(), o.end.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("meeting", "lunch", "2024-01-02T09:30:00Z", "2024-01-02T09:45:00Z"),
+                ("meeting", "dentist", "2024-01-02T09:30:00Z", "2024-01-02T10:00:00Z"),
+                ("lunch", "dentist", "2024-01-02T09:30:00Z", "2024-01-02T09:45:00Z"),
+                ("trip", "call", "2024-01-03T23:00:00Z", "2024-01-04T00:00:00Z"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use crate::ics;
+use crate::models::CalendarEvent;
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, Utc, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The subset of RFC 5545 RRULE that calendars actually send:
+/// FREQ, INTERVAL, COUNT, UNTIL and BYDAY. Other parts are ignored.
+#[derive(Debug, Clone)]
+struct Rule {
+    freq: Freq,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    /// "MO" or, for monthly rules, "2MO" / "-1FR"
+    by_day: Vec<(Option<i32>, Weekday)>,
+}
+
+fn parse_rule(rrule: &str) -> Option<Rule> {
+    let mut freq = None;
+    let mut rule = Rule { freq: Freq::Daily, interval: 1, count: None, until: None, by_day: Vec::new() };
+    for part in rrule.split(';') {
+        let Some((key, value)) = part.split_once('=') else { continue };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                freq = match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Some(Freq::Daily),
+                    "WEEKLY" => Some(Freq::Weekly),
+                    "MONTHLY" => Some(Freq::Monthly),
+                    "YEARLY" => Some(Freq::Yearly),
+                    _ => None,
+                }
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|&i| i > 0)?,
+            "COUNT" => rule.count = value.parse().ok(),
+            "UNTIL" => rule.until = ics::parse_datetime(value),
+            "BYDAY" => rule.by_day = value.split(',').filter_map(parse_by_day).collect(),
+            _ => {}
+        }
+    }
+    rule.freq = freq?;
+    Some(rule)
+}
+
+fn parse_by_day(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let value = value.trim().to_ascii_uppercase();
+    let (ordinal, day) = value.split_at(value.len().checked_sub(2)?);
+    let weekday = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(n.trim_start_matches('+').parse().ok()?),
+    };
+    Some((ordinal, weekday))
+}
+
+/// First day of the `n`th period after the one holding `start`
+fn period_anchor(rule: &Rule, start: NaiveDate, n: u32) -> Option<NaiveDate> {
+    let steps = n.checked_mul(rule.interval)?;
+    match rule.freq {
+        Freq::Daily => start.checked_add_days(Days::new(steps.into())),
+        Freq::Weekly => {
+            let monday = start - Duration::days(start.weekday().num_days_from_monday().into());
+            monday.checked_add_days(Days::new(u64::from(steps) * 7))
+        }
+        Freq::Monthly => start.with_day(1)?.checked_add_months(Months::new(steps)),
+        Freq::Yearly => start.with_day(1)?.with_month(1)?.checked_add_months(Months::new(steps.checked_mul(12)?)),
+    }
+}
+
+/// Candidate dates in the period starting at `anchor`, in order
+fn period_dates(rule: &Rule, start: NaiveDate, anchor: NaiveDate) -> Vec<NaiveDate> {
+    let mut dates = match rule.freq {
+        Freq::Daily => vec![anchor],
+        Freq::Weekly if rule.by_day.is_empty() => {
+            vec![anchor + Duration::days(start.weekday().num_days_from_monday().into())]
+        }
+        Freq::Weekly => rule
+            .by_day
+            .iter()
+            .map(|(_, day)| anchor + Duration::days(day.num_days_from_monday().into()))
+            .collect(),
+        Freq::Monthly if rule.by_day.is_empty() => anchor.with_day(start.day()).into_iter().collect(),
+        Freq::Monthly => rule
+            .by_day
+            .iter()
+            .flat_map(|&(ordinal, day)| weekdays_in_month(anchor, ordinal, day))
+            .collect(),
+        // Feb 29 only recurs in leap years
+        Freq::Yearly => anchor
+            .with_month(start.month())
+            .and_then(|d| d.with_day(start.day()))
+            .into_iter()
+            .collect(),
+    };
+    dates.sort();
+    dates.dedup();
+    dates
+}
+
+// Every `day` of the month, or only the nth (counting from the end when negative)
+fn weekdays_in_month(first: NaiveDate, ordinal: Option<i32>, day: Weekday) -> Vec<NaiveDate> {
+    let all: Vec<NaiveDate> = first
+        .iter_days()
+        .take_while(|d| d.month() == first.month())
+        .filter(|d| d.weekday() == day)
+        .collect();
+    match ordinal {
+        None => all,
+        Some(n) if n > 0 => all.get(n as usize - 1).copied().into_iter().collect(),
+        Some(n) => all.len().checked_sub(n.unsigned_abs() as usize).and_then(|i| all.get(i)).copied().into_iter().collect(),
+    }
+}
+
+/// Start times of the occurrences that begin in [from, to). Events without a
+/// rule, or with one this doesn't understand, occur once at `start`.
+pub fn occurrences(start: DateTime<Utc>, rrule: Option<&str>, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let Some(rule) = rrule.and_then(parse_rule) else {
+        return if start >= from && start < to { vec![start] } else { Vec::new() };
+    };
+
+    let time = start.time();
+    let mut found = Vec::new();
+    let mut seen = 0;
+    for n in 0.. {
+        let Some(anchor) = period_anchor(&rule, start.date_naive(), n) else { break };
+        if anchor.and_time(time).and_utc() >= to {
+            break;
+        }
+        for date in period_dates(&rule, start.date_naive(), anchor) {
+            let at = date.and_time(time).and_utc();
+            if at < start {
+                continue;
+            }
+            seen += 1;
+            if rule.count.is_some_and(|c| seen > c) || rule.until.is_some_and(|u| at > u) || at >= to {
+                return found;
+            }
+            if at >= from {
+                found.push(at);
+            }
+        }
+    }
+    found
+}
+
+/// When an event starts and ends in UTC. All-day events without a DTEND
+/// last the whole day; timed ones without a DTEND take no time.
+pub fn span(event: &CalendarEvent) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let tzid = event.tzid.as_deref();
+    let start = event.start_utc.or_else(|| ics::to_utc(&event.dtstart, tzid))?;
+    let end = event
+        .end_utc
+        .or_else(|| event.dtend.as_deref().and_then(|d| ics::to_utc(d, tzid)));
+    let end = match end {
+        Some(end) if end >= start => end,
+        _ if event.all_day || ics::is_date(&event.dtstart) => start + Duration::days(1),
+        _ => start,
+    };
+    Some((start, end))
+}
+
+/// One copy of `event` per occurrence starting in [from, to), each with its
+/// own DTSTART/DTEND in the event's original format and zone
+pub fn expand(event: &CalendarEvent, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<CalendarEvent> {
+    // Recurrence follows the event's own clock, so a 9:00 meeting stays at
+    // 9:00 across DST changes. The window is widened by the largest UTC
+    // offset and applied again once each occurrence is in UTC.
+    let Some(wall_start) = ics::parse_datetime(&event.dtstart) else {
+        return Vec::new();
+    };
+    let duration = event
+        .dtend
+        .as_deref()
+        .and_then(ics::parse_datetime)
+        .map(|end| end - wall_start);
+    let slack = Duration::hours(14);
+    let tzid = event.tzid.as_deref();
+
+    occurrences(wall_start, event.rrule.as_deref(), from - slack, to + slack)
+        .into_iter()
+        .filter_map(|at| {
+            let mut occurrence = event.clone();
+            occurrence.dtstart = ics::format_like(&event.dtstart, at);
+            occurrence.start_utc = ics::to_utc(&occurrence.dtstart, tzid);
+            if let (Some(dtend), Some(duration)) = (&event.dtend, duration) {
+                let dtend = ics::format_like(dtend, at + duration);
+                occurrence.end_utc = ics::to_utc(&dtend, tzid);
+                occurrence.dtend = Some(dtend);
+            }
+            let start = occurrence.start_utc?;
+            (start >= from && start < to).then_some(occurrence)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        ics::parse_datetime(value).unwrap()
+    }
+
+    #[test]
+    fn test_weekly_by_day_with_count() {
+        let found = occurrences(
+            utc("20240101T090000Z"),
+            Some("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4"),
+            utc("20240101"),
+            utc("20250101"),
+        );
+        let days: Vec<String> = found.iter().map(|d| d.format("%Y%m%d").to_string()).collect();
+        assert_eq!(days, ["20240101", "20240103", "20240108", "20240110"]);
+    }
+
+    #[test]
+    fn test_monthly_last_friday_within_window() {
+        let found = occurrences(
+            utc("20240126T150000Z"),
+            Some("FREQ=MONTHLY;BYDAY=-1FR"),
+            utc("20240301"),
+            utc("20240501"),
+        );
+        let days: Vec<String> = found.iter().map(|d| d.format("%Y%m%d").to_string()).collect();
+        assert_eq!(days, ["20240329", "20240426"]);
+    }
+
+    #[test]
+    fn test_expand_keeps_duration_and_format() {
+        let event = CalendarEvent {
+            uid: "daily".into(),
+            summary: "Standup".into(),
+            description: None,
+            dtstart: "20240101T090000Z".into(),
+            dtend: Some("20240101T091500Z".into()),
+            location: None,
+            source_id: "s".into(),
+            last_modified: "20240101T000000Z".into(),
+            href: None,
+            etag: None,
+            rrule: Some("FREQ=DAILY;UNTIL=20240103T090000Z".into()),
+            alarms: Vec::new(),
+            tzid: None,
+            all_day: false,
+            start_utc: None,
+            end_utc: None,
+        };
+        let expanded = expand(&event, utc("20240102"), utc("20240110"));
+        assert_eq!(expanded.len(), 2);
+        assert_eq!(expanded[0].dtstart, "20240102T090000Z");
+        assert_eq!(expanded[0].dtend.as_deref(), Some("20240102T091500Z"));
+    }
+
+    #[test]
+    fn test_expand_keeps_local_time_across_dst() {
+        let event = CalendarEvent {
+            uid: "weekly".into(),
+            summary: "Sync".into(),
+            description: None,
+            dtstart: "20240301T090000".into(),
+            dtend: None,
+            location: None,
+            source_id: "s".into(),
+            last_modified: "20240101T000000Z".into(),
+            href: None,
+            etag: None,
+            rrule: Some("FREQ=WEEKLY".into()),
+            alarms: Vec::new(),
+            tzid: Some("Europe/Berlin".into()),
+            all_day: false,
+            start_utc: None,
+            end_utc: None,
+        };
+        let expanded = expand(&event, utc("20240320"), utc("20240406"));
+        let starts: Vec<String> = expanded.iter().map(|e| e.start_utc.unwrap().format("%m%dT%H%M").to_string()).collect();
+        assert_eq!(starts, ["0322T0800", "0329T0800", "0405T0700"]);
+        assert_eq!(expanded[2].dtstart, "20240405T090000");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::db;
+use crate::models::{LogEntry, SyncOptions, SyncReport, SyncStatus};
+use crate::sync_engine;
+use chrono::Utc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const DEFAULT_INTERVAL_MINUTES: u64 = 15;
+
+/// Background sync on a timer. Manual and scheduled syncs both go through
+/// `sync`, so only one runs at a time and any sync restarts the countdown.
+pub struct Scheduler {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    timer: Mutex<Timer>,
+    wake: Condvar,
+    running: AtomicBool,
+    last_error: Mutex<Option<String>>,
+}
+
+struct Timer {
+    enabled: bool,
+    interval: Duration,
+    next_due: Instant,
+}
+
+impl Scheduler {
+    pub fn start(app: AppHandle) -> Self {
+        let enabled = db::get_setting("auto_sync").ok().flatten().as_deref() == Some("true");
+        let minutes = db::get_setting("sync_interval_minutes")
+            .ok()
+            .flatten()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+        let interval = Duration::from_secs(minutes.max(1) * 60);
+
+        let scheduler = Scheduler {
+            shared: Arc::new(Shared {
+                timer: Mutex::new(Timer { enabled, interval, next_due: Instant::now() + interval }),
+                wake: Condvar::new(),
+                running: AtomicBool::new(false),
+                last_error: Mutex::new(None),
+            }),
+        };
+
+        let shared = Arc::clone(&scheduler.shared);
+        thread::spawn(move || {
+            let worker = Scheduler { shared };
+            let mut timer = worker.shared.timer.lock().unwrap();
+            loop {
+                if !timer.enabled {
+                    timer = worker.shared.wake.wait(timer).unwrap();
+                    continue;
+                }
+                let now = Instant::now();
+                if now < timer.next_due {
+                    let wait = timer.next_due - now;
+                    timer = worker.shared.wake.wait_timeout(timer, wait).unwrap().0;
+                    continue;
+                }
+
+                // A manual sync in progress already counts as this tick
+                timer.next_due = now + timer.interval;
+                drop(timer);
+                let _ = worker.sync(&app, &load_options(), "scheduled");
+                timer = worker.shared.timer.lock().unwrap();
+            }
+        });
+
+        scheduler
+    }
+
+    pub fn configure(&self, enabled: bool, interval_minutes: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let minutes = interval_minutes.max(1);
+        db::set_setting("auto_sync", if enabled { "true" } else { "false" })?;
+        db::set_setting("sync_interval_minutes", &minutes.to_string())?;
+
+        let mut timer = self.shared.timer.lock().unwrap();
+        timer.enabled = enabled;
+        timer.interval = Duration::from_secs(minutes * 60);
+        timer.next_due = Instant::now() + timer.interval;
+        self.shared.wake.notify_all();
+        Ok(())
+    }
+
+    /// Run one sync unless another is already in progress
+    pub fn sync(&self, app: &AppHandle, options: &SyncOptions, trigger: &str) -> Result<SyncReport, String> {
+        if self.shared.running.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return Err("A sync is already running".into());
+        }
+        self.emit_status(app);
+
+        // Disabled sources are skipped until the user turns them back on
+        let result = db::get_sources()
+            .and_then(|sources| {
+                let enabled: Vec<_> = sources.into_iter().filter(|s| s.enabled).collect();
+                sync_engine::run_sync(&enabled, options.two_way, options.dedup, &options.conflict_strategy)
+            })
+            .map_err(|e| e.to_string());
+
+        match &result {
+            Ok(report) => {
+                let _ = db::set_setting("last_sync_at", &Utc::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                let _ = db::insert_log(&LogEntry::info("sync", &format!("{} ({})", report.summary, trigger)));
+                *self.shared.last_error.lock().unwrap() = None;
+            }
+            Err(e) => {
+                let _ = db::insert_log(&LogEntry::conflict("sync", &format!("{} sync failed: {}", trigger, e)));
+                *self.shared.last_error.lock().unwrap() = Some(e.clone());
+            }
+        }
+
+        {
+            let mut timer = self.shared.timer.lock().unwrap();
+            timer.next_due = Instant::now() + timer.interval;
+            self.shared.wake.notify_all();
+        }
+        self.shared.running.store(false, Ordering::SeqCst);
+        self.emit_status(app);
+        result
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        let running = self.shared.running.load(Ordering::SeqCst);
+        let last_error = self.shared.last_error.lock().unwrap().clone();
+        let timer = self.shared.timer.lock().unwrap();
+
+        let next_sync = timer.enabled.then(|| {
+            let remaining = timer.next_due.saturating_duration_since(Instant::now());
+            let at = Utc::now() + chrono::Duration::from_std(remaining).unwrap_or_default();
+            at.format("%Y-%m-%d %H:%M:%S").to_string()
+        });
+        let state = if running {
+            "running"
+        } else if last_error.is_some() {
+            "error"
+        } else {
+            "idle"
+        };
+
+        SyncStatus {
+            state: state.to_string(),
+            auto_sync: timer.enabled,
+            interval_minutes: timer.interval.as_secs() / 60,
+            last_synced: db::get_setting("last_sync_at").ok().flatten(),
+            next_sync,
+            error: last_error,
+        }
+    }
+
+    fn emit_status(&self, app: &AppHandle) {
+        let _ = app.emit("sync-status", self.status());
+    }
+}
+
+/// Options from the last manual sync, so scheduled runs behave the same way
+pub fn save_options(options: &SyncOptions) -> Result<(), Box<dyn std::error::Error>> {
+    db::set_setting("sync_options", &serde_json::to_string(options)?)
+}
+
+fn load_options() -> SyncOptions {
+    db::get_setting("sync_options")
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
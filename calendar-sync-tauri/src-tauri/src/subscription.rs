@@ -0,0 +1,54 @@
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A published calendar as served, with the validators for the next request
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub content: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Calendar apps hand out webcal:// links for the same feed over HTTP(S)
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    }
+}
+
+/// GET a read-only .ics feed. Passing the ETag and Last-Modified from the
+/// previous fetch makes it conditional; `Ok(None)` means nothing changed.
+/// Redirects are followed and gzip bodies decoded by reqwest.
+pub fn fetch(url: &str, etag: Option<&str>, last_modified: Option<&str>) -> Result<Option<Feed>, Box<dyn std::error::Error>> {
+    let client = Client::builder().timeout(TIMEOUT).build()?;
+    let mut request = client.get(normalize_url(url));
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send()?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let response = response.error_for_status()?;
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let content = response.text()?;
+    Ok(Some(Feed { content, etag, last_modified }))
+}
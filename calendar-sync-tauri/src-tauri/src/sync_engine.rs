@@ -1,6 +1,13 @@
-use crate::models::{CalendarSource, CalendarEvent, LogEntry, SyncConflict};
+use crate::models::{CalendarSource, CalendarEvent, LogEntry, SyncConflict, SyncReport};
+use crate::caldav::{CalDavClient, DavResource, DeleteOutcome};
 use crate::db;
-use std::collections::HashMap;
+use crate::{ics, subscription};
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+
+/// Where each event currently lives on each CalDAV server, with its ETag:
+/// source id → uid → resource
+type RemoteResources = HashMap<String, HashMap<String, DavResource>>;
 
 /// Run sync across all configured sources
 pub fn run_sync(
@@ -8,14 +15,53 @@ pub fn run_sync(
     two_way: bool,
     dedup: bool,
     conflict_strategy: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
+) -> Result<SyncReport, Box<dyn std::error::Error>> {
     if sources.is_empty() {
-        return Ok("No sources configured. Add a calendar source first.".into());
+        return Ok(SyncReport {
+            summary: "No sources configured. Add a calendar source first.".into(),
+            pending_conflicts: Vec::new(),
+        });
     }
 
     let mut total_synced = 0;
     let mut total_conflicts = 0;
+    let mut total_pending = 0;
     let mut total_deduped = 0;
+    let mut total_deleted = 0;
+    let mut total_fetched = 0;
+
+    // Pull server changes first. Locally deleted events are left out so the
+    // fetch can't resurrect them before the deletion is propagated.
+    let tombstoned: HashSet<String> = db::get_deleted_events()?
+        .into_iter()
+        .map(|d| d.uid)
+        .collect();
+    let mut remote = RemoteResources::new();
+    for source in sources {
+        if let Some(client) = caldav_client(source) {
+            // A source we couldn't list is skipped this round, so a failed
+            // request can't be mistaken for events having been removed
+            match fetch_incremental(source, &client, &tombstoned, two_way) {
+                Ok((resources, fetched)) => {
+                    remote.insert(source.id.clone(), resources);
+                    total_fetched += fetched;
+                }
+                Err(e) => {
+                    db::insert_log(&LogEntry::conflict(
+                        "sync",
+                        &format!("Skipped {}: listing its events failed: {}", source.url.as_deref().unwrap_or(&source.name), e),
+                    ))?;
+                }
+            }
+        } else if source.source_type == "ics_url" {
+            total_fetched += refresh_subscription(source, &tombstoned)?;
+        }
+    }
+
+    // Propagate local deletions before anything could copy the event back
+    if two_way && sources.len() >= 2 {
+        total_deleted = propagate_deletions(sources, &remote)?;
+    }
 
     // Collect all events by source
     let mut all_events: HashMap<String, Vec<CalendarEvent>> = HashMap::new();
@@ -29,35 +75,59 @@ pub fn run_sync(
         let mut seen: HashMap<String, &CalendarEvent> = HashMap::new();
         for events in all_events.values() {
             for event in events {
-                let key = format!("{}|{}", event.summary, event.dtstart);
-                if seen.contains_key(&key) {
-                    total_deduped += 1;
-                    db::insert_log(&LogEntry::info("dedup", &format!("Duplicate: {}", event.summary)))?;
-                } else {
-                    seen.insert(key, event);
+                let key = format!("{}|{}", event.summary, event.start_key());
+                match seen.get(&key) {
+                    // Another source's copy of the same event isn't a duplicate
+                    Some(first) if first.uid == event.uid => {}
+                    Some(_) => {
+                        total_deduped += 1;
+                        db::insert_log(&LogEntry::info("dedup", &format!("Duplicate: {}", event.summary)))?;
+                    }
+                    None => {
+                        seen.insert(key, event);
+                    }
                 }
             }
         }
     }
 
-    // Detect conflicts (same UID, different content across sources)
-    if sources.len() >= 2 {
-        let conflicts = detect_conflicts(&all_events);
-        for conflict in &conflicts {
-            total_conflicts += 1;
-            let resolution = resolve_conflict(conflict, conflict_strategy);
+    // Detect conflicts (same UID, different content across sources). Each
+    // source keeps its own copy, so this includes edits just fetched.
+    let conflicts = if sources.len() >= 2 {
+        detect_conflicts(&all_events)
+    } else {
+        Vec::new()
+    };
+    for mut conflict in conflicts {
+        total_conflicts += 1;
+        let resolution = resolve_conflict(&conflict, conflict_strategy);
+        if resolution == "ask" {
+            total_pending += 1;
+            db::save_pending_conflict(&conflict)?;
             db::insert_log(&LogEntry::conflict(
                 "conflict",
-                &format!("Conflict on '{}': resolved with '{}'", conflict.event_uid, resolution),
+                &format!("Conflict on '{}' ({}): waiting for you to pick a version", conflict.source_version.summary, conflict.event_uid),
             ))?;
+            continue;
         }
+
+        let detail = match apply_resolution(&conflict, &resolution, sources) {
+            Ok(action) => {
+                conflict.resolution = Some(resolution);
+                format!("Conflict on '{}' ({}): {}", conflict.source_version.summary, conflict.event_uid, action)
+            }
+            Err(e) => format!("Conflict on '{}' ({}): applying the {} version failed: {}", conflict.source_version.summary, conflict.event_uid, resolution, e),
+        };
+        db::insert_log(&LogEntry::conflict("conflict", &detail))?;
     }
 
-    // Two-way sync: propagate events between sources
-    if two_way && sources.len() >= 2 {
-        for (i, source) in sources.iter().enumerate() {
+    // Two-way sync: propagate events between sources. Subscriptions are
+    // read-only, so they neither send nor receive copies.
+    let writable: Vec<&CalendarSource> = sources.iter().filter(|s| !is_read_only(s)).collect();
+    if two_way && writable.len() >= 2 {
+        for (i, source) in writable.iter().enumerate() {
             let source_events = all_events.get(&source.id).cloned().unwrap_or_default();
-            for (j, other_source) in sources.iter().enumerate() {
+            for (j, other_source) in writable.iter().enumerate() {
                 if i == j { continue; }
                 let other_events = all_events.get(&other_source.id).cloned().unwrap_or_default();
                 let other_uids: Vec<&str> = other_events.iter().map(|e| e.uid.as_str()).collect();
@@ -66,6 +136,8 @@ pub fn run_sync(
                     if !other_uids.contains(&event.uid.as_str()) {
                         let mut new_event = event.clone();
                         new_event.source_id = other_source.id.clone();
+                        new_event.href = None;
+                        new_event.etag = None;
                         db::insert_event(&new_event)?;
                         total_synced += 1;
                     }
@@ -76,12 +148,271 @@ pub fn run_sync(
 
     total_synced += all_events.values().map(|v| v.len()).sum::<usize>();
 
-    Ok(format!(
-        "✅ Sync complete: {} events processed, {} conflicts resolved, {} duplicates removed",
-        total_synced, total_conflicts, total_deduped
+    let summary = format!(
+        "✅ Sync complete: {} events processed ({} fetched), {} conflicts resolved, {} awaiting your decision, {} duplicates removed, {} deletions propagated",
+        total_synced, total_fetched, total_conflicts - total_pending, total_pending, total_deduped, total_deleted
+    );
+    Ok(SyncReport {
+        summary,
+        pending_conflicts: db::get_pending_conflicts()?,
+    })
+}
+
+/// Apply a conflict decision: `keep` ("source" or "target") is written to the
+/// database and uploaded to the source that held the losing version.
+/// Returns a description of what was done, for the log.
+pub fn apply_resolution(conflict: &SyncConflict, keep: &str, sources: &[CalendarSource]) -> Result<String, Box<dyn std::error::Error>> {
+    let (winner, loser) = match keep {
+        "source" => (&conflict.source_version, &conflict.target_version),
+        "target" => (&conflict.target_version, &conflict.source_version),
+        other => return Err(format!("Unknown resolution '{}', expected 'source' or 'target'", other).into()),
+    };
+
+    db::insert_event(winner)?;
+    let mut action = format!("kept the version from {}", source_label(sources, &winner.source_id));
+
+    // The losing source's copy takes the kept version too, so the two
+    // copies agree and the conflict isn't raised again next sync
+    let mut copy = winner.clone();
+    copy.source_id = loser.source_id.clone();
+    copy.href = loser.href.clone();
+    copy.etag = loser.etag.clone();
+
+    let loser_source = sources.iter().find(|s| s.id == loser.source_id);
+    match loser_source.and_then(|s| caldav_client(s).map(|client| (s, client))) {
+        Some((source, client)) => {
+            // Overwrite the losing copy where that server keeps it
+            copy.etag = client.put_event("", &copy)?;
+            action.push_str(&format!(" and uploaded it to {}", source_label(sources, &source.id)));
+        }
+        None => action.push_str(&format!(
+            " (not uploaded: {} is read-only)",
+            source_label(sources, &loser.source_id)
+        )),
+    }
+    db::insert_event(&copy)?;
+    Ok(action)
+}
+
+fn source_label<'a>(sources: &'a [CalendarSource], source_id: &'a str) -> &'a str {
+    sources
+        .iter()
+        .find(|s| s.id == source_id)
+        .map(|s| s.url.as_deref().unwrap_or(&s.name))
+        .unwrap_or(source_id)
+}
+
+/// Sources we can only read from, such as published .ics feeds
+fn is_read_only(source: &CalendarSource) -> bool {
+    source.source_type == "ics_url"
+}
+
+/// Re-download a subscribed .ics feed unless the server says it hasn't
+/// changed. The feed is the whole calendar, so events missing from it are
+/// removed; the ETag/Last-Modified it came with are kept in the source's
+/// config for the next request. Returns the number of events downloaded.
+fn refresh_subscription(
+    source: &CalendarSource,
+    tombstoned: &HashSet<String>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut config: serde_json::Value = serde_json::from_str(&source.config)?;
+    let url = config["url"].as_str().ok_or("ICS subscription has no URL")?.to_string();
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let Some(feed) = subscription::fetch(&url, config["etag"].as_str(), config["last_modified"].as_str())? else {
+        db::set_last_synced(&source.id, &now)?;
+        return Ok(0);
+    };
+    let events = ics::parse_ics(&feed.content, &source.id)?;
+    let fetched = events.len();
+
+    let listed: HashSet<&str> = events.iter().map(|e| e.uid.as_str()).collect();
+    for existing in db::get_events_by_source(&source.id)? {
+        if !listed.contains(existing.uid.as_str()) {
+            db::remove_event(&existing.uid, &source.id)?;
+        }
+    }
+    for event in events {
+        if tombstoned.contains(&event.uid) {
+            continue;
+        }
+        db::insert_event(&event)?;
+    }
+
+    config["etag"] = feed.etag.into();
+    config["last_modified"] = feed.last_modified.into();
+    let mut updated = source.clone();
+    updated.config = config.to_string();
+    db::update_source(&updated)?;
+    db::set_last_synced(&source.id, &now)?;
+    Ok(fetched)
+}
+
+/// Build a client for a CalDAV source from its stored config
+fn caldav_client(source: &CalendarSource) -> Option<CalDavClient> {
+    if source.source_type != "caldav" {
+        return None;
+    }
+    let config: serde_json::Value = serde_json::from_str(&source.config).ok()?;
+    Some(CalDavClient::new(
+        config["url"].as_str()?,
+        config["username"].as_str().unwrap_or(""),
+        config["password"].as_str().unwrap_or(""),
     ))
 }
 
+/// Bring a CalDAV source's events up to date. Only resources whose ETag
+/// differs from the one stored with the event are downloaded; resources that
+/// vanished from the server are removed locally (and, under two-way sync,
+/// tombstoned so the deletion reaches the other sources), unless the server
+/// lists nothing at all; see `vanished`.
+/// Returns the server's uid → resource map and the number of events downloaded.
+fn fetch_incremental(
+    source: &CalendarSource,
+    client: &CalDavClient,
+    tombstoned: &HashSet<String>,
+    two_way: bool,
+) -> Result<(HashMap<String, DavResource>, usize), Box<dyn std::error::Error>> {
+    let local = db::get_events_by_source(&source.id)?;
+    let known: HashMap<&str, &CalendarEvent> = local
+        .iter()
+        .filter_map(|e| e.href.as_deref().map(|href| (href, e)))
+        .collect();
+
+    let listing = client.list_etags("")?;
+    let mut resources = HashMap::new();
+    let mut changed = Vec::new();
+    for resource in &listing {
+        match known.get(resource.href.as_str()) {
+            Some(event) if event.etag.is_some() && event.etag == resource.etag => {
+                resources.insert(event.uid.clone(), resource.clone());
+            }
+            _ => changed.push(resource.href.clone()),
+        }
+    }
+
+    let fetched = if changed.is_empty() {
+        Vec::new()
+    } else {
+        client.fetch_hrefs("", &changed, &source.id)?
+    };
+    let fetched_count = fetched.len();
+    for event in fetched {
+        if let Some(href) = &event.href {
+            let resource = DavResource { href: href.clone(), etag: event.etag.clone(), calendar_data: None };
+            resources.insert(event.uid.clone(), resource);
+        }
+        if tombstoned.contains(&event.uid) {
+            continue;
+        }
+        db::insert_event(&event)?;
+    }
+
+    let Some(removed) = vanished(&known, &listing) else {
+        db::insert_log(&LogEntry::conflict(
+            "delete",
+            &format!(
+                "{} listed no events although {} were synced from it before; not treating them as deleted",
+                source.url.as_deref().unwrap_or(&source.name), known.len()
+            ),
+        ))?;
+        return Ok((resources, fetched_count));
+    };
+    for event in removed {
+        if two_way {
+            db::delete_event(&event.uid)?;
+        } else {
+            db::remove_event(&event.uid, &source.id)?;
+        }
+        db::insert_log(&LogEntry::info(
+            "delete",
+            &format!("'{}' was removed from {}", event.summary, source.url.as_deref().unwrap_or(&source.name)),
+        ))?;
+    }
+
+    db::set_last_synced(&source.id, &Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())?;
+    Ok((resources, fetched_count))
+}
+
+/// Known events whose href is missing from a successful listing. None when
+/// the server suddenly lists nothing at all: that is far more likely a
+/// misbehaving server or a wrong calendar URL than every event having been
+/// deleted, and tombstoning them would delete them from every other source.
+fn vanished<'a>(known: &HashMap<&str, &'a CalendarEvent>, listing: &[DavResource]) -> Option<Vec<&'a CalendarEvent>> {
+    if listing.is_empty() && !known.is_empty() {
+        return None;
+    }
+    let listed: HashSet<&str> = listing.iter().map(|r| r.href.as_str()).collect();
+    Some(
+        known
+            .iter()
+            .filter(|(href, _)| !listed.contains(*href))
+            .map(|(_, event)| *event)
+            .collect(),
+    )
+}
+
+/// Remove deleted events from every server that still lists them. That
+/// includes the event's own source when it was deleted locally; a server
+/// the event vanished from no longer lists it and is left alone. A tombstone
+/// is only cleared once all servers have either deleted the event or refused
+/// because it was edited there in the meantime; a server that couldn't be
+/// listed this sync keeps it for the next one.
+fn propagate_deletions(sources: &[CalendarSource], remote: &RemoteResources) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut propagated = 0;
+
+    for deleted in db::get_deleted_events()? {
+        let mut failed = false;
+
+        for source in sources {
+            let Some(client) = caldav_client(source) else {
+                continue;
+            };
+            let Some(listed) = remote.get(&source.id) else {
+                failed = true;
+                continue;
+            };
+            let Some(resource) = listed.get(&deleted.uid) else {
+                continue;
+            };
+            // If-Match with the ETag we just saw, so edits made on the
+            // server since then aren't silently thrown away
+            match client.delete_event(&resource.href, resource.etag.as_deref()) {
+                Ok(DeleteOutcome::Deleted) => {
+                    propagated += 1;
+                    db::insert_log(&LogEntry::info(
+                        "delete",
+                        &format!("Deleted '{}' from {}", deleted.uid, source.url.as_deref().unwrap_or(&source.name)),
+                    ))?;
+                }
+                Ok(DeleteOutcome::AlreadyGone) => {}
+                Ok(DeleteOutcome::Conflict) => {
+                    db::insert_log(&LogEntry::conflict(
+                        "delete",
+                        &format!(
+                            "Not deleting '{}' from {}: it was modified on the server since the last sync",
+                            deleted.uid, source.url.as_deref().unwrap_or(&source.name)
+                        ),
+                    ))?;
+                }
+                Err(e) => {
+                    failed = true;
+                    db::insert_log(&LogEntry::conflict(
+                        "delete",
+                        &format!("Deleting '{}' failed, will retry next sync: {}", deleted.uid, e),
+                    ))?;
+                }
+            }
+        }
+
+        if !failed {
+            db::clear_deleted_event(&deleted.uid)?;
+        }
+    }
+
+    Ok(propagated)
+}
+
 /// Preview pending changes without applying
 pub fn preview(sources: &[CalendarSource]) -> Result<String, Box<dyn std::error::Error>> {
     if sources.is_empty() {
@@ -113,7 +444,7 @@ fn detect_conflicts(all_events: &HashMap<String, Vec<CalendarEvent>>) -> Vec<Syn
         if versions.len() >= 2 {
             let a = versions[0];
             let b = versions[1];
-            if a.summary != b.summary || a.dtstart != b.dtstart || a.description != b.description {
+            if differs(a, b) {
                 conflicts.push(SyncConflict {
                     event_uid: a.uid.clone(),
                     source_version: a.clone(),
@@ -126,6 +457,10 @@ fn detect_conflicts(all_events: &HashMap<String, Vec<CalendarEvent>>) -> Vec<Syn
     conflicts
 }
 
+fn differs(a: &CalendarEvent, b: &CalendarEvent) -> bool {
+    a.summary != b.summary || a.start_key() != b.start_key() || a.description != b.description
+}
+
 fn resolve_conflict(conflict: &SyncConflict, strategy: &str) -> String {
     match strategy {
         "newest" => {
@@ -140,3 +475,45 @@ fn resolve_conflict(conflict: &SyncConflict, strategy: &str) -> String {
         _ => "ask".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(uid: &str, href: &str) -> CalendarEvent {
+        CalendarEvent {
+            uid: uid.into(),
+            summary: uid.into(),
+            description: None,
+            dtstart: "20240101T090000Z".into(),
+            dtend: None,
+            location: None,
+            source_id: "a".into(),
+            last_modified: "20240101T000000Z".into(),
+            href: Some(href.into()),
+            etag: Some("\"1\"".into()),
+            rrule: None,
+            alarms: Vec::new(),
+            tzid: None,
+            all_day: false,
+            start_utc: None,
+            end_utc: None,
+        }
+    }
+
+    fn resource(href: &str) -> DavResource {
+        DavResource { href: href.into(), etag: Some("\"1\"".into()), calendar_data: None }
+    }
+
+    #[test]
+    fn test_vanished_refuses_empty_listing() {
+        let (one, two) = (event("one", "/cal/one.ics"), event("two", "/cal/two.ics"));
+        let known: HashMap<&str, &CalendarEvent> = [("/cal/one.ics", &one), ("/cal/two.ics", &two)].into();
+
+        let removed = vanished(&known, &[resource("/cal/one.ics")]).unwrap();
+        assert_eq!(removed.iter().map(|e| e.uid.as_str()).collect::<Vec<_>>(), ["two"]);
+
+        assert!(vanished(&known, &[]).is_none());
+        assert!(vanished(&HashMap::new(), &[]).unwrap().is_empty());
+    }
+}
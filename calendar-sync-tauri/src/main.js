@@ -36,6 +36,21 @@ document.getElementById('btn-outlook').addEventListener('click', async () => {
   } catch (e) { console.error(e); }
 });
 
+document.getElementById('btn-ics-url').addEventListener('click', () => {
+  document.getElementById('ics-url-form').classList.toggle('hidden');
+});
+
+// Read-only .ics subscription, refreshed on every sync
+document.getElementById('ics-url-subscribe').addEventListener('click', async () => {
+  const url = document.getElementById('ics-url').value.trim();
+  if (!url) return;
+  try {
+    await invoke('add_ics_url_source', { url, name: document.getElementById('ics-url-name').value });
+    document.getElementById('ics-url-form').classList.add('hidden');
+    refreshSources();
+  } catch (e) { console.error(e); }
+});
+
 // CalDAV connect
 document.getElementById('caldav-connect').addEventListener('click', async () => {
   const url = document.getElementById('caldav-url').value;
@@ -59,12 +74,47 @@ document.getElementById('btn-sync-now').addEventListener('click', async () => {
       dedup: document.getElementById('opt-dedup').checked,
       conflictStrategy: document.getElementById('conflict-strategy').value,
     });
-    status.textContent = result || '✅ Sync complete!';
+    status.textContent = (result && result.summary) || '✅ Sync complete!';
+    renderConflicts(result ? result.pending_conflicts : []);
   } catch (e) {
     status.textContent = `❌ ${e}`;
   }
 });
 
+// Conflicts left for the user when the strategy is "ask"
+function renderConflicts(conflicts) {
+  const list = document.getElementById('sync-conflicts');
+  if (!conflicts || conflicts.length === 0) {
+    list.classList.add('hidden');
+    list.innerHTML = '';
+    return;
+  }
+  list.classList.remove('hidden');
+  list.innerHTML = conflicts.map(c =>
+    `<div class="log-entry conflict" data-uid="${c.event_uid}">
+      <span class="action">conflict</span> ${c.source_version.summary}
+      <div class="conflict-versions">
+        <button class="btn-secondary" data-keep="source">Keep “${c.source_version.summary}” · ${c.source_version.dtstart}</button>
+        <button class="btn-secondary" data-keep="target">Keep “${c.target_version.summary}” · ${c.target_version.dtstart}</button>
+      </div>
+    </div>`
+  ).join('');
+}
+
+document.getElementById('sync-conflicts').addEventListener('click', async (e) => {
+  const button = e.target.closest('button[data-keep]');
+  if (!button) return;
+  const entry = button.closest('[data-uid]');
+  const status = document.getElementById('sync-status');
+  try {
+    status.textContent = await invoke('resolve_conflict_manual', { uid: entry.dataset.uid, keep: button.dataset.keep });
+    entry.remove();
+    if (!document.querySelector('#sync-conflicts [data-uid]')) renderConflicts([]);
+  } catch (err) {
+    status.textContent = `❌ ${err}`;
+  }
+});
+
 document.getElementById('btn-sync-preview').addEventListener('click', async () => {
   try {
     const changes = await invoke('preview_sync');
@@ -74,11 +124,82 @@ document.getElementById('btn-sync-preview').addEventListener('click', async () =
   } catch (e) { console.error(e); }
 });
 
-// Auto-schedule toggle
-document.getElementById('opt-auto').addEventListener('change', (e) => {
-  document.getElementById('auto-interval-group').style.display = e.target.checked ? 'flex' : 'none';
+// Export merged calendar
+document.getElementById('btn-export-ics').addEventListener('click', async () => {
+  const status = document.getElementById('sync-status');
+  try {
+    const outputPath = window.__TAURI__
+      ? await window.__TAURI__.dialog.save({ defaultPath: 'calendar.ics', filters: [{ name: 'iCalendar', extensions: ['ics'] }] })
+      : null;
+    if (!outputPath) return;
+    const count = await invoke('export_ics', { outputPath, sourceIds: null });
+    status.classList.remove('hidden');
+    status.textContent = `✅ Exported ${count} events to ${outputPath}`;
+  } catch (e) {
+    status.classList.remove('hidden');
+    status.textContent = `❌ ${e}`;
+  }
 });
 
+// Auto-schedule toggle
+async function updateAutoSync() {
+  const enabled = document.getElementById('opt-auto').checked;
+  document.getElementById('auto-interval-group').style.display = enabled ? 'flex' : 'none';
+  try {
+    showSyncStatus(await invoke('set_auto_sync', {
+      enabled,
+      intervalMinutes: parseInt(document.getElementById('sync-interval').value, 10),
+    }));
+  } catch (e) { console.error(e); }
+}
+document.getElementById('opt-auto').addEventListener('change', updateAutoSync);
+document.getElementById('sync-interval').addEventListener('change', updateAutoSync);
+
+// "last synced 5 min ago", kept current by the scheduler's sync-status events
+let syncStatus = null;
+
+function timeAgo(timestamp) {
+  // Timestamps are stored as UTC "YYYY-MM-DD HH:MM:SS"
+  const minutes = Math.round((Date.now() - new Date(timestamp.replace(' ', 'T') + 'Z')) / 60000);
+  if (minutes < 1) return 'just now';
+  if (minutes < 60) return `${minutes} min ago`;
+  const hours = Math.round(minutes / 60);
+  return hours < 24 ? `${hours} h ago` : `${Math.round(hours / 24)} d ago`;
+}
+
+function showSyncStatus(status) {
+  if (status) syncStatus = status;
+  if (!syncStatus) return;
+  const parts = [];
+  if (syncStatus.state === 'running') parts.push('⏳ Syncing...');
+  else if (syncStatus.state === 'error') parts.push(`❌ Last sync failed: ${syncStatus.error}`);
+  parts.push(syncStatus.last_synced ? `Last synced ${timeAgo(syncStatus.last_synced)}` : 'Never synced');
+  if (syncStatus.auto_sync) parts.push(`auto-sync every ${syncStatus.interval_minutes} min`);
+  document.getElementById('auto-sync-status').textContent = parts.join(' · ');
+}
+
+async function initSyncStatus() {
+  try {
+    const status = await invoke('get_sync_status');
+    if (!status) return;
+    document.getElementById('opt-auto').checked = status.auto_sync;
+    document.getElementById('auto-interval-group').style.display = status.auto_sync ? 'flex' : 'none';
+    const interval = document.getElementById('sync-interval');
+    if ([...interval.options].some(o => o.value === String(status.interval_minutes))) {
+      interval.value = String(status.interval_minutes);
+    }
+    showSyncStatus(status);
+  } catch (e) { console.error(e); }
+}
+
+if (window.__TAURI__) {
+  window.__TAURI__.event.listen('sync-status', (event) => {
+    showSyncStatus(event.payload);
+    if (event.payload.state !== 'running') { refreshSources(); refreshLog(); }
+  });
+}
+setInterval(() => showSyncStatus(), 60000);
+
 // Log
 document.getElementById('btn-refresh-log').addEventListener('click', refreshLog);
 document.getElementById('btn-clear-log').addEventListener('click', async () => {
@@ -88,20 +209,106 @@ document.getElementById('btn-clear-log').addEventListener('click', async () => {
   } catch (e) { console.error(e); }
 });
 
+// Events in a date range, colored by source
+let sourceColors = {};
+
+// Timed events in the local zone; all-day events are the same day everywhere
+function formatEventTime(event) {
+  const m = event.dtstart.match(/^(\d{4})(\d{2})(\d{2})/);
+  if (event.all_day && m) return `${m[1]}-${m[2]}-${m[3]} (all day)`;
+  if (!event.start_utc) return event.dtstart;
+  return new Date(event.start_utc).toLocaleString([], { dateStyle: 'medium', timeStyle: 'short' });
+}
+
+async function refreshEvents() {
+  const from = document.getElementById('events-from').value;
+  const to = document.getElementById('events-to').value;
+  const list = document.getElementById('events-list');
+  if (!from || !to) return;
+  try {
+    const events = await invoke('get_events', {
+      from,
+      to,
+      sourceIds: null,
+      textQuery: document.getElementById('events-search').value || null,
+    });
+    if (!events || events.length === 0) {
+      list.innerHTML = '<p class="placeholder">No events in this range.</p>';
+      return;
+    }
+    list.innerHTML = events.map(e =>
+      `<div class="log-entry event-row"><span class="source-dot" style="background:${sourceColors[e.source_id] || 'var(--accent)'}"></span><span class="timestamp">${formatEventTime(e)}</span> ${e.summary}${e.location ? ` <span class="timestamp">· ${e.location}</span>` : ''}</div>`
+    ).join('');
+  } catch (err) {
+    list.innerHTML = `<p class="placeholder">❌ ${err}</p>`;
+  }
+}
+
+document.getElementById('btn-events-load').addEventListener('click', refreshEvents);
+
+// Double bookings between calendars in the same range
+document.getElementById('btn-events-overlaps').addEventListener('click', async () => {
+  const from = document.getElementById('events-from').value;
+  const to = document.getElementById('events-to').value;
+  const list = document.getElementById('events-list');
+  if (!from || !to) return;
+  try {
+    const overlaps = await invoke('find_overlaps', { from, to });
+    if (!overlaps || overlaps.length === 0) {
+      list.innerHTML = '<p class="placeholder">No overlapping events in this range.</p>';
+      return;
+    }
+    list.innerHTML = overlaps.map(o =>
+      `<div class="log-entry conflict"><span class="action">overlap</span> ${o.first.summary} ↔ ${o.second.summary} <span class="timestamp">${o.start.replace('T', ' ').slice(0, 16)} – ${o.end.replace('T', ' ').slice(0, 16)} UTC</span></div>`
+    ).join('');
+  } catch (err) {
+    list.innerHTML = `<p class="placeholder">❌ ${err}</p>`;
+  }
+});
+document.getElementById('events-search').addEventListener('keydown', (e) => {
+  if (e.key === 'Enter') refreshEvents();
+});
+
+(() => {
+  const today = new Date();
+  const inMonth = new Date(today.getTime() + 30 * 86400000);
+  document.getElementById('events-from').value = today.toISOString().slice(0, 10);
+  document.getElementById('events-to').value = inMonth.toISOString().slice(0, 10);
+})();
+
 async function refreshSources() {
   try {
     const sources = await invoke('list_sources');
+    sourceColors = Object.fromEntries((sources || []).filter(s => s.color).map(s => [s.id, s.color]));
     const list = document.getElementById('sources-list');
     if (!sources || sources.length === 0) {
       list.innerHTML = '<p class="placeholder">No sources configured yet.</p>';
       return;
     }
     list.innerHTML = sources.map(s =>
-      `<div class="log-entry"><span class="action">${s.source_type}</span> — ${s.name || s.url || 'configured'} <span class="timestamp">${s.added_at || ''}</span></div>`
+      `<div class="log-entry source-row${s.enabled === false ? ' disabled' : ''}" data-id="${s.id}">
+        <input type="color" class="source-color" value="${s.color || '#4f8cff'}" title="Calendar color" />
+        <span class="action">${s.source_type}</span> — ${s.name || s.url || 'configured'} <span class="timestamp">${s.last_synced ? `synced ${s.last_synced}` : s.added_at || ''}</span>
+        <label class="option source-enabled"><input type="checkbox" ${s.enabled === false ? '' : 'checked'} /> Sync</label>
+      </div>`
     ).join('');
   } catch (e) { console.error(e); }
 }
 
+// Per-source color and on/off switch
+document.getElementById('sources-list').addEventListener('change', async (e) => {
+  const row = e.target.closest('[data-id]');
+  if (!row) return;
+  try {
+    if (e.target.classList.contains('source-color')) {
+      await invoke('set_source_color', { id: row.dataset.id, color: e.target.value });
+    } else if (e.target.type === 'checkbox') {
+      await invoke('set_source_enabled', { id: row.dataset.id, enabled: e.target.checked });
+      row.classList.toggle('disabled', !e.target.checked);
+    }
+  } catch (err) { console.error(err); }
+});
+
 async function refreshLog() {
   try {
     const entries = await invoke('get_log');
@@ -117,5 +324,6 @@ async function refreshLog() {
 }
 
 // Init
-refreshSources();
+refreshSources().then(refreshEvents);
 refreshLog();
+initSyncStatus();
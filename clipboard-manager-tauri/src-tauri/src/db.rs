@@ -8,6 +8,8 @@ pub struct ClipEntry {
     pub category: String,
     pub pinned: bool,
     pub created_at: String,
+    pub sensitive: Option<String>,  // name of the rule that flagged it
+    pub expires_at: Option<String>, // deleted after this time (local, like created_at)
 }
 
 pub struct Database {
@@ -30,12 +32,26 @@ impl Database {
             );
             CREATE INDEX IF NOT EXISTS idx_category ON entries(category);
             CREATE INDEX IF NOT EXISTS idx_pinned ON entries(pinned);
-            CREATE INDEX IF NOT EXISTS idx_created ON entries(created_at DESC);",
+            CREATE INDEX IF NOT EXISTS idx_created ON entries(created_at DESC);
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );",
         )?;
+        add_column_if_missing(&conn, "sensitive", "TEXT")?;
+        add_column_if_missing(&conn, "expires_at", "TEXT")?;
         Ok(Self { conn })
     }
 
-    pub fn insert(&self, content: &str, category: &str) -> Result<i64> {
+    /// `sensitive` names the rule that flagged the content, if any; with
+    /// `ttl_secs` the entry is removed by `purge_expired` once that time has passed.
+    pub fn insert(
+        &self,
+        content: &str,
+        category: &str,
+        sensitive: Option<&str>,
+        ttl_secs: Option<u64>,
+    ) -> Result<i64> {
         // Avoid duplicate of most recent entry
         let last: Option<String> = self
             .conn
@@ -48,9 +64,11 @@ impl Database {
         if last.as_deref() == Some(content) {
             return Ok(0);
         }
+        let ttl = ttl_secs.map(|secs| format!("+{} seconds", secs));
         self.conn.execute(
-            "INSERT INTO entries (content, category) VALUES (?1, ?2)",
-            params![content, category],
+            "INSERT INTO entries (content, category, sensitive, expires_at)
+             VALUES (?1, ?2, ?3, CASE WHEN ?4 IS NULL THEN NULL ELSE datetime('now', 'localtime', ?4) END)",
+            params![content, category, sensitive, ttl],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
@@ -63,7 +81,7 @@ impl Database {
         limit: usize,
         offset: usize,
     ) -> Result<Vec<ClipEntry>> {
-        let mut sql = String::from("SELECT id, content, category, pinned, created_at FROM entries WHERE 1=1");
+        let mut sql = String::from("SELECT id, content, category, pinned, created_at, sensitive, expires_at FROM entries WHERE 1=1");
         let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
 
         if let Some(q) = query {
@@ -95,6 +113,8 @@ impl Database {
                     category: row.get(2)?,
                     pinned: row.get::<_, i32>(3)? != 0,
                     created_at: row.get(4)?,
+                    sensitive: row.get(5)?,
+                    expires_at: row.get(6)?,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
@@ -124,6 +144,30 @@ impl Database {
         Ok(())
     }
 
+    /// Delete entries whose TTL ran out, pinned or not
+    pub fn purge_expired(&self) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM entries WHERE expires_at IS NOT NULL AND expires_at <= datetime('now', 'localtime')",
+            [],
+        )
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        match self.conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0)) {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
     pub fn enforce_limit(&self, max: usize) -> Result<()> {
         self.conn.execute(
             "DELETE FROM entries WHERE pinned = 0 AND id NOT IN (SELECT id FROM entries ORDER BY pinned DESC, id DESC LIMIT ?1)",
@@ -133,6 +177,19 @@ impl Database {
     }
 }
 
+// Columns added after the first release; CREATE TABLE IF NOT EXISTS won't add them
+fn add_column_if_missing(conn: &Connection, column: &str, decl: &str) -> Result<()> {
+    let exists = conn
+        .prepare("PRAGMA table_info(entries)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE entries ADD COLUMN {} {}", column, decl), [])?;
+    }
+    Ok(())
+}
+
 fn dirs_next() -> Option<std::path::PathBuf> {
     std::env::var("HOME")
         .ok()
@@ -1,7 +1,9 @@
 mod db;
+mod sensitive;
 
 use arboard::Clipboard;
 use db::{ClipEntry, Database};
+use sensitive::{Detector, SensitiveSettings};
 use serde::Serialize;
 use tauri::Emitter;
 use sha2::{Digest, Sha256};
@@ -11,6 +13,7 @@ use tauri::{AppHandle, Manager, State};
 
 struct AppState {
     db: Mutex<Database>,
+    sensitive: Mutex<(SensitiveSettings, Detector)>,
 }
 
 #[derive(Serialize)]
@@ -116,6 +119,43 @@ fn export_entries(
     }
 }
 
+#[tauri::command]
+fn get_sensitive_settings(state: State<AppState>) -> Result<SensitiveSettings, String> {
+    let sensitive = state.sensitive.lock().map_err(|e| e.to_string())?;
+    Ok(sensitive.0.clone())
+}
+
+/// Replace the sensitive-content mode, TTL and rules. Patterns are checked
+/// before anything is saved.
+#[tauri::command]
+fn set_sensitive_settings(state: State<AppState>, settings: SensitiveSettings) -> Result<(), String> {
+    if !matches!(settings.mode.as_str(), "skip" | "redact" | "store") {
+        return Err("Mode must be 'skip', 'redact' or 'store'".into());
+    }
+    let detector = Detector::new(&settings.rules)?;
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.set_setting("sensitive", &json).map_err(|e| e.to_string())?;
+    }
+    *state.sensitive.lock().map_err(|e| e.to_string())? = (settings, detector);
+    Ok(())
+}
+
+fn load_sensitive_settings(db: &Database) -> (SensitiveSettings, Detector) {
+    let saved = db
+        .get_setting("sensitive")
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<SensitiveSettings>(&json).ok())
+        .and_then(|settings| Detector::new(&settings.rules).ok().map(|d| (settings, d)));
+    saved.unwrap_or_else(|| {
+        let settings = SensitiveSettings::default();
+        let detector = Detector::new(&settings.rules).expect("built-in rules compile");
+        (settings, detector)
+    })
+}
+
 #[tauri::command]
 fn copy_to_clipboard(content: String) -> Result<(), String> {
     let mut clip = Clipboard::new().map_err(|e| e.to_string())?;
@@ -127,6 +167,16 @@ fn start_clipboard_monitor(app: AppHandle) {
         let mut last_hash = String::new();
         loop {
             std::thread::sleep(Duration::from_millis(500));
+            let purged = app
+                .state::<AppState>()
+                .db
+                .lock()
+                .map(|db| db.purge_expired().unwrap_or(0))
+                .unwrap_or(0);
+            if purged > 0 {
+                let _ = app.emit("clipboard-updated", ());
+            }
+
             let text = {
                 let Ok(mut clip) = Clipboard::new() else { continue };
                 match clip.get_text() {
@@ -139,10 +189,27 @@ fn start_clipboard_monitor(app: AppHandle) {
                 continue;
             }
             last_hash = hash;
-            let category = detect_category(&text);
             let state = app.state::<AppState>();
+
+            // Secrets are skipped, redacted, or kept with a short TTL. Once
+            // redacted there's no secret left to expire.
+            let (flag, content, ttl) = match state.sensitive.lock() {
+                Ok(sensitive) => {
+                    let (settings, detector) = &*sensitive;
+                    match detector.detect(&text) {
+                        None => (None, text, None),
+                        Some(_) if settings.mode == "skip" => continue,
+                        Some(rule) if settings.mode == "redact" => (Some(rule), detector.redact(&text), None),
+                        Some(rule) => (Some(rule), text, Some(settings.ttl_secs)),
+                    }
+                }
+                Err(_) => continue,
+            };
+
+            // Categorised by what is actually stored
+            let category = detect_category(&content);
             if let Ok(db) = state.db.lock() {
-                let _ = db.insert(&text, &category);
+                let _ = db.insert(&content, &category, flag.as_deref(), ttl);
                 let _ = db.enforce_limit(1000);
             }
             let _ = app.emit("clipboard-updated", ());
@@ -152,10 +219,14 @@ fn start_clipboard_monitor(app: AppHandle) {
 
 pub fn run() {
     let db = Database::new().expect("Failed to initialize database");
+    let sensitive = load_sensitive_settings(&db);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(AppState { db: Mutex::new(db) })
+        .manage(AppState {
+            db: Mutex::new(db),
+            sensitive: Mutex::new(sensitive),
+        })
         .invoke_handler(tauri::generate_handler![
             get_entries,
             toggle_pin,
@@ -164,6 +235,8 @@ pub fn run() {
             get_stats,
             export_entries,
             copy_to_clipboard,
+            get_sensitive_settings,
+            set_sensitive_settings,
         ])
         .setup(|app| {
             start_clipboard_monitor(app.handle().clone());
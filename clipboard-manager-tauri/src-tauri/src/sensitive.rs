@@ -0,0 +1,120 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A pattern for content that shouldn't sit in the history (keys, card numbers…)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensitiveRule {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Only count digit runs that pass the Luhn check (card numbers)
+    #[serde(default)]
+    pub luhn: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// What the monitor does with a copied secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensitiveSettings {
+    pub mode: String, // "skip" | "redact" | "store"
+    /// Flagged entries stored with the secret intact ("store") are deleted
+    /// after this many seconds
+    pub ttl_secs: u64,
+    pub rules: Vec<SensitiveRule>,
+}
+
+impl Default for SensitiveSettings {
+    fn default() -> Self {
+        Self {
+            mode: "redact".into(),
+            ttl_secs: 300,
+            rules: default_rules(),
+        }
+    }
+}
+
+pub fn default_rules() -> Vec<SensitiveRule> {
+    let rule = |name: &str, pattern: &str, luhn: bool| SensitiveRule {
+        name: name.into(),
+        pattern: pattern.into(),
+        enabled: true,
+        luhn,
+    };
+    vec![
+        rule("Credit card", r"\b(?:\d[ -]?){12,18}\d\b", true),
+        rule("Secret key (sk-…)", r"\bsk-[A-Za-z0-9_-]{20,}", false),
+        rule("GitHub token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})", false),
+        rule("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", false),
+        rule("Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}", false),
+        rule("JWT", r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}", false),
+        rule("Private key", r"-----BEGIN (?:[A-Z]+ )?PRIVATE KEY-----", false),
+    ]
+}
+
+/// Rules compiled once per settings change
+pub struct Detector {
+    rules: Vec<(SensitiveRule, Regex)>,
+}
+
+impl Detector {
+    pub fn new(rules: &[SensitiveRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for rule in rules.iter().filter(|r| r.enabled) {
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid pattern for '{}': {}", rule.name, e))?;
+            compiled.push((rule.clone(), regex));
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    /// Name of the first rule the content trips, if any
+    pub fn detect(&self, content: &str) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|(rule, regex)| regex.find_iter(content).any(|m| !rule.luhn || luhn_valid(m.as_str())))
+            .map(|(rule, _)| rule.name.clone())
+    }
+
+    /// Replace every match with a `[REDACTED: rule]` marker
+    pub fn redact(&self, content: &str) -> String {
+        let mut text = content.to_string();
+        for (rule, regex) in &self.rules {
+            let marker = format!("[REDACTED: {}]", rule.name);
+            text = regex
+                .replace_all(&text, |caps: &regex::Captures| {
+                    if !rule.luhn || luhn_valid(&caps[0]) {
+                        marker.clone()
+                    } else {
+                        caps[0].to_string()
+                    }
+                })
+                .into_owned();
+        }
+        text
+    }
+}
+
+fn luhn_valid(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
@@ -1,4 +1,4 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, params, ToSql};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Mutex;
@@ -8,12 +8,100 @@ pub struct ClipItem {
     pub id: String,
     pub content: String,
     pub category: String,       // text | link | code | image
+    pub language: Option<String>, // best guess for code: rust | python | js | sql | json | shell | …
+    pub archived: bool,
+    pub pin_expires_at: Option<String>, // ISO 8601, None = pinned for good
+    pub use_count: i64,                 // times pasted via copy_to_clipboard
+    pub last_used_at: Option<String>,   // ISO 8601
+    pub is_template: bool,              // content has {{placeholders}} for expand_template
     pub pinned: bool,
     pub favorite: bool,
     pub timestamp: String,       // ISO 8601
     pub preview: String,         // truncated preview
 }
 
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CategoryStats {
+    pub category: String,
+    pub count: usize,
+    pub pinned: usize,
+    pub favorites: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    pub total: usize,
+    pub pinned: usize,
+    pub favorites: usize,
+    pub archived: usize,
+    pub total_bytes: u64,   // UTF-8 size of all stored content
+    pub categories: Vec<CategoryStats>,
+}
+
+/// Narrows an export to what the user is looking at. Empty = everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportFilter {
+    pub query: Option<String>,
+    pub category: Option<String>,  // "all" or None = any
+    pub pinned_only: bool,
+    pub favorites_only: bool,
+    pub since: Option<String>,     // YYYY-MM-DD or RFC 3339, inclusive
+    pub until: Option<String>,     // YYYY-MM-DD (whole day) or RFC 3339, exclusive
+}
+
+/// One record of an `export_data` JSON or CSV file. Only `content` is
+/// required; CSV exports carry just the basic columns.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ImportRecord {
+    pub content: String,
+    pub category: Option<String>,
+    pub language: Option<String>,
+    pub archived: bool,
+    pub pin_expires_at: Option<String>,
+    pub use_count: i64,
+    pub last_used_at: Option<String>,
+    pub is_template: bool,
+    pub pinned: bool,
+    pub favorite: bool,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportResult {
+    pub imported: usize,
+    pub skipped: usize,  // content already in history (or earlier in the file)
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+const CLIP_COLUMNS: &str = "id, content, category, pinned, favorite, timestamp, preview, language, archived, pin_expires_at, use_count, last_used_at, is_template";
+
+// Main list: ?1 = search text ("" = any), ?2 = category ("all", "archived" or a category).
+// Archived items only show up under "archived".
+const LIST_FILTER: &str = "((?2 = 'archived' AND archived = 1) OR (?2 <> 'archived' AND archived = 0 AND (?2 = 'all' OR category = ?2)))
+    AND (?1 = '' OR content LIKE '%' || ?1 || '%')";
+
+fn clip_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClipItem> {
+    Ok(ClipItem {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        category: row.get(2)?,
+        pinned: row.get::<_, i32>(3)? != 0,
+        favorite: row.get::<_, i32>(4)? != 0,
+        timestamp: row.get(5)?,
+        preview: row.get(6)?,
+        language: row.get(7)?,
+        archived: row.get::<_, i32>(8)? != 0,
+        pin_expires_at: row.get(9)?,
+        use_count: row.get(10)?,
+        last_used_at: row.get(11)?,
+        is_template: row.get::<_, i32>(12)? != 0,
+    })
+}
+
 pub struct Database {
     conn: Mutex<Connection>,
 }
@@ -42,6 +130,15 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_clips_pinned ON clips(pinned);
             CREATE INDEX IF NOT EXISTS idx_clips_content ON clips(content);
         ").map_err(|e| e.to_string())?;
+        if add_column_if_missing(&conn, "language", "TEXT")? {
+            backfill_languages(&conn)?;
+        }
+        // Existing pins keep a NULL expiry, i.e. never expire
+        add_column_if_missing(&conn, "archived", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "pin_expires_at", "TEXT")?;
+        add_column_if_missing(&conn, "use_count", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "last_used_at", "TEXT")?;
+        add_column_if_missing(&conn, "is_template", "INTEGER NOT NULL DEFAULT 0")?;
 
         Ok(Self { conn: Mutex::new(conn) })
     }
@@ -80,13 +177,14 @@ impl Database {
 
         let id = uuid::Uuid::new_v4().to_string();
         let category = categorize(content);
+        let language = if category == "code" { detect_language(content) } else { None };
         let preview = make_preview(content);
         let now = chrono::Utc::now().to_rfc3339();
 
         conn.execute(
-            "INSERT INTO clips (id, content, category, pinned, favorite, timestamp, preview)
-             VALUES (?1, ?2, ?3, 0, 0, ?4, ?5)",
-            params![id, content, category, now, preview],
+            "INSERT INTO clips (id, content, category, pinned, favorite, timestamp, preview, language)
+             VALUES (?1, ?2, ?3, 0, 0, ?4, ?5, ?6)",
+            params![id, content, category, now, preview, language],
         ).map_err(|e| e.to_string())?;
 
         // Auto-cleanup: keep max 2000 unpinned items
@@ -99,70 +197,29 @@ impl Database {
         self.get_by_id_conn(&conn, &id)
     }
 
+    pub fn get(&self, id: &str) -> Result<Option<ClipItem>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        self.get_by_id_conn(&conn, id)
+    }
+
     fn get_by_id_conn(&self, conn: &Connection, id: &str) -> Result<Option<ClipItem>, String> {
         conn.query_row(
-            "SELECT id, content, category, pinned, favorite, timestamp, preview FROM clips WHERE id = ?1",
+            &format!("SELECT {} FROM clips WHERE id = ?1", CLIP_COLUMNS),
             params![id],
-            |row| Ok(ClipItem {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                category: row.get(2)?,
-                pinned: row.get::<_, i32>(3)? != 0,
-                favorite: row.get::<_, i32>(4)? != 0,
-                timestamp: row.get(5)?,
-                preview: row.get(6)?,
-            }),
+            clip_from_row,
         ).map(Some).map_err(|e| e.to_string())
     }
 
     pub fn search(&self, query: &str, category: &str, limit: usize, offset: usize) -> Result<Vec<ClipItem>, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
-
-        let (sql, use_query) = match (query.is_empty(), category == "all") {
-            (true, true) => (
-                "SELECT id, content, category, pinned, favorite, timestamp, preview FROM clips ORDER BY pinned DESC, timestamp DESC LIMIT ?1 OFFSET ?2".to_string(),
-                false
-            ),
-            (true, false) => (
-                format!("SELECT id, content, category, pinned, favorite, timestamp, preview FROM clips WHERE category = '{}' ORDER BY pinned DESC, timestamp DESC LIMIT ?1 OFFSET ?2", category),
-                false
-            ),
-            (false, true) => (
-                "SELECT id, content, category, pinned, favorite, timestamp, preview FROM clips WHERE content LIKE '%' || ?3 || '%' ORDER BY pinned DESC, timestamp DESC LIMIT ?1 OFFSET ?2".to_string(),
-                true
-            ),
-            (false, false) => (
-                format!("SELECT id, content, category, pinned, favorite, timestamp, preview FROM clips WHERE category = '{}' AND content LIKE '%' || ?3 || '%' ORDER BY pinned DESC, timestamp DESC LIMIT ?1 OFFSET ?2", category),
-                true
-            ),
-        };
-
+        let sql = format!(
+            "SELECT {} FROM clips WHERE {} ORDER BY pinned DESC, timestamp DESC LIMIT ?3 OFFSET ?4",
+            CLIP_COLUMNS, LIST_FILTER
+        );
         let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
-        let rows = if use_query {
-            stmt.query_map(params![limit as i64, offset as i64, query], |row| {
-                Ok(ClipItem {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    category: row.get(2)?,
-                    pinned: row.get::<_, i32>(3)? != 0,
-                    favorite: row.get::<_, i32>(4)? != 0,
-                    timestamp: row.get(5)?,
-                    preview: row.get(6)?,
-                })
-            }).map_err(|e| e.to_string())?
-        } else {
-            stmt.query_map(params![limit as i64, offset as i64], |row| {
-                Ok(ClipItem {
-                    id: row.get(0)?,
-                    content: row.get(1)?,
-                    category: row.get(2)?,
-                    pinned: row.get::<_, i32>(3)? != 0,
-                    favorite: row.get::<_, i32>(4)? != 0,
-                    timestamp: row.get(5)?,
-                    preview: row.get(6)?,
-                })
-            }).map_err(|e| e.to_string())?
-        };
+        let rows = stmt
+            .query_map(params![query, category, limit as i64, offset as i64], clip_from_row)
+            .map_err(|e| e.to_string())?;
 
         let mut items = Vec::new();
         for row in rows {
@@ -173,31 +230,115 @@ impl Database {
 
     pub fn count(&self, query: &str, category: &str) -> Result<usize, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
-        let (sql, use_query) = match (query.is_empty(), category == "all") {
-            (true, true) => ("SELECT COUNT(*) FROM clips".to_string(), false),
-            (true, false) => (format!("SELECT COUNT(*) FROM clips WHERE category = '{}'", category), false),
-            (false, true) => ("SELECT COUNT(*) FROM clips WHERE content LIKE '%' || ?1 || '%'".to_string(), true),
-            (false, false) => (format!("SELECT COUNT(*) FROM clips WHERE category = '{}' AND content LIKE '%' || ?1 || '%'", category), true),
-        };
-        let count: i64 = if use_query {
-            conn.query_row(&sql, params![query], |r| r.get(0))
-        } else {
-            conn.query_row(&sql, [], |r| r.get(0))
-        }.map_err(|e| e.to_string())?;
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM clips WHERE {}", LIST_FILTER), params![query, category], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
         Ok(count as usize)
     }
 
+    /// Record that an item was pasted, so quick_match ranks it higher
+    pub fn mark_used(&self, content: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE clips SET use_count = use_count + 1, last_used_at = ?1 WHERE content = ?2",
+            params![chrono::Utc::now().to_rfc3339(), content.trim()],
+        ).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// The single best item for a typed prefix: a case-insensitive prefix
+    /// match if there is one, otherwise the best fuzzy (in-order characters)
+    /// match. Ties go to the most used, then most recently used item.
+    pub fn quick_match(&self, prefix: &str, category: &str) -> Result<Option<ClipItem>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let order = "ORDER BY use_count DESC, COALESCE(last_used_at, timestamp) DESC";
+        let scope = "archived = 0 AND (?1 = 'all' OR category = ?1)";
+
+        let escaped = prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let exact = conn.query_row(
+            &format!("SELECT {} FROM clips WHERE {} AND content LIKE ?2 || '%' ESCAPE '\\' {} LIMIT 1", CLIP_COLUMNS, scope, order),
+            params![category, escaped],
+            clip_from_row,
+        );
+        match exact {
+            Ok(item) => return Ok(Some(item)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM clips WHERE {} {} LIMIT 500", CLIP_COLUMNS, scope, order))
+            .map_err(|e| e.to_string())?;
+        let candidates = stmt.query_map(params![category], clip_from_row).map_err(|e| e.to_string())?;
+
+        // Tighter matches win; the SQL order breaks ties
+        let mut best: Option<(usize, ClipItem)> = None;
+        for item in candidates.filter_map(|r| r.ok()) {
+            if let Some(span) = fuzzy_span(&item.content, prefix) {
+                if best.as_ref().is_none_or(|(best_span, _)| span < *best_span) {
+                    best = Some((span, item));
+                }
+            }
+        }
+        Ok(best.map(|(_, item)| item))
+    }
+
     pub fn delete(&self, id: &str) -> Result<(), String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         conn.execute("DELETE FROM clips WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn toggle_pin(&self, id: &str) -> Result<bool, String> {
+    /// Pin or unpin. A pin with `expires_in_days` lapses back to a normal
+    /// item after that long; without it the pin never expires.
+    pub fn toggle_pin(&self, id: &str, expires_in_days: Option<i64>) -> Result<bool, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
-        conn.execute("UPDATE clips SET pinned = 1 - pinned WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
         let pinned: i32 = conn.query_row("SELECT pinned FROM clips WHERE id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
-        Ok(pinned != 0)
+        if pinned != 0 {
+            conn.execute("UPDATE clips SET pinned = 0, pin_expires_at = NULL WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        } else {
+            let expires_at = expires_in_days
+                .map(|days| (chrono::Utc::now() + chrono::Duration::days(days)).to_rfc3339());
+            conn.execute(
+                "UPDATE clips SET pinned = 1, archived = 0, pin_expires_at = ?1 WHERE id = ?2",
+                params![expires_at, id],
+            ).map_err(|e| e.to_string())?;
+        }
+        Ok(pinned == 0)
+    }
+
+    /// Unpin items whose pin has expired so normal cleanup applies to them again
+    pub fn expire_pins(&self) -> Result<usize, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let count = conn.execute(
+            "UPDATE clips SET pinned = 0, pin_expires_at = NULL
+             WHERE pinned = 1 AND pin_expires_at IS NOT NULL AND pin_expires_at < ?1",
+            params![chrono::Utc::now().to_rfc3339()],
+        ).map_err(|e| e.to_string())?;
+        Ok(count)
+    }
+
+    /// Archived items are hidden from the main list but, unlike pinned ones,
+    /// still age out with the retention rules. Archiving drops any pin.
+    pub fn toggle_archive(&self, id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let archived: i32 = conn.query_row("SELECT archived FROM clips WHERE id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
+        if archived != 0 {
+            conn.execute("UPDATE clips SET archived = 0 WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        } else {
+            conn.execute(
+                "UPDATE clips SET archived = 1, pinned = 0, pin_expires_at = NULL WHERE id = ?1",
+                params![id],
+            ).map_err(|e| e.to_string())?;
+        }
+        Ok(archived == 0)
+    }
+
+    pub fn toggle_template(&self, id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("UPDATE clips SET is_template = 1 - is_template WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        let template: i32 = conn.query_row("SELECT is_template FROM clips WHERE id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
+        Ok(template != 0)
     }
 
     pub fn toggle_favorite(&self, id: &str) -> Result<bool, String> {
@@ -213,13 +354,59 @@ impl Database {
         Ok(count)
     }
 
-    pub fn export_json(&self) -> Result<String, String> {
-        let items = self.search("", "all", 100000, 0)?;
+    /// Items matching `filter`, in the same order as the main list
+    pub fn export_items(&self, filter: &ExportFilter) -> Result<Vec<ClipItem>, String> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(query) = filter.query.as_deref().filter(|q| !q.is_empty()) {
+            conditions.push("content LIKE '%' || ? || '%'");
+            values.push(Box::new(query.to_string()));
+        }
+        match filter.category.as_deref() {
+            None | Some("all") => {}
+            Some("archived") => conditions.push("archived = 1"),
+            Some(category) => {
+                conditions.push("category = ?");
+                values.push(Box::new(category.to_string()));
+            }
+        }
+        if filter.pinned_only {
+            conditions.push("pinned = 1");
+        }
+        if filter.favorites_only {
+            conditions.push("favorite = 1");
+        }
+        if let Some(since) = &filter.since {
+            conditions.push("timestamp >= ?");
+            values.push(Box::new(range_bound(since, false)?));
+        }
+        if let Some(until) = &filter.until {
+            conditions.push("timestamp < ?");
+            values.push(Box::new(range_bound(until, true)?));
+        }
+
+        let mut sql = format!("SELECT {} FROM clips", CLIP_COLUMNS);
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY pinned DESC, timestamp DESC");
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+        let rows = stmt.query_map(params.as_slice(), clip_from_row).map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+    }
+
+    pub fn export_json(&self, filter: &ExportFilter) -> Result<String, String> {
+        let items = self.export_items(filter)?;
         serde_json::to_string_pretty(&items).map_err(|e| e.to_string())
     }
 
-    pub fn export_csv(&self) -> Result<String, String> {
-        let items = self.search("", "all", 100000, 0)?;
+    pub fn export_csv(&self, filter: &ExportFilter) -> Result<String, String> {
+        let items = self.export_items(filter)?;
         let mut wtr = csv::Writer::from_writer(Vec::new());
         wtr.write_record(&["id", "content", "category", "pinned", "favorite", "timestamp"]).map_err(|e| e.to_string())?;
         for item in &items {
@@ -232,6 +419,114 @@ impl Database {
         String::from_utf8(data).map_err(|e| e.to_string())
     }
 
+    pub fn export_markdown(&self, filter: &ExportFilter) -> Result<String, String> {
+        let items = self.export_items(filter)?;
+        let mut md = String::from("# Clipboard History\n");
+        for item in &items {
+            let mut heading = item.timestamp.get(..16).unwrap_or(&item.timestamp).replace('T', " ");
+            if item.pinned { heading.push_str(" · 📌"); }
+            if item.favorite { heading.push_str(" · ★"); }
+            md.push_str(&format!("\n## {}\n\n", heading));
+
+            match item.category.as_str() {
+                "code" => {
+                    let fence = code_fence(&item.content);
+                    md.push_str(&format!("{}\n{}\n{}\n", fence, item.content, fence));
+                }
+                "link" => md.push_str(&format!("[{}](<{}>)\n", item.content, item.content)),
+                "image" if item.content.starts_with("data:image/") => {
+                    md.push_str(&format!("![clipboard image]({})\n", item.content));
+                }
+                "image" => md.push_str("_(image data)_\n"),
+                _ => md.push_str(&format!("{}\n", item.content)),
+            }
+        }
+        Ok(md)
+    }
+
+    /// Insert imported records in one transaction. `replace_all` clears the
+    /// history first; either way content that is already present is skipped.
+    pub fn import(&self, records: Vec<Result<ImportRecord, String>>, replace_all: bool) -> Result<ImportResult, String> {
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        if replace_all {
+            tx.execute("DELETE FROM clips", []).map_err(|e| e.to_string())?;
+        }
+
+        let mut result = ImportResult::default();
+        for (i, record) in records.into_iter().enumerate() {
+            let record = match record.and_then(validate_import) {
+                Ok(record) => record,
+                Err(e) => {
+                    result.failed += 1;
+                    result.errors.push(format!("Record {}: {}", i + 1, e));
+                    continue;
+                }
+            };
+
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM clips WHERE content = ?1)",
+                params![record.content],
+                |r| r.get(0),
+            ).map_err(|e| e.to_string())?;
+            if exists {
+                result.skipped += 1;
+                continue;
+            }
+
+            tx.execute(
+                &format!("INSERT INTO clips ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)", CLIP_COLUMNS),
+                params![
+                    uuid::Uuid::new_v4().to_string(), record.content, record.category,
+                    record.pinned as i32, record.favorite as i32, record.timestamp,
+                    make_preview(&record.content), record.language, record.archived as i32,
+                    record.pin_expires_at, record.use_count, record.last_used_at, record.is_template as i32,
+                ],
+            ).map_err(|e| e.to_string())?;
+            result.imported += 1;
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(result)
+    }
+
+    /// Counts and sizes per category, aggregated in SQLite
+    pub fn stats(&self) -> Result<Stats, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare(
+            "SELECT category, COUNT(*),
+                    SUM(CASE WHEN pinned = 1 THEN 1 ELSE 0 END),
+                    SUM(CASE WHEN favorite = 1 THEN 1 ELSE 0 END),
+                    SUM(CASE WHEN archived = 1 THEN 1 ELSE 0 END),
+                    SUM(length(CAST(content AS BLOB)))
+             FROM clips GROUP BY category ORDER BY COUNT(*) DESC",
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                CategoryStats {
+                    category: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as usize,
+                    pinned: row.get::<_, i64>(2)? as usize,
+                    favorites: row.get::<_, i64>(3)? as usize,
+                    bytes: row.get::<_, i64>(5)? as u64,
+                },
+                row.get::<_, i64>(4)? as usize,
+            ))
+        }).map_err(|e| e.to_string())?;
+
+        let mut stats = Stats::default();
+        for row in rows {
+            let (category, archived) = row.map_err(|e| e.to_string())?;
+            stats.total += category.count;
+            stats.pinned += category.pinned;
+            stats.favorites += category.favorites;
+            stats.archived += archived;
+            stats.total_bytes += category.bytes;
+            stats.categories.push(category);
+        }
+        Ok(stats)
+    }
+
     pub fn cleanup_old(&self, days: i64) -> Result<usize, String> {
         let conn = self.conn.lock().map_err(|e| e.to_string())?;
         let cutoff = (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
@@ -243,6 +538,91 @@ impl Database {
     }
 }
 
+/// Records from an `export_data` file, each parsed on its own so one bad
+/// record doesn't sink the whole import
+pub fn parse_import(format: &str, content: &str) -> Result<Vec<Result<ImportRecord, String>>, String> {
+    match format {
+        "csv" => {
+            let mut rdr = csv::Reader::from_reader(content.as_bytes());
+            Ok(rdr.deserialize().map(|r| r.map_err(|e| e.to_string())).collect())
+        }
+        "json" => {
+            let values: Vec<serde_json::Value> = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+            Ok(values.into_iter().map(|v| serde_json::from_value(v).map_err(|e| e.to_string())).collect())
+        }
+        _ => Err(format!("Unsupported import format: {}", format)),
+    }
+}
+
+// Fills in what the file left out and rejects what the database couldn't use
+fn validate_import(mut record: ImportRecord) -> Result<ImportRecord, String> {
+    record.content = record.content.trim().to_string();
+    if record.content.is_empty() {
+        return Err("empty content".to_string());
+    }
+
+    let category_ok = record.category.as_deref().is_some_and(|c| ["text", "link", "code", "image"].contains(&c));
+    if !category_ok {
+        record.category = Some(categorize(&record.content));
+    }
+    if record.category.as_deref() == Some("code") && record.language.is_none() {
+        record.language = detect_language(&record.content);
+    }
+
+    record.timestamp = Some(match record.timestamp.as_deref() {
+        Some(ts) => normalize_timestamp(ts)?,
+        None => chrono::Utc::now().to_rfc3339(),
+    });
+    record.pin_expires_at = record.pin_expires_at.as_deref().map(normalize_timestamp).transpose()?;
+    record.last_used_at = record.last_used_at.as_deref().map(normalize_timestamp).transpose()?;
+    if !record.pinned {
+        record.pin_expires_at = None;
+    }
+    record.use_count = record.use_count.max(0);
+    Ok(record)
+}
+
+// Stored timestamps are UTC RFC 3339 so they sort as text
+fn normalize_timestamp(value: &str) -> Result<String, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339())
+        .map_err(|_| format!("invalid timestamp: {}", value))
+}
+
+// CREATE TABLE IF NOT EXISTS keeps older databases as they were, so newer
+// columns are added here. Returns true if the column was just created.
+fn add_column_if_missing(conn: &Connection, column: &str, decl: &str) -> Result<bool, String> {
+    let exists = conn
+        .prepare("PRAGMA table_info(clips)")
+        .and_then(|mut stmt| {
+            let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+            Ok(names.filter_map(|r| r.ok()).any(|name| name == column))
+        })
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE clips ADD COLUMN {} {}", column, decl), [])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(!exists)
+}
+
+fn backfill_languages(conn: &Connection) -> Result<(), String> {
+    let code: Vec<(String, String)> = conn
+        .prepare("SELECT id, content FROM clips WHERE category = 'code'")
+        .and_then(|mut stmt| {
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            Ok(rows.filter_map(|r| r.ok()).collect())
+        })
+        .map_err(|e| e.to_string())?;
+    for (id, content) in code {
+        conn.execute(
+            "UPDATE clips SET language = ?1 WHERE id = ?2",
+            params![detect_language(&content), id],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 fn dirs_next() -> Option<PathBuf> {
     #[cfg(target_os = "macos")]
     {
@@ -283,6 +663,127 @@ fn categorize(text: &str) -> String {
     "text".to_string()
 }
 
+// Keywords that are distinctive for each language; the best-scoring one wins
+const LANGUAGE_HINTS: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn ", "use std", "::", "&str", "&self", "println!", "#[derive", "match ", "Option<", "Vec<"]),
+    ("python", &["def ", "elif ", "self.", "print(", "import ", "__init__", "None", "True:", "lambda ", "):\n"]),
+    ("typescript", &["interface ", ": string", ": number", ": boolean", "export type ", "implements ", "readonly "]),
+    ("js", &["function ", "const ", "=> ", "console.log", "let ", "var ", "===", "require(", "document.", "export default"]),
+    ("go", &["package ", "func ", ":= ", "fmt.", "import (", "err != nil", "chan "]),
+    ("java", &["public class ", "public static void", "System.out", "private ", "@Override", "extends "]),
+    ("c", &["#include", "int main", "printf(", "malloc(", "void ", "->", "NULL"]),
+    ("shell", &["echo ", "$1", "fi\n", "then\n", "done\n", "sudo ", "| grep", "${", "export ", "if [ "]),
+    ("css", &["px;", "color:", "margin:", "padding:", "display:", "@media", "font-size:", "!important"]),
+];
+
+const SQL_KEYWORDS: &[&str] = &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "UPDATE ", "DELETE FROM", "CREATE TABLE", "JOIN ", "GROUP BY", "ORDER BY", "VALUES"];
+
+/// Best guess at the language of a code snippet, for syntax highlighting
+pub fn detect_language(content: &str) -> Option<String> {
+    let text = content.trim();
+
+    if let Some(shebang) = text.lines().next().and_then(|l| l.strip_prefix("#!")) {
+        let lang = if shebang.contains("python") {
+            "python"
+        } else if shebang.contains("node") {
+            "js"
+        } else if shebang.contains("ruby") {
+            "ruby"
+        } else if shebang.contains("perl") {
+            "perl"
+        } else {
+            "shell"
+        };
+        return Some(lang.to_string());
+    }
+
+    if (text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+    {
+        return Some("json".to_string());
+    }
+    if text.starts_with("<?xml") {
+        return Some("xml".to_string());
+    }
+    if text.starts_with('<') && text.contains("</") {
+        return Some("html".to_string());
+    }
+
+    // SQL is usually a statement on its own and often lowercase
+    let upper = text.to_uppercase();
+    let sql_score = SQL_KEYWORDS.iter().filter(|k| upper.contains(*k)).count();
+    let starts_sql = ["SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "WITH", "DROP"]
+        .iter()
+        .any(|k| upper.starts_with(k));
+    if starts_sql && sql_score >= 2 {
+        return Some("sql".to_string());
+    }
+
+    let (lang, score) = LANGUAGE_HINTS
+        .iter()
+        .map(|(lang, hints)| (*lang, hints.iter().filter(|h| text.contains(*h)).count()))
+        .fold(("", 0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+    if score >= 2 {
+        Some(lang.to_string())
+    } else if sql_score >= 3 {
+        Some("sql".to_string())
+    } else {
+        None
+    }
+}
+
+// Length of the shortest stretch of `text` containing the characters of
+// `pattern` in order (case-insensitive), or None if they don't all appear
+fn fuzzy_span(text: &str, pattern: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let pattern: Vec<char> = pattern.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let first = *pattern.first()?;
+
+    let mut best: Option<usize> = None;
+    for start in (0..text.len()).filter(|&i| text[i] == first) {
+        let mut matched = 1;
+        let mut end = start;
+        for (i, c) in text.iter().enumerate().skip(start + 1) {
+            if matched == pattern.len() {
+                break;
+            }
+            if *c == pattern[matched] {
+                matched += 1;
+                end = i;
+            }
+        }
+        if matched < pattern.len() {
+            break;
+        }
+        let span = end - start + 1;
+        best = Some(best.map_or(span, |b| b.min(span)));
+    }
+    best
+}
+
+// A date alone covers the whole day; full timestamps are used as given
+fn range_bound(value: &str, end: bool) -> Result<String, String> {
+    match chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => {
+            let date = if end { date + chrono::Duration::days(1) } else { date };
+            Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339())
+        }
+        Err(_) => chrono::DateTime::parse_from_rfc3339(value)
+            .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339())
+            .map_err(|_| format!("Invalid date: {}", value)),
+    }
+}
+
+// Longer than any backtick run in the content, so the block can't close early
+fn code_fence(content: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
 fn make_preview(text: &str) -> String {
     let lines: Vec<&str> = text.lines().take(4).collect();
     let mut preview = lines.join("\n");
@@ -294,3 +795,59 @@ fn make_preview(text: &str) -> String {
     }
     preview
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let cases = [
+            ("rust", "use std::fs;\n\npub fn read(path: &str) -> Option<String> {\n    let mut s = String::new();\n    fs::read_to_string(path).ok()\n}"),
+            ("python", "def greet(name):\n    if name is None:\n        return\n    print(f\"hi {name}\")"),
+            ("python", "#!/usr/bin/env python3\nimport sys\nprint(sys.argv)"),
+            ("js", "const add = (a, b) => a + b;\nfunction main() {\n  console.log(add(1, 2));\n}"),
+            ("typescript", "interface User {\n  name: string;\n  age: number;\n}\nconst u: User = { name: 'a', age: 1 };"),
+            ("sql", "select id, name\nfrom users\nwhere active = 1\norder by name;"),
+            ("json", "{\n  \"name\": \"core\",\n  \"tags\": [1, 2, 3]\n}"),
+            ("shell", "#!/bin/bash\nset -e\necho \"building\""),
+            ("shell", "if [ -z \"$1\" ]; then\n  echo \"usage\"\n  exit 1\nfi\n"),
+            ("go", "package main\n\nimport \"fmt\"\n\nfunc main() {\n    x := 1\n    fmt.Println(x)\n}"),
+            ("css", ".card {\n  margin: 0 auto;\n  padding: 8px;\n  color: #fff;\n}"),
+            ("html", "<div class=\"card\">\n  <p>Hello</p>\n</div>"),
+        ];
+        for (expected, snippet) in cases {
+            assert_eq!(detect_language(snippet).as_deref(), Some(expected), "snippet:\n{}", snippet);
+        }
+    }
+
+    #[test]
+    fn test_parse_import_round_trips_exports() {
+        let csv = "id,content,category,pinned,favorite,timestamp\n\
+                   a,hello,text,true,false,2024-05-01T10:00:00+02:00\n\
+                   b,,text,false,false,2024-05-01T10:00:00Z\n\
+                   c,later,bogus,false,true,yesterday\n";
+        let records: Vec<_> = parse_import("csv", csv).unwrap().into_iter().map(|r| r.and_then(validate_import)).collect();
+        let first = records[0].as_ref().unwrap();
+        assert!(first.pinned && !first.favorite);
+        assert_eq!(first.timestamp.as_deref(), Some("2024-05-01T08:00:00+00:00"));
+        assert!(records[1].is_err(), "empty content");
+        assert!(records[2].is_err(), "bad timestamp");
+
+        let json = r#"[{"content": "https://example.com", "favorite": true, "use_count": 3}, {"content": 5}]"#;
+        let records = parse_import("json", json).unwrap();
+        let link = records[0].clone().and_then(validate_import).unwrap();
+        assert_eq!(link.category.as_deref(), Some("link"));
+        assert!(link.favorite);
+        assert_eq!(link.use_count, 3);
+        assert!(records[1].is_err());
+
+        assert!(parse_import("xml", "").is_err());
+    }
+
+    #[test]
+    fn test_detect_language_plain_text() {
+        assert_eq!(detect_language("Meeting moved to Thursday at 3pm."), None);
+        assert_eq!(detect_language("{ not json"), None);
+    }
+}
@@ -1,15 +1,18 @@
 mod db;
+mod preview;
 
-use db::{ClipItem, Database};
+use db::{ClipItem, Database, ExportFilter, ImportResult, Stats};
 use std::sync::Arc;
-use tauri::{Manager, State};
+use tauri::{Emitter, Manager, State};
 use tokio::sync::Mutex as TokioMutex;
 use arboard::Clipboard;
+use std::collections::HashMap;
 
 struct AppState {
     db: Database,
     last_clipboard: TokioMutex<String>,
     monitoring: TokioMutex<bool>,
+    link_previews: TokioMutex<HashMap<String, String>>, // url → rendered card
 }
 
 // ── Tauri Commands ──────────────────────────────────────────────────────────
@@ -34,6 +37,11 @@ async fn get_count(
     state.db.count(&query, &category)
 }
 
+#[tauri::command]
+async fn get_stats(state: State<'_, Arc<AppState>>) -> Result<Stats, String> {
+    state.db.stats()
+}
+
 #[tauri::command]
 async fn add_item(state: State<'_, Arc<AppState>>, content: String) -> Result<Option<ClipItem>, String> {
     state.db.add(&content)
@@ -45,8 +53,18 @@ async fn delete_item(state: State<'_, Arc<AppState>>, id: String) -> Result<(),
 }
 
 #[tauri::command]
-async fn toggle_pin(state: State<'_, Arc<AppState>>, id: String) -> Result<bool, String> {
-    state.db.toggle_pin(&id)
+async fn toggle_pin(state: State<'_, Arc<AppState>>, id: String, expires_in_days: Option<i64>) -> Result<bool, String> {
+    state.db.toggle_pin(&id, expires_in_days)
+}
+
+#[tauri::command]
+async fn toggle_archive(state: State<'_, Arc<AppState>>, id: String) -> Result<bool, String> {
+    state.db.toggle_archive(&id)
+}
+
+#[tauri::command]
+async fn expire_pins(state: State<'_, Arc<AppState>>) -> Result<usize, String> {
+    state.db.expire_pins()
 }
 
 #[tauri::command]
@@ -60,13 +78,38 @@ async fn clear_unpinned(state: State<'_, Arc<AppState>>) -> Result<usize, String
 }
 
 #[tauri::command]
-async fn export_data(state: State<'_, Arc<AppState>>, format: String) -> Result<String, String> {
+async fn export_data(
+    state: State<'_, Arc<AppState>>,
+    format: String,
+    filter: Option<ExportFilter>,
+) -> Result<String, String> {
+    let filter = filter.unwrap_or_default();
     match format.as_str() {
-        "csv" => state.db.export_csv(),
-        _ => state.db.export_json(),
+        "csv" => state.db.export_csv(&filter),
+        "md" | "markdown" => state.db.export_markdown(&filter),
+        _ => state.db.export_json(&filter),
     }
 }
 
+/// Load a JSON or CSV file written by `export_data`. `merge_strategy` is
+/// "skip_duplicates" (keep history, skip content already in it) or
+/// "replace_all" (clear history first).
+#[tauri::command]
+async fn import_data(
+    state: State<'_, Arc<AppState>>,
+    format: String,
+    content: String,
+    merge_strategy: String,
+) -> Result<ImportResult, String> {
+    let replace_all = match merge_strategy.as_str() {
+        "skip_duplicates" => false,
+        "replace_all" => true,
+        other => return Err(format!("Unknown merge strategy: {}", other)),
+    };
+    let records = db::parse_import(&format, &content)?;
+    state.db.import(records, replace_all)
+}
+
 #[tauri::command]
 async fn cleanup_old(state: State<'_, Arc<AppState>>, days: i64) -> Result<usize, String> {
     state.db.cleanup_old(days)
@@ -74,16 +117,118 @@ async fn cleanup_old(state: State<'_, Arc<AppState>>, days: i64) -> Result<usize
 
 #[tauri::command]
 async fn copy_to_clipboard(state: State<'_, Arc<AppState>>, content: String) -> Result<(), String> {
+    set_clipboard(&state, &content).await?;
+    state.db.mark_used(&content)
+}
+
+async fn set_clipboard(state: &AppState, content: &str) -> Result<(), String> {
     // Update last_clipboard to avoid re-detecting
     {
         let mut last = state.last_clipboard.lock().await;
-        *last = content.clone();
+        *last = content.to_string();
     }
     let mut clip = Clipboard::new().map_err(|e| e.to_string())?;
-    clip.set_text(&content).map_err(|e| e.to_string())?;
+    clip.set_text(content).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+#[tauri::command]
+async fn toggle_template(state: State<'_, Arc<AppState>>, id: String) -> Result<bool, String> {
+    state.db.toggle_template(&id)
+}
+
+/// Fill in a template's `{{name}}` placeholders from `vars`, falling back to
+/// the built-ins `{{date}}`, `{{time}}`, `{{datetime}}` and `{{clipboard}}`.
+/// Unknown placeholders are left as they are.
+#[tauri::command]
+async fn expand_template(
+    state: State<'_, Arc<AppState>>,
+    id: String,
+    vars: HashMap<String, String>,
+    copy: Option<bool>,
+) -> Result<String, String> {
+    let item = state.db.get(&id)?.ok_or("Item not found")?;
+    if !item.is_template {
+        return Err("Item is not a template".to_string());
+    }
+
+    let expanded = expand_placeholders(&item.content, |name| {
+        if let Some(value) = vars.get(name) {
+            return Some(value.clone());
+        }
+        let now = chrono::Local::now();
+        match name {
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "time" => Some(now.format("%H:%M").to_string()),
+            "datetime" => Some(now.format("%Y-%m-%d %H:%M").to_string()),
+            "clipboard" => Clipboard::new().and_then(|mut c| c.get_text()).ok(),
+            _ => None,
+        }
+    });
+
+    if copy.unwrap_or(false) {
+        set_clipboard(&state, &expanded).await?;
+        state.db.mark_used(&item.content)?;
+    }
+    Ok(expanded)
+}
+
+fn expand_placeholders(template: &str, mut lookup: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        let placeholder = &rest[start..start + 2 + len + 2];
+        out.push_str(&rest[..start]);
+        match lookup(placeholder[2..placeholder.len() - 2].trim()) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rendered HTML for the preview pane: highlighted code, Markdown, or a
+/// title card for links. Anything else, or a link that can't be fetched,
+/// comes back as escaped text.
+#[tauri::command]
+async fn render_preview(state: State<'_, Arc<AppState>>, id: String) -> Result<String, String> {
+    let item = state.db.get(&id)?.ok_or("Item not found")?;
+    let content = item.content.as_str();
+    match item.category.as_str() {
+        "code" => Ok(preview::code_html(content, item.language.as_deref())),
+        "link" => {
+            if let Some(card) = state.link_previews.lock().await.get(content) {
+                return Ok(card.clone());
+            }
+            match preview::link_html(content).await {
+                Ok(card) => {
+                    state.link_previews.lock().await.insert(item.content.clone(), card.clone());
+                    Ok(card)
+                }
+                Err(_) => Ok(preview::raw_html(content)),
+            }
+        }
+        "image" if content.starts_with("data:image/") => {
+            Ok(format!("<img class=\"preview-image\" src=\"{}\" />", preview::escape_html(content)))
+        }
+        _ if preview::looks_like_markdown(content) => Ok(preview::markdown_html(content)),
+        _ => Ok(preview::raw_html(content)),
+    }
+}
+
+/// Single best entry for a launcher-style quick paste
+#[tauri::command]
+async fn quick_match(
+    state: State<'_, Arc<AppState>>,
+    prefix: String,
+    category: Option<String>,
+) -> Result<Option<ClipItem>, String> {
+    state.db.quick_match(&prefix, category.as_deref().unwrap_or("all"))
+}
+
 #[tauri::command]
 async fn set_monitoring(state: State<'_, Arc<AppState>>, enabled: bool) -> Result<(), String> {
     let mut m = state.monitoring.lock().await;
@@ -145,6 +290,19 @@ fn start_clipboard_monitor(app: tauri::AppHandle, state: Arc<AppState>) {
     });
 }
 
+// ── Pin Expiry ──────────────────────────────────────────────────────────────
+
+fn start_pin_sweeper(app: tauri::AppHandle, state: Arc<AppState>) {
+    std::thread::spawn(move || loop {
+        if let Ok(count) = state.db.expire_pins() {
+            if count > 0 {
+                let _ = app.emit("clipboard-changed", ());
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(3600));
+    });
+}
+
 // ── App Setup ───────────────────────────────────────────────────────────────
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -154,6 +312,7 @@ pub fn run() {
         db,
         last_clipboard: TokioMutex::new(String::new()),
         monitoring: TokioMutex::new(true),
+        link_previews: TokioMutex::new(HashMap::new()),
     });
 
     tauri::Builder::default()
@@ -161,20 +320,29 @@ pub fn run() {
         .manage(state.clone())
         .setup(move |app| {
             let handle = app.handle().clone();
-            start_clipboard_monitor(handle, state.clone());
+            start_clipboard_monitor(handle.clone(), state.clone());
+            start_pin_sweeper(handle, state.clone());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_items,
             get_count,
+            get_stats,
             add_item,
             delete_item,
             toggle_pin,
+            toggle_archive,
+            expire_pins,
             toggle_favorite,
             clear_unpinned,
             export_data,
+            import_data,
             cleanup_old,
             copy_to_clipboard,
+            quick_match,
+            toggle_template,
+            expand_template,
+            render_preview,
             set_monitoring,
             get_monitoring,
         ])
@@ -0,0 +1,235 @@
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::sync::OnceLock;
+use std::time::Duration;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+const THEME: &str = "base16-ocean.dark";
+const LINK_TIMEOUT: Duration = Duration::from_secs(5);
+// Title and OpenGraph tags are in <head>, so there is no need to read whole pages
+const LINK_MAX_BYTES: usize = 256 * 1024;
+
+// Loading the bundled syntaxes takes a moment, so it happens on first use
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The content as-is, for anything that has no richer rendering
+pub fn raw_html(text: &str) -> String {
+    format!("<pre class=\"preview-raw\">{}</pre>", escape_html(text))
+}
+
+/// Plain-text clips with a couple of Markdown block markers, a link or bold text
+pub fn looks_like_markdown(text: &str) -> bool {
+    let blocks = text
+        .lines()
+        .map(str::trim_start)
+        .filter(|l| {
+            (l.starts_with('#') && l.trim_start_matches('#').starts_with(' '))
+                || ["- ", "* ", "> ", "```", "1. ", "| "].iter().any(|m| l.starts_with(m))
+        })
+        .count();
+    blocks >= 2 || text.contains("](") || text.contains("**")
+}
+
+pub fn markdown_html(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    // Raw HTML and script links in a clip are shown as text, never run in the UI
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) if !is_web_url(&dest_url) => {
+            Event::Start(Tag::Link { link_type, dest_url: CowStr::Borrowed("#"), title, id })
+        }
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    format!("<div class=\"preview-markdown\">{}</div>", out)
+}
+
+pub fn code_html(code: &str, language: Option<&str>) -> String {
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let themes = THEMES.get_or_init(ThemeSet::load_defaults);
+    let syntax = language
+        .and_then(|lang| syntaxes.find_syntax_by_token(syntax_token(lang)))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    highlighted_html_for_string(code, syntaxes, syntax, &themes.themes[THEME])
+        .unwrap_or_else(|_| raw_html(code))
+}
+
+// detect_language names the bundled syntaxes know under another token
+fn syntax_token(language: &str) -> &str {
+    match language {
+        "typescript" => "js",
+        "shell" => "sh",
+        other => other,
+    }
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// A title card for a link from the page's OpenGraph tags or <title>.
+/// Errors on timeouts, HTTP errors and pages without a title.
+pub async fn link_html(url: &str) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(LINK_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= LINK_MAX_BYTES {
+            break;
+        }
+    }
+
+    let meta = page_meta(&String::from_utf8_lossy(&body));
+    let title = meta.title.ok_or("Page has no title")?;
+    let mut card = format!("<a class=\"preview-link\" href=\"{}\">", escape_html(url));
+    if let Some(image) = meta.image.filter(|i| is_web_url(i)) {
+        card.push_str(&format!("<img class=\"preview-link-image\" src=\"{}\" />", escape_html(&image)));
+    }
+    if let Some(site) = meta.site {
+        card.push_str(&format!("<div class=\"preview-link-site\">{}</div>", escape_html(&site)));
+    }
+    card.push_str(&format!("<div class=\"preview-link-title\">{}</div>", escape_html(&title)));
+    if let Some(description) = meta.description {
+        card.push_str(&format!("<div class=\"preview-link-desc\">{}</div>", escape_html(&description)));
+    }
+    card.push_str(&format!("<div class=\"preview-link-url\">{}</div></a>", escape_html(url)));
+    Ok(card)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct PageMeta {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    site: Option<String>,
+}
+
+// A forgiving scan of <meta> and <title> tags; pages are too messy to need more
+fn page_meta(page: &str) -> PageMeta {
+    let lower = page.to_ascii_lowercase();
+    let mut meta = PageMeta::default();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta").map(|i| pos + i) {
+        let Some(len) = lower[start..].find('>') else { break };
+        let tag = &page[start..start + len];
+        pos = start + len;
+
+        let Some(content) = attr(tag, "content").filter(|c| !c.is_empty()) else { continue };
+        let key = attr(tag, "property").or_else(|| attr(tag, "name")).map(|k| k.to_ascii_lowercase());
+        match key.as_deref() {
+            Some("og:title") => meta.title = Some(content),
+            Some("og:description") => meta.description = Some(content),
+            Some("description") if meta.description.is_none() => meta.description = Some(content),
+            Some("og:image") => meta.image = Some(content),
+            Some("og:site_name") => meta.site = Some(content),
+            _ => {}
+        }
+    }
+
+    if meta.title.is_none() {
+        let title = lower.find("<title").and_then(|start| {
+            let open = start + lower[start..].find('>')? + 1;
+            let close = open + lower[open..].find("</title")?;
+            Some(decode_entities(page[open..close].trim()))
+        });
+        meta.title = title.filter(|t| !t.is_empty());
+    }
+    meta
+}
+
+// Value of `name="…"` (or '…' or unquoted) inside a tag
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name).map(|i| from + i) {
+        from = i + name.len();
+        let after = lower[from..].trim_start();
+        if !lower[..i].ends_with(|c: char| c.is_ascii_whitespace()) || !after.starts_with('=') {
+            continue;
+        }
+        // Lowercasing ASCII keeps byte offsets, so `lower` positions index `tag`
+        let value = tag[tag.len() - after.len() + 1..].trim_start();
+        let raw = match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+            _ => value.split(|c: char| c.is_ascii_whitespace()).next()?,
+        };
+        return Some(decode_entities(raw.trim()));
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_meta_prefers_opengraph() {
+        let page = r#"<html><head>
+            <title>Fallback &amp; Co</title>
+            <meta name="description" content="Plain description">
+            <META property='og:title' content='Open &quot;Graph&quot; Title' />
+            <meta property="og:image" content="https://example.com/card.png">
+            </head></html>"#;
+        let meta = page_meta(page);
+        assert_eq!(meta.title.as_deref(), Some("Open \"Graph\" Title"));
+        assert_eq!(meta.description.as_deref(), Some("Plain description"));
+        assert_eq!(meta.image.as_deref(), Some("https://example.com/card.png"));
+
+        let meta = page_meta("<title>\n  Fallback &amp; Co\n</title>");
+        assert_eq!(meta.title.as_deref(), Some("Fallback & Co"));
+    }
+
+    #[test]
+    fn test_markdown_html_escapes_raw_html() {
+        let html = markdown_html("# Notes\n\n<script>alert(1)</script>\n\n[x](javascript:alert(1))");
+        assert!(html.contains("<h1>Notes</h1>"));
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("javascript:"));
+    }
+
+    #[test]
+    fn test_looks_like_markdown() {
+        assert!(looks_like_markdown("# Title\n\n- one\n- two"));
+        assert!(looks_like_markdown("see [docs](https://example.com)"));
+        assert!(!looks_like_markdown("Meeting moved to Thursday at 3pm."));
+    }
+}
@@ -60,6 +60,14 @@ function formatTime(iso) {
   } catch { return ''; }
 }
 
+function formatRemaining(iso) {
+  const diff = new Date(iso) - new Date();
+  if (diff <= 0) return 'expired';
+  if (diff < 3600000) return `${Math.ceil(diff/60000)}m left`;
+  if (diff < 86400000) return `${Math.ceil(diff/3600000)}h left`;
+  return `${Math.ceil(diff/86400000)}d left`;
+}
+
 // ── Category Info ──────────────────────────────────────────────────────────
 const catInfo = {
   text: { icon: '📝', label: 'text' },
@@ -89,18 +97,23 @@ function renderItems() {
           <div class="card-meta">
             <span class="card-category">${cat.icon}</span>
             <span class="card-badge ${item.category}">${cat.label}</span>
-            ${item.pinned ? '<span class="card-badge" style="color:var(--pin-color);border-color:rgba(255,204,0,0.2)">📌 pinned</span>' : ''}
+            ${item.language ? `<span class="card-badge code">${item.language}</span>` : ''}
+            ${item.is_template ? '<span class="card-badge">🧩 template</span>' : ''}
+            ${item.pinned ? `<span class="card-badge" style="color:var(--pin-color);border-color:rgba(255,204,0,0.2)" title="${item.pin_expires_at ? `Pinned until ${new Date(item.pin_expires_at).toLocaleString()}` : 'Pinned'}">📌 pinned${item.pin_expires_at ? ` · ${formatRemaining(item.pin_expires_at)}` : ''}</span>` : ''}
             ${item.favorite ? '<span class="card-badge" style="color:var(--fav-color);border-color:rgba(255,107,157,0.2)">★ fav</span>' : ''}
             <span class="card-time">${formatTime(item.timestamp)}</span>
           </div>
           <div class="card-actions">
             <button class="card-action-btn copy" onclick="copyItem('${item.id}', event)" title="Copy">📋</button>
-            <button class="card-action-btn ${item.pinned ? 'pin-active' : ''}" onclick="togglePin('${item.id}', event)" title="Pin">📌</button>
+            <button class="card-action-btn" onclick="showPreview('${item.id}', event)" title="Preview">👁</button>
+            <button class="card-action-btn ${item.pinned ? 'pin-active' : ''}" onclick="togglePin('${item.id}', event)" title="Pin (Shift-click to pin for a number of days)">📌</button>
+            <button class="card-action-btn ${item.is_template ? 'template-active' : ''}" onclick="toggleTemplate('${item.id}', event)" title="Template ({{name}}, {{date}}, {{time}}, {{clipboard}})">🧩</button>
+            <button class="card-action-btn ${item.archived ? 'archive-active' : ''}" onclick="toggleArchive('${item.id}', event)" title="${item.archived ? 'Unarchive' : 'Archive'}">🗄</button>
             <button class="card-action-btn ${item.favorite ? 'fav-active' : ''}" onclick="toggleFav('${item.id}', event)" title="Favorite">★</button>
             <button class="card-action-btn delete" onclick="deleteItem('${item.id}', event)" title="Delete">✕</button>
           </div>
         </div>
-        <div class="card-content ${contentClass}">${escapeHtml(item.preview)}${needsFade ? '<div class="card-content-fade"></div>' : ''}</div>
+        <div class="card-content ${contentClass}"${item.language ? ` data-language="${item.language}"` : ''}>${escapeHtml(item.preview)}${needsFade ? '<div class="card-content-fade"></div>' : ''}</div>
       </div>
     `;
   }).join('');
@@ -158,6 +171,7 @@ async function loadItems(reset = true) {
     state.offset += items.length;
 
     renderItems();
+    if (reset) loadStats();
   } catch (e) {
     console.error('Failed to load items:', e);
   } finally {
@@ -165,6 +179,23 @@ async function loadItems(reset = true) {
   }
 }
 
+// Breakdown shown when hovering the item count
+async function loadStats() {
+  try {
+    const stats = await invoke('get_stats');
+    const lines = stats.categories.map(c => `${(catInfo[c.category] || catInfo.text).label}: ${c.count}`);
+    lines.push(`📌 ${stats.pinned} pinned · ★ ${stats.favorites} favorites · 🗄 ${stats.archived} archived`);
+    lines.push(`${stats.total} items · ${formatBytes(stats.total_bytes)}`);
+    itemCount.title = lines.join('\n');
+  } catch (e) { console.error(e); }
+}
+
+function formatBytes(bytes) {
+  if (bytes >= 1048576) return `${(bytes / 1048576).toFixed(1)} MB`;
+  if (bytes >= 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${bytes} B`;
+}
+
 // ── Infinite Scroll ────────────────────────────────────────────────────────
 itemsContainer.addEventListener('scroll', () => {
   const { scrollTop, scrollHeight, clientHeight } = itemsContainer;
@@ -174,8 +205,24 @@ itemsContainer.addEventListener('scroll', () => {
 });
 
 // ── Actions ────────────────────────────────────────────────────────────────
+const BUILTIN_PLACEHOLDERS = ['date', 'time', 'datetime', 'clipboard'];
+
 async function copyToClipboard(item) {
   try {
+    if (item.is_template) {
+      // Ask for each custom {{placeholder}}; built-ins are filled in by the backend
+      const names = [...new Set([...item.content.matchAll(/\{\{\s*([^{}]+?)\s*\}\}/g)].map(m => m[1]))]
+        .filter(name => !BUILTIN_PLACEHOLDERS.includes(name));
+      const vars = {};
+      for (const name of names) {
+        const value = prompt(`Value for {{${name}}}`);
+        if (value === null) return;
+        vars[name] = value;
+      }
+      await invoke('expand_template', { id: item.id, vars, copy: true });
+      toast('✓ Template copied to clipboard');
+      return;
+    }
     await invoke('copy_to_clipboard', { content: item.content });
     toast('✓ Copied to clipboard');
   } catch (e) {
@@ -189,10 +236,44 @@ window.copyItem = async (id, e) => {
   if (item) await copyToClipboard(item);
 };
 
+// Rendered by the backend: highlighted code, Markdown, or a link card
+const previewOverlay = $('#preview-overlay');
+
+window.showPreview = async (id, e) => {
+  e.stopPropagation();
+  try {
+    $('#preview-body').innerHTML = await invoke('render_preview', { id });
+    previewOverlay.classList.add('visible');
+  } catch (e) { console.error(e); }
+};
+
+function closePreview() {
+  previewOverlay.classList.remove('visible');
+}
+
+$('#preview-close').addEventListener('click', closePreview);
+// Links open in the browser instead of navigating the app window
+$('#preview-body').addEventListener('click', (e) => {
+  const link = e.target.closest('a');
+  if (!link) return;
+  e.preventDefault();
+  if (link.href.startsWith('http')) window.__TAURI__.shell.open(link.href);
+});
+previewOverlay.addEventListener('click', (e) => {
+  if (e.target === previewOverlay) closePreview();
+});
+
 window.togglePin = async (id, e) => {
   e.stopPropagation();
+  let expiresInDays = null;
+  const current = state.items.find(it => it.id === id);
+  if (e.shiftKey && current && !current.pinned) {
+    const days = parseInt(prompt('Keep pinned for how many days?', '7'), 10);
+    if (!days || days < 1) return;
+    expiresInDays = days;
+  }
   try {
-    const pinned = await invoke('toggle_pin', { id });
+    const pinned = await invoke('toggle_pin', { id, expiresInDays });
     const item = state.items.find(it => it.id === id);
     if (item) item.pinned = pinned;
     toast(pinned ? '📌 Pinned' : 'Unpinned');
@@ -200,6 +281,24 @@ window.togglePin = async (id, e) => {
   } catch (e) { console.error(e); }
 };
 
+window.toggleTemplate = async (id, e) => {
+  e.stopPropagation();
+  try {
+    const template = await invoke('toggle_template', { id });
+    toast(template ? '🧩 Marked as template' : 'No longer a template');
+    await loadItems();
+  } catch (e) { console.error(e); }
+};
+
+window.toggleArchive = async (id, e) => {
+  e.stopPropagation();
+  try {
+    const archived = await invoke('toggle_archive', { id });
+    toast(archived ? '🗄 Archived' : 'Unarchived');
+    await loadItems();
+  } catch (e) { console.error(e); }
+};
+
 window.toggleFav = async (id, e) => {
   e.stopPropagation();
   try {
@@ -250,9 +349,14 @@ statusBtn.addEventListener('click', async () => {
 });
 
 // ── Toolbar ────────────────────────────────────────────────────────────────
+// Exports follow the current search and category filter
+function exportFilter() {
+  return { query: state.query, category: state.category };
+}
+
 $('#export-json-btn').addEventListener('click', async () => {
   try {
-    const data = await invoke('export_data', { format: 'json' });
+    const data = await invoke('export_data', { format: 'json', filter: exportFilter() });
     downloadFile('clipboard-history.json', data, 'application/json');
     toast('📥 Exported JSON');
   } catch (e) { console.error(e); }
@@ -260,12 +364,40 @@ $('#export-json-btn').addEventListener('click', async () => {
 
 $('#export-csv-btn').addEventListener('click', async () => {
   try {
-    const data = await invoke('export_data', { format: 'csv' });
+    const data = await invoke('export_data', { format: 'csv', filter: exportFilter() });
     downloadFile('clipboard-history.csv', data, 'text/csv');
     toast('📊 Exported CSV');
   } catch (e) { console.error(e); }
 });
 
+$('#export-md-btn').addEventListener('click', async () => {
+  try {
+    const data = await invoke('export_data', { format: 'md', filter: exportFilter() });
+    downloadFile('clipboard-history.md', data, 'text/markdown');
+    toast('📝 Exported Markdown');
+  } catch (e) { console.error(e); }
+});
+
+$('#import-btn').addEventListener('click', () => $('#import-file').click());
+
+$('#import-file').addEventListener('change', async (e) => {
+  const file = e.target.files[0];
+  e.target.value = '';
+  if (!file) return;
+  const format = file.name.toLowerCase().endsWith('.csv') ? 'csv' : 'json';
+  const mergeStrategy = confirm('Replace the whole history with this file?\n\nOK = replace all · Cancel = merge, skipping duplicates')
+    ? 'replace_all' : 'skip_duplicates';
+  try {
+    const result = await invoke('import_data', { format, content: await file.text(), mergeStrategy });
+    if (result.errors.length) console.warn('Import errors:', result.errors);
+    toast(`📤 Imported ${result.imported} · skipped ${result.skipped} · failed ${result.failed}`);
+    loadItems();
+  } catch (e) {
+    console.error(e);
+    toast(`Import failed: ${e}`);
+  }
+});
+
 $('#cleanup-btn').addEventListener('click', async () => {
   try {
     const count = await invoke('cleanup_old', { days: 30 });
@@ -302,7 +434,11 @@ document.addEventListener('keydown', (e) => {
     return;
   }
 
-  // Escape → clear search
+  // Escape → close preview, otherwise clear search
+  if (e.key === 'Escape' && previewOverlay.classList.contains('visible')) {
+    closePreview();
+    return;
+  }
   if (e.key === 'Escape') {
     searchInput.value = '';
     state.query = '';
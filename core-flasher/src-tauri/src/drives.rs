@@ -1,4 +1,4 @@
-use crate::DriveInfo;
+use crate::{DriveInfo, PartitionInfo};
 
 #[cfg(target_os = "macos")]
 pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
@@ -88,6 +88,14 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
                 removable = true;
             }
 
+            let mut partitions = Vec::new();
+            let mut mount_points = Vec::new();
+            for id in mac_partition_ids(&list_text, &device) {
+                let (part, mount_point) = mac_partition_info(&id).await?;
+                mount_points.extend(mount_point);
+                partitions.push(part);
+            }
+
             drives.push(DriveInfo {
                 device: device.clone(),
                 name,
@@ -95,6 +103,9 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
                 size_human: bytesize::ByteSize(size).to_string(),
                 removable,
                 is_system,
+                mounted: !mount_points.is_empty(),
+                mount_points,
+                partitions,
             });
         }
     }
@@ -103,10 +114,72 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
     Ok(drives.into_iter().filter(|d| !d.is_system).collect())
 }
 
+// Partition identifiers (disk4s1, disk4s2…) listed under a disk's header in
+// `diskutil list` output
+#[cfg(target_os = "macos")]
+fn mac_partition_ids(list_text: &str, device: &str) -> Vec<String> {
+    let disk = device.trim_start_matches("/dev/");
+    let prefix = format!("{}s", disk);
+    list_text
+        .lines()
+        .skip_while(|l| l.split_whitespace().next() != Some(device))
+        .skip(1)
+        .take_while(|l| !l.starts_with("/dev/"))
+        .filter_map(|l| l.split_whitespace().last())
+        .filter(|id| id.starts_with(&prefix))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+async fn mac_partition_info(id: &str) -> Result<(PartitionInfo, Option<String>), String> {
+    let output = tokio::process::Command::new("diskutil")
+        .args(["info", id])
+        .output()
+        .await
+        .map_err(|e| format!("diskutil info failed: {}", e))?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut part = PartitionInfo {
+        name: id.to_string(),
+        label: String::new(),
+        fs: String::new(),
+        size: 0,
+    };
+    let mut mount_point = None;
+
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Volume Name" if !value.starts_with("Not applicable") => {
+                part.label = value.to_string()
+            }
+            "File System Personality" => part.fs = value.to_string(),
+            "Disk Size" => {
+                // "Disk Size: 31.0 GB (31016378368 Bytes)"
+                if let Some(bytes_part) = value.split('(').nth(1) {
+                    if let Some(num) = bytes_part.split_whitespace().next() {
+                        part.size = num.parse().unwrap_or(0);
+                    }
+                }
+            }
+            "Mount Point" if !value.is_empty() && !value.starts_with("Not applicable") => {
+                mount_point = Some(value.to_string())
+            }
+            _ => {}
+        }
+    }
+
+    Ok((part, mount_point))
+}
+
 #[cfg(target_os = "linux")]
 pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
     let output = tokio::process::Command::new("lsblk")
-        .args(["-J", "-b", "-o", "NAME,SIZE,RM,TYPE,MOUNTPOINT,LABEL,TRAN"])
+        .args(["-J", "-b", "-o", "NAME,SIZE,RM,TYPE,MOUNTPOINT,LABEL,TRAN,FSTYPE"])
         .output()
         .await
         .map_err(|e| format!("Failed to run lsblk: {}", e))?;
@@ -137,12 +210,26 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
 
             // System disk detection
             let mut is_system = false;
+            let mut mount_points = Vec::new();
+            let mut partitions = Vec::new();
+            if let Some(dmp) = dev["mountpoint"].as_str() {
+                mount_points.push(dmp.to_string());
+            }
             if let Some(children) = dev["children"].as_array() {
                 for child in children {
                     let mp = child["mountpoint"].as_str().unwrap_or("");
                     if mp == "/" || mp == "/boot" || mp == "/home" || mp.starts_with("/boot/") {
                         is_system = true;
                     }
+                    if !mp.is_empty() {
+                        mount_points.push(mp.to_string());
+                    }
+                    partitions.push(PartitionInfo {
+                        name: child["name"].as_str().unwrap_or("").to_string(),
+                        label: child["label"].as_str().unwrap_or("").to_string(),
+                        fs: child["fstype"].as_str().unwrap_or("").to_string(),
+                        size: child["size"].as_u64().unwrap_or(0),
+                    });
                 }
             }
 
@@ -154,6 +241,9 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
                     size_human: bytesize::ByteSize(size).to_string(),
                     removable,
                     is_system: false,
+                    mounted: !mount_points.is_empty(),
+                    mount_points,
+                    partitions,
                 });
             }
         }
@@ -195,6 +285,7 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
         let is_system = disk["IsSystem"].as_bool().unwrap_or(false);
 
         if !is_system {
+            let (partitions, mount_points) = windows_partitions(num).await;
             drives.push(DriveInfo {
                 device: format!("\\\\.\\PhysicalDrive{}", num),
                 name,
@@ -202,9 +293,60 @@ pub async fn list_usb_drives() -> Result<Vec<DriveInfo>, String> {
                 size_human: bytesize::ByteSize(size).to_string(),
                 removable: true,
                 is_system: false,
+                mounted: !mount_points.is_empty(),
+                mount_points,
+                partitions,
             });
         }
     }
 
     Ok(drives)
 }
+
+#[cfg(target_os = "windows")]
+async fn windows_partitions(disk_number: u64) -> (Vec<PartitionInfo>, Vec<String>) {
+    let script = format!(
+        "Get-Partition -DiskNumber {} | ForEach-Object {{ \
+         $v = $_ | Get-Volume -ErrorAction SilentlyContinue; \
+         [pscustomobject]@{{ Number = $_.PartitionNumber; Letter = [string]$_.DriveLetter; \
+         Size = $_.Size; Label = $v.FileSystemLabel; FileSystem = $v.FileSystem }} }} | ConvertTo-Json",
+        disk_number
+    );
+    let output = match tokio::process::Command::new("powershell")
+        .args(["-Command", &script])
+        .output()
+        .await
+    {
+        Ok(o) => o,
+        Err(_) => return (vec![], vec![]),
+    };
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let parsed: serde_json::Value = serde_json::from_str(&text).unwrap_or(serde_json::Value::Null);
+    let entries = match parsed {
+        serde_json::Value::Array(a) => a,
+        serde_json::Value::Object(_) => vec![parsed],
+        _ => vec![],
+    };
+
+    let mut partitions = Vec::new();
+    let mut mount_points = Vec::new();
+    for p in entries {
+        // Partitions without a letter serialize it as "\u0000"
+        let letter = p["Letter"]
+            .as_str()
+            .and_then(|l| l.chars().next())
+            .filter(|c| c.is_ascii_alphabetic());
+        if let Some(letter) = letter {
+            mount_points.push(format!("{}:\\", letter));
+        }
+        partitions.push(PartitionInfo {
+            name: format!("Partition {}", p["Number"].as_u64().unwrap_or(0)),
+            label: p["Label"].as_str().unwrap_or("").to_string(),
+            fs: p["FileSystem"].as_str().unwrap_or("").to_string(),
+            size: p["Size"].as_u64().unwrap_or(0),
+        });
+    }
+
+    (partitions, mount_points)
+}
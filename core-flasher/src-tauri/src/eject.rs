@@ -0,0 +1,183 @@
+use crate::drives;
+use tokio::process::Command;
+
+// Only drives that still show up as flashable (removable, non-system) may be
+// ejected, so a stale device path can never power off an internal disk.
+pub async fn eject_drive(device: &str) -> Result<(), String> {
+    let drives = drives::list_usb_drives().await?;
+    let drive = drives
+        .iter()
+        .find(|d| d.device == device)
+        .ok_or("Drive not found")?;
+
+    if drive.is_system {
+        return Err("SAFETY: Cannot eject system disk!".to_string());
+    }
+
+    eject(device, &drive.mount_points).await
+}
+
+fn stderr_of(output: &std::process::Output) -> String {
+    let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if err.is_empty() {
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    } else {
+        err
+    }
+}
+
+fn is_busy(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("busy") || lower.contains("dissented") || lower.contains("in use")
+}
+
+// "Drive is busy: Finder (PID 412), bash (PID 9031)"
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+async fn busy_error(device: &str, mount_points: &[String], message: &str) -> String {
+    let mut holders = Vec::new();
+    for mp in mount_points {
+        for holder in open_file_holders(mp).await {
+            if !holders.contains(&holder) {
+                holders.push(holder);
+            }
+        }
+    }
+    if holders.is_empty() {
+        format!("{} is busy and could not be ejected: {}", device, message)
+    } else {
+        format!(
+            "{} is busy — close these programs and try again: {}",
+            device,
+            holders.join(", ")
+        )
+    }
+}
+
+// Processes with files open below a mount point, via `lsof -F pc` output
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+async fn open_file_holders(mount_point: &str) -> Vec<String> {
+    let Ok(output) = Command::new("lsof")
+        .args(["-F", "pc", "+f", "--", mount_point])
+        .output()
+        .await
+    else {
+        return vec![];
+    };
+
+    let mut holders = Vec::new();
+    let mut pid = String::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(p) = line.strip_prefix('p') {
+            pid = p.to_string();
+        } else if let Some(cmd) = line.strip_prefix('c') {
+            holders.push(format!("{} (PID {})", cmd, pid));
+        }
+    }
+    holders
+}
+
+#[cfg(target_os = "macos")]
+async fn eject(device: &str, mount_points: &[String]) -> Result<(), String> {
+    let output = Command::new("diskutil")
+        .args(["eject", device])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run diskutil: {}", e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    // "Unmount of disk4 failed: at least one volume could not be unmounted
+    //  Unmount was dissented by PID 412 (/System/Library/.../Finder)"
+    let message = stderr_of(&output);
+    if is_busy(&message) || message.contains("could not be unmounted") {
+        return Err(busy_error(device, mount_points, &message).await);
+    }
+    Err(format!("Failed to eject {}: {}", device, message))
+}
+
+#[cfg(target_os = "linux")]
+async fn eject(device: &str, mount_points: &[String]) -> Result<(), String> {
+    let _ = Command::new("sync").output().await;
+
+    // Prefer udisks so unprivileged users can eject; fall back to umount
+    for mp in mount_points {
+        let udisks = Command::new("udisksctl")
+            .args(["unmount", "-p", &format!("mount:{}", mp)])
+            .output()
+            .await;
+        if matches!(&udisks, Ok(o) if o.status.success()) {
+            continue;
+        }
+
+        let output = Command::new("umount")
+            .arg(mp)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run umount: {}", e))?;
+        if !output.status.success() {
+            let message = stderr_of(&output);
+            if is_busy(&message) {
+                return Err(busy_error(device, mount_points, &message).await);
+            }
+            return Err(format!("Failed to unmount {}: {}", mp, message));
+        }
+    }
+
+    match Command::new("udisksctl")
+        .args(["power-off", "-b", device])
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!(
+            "{} was unmounted but could not be powered off: {}",
+            device,
+            stderr_of(&output)
+        )),
+        // Without udisks the unmounted drive is already safe to remove
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+async fn eject(device: &str, _mount_points: &[String]) -> Result<(), String> {
+    let number = device
+        .trim_start_matches("\\\\.\\PhysicalDrive")
+        .parse::<u64>()
+        .map_err(|_| format!("Unrecognised device path: {}", device))?;
+
+    // Win32_Volume.Dismount returns 4 when open handles require a forced dismount
+    let script = format!(
+        "$ErrorActionPreference = 'Stop'; \
+         foreach ($p in Get-Partition -DiskNumber {} | Where-Object {{ $_.DriveLetter }}) {{ \
+           $letter = \"$($p.DriveLetter):\"; \
+           $vol = Get-CimInstance Win32_Volume -Filter \"DriveLetter='$letter'\"; \
+           $r = Invoke-CimMethod -InputObject $vol -MethodName Dismount -Arguments @{{ Force = $false; Permanent = $false }}; \
+           if ($r.ReturnValue -eq 4) {{ throw \"busy: $letter is in use\" }} \
+           elseif ($r.ReturnValue -ne 0) {{ throw \"dismount of $letter failed with code $($r.ReturnValue)\" }} \
+           (New-Object -ComObject Shell.Application).Namespace(17).ParseName($letter).InvokeVerb('Eject') \
+         }}",
+        number
+    );
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let message = stderr_of(&output);
+    if is_busy(&message) {
+        return Err(format!(
+            "{} is busy — close any programs using the drive and try again",
+            device
+        ));
+    }
+    Err(format!("Failed to eject {}: {}", device, message))
+}
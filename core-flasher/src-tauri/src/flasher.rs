@@ -1,32 +1,165 @@
 use crate::FlashProgress;
 use md5::Md5;
 use sha2::{Digest, Sha256};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{Read, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender};
 use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Instant;
 use tauri::{AppHandle, Emitter};
 
 const BUFFER_SIZE: usize = 4 * 1024 * 1024; // 4MB buffer
 
+// ── Write pipeline ────────────────────────────────────────────────
+//
+// A reader thread fills buffers (decompressing ZIPs on the fly) while the
+// caller drains them into the device. Buffers are recycled through a second
+// channel, so at most `buffer_count` of them are ever allocated and the
+// reader can stay that many buffers ahead of the writer.
+
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineConfig {
+    pub buffer_size: usize,
+    pub buffer_count: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        PipelineConfig {
+            buffer_size: BUFFER_SIZE,
+            buffer_count: 4,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn new(buffer_size_mb: Option<usize>, buffer_count: Option<usize>) -> Self {
+        let default = Self::default();
+        PipelineConfig {
+            buffer_size: buffer_size_mb
+                .map(|mb| mb.clamp(1, 64) * 1024 * 1024)
+                .unwrap_or(default.buffer_size),
+            buffer_count: buffer_count
+                .map(|n| n.clamp(2, 32))
+                .unwrap_or(default.buffer_count),
+        }
+    }
+}
+
+/// Reader half of the pipeline, handed to the source closure of `copy_pipelined`.
+pub struct Feeder {
+    free: Receiver<Vec<u8>>,
+    filled: SyncSender<(Vec<u8>, usize)>,
+    cancel: Arc<Mutex<bool>>,
+}
+
+impl Feeder {
+    pub fn pump(self, reader: &mut dyn Read) -> Result<(), String> {
+        loop {
+            if *self.cancel.lock().unwrap() {
+                return Err("Flash cancelled".to_string());
+            }
+            // The writer hung up (write error or cancel) — nothing left to do
+            let Ok(mut buffer) = self.free.recv() else {
+                return Ok(());
+            };
+
+            // Fill the whole buffer so the device sees full-size writes
+            let mut n = 0;
+            while n < buffer.len() {
+                match reader.read(&mut buffer[n..]) {
+                    Ok(0) => break,
+                    Ok(read) => n += read,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(format!("Read error: {}", e)),
+                }
+            }
+            if n == 0 || self.filled.send((buffer, n)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Copies everything `source` pumps into `target`, overlapping reads and writes.
+/// `on_progress` is called with the total bytes written after every buffer.
+pub fn copy_pipelined<S, W>(
+    source: S,
+    target: &mut W,
+    config: PipelineConfig,
+    cancel: &Arc<Mutex<bool>>,
+    mut on_progress: impl FnMut(u64),
+) -> Result<u64, String>
+where
+    S: FnOnce(Feeder) -> Result<(), String> + Send + 'static,
+    W: Write,
+{
+    let buffer_count = config.buffer_count.max(1);
+    let (free_tx, free_rx) = mpsc::channel();
+    let (filled_tx, filled_rx) = mpsc::sync_channel(buffer_count);
+    for _ in 0..buffer_count {
+        let _ = free_tx.send(vec![0u8; config.buffer_size.max(512)]);
+    }
+
+    let feeder = Feeder {
+        free: free_rx,
+        filled: filled_tx,
+        cancel: cancel.clone(),
+    };
+    let reader = thread::spawn(move || source(feeder));
+
+    let mut bytes_written: u64 = 0;
+    let mut result = Ok(());
+    for (buffer, n) in filled_rx.iter() {
+        if *cancel.lock().unwrap() {
+            result = Err("Flash cancelled".to_string());
+            break;
+        }
+        if let Err(e) = target.write_all(&buffer[..n]) {
+            result = Err(format!("Write error: {}", e));
+            break;
+        }
+        bytes_written += n as u64;
+        on_progress(bytes_written);
+        let _ = free_tx.send(buffer);
+    }
+
+    // Hanging up both channels unblocks a reader that is still waiting on us
+    drop(filled_rx);
+    drop(free_tx);
+    let read_result = reader
+        .join()
+        .map_err(|_| "Reader thread panicked".to_string())?;
+    result?;
+    read_result?;
+    Ok(bytes_written)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlashOptions {
+    pub verify: bool,
+    pub eject_after: bool,
+    // Runs the whole flow against a discard sink; nothing touches the device
+    pub dry_run: bool,
+    pub pipeline: PipelineConfig,
+}
+
 pub async fn flash(
     app: &AppHandle,
     image_path: &str,
     device: &str,
-    verify: bool,
+    options: FlashOptions,
     cancel: Arc<Mutex<bool>>,
 ) -> Result<(), String> {
+    let FlashOptions {
+        dry_run,
+        pipeline: config,
+        ..
+    } = options;
     let image_path = image_path.to_string();
     let device = device.to_string();
     let app = app.clone();
 
-    // Handle ZIP extraction
-    let actual_path = if image_path.to_lowercase().ends_with(".zip") {
-        emit_progress(&app, 0, 0, 0.0, 0.0, 0, "extracting", "Extracting ZIP...");
-        extract_zip(&image_path).await?
-    } else {
-        image_path.clone()
-    };
-
     // Unmount the drive first (macOS)
     #[cfg(target_os = "macos")]
     {
@@ -37,14 +170,8 @@ pub async fn flash(
             .await;
     }
 
-    // Get file size
-    let file_size = std::fs::metadata(&actual_path)
-        .map_err(|e| format!("Cannot read image: {}", e))?
-        .len();
-
-    // Open source and target
-    let mut source =
-        std::fs::File::open(&actual_path).map_err(|e| format!("Cannot open image: {}", e))?;
+    // Get image size (uncompressed size for ZIPs)
+    let file_size = image_size(&image_path)?;
 
     // On macOS/Linux, we need raw device access
     let raw_device = if cfg!(target_os = "macos") {
@@ -53,71 +180,63 @@ pub async fn flash(
         device.clone()
     };
 
-    let mut target = std::fs::OpenOptions::new()
-        .write(true)
-        .open(&raw_device)
-        .map_err(|e| {
-            format!(
-                "Cannot open device {} — run with sudo or grant disk access: {}",
-                raw_device, e
-            )
-        })?;
+    let mut target: Box<dyn Write + Send> = if dry_run {
+        Box::new(std::io::sink())
+    } else {
+        Box::new(
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&raw_device)
+                .map_err(|e| {
+                    format!(
+                        "Cannot open device {} — run with sudo or grant disk access: {}",
+                        raw_device, e
+                    )
+                })?,
+        )
+    };
+    let label = if dry_run { "Dry run: writing" } else { "Writing" };
 
     // Write phase
-    let mut buffer = vec![0u8; BUFFER_SIZE];
-    let mut bytes_written: u64 = 0;
     let start = Instant::now();
+    let source_path = image_path.clone();
+    let written = copy_pipelined(
+        move |feeder| with_image_reader(&source_path, |reader| feeder.pump(reader)),
+        &mut target,
+        config,
+        &cancel,
+        |bytes_written| {
+            let elapsed = start.elapsed().as_secs_f64();
+            let speed = if elapsed > 0.0 {
+                bytes_written as f64 / elapsed / 1_048_576.0
+            } else {
+                0.0
+            };
+            let percent = (bytes_written as f64 / file_size.max(1) as f64) * 100.0;
+            let eta = if speed > 0.0 {
+                (file_size.saturating_sub(bytes_written) as f64 / (speed * 1_048_576.0)) as u64
+            } else {
+                0
+            };
 
-    loop {
-        if *cancel.lock().unwrap() {
             emit_progress(
                 &app,
                 bytes_written,
                 file_size,
-                0.0,
-                0.0,
-                0,
-                "error",
-                "Cancelled by user",
+                percent,
+                speed,
+                eta,
+                "writing",
+                &format!("{}... {:.1}%", label, percent),
             );
-            return Err("Flash cancelled".to_string());
-        }
+        },
+    );
 
-        let n = source
-            .read(&mut buffer)
-            .map_err(|e| format!("Read error: {}", e))?;
-        if n == 0 {
-            break;
+    if let Err(e) = written {
+        if *cancel.lock().unwrap() {
+            emit_progress(&app, 0, file_size, 0.0, 0.0, 0, "error", "Cancelled by user");
         }
-
-        target
-            .write_all(&buffer[..n])
-            .map_err(|e| format!("Write error: {}", e))?;
-
-        bytes_written += n as u64;
-        let elapsed = start.elapsed().as_secs_f64();
-        let speed = if elapsed > 0.0 {
-            bytes_written as f64 / elapsed / 1_048_576.0
-        } else {
-            0.0
-        };
-        let percent = (bytes_written as f64 / file_size as f64) * 100.0;
-        let eta = if speed > 0.0 {
-            ((file_size - bytes_written) as f64 / (speed * 1_048_576.0)) as u64
-        } else {
-            0
-        };
-
-        emit_progress(
-            &app,
-            bytes_written,
-            file_size,
-            percent,
-            speed,
-            eta,
-            "writing",
-            &format!("Writing... {:.1}%", percent),
-        );
+        return Err(e);
     }
 
     // Sync
@@ -126,8 +245,22 @@ pub async fn flash(
         .map_err(|e| format!("Flush error: {}", e))?;
     drop(target);
 
+    if dry_run {
+        emit_progress(
+            &app,
+            file_size,
+            file_size,
+            100.0,
+            0.0,
+            0,
+            "done",
+            "DRY RUN — no data written.",
+        );
+        return Ok(());
+    }
+
     // Verify phase
-    if verify {
+    if options.verify {
         emit_progress(
             &app,
             0,
@@ -139,59 +272,79 @@ pub async fn flash(
             "Verifying write...",
         );
 
-        source.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
         let mut target_read = std::fs::File::open(&raw_device).map_err(|e| {
             format!("Cannot open device for verification: {}", e)
         })?;
 
-        let mut src_buf = vec![0u8; BUFFER_SIZE];
-        let mut tgt_buf = vec![0u8; BUFFER_SIZE];
-        let mut verified: u64 = 0;
-        let verify_start = Instant::now();
+        with_image_reader(&image_path, |source| {
+            let mut src_buf = vec![0u8; config.buffer_size];
+            let mut tgt_buf = vec![0u8; config.buffer_size];
+            let mut verified: u64 = 0;
+            let verify_start = Instant::now();
 
-        loop {
-            if *cancel.lock().unwrap() {
-                return Err("Verification cancelled".to_string());
-            }
+            loop {
+                if *cancel.lock().unwrap() {
+                    return Err("Verification cancelled".to_string());
+                }
 
-            let n1 = source
-                .read(&mut src_buf)
-                .map_err(|e| format!("Read error: {}", e))?;
-            if n1 == 0 {
-                break;
-            }
+                let n1 = read_full(source, &mut src_buf)
+                    .map_err(|e| format!("Read error: {}", e))?;
+                if n1 == 0 {
+                    break;
+                }
 
-            let n2 = target_read
-                .read(&mut tgt_buf[..n1])
-                .map_err(|e| format!("Device read error: {}", e))?;
+                let n2 = read_full(&mut target_read, &mut tgt_buf[..n1])
+                    .map_err(|e| format!("Device read error: {}", e))?;
 
-            if n1 != n2 || src_buf[..n1] != tgt_buf[..n2] {
-                return Err(format!(
-                    "Verification FAILED at byte offset {}",
-                    verified
-                ));
-            }
+                if n1 != n2 || src_buf[..n1] != tgt_buf[..n2] {
+                    return Err(format!(
+                        "Verification FAILED at byte offset {}",
+                        verified
+                    ));
+                }
 
-            verified += n1 as u64;
-            let elapsed = verify_start.elapsed().as_secs_f64();
-            let speed = if elapsed > 0.0 {
-                verified as f64 / elapsed / 1_048_576.0
-            } else {
-                0.0
-            };
-            let percent = (verified as f64 / file_size as f64) * 100.0;
+                verified += n1 as u64;
+                let elapsed = verify_start.elapsed().as_secs_f64();
+                let speed = if elapsed > 0.0 {
+                    verified as f64 / elapsed / 1_048_576.0
+                } else {
+                    0.0
+                };
+                let percent = (verified as f64 / file_size.max(1) as f64) * 100.0;
+
+                emit_progress(
+                    &app,
+                    verified,
+                    file_size,
+                    percent,
+                    speed,
+                    0,
+                    "verifying",
+                    &format!("Verifying... {:.1}%", percent),
+                );
+            }
+            Ok(())
+        })?;
+    }
 
-            emit_progress(
-                &app,
-                verified,
-                file_size,
-                percent,
-                speed,
-                0,
-                "verifying",
-                &format!("Verifying... {:.1}%", percent),
-            );
-        }
+    // The image is on the drive at this point, so a failed eject is reported
+    // in the final message rather than as a flash error
+    let mut message = "Flash complete!".to_string();
+    if options.eject_after {
+        emit_progress(
+            &app,
+            file_size,
+            file_size,
+            100.0,
+            0.0,
+            0,
+            "ejecting",
+            "Ejecting drive...",
+        );
+        message = match crate::eject::eject_drive(&device).await {
+            Ok(()) => "Flash complete! Drive ejected.".to_string(),
+            Err(e) => format!("Flash complete! The drive was not ejected: {}", e),
+        };
     }
 
     emit_progress(
@@ -202,12 +355,25 @@ pub async fn flash(
         0.0,
         0,
         "done",
-        "Flash complete!",
+        &message,
     );
 
     Ok(())
 }
 
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
 fn emit_progress(
     app: &AppHandle,
     bytes_written: u64,
@@ -232,32 +398,52 @@ fn emit_progress(
     );
 }
 
-async fn extract_zip(zip_path: &str) -> Result<String, String> {
-    let file = std::fs::File::open(zip_path).map_err(|e| format!("Cannot open ZIP: {}", e))?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid ZIP: {}", e))?;
+fn is_zip(path: &str) -> bool {
+    path.to_lowercase().ends_with(".zip")
+}
 
-    // Find the first ISO/IMG/DMG in the archive
-    let mut target_name = None;
+// Index of the first ISO/IMG/DMG in the archive
+fn find_zip_image(archive: &mut zip::ZipArchive<std::fs::File>) -> Result<usize, String> {
     for i in 0..archive.len() {
         let entry = archive.by_index(i).map_err(|e| e.to_string())?;
         let name = entry.name().to_lowercase();
         if name.ends_with(".iso") || name.ends_with(".img") || name.ends_with(".dmg") {
-            target_name = Some(i);
-            break;
+            return Ok(i);
         }
     }
+    Err("No ISO/IMG/DMG found in ZIP".to_string())
+}
 
-    let idx = target_name.ok_or("No ISO/IMG/DMG found in ZIP")?;
-    let mut entry = archive.by_index(idx).map_err(|e| e.to_string())?;
-
-    let tmp_dir = std::env::temp_dir().join("core-flasher");
-    std::fs::create_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
+fn open_zip(path: &str) -> Result<zip::ZipArchive<std::fs::File>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open ZIP: {}", e))?;
+    zip::ZipArchive::new(file).map_err(|e| format!("Invalid ZIP: {}", e))
+}
 
-    let out_path = tmp_dir.join(entry.name().split('/').last().unwrap_or("image.img"));
-    let mut out_file = std::fs::File::create(&out_path).map_err(|e| e.to_string())?;
-    std::io::copy(&mut entry, &mut out_file).map_err(|e| e.to_string())?;
+fn image_size(path: &str) -> Result<u64, String> {
+    if is_zip(path) {
+        let mut archive = open_zip(path)?;
+        let idx = find_zip_image(&mut archive)?;
+        let entry = archive.by_index(idx).map_err(|e| e.to_string())?;
+        return Ok(entry.size());
+    }
+    Ok(std::fs::metadata(path)
+        .map_err(|e| format!("Cannot read image: {}", e))?
+        .len())
+}
 
-    Ok(out_path.to_string_lossy().to_string())
+// ZIP entries borrow their archive, so the reader is lent to `f` rather than returned
+fn with_image_reader<T>(
+    path: &str,
+    f: impl FnOnce(&mut dyn Read) -> Result<T, String>,
+) -> Result<T, String> {
+    if is_zip(path) {
+        let mut archive = open_zip(path)?;
+        let idx = find_zip_image(&mut archive)?;
+        let mut entry = archive.by_index(idx).map_err(|e| e.to_string())?;
+        return f(&mut entry);
+    }
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot open image: {}", e))?;
+    f(&mut file)
 }
 
 pub async fn compute_file_hash(path: &str, algorithm: &str) -> Result<String, String> {
@@ -290,3 +476,149 @@ pub async fn compute_file_hash(path: &str, algorithm: &str) -> Result<String, St
         _ => Err(format!("Unsupported algorithm: {}", algorithm)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("core-flasher-tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    // Pseudo-random so neither side can cheat with sparse or zero pages
+    fn write_image(path: &PathBuf, size: usize) {
+        let mut file = File::create(path).unwrap();
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut chunk = vec![0u8; 1024 * 1024];
+        for _ in 0..size / chunk.len() {
+            for b in chunk.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *b = state as u8;
+            }
+            file.write_all(&chunk).unwrap();
+        }
+    }
+
+    // Raw devices don't have a page cache to hide behind, so every write is
+    // synced to make the regular file behave like one
+    struct DeviceFile(File);
+
+    impl Write for DeviceFile {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let n = self.0.write(buf)?;
+            self.0.sync_data()?;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    fn copy_simple(src: &PathBuf, dst: &PathBuf) -> u64 {
+        let mut source = File::open(src).unwrap();
+        let mut target = DeviceFile(File::create(dst).unwrap());
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut written = 0;
+        loop {
+            let n = source.read(&mut buffer).unwrap();
+            if n == 0 {
+                break;
+            }
+            target.write_all(&buffer[..n]).unwrap();
+            written += n as u64;
+        }
+        written
+    }
+
+    fn copy_with_pipeline(src: &PathBuf, dst: &PathBuf, config: PipelineConfig) -> u64 {
+        let cancel = Arc::new(Mutex::new(false));
+        let mut target = DeviceFile(File::create(dst).unwrap());
+        let src = src.clone();
+        copy_pipelined(
+            move |feeder| feeder.pump(&mut File::open(&src).unwrap()),
+            &mut target,
+            config,
+            &cancel,
+            |_| {},
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn pipeline_copies_image_exactly() {
+        let src = scratch("exact.img");
+        let dst = scratch("exact.out");
+        // Not a multiple of the buffer size, to exercise the short last buffer
+        write_image(&src, 9 * 1024 * 1024);
+        let config = PipelineConfig {
+            buffer_size: 4 * 1024 * 1024,
+            buffer_count: 2,
+        };
+        assert_eq!(copy_with_pipeline(&src, &dst, config), 9 * 1024 * 1024);
+        assert!(std::fs::read(&src).unwrap() == std::fs::read(&dst).unwrap());
+        let _ = std::fs::remove_file(&src);
+        let _ = std::fs::remove_file(&dst);
+    }
+
+    #[test]
+    fn pipeline_stops_when_cancelled() {
+        let src = scratch("cancel.img");
+        let dst = scratch("cancel.out");
+        write_image(&src, 8 * 1024 * 1024);
+        let cancel = Arc::new(Mutex::new(false));
+        let flag = cancel.clone();
+        let mut target = File::create(&dst).unwrap();
+        let source = src.clone();
+        let result = copy_pipelined(
+            move |feeder| feeder.pump(&mut File::open(&source).unwrap()),
+            &mut target,
+            PipelineConfig {
+                buffer_size: 1024 * 1024,
+                buffer_count: 2,
+            },
+            &cancel,
+            |_| *flag.lock().unwrap() = true,
+        );
+        assert_eq!(result, Err("Flash cancelled".to_string()));
+        assert_eq!(std::fs::metadata(&dst).unwrap().len(), 1024 * 1024);
+        let _ = std::fs::remove_file(&src);
+        let _ = std::fs::remove_file(&dst);
+    }
+
+    // Writes a 512 MB image to a regular file with the old single-buffer loop
+    // and with the pipeline, and prints both throughputs:
+    //   cargo test --release -- --ignored --nocapture bench_pipeline
+    #[test]
+    #[ignore]
+    fn bench_pipeline() {
+        const SIZE: usize = 512 * 1024 * 1024;
+        let src = scratch("bench.img");
+        let dst = scratch("bench.out");
+        write_image(&src, SIZE);
+
+        let mb = SIZE as f64 / 1_048_576.0;
+        let start = Instant::now();
+        copy_simple(&src, &dst);
+        let simple = mb / start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        copy_with_pipeline(&src, &dst, PipelineConfig::default());
+        let pipelined = mb / start.elapsed().as_secs_f64();
+
+        println!(
+            "single buffer: {:.0} MB/s, pipelined: {:.0} MB/s ({:+.0}%)",
+            simple,
+            pipelined,
+            (pipelined / simple - 1.0) * 100.0
+        );
+        let _ = std::fs::remove_file(&src);
+        let _ = std::fs::remove_file(&dst);
+    }
+}
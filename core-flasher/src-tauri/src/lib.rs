@@ -1,4 +1,5 @@
 mod drives;
+mod eject;
 mod flasher;
 
 use serde::{Deserialize, Serialize};
@@ -13,6 +14,17 @@ pub struct DriveInfo {
     pub size_human: String,
     pub removable: bool,
     pub is_system: bool,
+    pub mount_points: Vec<String>,
+    pub partitions: Vec<PartitionInfo>,
+    pub mounted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionInfo {
+    pub name: String,
+    pub label: String,
+    pub fs: String,
+    pub size: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,11 +85,16 @@ async fn select_image(path: String) -> Result<ImageInfo, String> {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn flash_image(
     app: AppHandle,
     image_path: String,
     device: String,
     verify: bool,
+    eject_after: bool,
+    dry_run: bool,
+    buffer_size_mb: Option<usize>,
+    buffer_count: Option<usize>,
     state: State<'_, FlashState>,
 ) -> Result<(), String> {
     // Reset cancel flag
@@ -99,9 +116,15 @@ async fn flash_image(
         return Err("SAFETY: Target drive is not removable!".to_string());
     }
 
+    let options = flasher::FlashOptions {
+        verify,
+        eject_after,
+        dry_run,
+        pipeline: flasher::PipelineConfig::new(buffer_size_mb, buffer_count),
+    };
     let app_clone = app.clone();
     tokio::spawn(async move {
-        let result = flasher::flash(&app_clone, &image_path, &device, verify, cancel).await;
+        let result = flasher::flash(&app_clone, &image_path, &device, options, cancel).await;
         if let Err(e) = result {
             let _ = app_clone.emit(
                 "flash-progress",
@@ -127,6 +150,11 @@ async fn cancel_flash(state: State<'_, FlashState>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+async fn eject_drive(device: String) -> Result<(), String> {
+    eject::eject_drive(&device).await
+}
+
 #[tauri::command]
 async fn compute_hash(path: String, algorithm: String) -> Result<String, String> {
     flasher::compute_file_hash(&path, &algorithm).await
@@ -145,6 +173,7 @@ pub fn run() {
             select_image,
             flash_image,
             cancel_flash,
+            eject_drive,
             compute_hash,
         ])
         .run(tauri::generate_context!())
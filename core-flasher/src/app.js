@@ -98,6 +98,7 @@ async function refreshDrives() {
         <div class="drive-details">
           <div class="drive-name">${drive.name}</div>
           <div class="drive-meta">${drive.device} · ${drive.size_human}</div>
+          ${partitionSummary(drive)}
         </div>
       `;
       el.addEventListener('click', () => selectDrive(drive, el));
@@ -108,10 +109,30 @@ async function refreshDrives() {
   }
 }
 
+// Existing partitions, so users can recognise what they are about to erase
+function partitionSummary(drive) {
+  const parts = drive.partitions.map(p => {
+    const label = p.label || p.name;
+    const fs = p.fs ? ` (${p.fs})` : '';
+    return `${label}${fs}`;
+  });
+  let html = parts.length
+    ? `<div class="drive-parts">${parts.join(' · ')}</div>`
+    : '<div class="drive-parts">No partitions</div>';
+  if (drive.mounted) {
+    html += `<div class="drive-mounted">Mounted at ${drive.mount_points.join(', ')}</div>`;
+  }
+  return html;
+}
+
 function selectDrive(drive, el) {
   document.querySelectorAll('.drive-item').forEach(d => d.classList.remove('selected'));
   el.classList.add('selected');
   selectedDrive = drive;
+  const labels = drive.partitions.map(p => p.label).filter(Boolean);
+  document.getElementById('warningDetail').textContent = labels.length
+    ? `Partitions that will be lost: ${labels.join(', ')}${drive.mounted ? ' (currently mounted)' : ''}`
+    : '';
   document.getElementById('warningBox').style.display = 'block';
   document.getElementById('btnNext2').disabled = false;
 }
@@ -121,13 +142,17 @@ function updateSummary() {
   document.getElementById('summaryImage').textContent = selectedImage ? selectedImage.name : '—';
   document.getElementById('summaryDrive').textContent = selectedDrive ? `${selectedDrive.name} (${selectedDrive.device})` : '—';
   document.getElementById('summaryVerify').textContent = document.getElementById('verifyCheck').checked ? 'Yes' : 'No';
+  document.getElementById('summaryDryRun').style.display = document.getElementById('dryRunCheck').checked ? 'block' : 'none';
 }
 
 async function startFlash() {
   if (!selectedImage || !selectedDrive) return;
 
-  const ok = confirm(`⚠️ ALL DATA on ${selectedDrive.name} (${selectedDrive.device}) will be PERMANENTLY ERASED!\n\nAre you sure you want to continue?`);
-  if (!ok) return;
+  const dryRun = document.getElementById('dryRunCheck').checked;
+  if (!dryRun) {
+    const ok = confirm(`⚠️ ALL DATA on ${selectedDrive.name} (${selectedDrive.device}) will be PERMANENTLY ERASED!\n\nAre you sure you want to continue?`);
+    if (!ok) return;
+  }
 
   document.getElementById('flashButtons').style.display = 'none';
   document.getElementById('cancelBtn').style.display = 'flex';
@@ -138,6 +163,8 @@ async function startFlash() {
       imagePath: selectedImage.path,
       device: selectedDrive.device,
       verify: document.getElementById('verifyCheck').checked,
+      ejectAfter: document.getElementById('ejectCheck').checked,
+      dryRun,
     });
   } catch (e) {
     alert('Flash error: ' + e);
@@ -145,6 +172,20 @@ async function startFlash() {
   }
 }
 
+async function ejectDrive() {
+  if (!selectedDrive) return;
+  const btn = document.getElementById('ejectBtn');
+  btn.disabled = true;
+  try {
+    await invoke('eject_drive', { device: selectedDrive.device });
+    document.getElementById('doneDetail').textContent = 'Drive ejected. You can safely remove it.';
+    btn.style.display = 'none';
+  } catch (e) {
+    alert('Eject failed: ' + e);
+    btn.disabled = false;
+  }
+}
+
 async function cancelFlash() {
   try {
     await invoke('cancel_flash');
@@ -173,6 +214,16 @@ listen('flash-progress', (event) => {
   }
 
   if (p.phase === 'done') {
+    const dryRun = p.message.startsWith('DRY RUN');
+    document.getElementById('doneTitle').textContent = dryRun ? 'Dry run complete!' : 'Flash complete!';
+    const ejected = p.message.includes('Drive ejected');
+    document.getElementById('doneDetail').textContent = dryRun
+      ? p.message
+      : ejected
+      ? 'Drive ejected. You can safely remove it.'
+      : p.message.replace(/^Flash complete!\s*/, '') || 'You can safely remove the USB drive.';
+    document.getElementById('ejectBtn').style.display = ejected || dryRun ? 'none' : '';
+    document.getElementById('ejectBtn').disabled = false;
     document.getElementById('cancelBtn').style.display = 'none';
     document.getElementById('doneMessage').style.display = 'block';
     circle.style.stroke = 'var(--accent)';
@@ -206,4 +257,5 @@ window.computeHash = computeHash;
 window.refreshDrives = refreshDrives;
 window.startFlash = startFlash;
 window.cancelFlash = cancelFlash;
+window.ejectDrive = ejectDrive;
 window.resetApp = resetApp;
@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{epub, DrmStatus};
+
+// Signatures of the common DRM schemes. Detection only: a protected book is
+// reported so the user knows why calibre can't convert it.
+
+// Font obfuscation shows up in encryption.xml too, but isn't DRM
+const FONT_OBFUSCATION: &[&str] = &[
+    "http://www.idpf.org/2008/embedding",
+    "http://ns.adobe.com/pdf/enc#RC",
+];
+
+pub fn detect(path: &str) -> Result<DrmStatus, String> {
+    let ext = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let scheme = match ext.as_str() {
+        "acsm" => {
+            return Ok(DrmStatus {
+                has_drm: true,
+                scheme: Some("Adobe ACSM ticket".into()),
+                message: "This .acsm file is a download ticket, not a book. Open it in Adobe Digital Editions \
+                          to get the actual (DRM-protected) book file."
+                    .into(),
+            });
+        }
+        "epub" => epub_scheme(path)?,
+        "pdf" => {
+            let data = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            if contains(&data, b"EBX_HANDLER") {
+                Some("Adobe ADEPT")
+            } else if contains(&data, b"/Encrypt") {
+                return Ok(DrmStatus {
+                    has_drm: false,
+                    scheme: None,
+                    message: "This PDF is encrypted. If it needs a password to open, conversion will fail.".into(),
+                });
+            } else {
+                None
+            }
+        }
+        _ => kindle_scheme(path)?,
+    };
+
+    Ok(match scheme {
+        Some(scheme) => DrmStatus {
+            has_drm: true,
+            scheme: Some(scheme.to_string()),
+            message: format!(
+                "This book is DRM-protected ({}). DRM-protected files cannot be converted; \
+                 use a DRM-free copy from the store or publisher.",
+                scheme
+            ),
+        },
+        None => DrmStatus {
+            has_drm: false,
+            scheme: None,
+            message: "No DRM detected".into(),
+        },
+    })
+}
+
+fn epub_scheme(path: &str) -> Result<Option<&'static str>, String> {
+    let mut archive = epub::open(path)?;
+    let names: HashSet<String> = archive.file_names().map(String::from).collect();
+
+    if names.contains("META-INF/rights.xml") {
+        return Ok(Some("Adobe ADEPT"));
+    }
+    if names.contains("META-INF/license.lcpl") {
+        return Ok(Some("Readium LCP"));
+    }
+    if names.contains("META-INF/sinf.xml") {
+        return Ok(Some("Apple FairPlay"));
+    }
+    // Some other scheme encrypting content rather than just fonts
+    if let Ok(encryption) = epub::read_text(&mut archive, "META-INF/encryption.xml") {
+        let encrypts_content = epub::scan_tags(&encryption)
+            .iter()
+            .filter(|t| t.name == "EncryptionMethod" && !t.closing)
+            .filter_map(|t| epub::attr(t.raw, "Algorithm"))
+            .any(|algorithm| !FONT_OBFUSCATION.contains(&algorithm.as_str()));
+        if encrypts_content {
+            return Ok(Some("unrecognised EPUB encryption"));
+        }
+    }
+    Ok(None)
+}
+
+// MOBI/AZW/KFX/Topaz are told apart by their headers rather than the extension
+fn kindle_scheme(path: &str) -> Result<Option<&'static str>, String> {
+    let read_err = |e: std::io::Error| format!("Cannot read {}: {}", path, e);
+    let mut file = std::fs::File::open(path).map_err(read_err)?;
+    let mut header = Vec::new();
+    (&mut file).take(4096).read_to_end(&mut header).map_err(read_err)?;
+
+    if header.starts_with(b"TPZ") {
+        return Ok(Some("Kindle Topaz"));
+    }
+    if header.starts_with(b"\xeaDRMION\xee") {
+        return Ok(Some("Kindle KFX"));
+    }
+    if header.starts_with(b"CONT") {
+        // A KFX container only holds DRMION fragments when protected
+        let data = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        return Ok(contains(&data, b"DRMION").then_some("Kindle KFX"));
+    }
+
+    // PalmDB: type/creator at 60, first record offset at 78. The MOBI
+    // header's encryption type sits 12 bytes into that first record, which
+    // lies past the header we read when the record list is long.
+    if header.len() >= 86 && (&header[60..68] == b"BOOKMOBI" || &header[60..68] == b"TEXtREAd") {
+        let record0 = u32::from_be_bytes([header[78], header[79], header[80], header[81]]);
+        let mut encryption = [0u8; 2];
+        file.seek(SeekFrom::Start(u64::from(record0) + 12)).map_err(read_err)?;
+        if file.read_exact(&mut encryption).is_ok() {
+            return Ok(match u16::from_be_bytes(encryption) {
+                0 => None,
+                1 => Some("old Mobipocket"),
+                _ => Some("Kindle (Mobipocket)"),
+            });
+        }
+    }
+    Ok(None)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PalmDB with `records` entries whose first record holds a MOBI header
+    fn mobi(records: usize, encryption: u16) -> Vec<u8> {
+        let mut data = vec![0u8; 78];
+        data[60..68].copy_from_slice(b"BOOKMOBI");
+        data[76..78].copy_from_slice(&(records as u16).to_be_bytes());
+        let record0 = 78 + 8 * records + 2;
+        for i in 0..records {
+            data.extend_from_slice(&((record0 + i * 16) as u32).to_be_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+        data.extend_from_slice(&[0, 0]);
+        let mut header = [0u8; 16];
+        header[12..14].copy_from_slice(&encryption.to_be_bytes());
+        data.extend_from_slice(&header);
+        data
+    }
+
+    #[test]
+    fn test_kindle_scheme_reads_record0_past_first_4k() {
+        let dir = std::env::temp_dir().join(format!("drm_mobi_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        std::fs::write(path("big.azw"), mobi(600, 2)).unwrap();
+        std::fs::write(path("small.mobi"), mobi(3, 2)).unwrap();
+        std::fs::write(path("free.mobi"), mobi(600, 0)).unwrap();
+        assert_eq!(kindle_scheme(&path("big.azw")).unwrap(), Some("Kindle (Mobipocket)"));
+        assert_eq!(kindle_scheme(&path("small.mobi")).unwrap(), Some("Kindle (Mobipocket)"));
+        assert_eq!(kindle_scheme(&path("free.mobi")).unwrap(), None);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,1022 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::ValidationIssue;
+
+// Just enough of the OCF/OPF/NCX formats to take an EPUB apart. The XML is
+// scanned tag by tag rather than parsed, matching elements on their local name.
+
+pub struct ManifestItem {
+    pub id: String,
+    /// Path inside the zip
+    pub path: String,
+    /// href exactly as written in the OPF (relative to it)
+    pub href: String,
+    pub media_type: String,
+    pub properties: String,
+}
+
+pub struct Package {
+    pub opf_path: String,
+    pub title: Option<String>,
+    pub language: Option<String>,
+    pub identifier: Option<String>,
+    /// Manifest id of the cover image from EPUB 2 `<meta name="cover">`
+    pub cover_id: Option<String>,
+    pub items: Vec<ManifestItem>,
+    /// Manifest ids in reading order
+    pub spine: Vec<String>,
+    pub toc_id: Option<String>,
+}
+
+impl Package {
+    pub fn item(&self, id: &str) -> Option<&ManifestItem> {
+        self.items.iter().find(|i| i.id == id)
+    }
+
+    pub fn spine_items(&self) -> Vec<&ManifestItem> {
+        self.spine.iter().filter_map(|id| self.item(id)).collect()
+    }
+}
+
+pub struct TocEntry {
+    pub title: String,
+    /// Path inside the zip
+    pub path: String,
+    pub fragment: Option<String>,
+}
+
+pub fn open(path: &str) -> Result<ZipArchive<File>, String> {
+    let is_epub = Path::new(path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("epub"));
+    if !is_epub {
+        return Err(format!("Not an EPUB file: {}", path));
+    }
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+    ZipArchive::new(file).map_err(|e| format!("Not a valid EPUB (bad zip): {}", e))
+}
+
+pub fn read_bytes(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("Missing {} in EPUB", name))?;
+    let mut data = Vec::new();
+    entry
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(data)
+}
+
+pub fn read_text(archive: &mut ZipArchive<File>, name: &str) -> Result<String, String> {
+    read_bytes(archive, name).map(|b| String::from_utf8_lossy(&b).into_owned())
+}
+
+/// The OPF path named by META-INF/container.xml
+pub fn rootfile(archive: &mut ZipArchive<File>) -> Result<String, String> {
+    let container = read_text(archive, "META-INF/container.xml")?;
+    scan_tags(&container)
+        .iter()
+        .filter(|t| t.name == "rootfile" && !t.closing)
+        .filter_map(|t| attr(t.raw, "full-path"))
+        .next()
+        .ok_or_else(|| "container.xml names no rootfile".to_string())
+}
+
+pub fn read_package(archive: &mut ZipArchive<File>) -> Result<Package, String> {
+    let opf_path = rootfile(archive)?;
+    let opf = read_text(archive, &opf_path)?;
+    let base = parent_dir(&opf_path);
+    let tags = scan_tags(&opf);
+
+    let mut package = Package {
+        opf_path: opf_path.clone(),
+        title: None,
+        language: None,
+        identifier: None,
+        cover_id: None,
+        items: Vec::new(),
+        spine: Vec::new(),
+        toc_id: None,
+    };
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.closing {
+            continue;
+        }
+        match tag.name {
+            "title" if package.title.is_none() => package.title = Some(inner_text(&opf, &tags, i)),
+            "language" if package.language.is_none() => package.language = Some(inner_text(&opf, &tags, i)),
+            "identifier" if package.identifier.is_none() => package.identifier = Some(inner_text(&opf, &tags, i)),
+            "meta" if attr(tag.raw, "name").as_deref() == Some("cover") => package.cover_id = attr(tag.raw, "content"),
+            "item" => {
+                let (Some(id), Some(href)) = (attr(tag.raw, "id"), attr(tag.raw, "href")) else {
+                    continue;
+                };
+                package.items.push(ManifestItem {
+                    id,
+                    path: resolve(base, &href),
+                    href,
+                    media_type: attr(tag.raw, "media-type").unwrap_or_default(),
+                    properties: attr(tag.raw, "properties").unwrap_or_default(),
+                });
+            }
+            "spine" => package.toc_id = attr(tag.raw, "toc"),
+            // Non-linear items (popups, answer keys) aren't part of the reading order
+            "itemref" if attr(tag.raw, "linear").as_deref() != Some("no") => {
+                if let Some(idref) = attr(tag.raw, "idref") {
+                    package.spine.push(idref);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(package)
+}
+
+/// Top-level table of contents entries, from the EPUB 3 nav document or
+/// else the EPUB 2 NCX
+pub fn top_level_toc(archive: &mut ZipArchive<File>, package: &Package) -> Vec<TocEntry> {
+    if let Some(nav) = package.items.iter().find(|i| i.properties.split_whitespace().any(|p| p == "nav")) {
+        if let Ok(xml) = read_text(archive, &nav.path) {
+            let entries = nav_toc(&xml, parent_dir(&nav.path));
+            if !entries.is_empty() {
+                return entries;
+            }
+        }
+    }
+    let ncx = package
+        .toc_id
+        .as_deref()
+        .and_then(|id| package.item(id))
+        .or_else(|| package.items.iter().find(|i| i.media_type == "application/x-dtbncx+xml"));
+    match ncx {
+        Some(ncx) => read_text(archive, &ncx.path)
+            .map(|xml| ncx_toc(&xml, parent_dir(&ncx.path)))
+            .unwrap_or_default(),
+        None => Vec::new(),
+    }
+}
+
+fn ncx_toc(xml: &str, base: &str) -> Vec<TocEntry> {
+    let tags = scan_tags(xml);
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut title: Option<String> = None;
+    let mut src: Option<String> = None;
+    for (i, tag) in tags.iter().enumerate() {
+        match (tag.name, tag.closing) {
+            ("navPoint", false) => {
+                depth += 1;
+                if depth == 1 {
+                    title = None;
+                    src = None;
+                }
+            }
+            ("navPoint", true) => {
+                if depth == 1 {
+                    if let Some(src) = src.take() {
+                        entries.push(toc_entry(title.take().unwrap_or_default(), &src, base));
+                    }
+                }
+                depth -= 1;
+            }
+            ("text", false) if depth == 1 && title.is_none() => title = Some(inner_text(xml, &tags, i)),
+            ("content", false) if depth == 1 && src.is_none() => src = attr(tag.raw, "src"),
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn nav_toc(xml: &str, base: &str) -> Vec<TocEntry> {
+    let tags = scan_tags(xml);
+    let navs: Vec<usize> = (0..tags.len())
+        .filter(|&i| tags[i].name == "nav" && !tags[i].closing)
+        .collect();
+    let Some(&start) = navs
+        .iter()
+        .find(|&&i| attr(tags[i].raw, "epub:type").is_some_and(|t| t.split_whitespace().any(|t| t == "toc")))
+        .or(navs.first())
+    else {
+        return Vec::new();
+    };
+
+    let mut entries = Vec::new();
+    let mut ol_depth = 0;
+    let mut in_item = false;
+    for i in start + 1..tags.len() {
+        let tag = &tags[i];
+        match (tag.name, tag.closing) {
+            ("nav", true) => break,
+            ("ol", false) => ol_depth += 1,
+            ("ol", true) => ol_depth -= 1,
+            ("li", false) if ol_depth == 1 => in_item = true,
+            ("a", false) if ol_depth == 1 && in_item => {
+                in_item = false;
+                if let Some(href) = attr(tag.raw, "href") {
+                    entries.push(toc_entry(inner_text(xml, &tags, i), &href, base));
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn toc_entry(title: String, href: &str, base: &str) -> TocEntry {
+    let (file, fragment) = match href.split_once('#') {
+        Some((file, fragment)) => (file, Some(fragment.to_string())),
+        None => (href, None),
+    };
+    TocEntry {
+        title,
+        path: resolve(base, file),
+        fragment,
+    }
+}
+
+/// Zip paths of the files a document pulls in: images, stylesheets, fonts…
+/// Links to other documents are included too; callers filter by manifest.
+pub fn references(content: &str, base: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    for tag in scan_tags(content).iter().filter(|t| !t.closing) {
+        for name in ["src", "href", "xlink:href", "poster"] {
+            if let Some(value) = attr(tag.raw, name) {
+                refs.push(value);
+            }
+        }
+    }
+    // CSS, either a stylesheet or inline <style>
+    let mut rest = content;
+    while let Some(pos) = rest.find("url(") {
+        rest = &rest[pos + 4..];
+        if let Some(end) = rest.find(')') {
+            refs.push(rest[..end].trim().trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+    }
+    let mut rest = content;
+    while let Some(pos) = rest.find("@import") {
+        rest = &rest[pos + 7..];
+        let trimmed = rest.trim_start();
+        if let Some(quote) = trimmed.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            if let Some(end) = trimmed[1..].find(quote) {
+                refs.push(trimmed[1..1 + end].to_string());
+            }
+        }
+    }
+
+    refs.into_iter()
+        .filter(|r| !r.is_empty() && !r.starts_with('#') && !r.contains(':'))
+        .map(|r| resolve(base, r.split('#').next().unwrap_or("")))
+        .collect()
+}
+
+/// Write each top-level TOC section as its own EPUB. A section runs from the
+/// spine file its entry points at up to where the next section starts, so
+/// chapters spread over several files stay together.
+pub fn split_by_chapter(path: &str, output_dir: &str) -> Result<Vec<String>, String> {
+    let mut archive = open(path)?;
+    let package = read_package(&mut archive)?;
+    let spine = package.spine_items();
+    let toc = top_level_toc(&mut archive, &package);
+
+    let mut sections: Vec<(usize, &TocEntry)> = Vec::new();
+    for entry in &toc {
+        if let Some(index) = spine.iter().position(|item| item.path == entry.path) {
+            if !sections.iter().any(|(start, _)| *start == index) {
+                sections.push((index, entry));
+            }
+        }
+    }
+    if sections.is_empty() {
+        return Err("This EPUB has no table of contents entries to split by".to_string());
+    }
+    sections.sort_by_key(|(start, _)| *start);
+
+    std::fs::create_dir_all(output_dir).map_err(|e| format!("Failed to create output folder: {}", e))?;
+
+    let book_title = package.title.clone().unwrap_or_else(|| "Untitled".to_string());
+    let width = sections.len().to_string().len().max(2);
+    let mut created = Vec::new();
+
+    for (n, &(start, entry)) in sections.iter().enumerate() {
+        let end = sections.get(n + 1).map_or(spine.len(), |(next, _)| *next);
+        let documents = &spine[start..end];
+
+        // Everything the chapter's documents use, following stylesheets one level down
+        let mut wanted: HashSet<String> = documents.iter().map(|d| d.path.clone()).collect();
+        let mut queue: Vec<String> = wanted.iter().cloned().collect();
+        while let Some(file) = queue.pop() {
+            let Ok(content) = read_text(&mut archive, &file) else {
+                continue;
+            };
+            for reference in references(&content, parent_dir(&file)) {
+                let is_document = package
+                    .items
+                    .iter()
+                    .any(|i| i.path == reference && i.media_type == "application/xhtml+xml");
+                if is_document || !wanted.insert(reference.clone()) {
+                    continue;
+                }
+                if reference.ends_with(".css") {
+                    queue.push(reference);
+                }
+            }
+        }
+        let items: Vec<&ManifestItem> = package
+            .items
+            .iter()
+            .filter(|i| wanted.contains(&i.path))
+            .collect();
+
+        let title = if entry.title.trim().is_empty() {
+            format!("Chapter {}", n + 1)
+        } else {
+            entry.title.trim().to_string()
+        };
+        let mut name = sanitize_filename(&title);
+        if name.is_empty() {
+            name = format!("Chapter {}", n + 1);
+        }
+        let out_path = Path::new(output_dir).join(format!("{:0width$} - {}.epub", n + 1, name, width = width));
+
+        let first_href = documents[0].href.clone();
+        let start_href = match &entry.fragment {
+            Some(fragment) => format!("{}#{}", first_href, fragment),
+            None => first_href,
+        };
+        write_chapter(
+            &mut archive,
+            &package,
+            &items,
+            documents,
+            &format!("{} — {}", book_title, title),
+            &title,
+            &start_href,
+            &out_path,
+        )?;
+        created.push(out_path.to_string_lossy().to_string());
+    }
+
+    Ok(created)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_chapter(
+    archive: &mut ZipArchive<File>,
+    package: &Package,
+    items: &[&ManifestItem],
+    documents: &[&ManifestItem],
+    book_title: &str,
+    chapter_title: &str,
+    start_href: &str,
+    out_path: &Path,
+) -> Result<(), String> {
+    let err = |e: zip::result::ZipError| format!("Failed to write {}: {}", out_path.display(), e);
+    let io_err = |e: std::io::Error| format!("Failed to write {}: {}", out_path.display(), e);
+
+    let file = File::create(out_path).map_err(io_err)?;
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // mimetype must come first and uncompressed
+    zip.start_file("mimetype", stored).map_err(err)?;
+    zip.write_all(b"application/epub+zip").map_err(io_err)?;
+
+    zip.start_file("META-INF/container.xml", deflated).map_err(err)?;
+    zip.write_all(
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="{}" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#,
+            xml_escape(&package.opf_path)
+        )
+        .as_bytes(),
+    )
+    .map_err(io_err)?;
+
+    for item in items {
+        let data = read_bytes(archive, &item.path)?;
+        zip.start_file(item.path.as_str(), deflated).map_err(err)?;
+        zip.write_all(&data).map_err(io_err)?;
+    }
+
+    // The NCX sits next to the OPF so the original hrefs stay valid in both
+    let mut ncx_name = "toc.ncx".to_string();
+    while items.iter().any(|i| i.href == ncx_name) {
+        ncx_name = format!("split-{}", ncx_name);
+    }
+    let uid = format!("urn:uuid:{}", uuid::Uuid::new_v4());
+    let language = package.language.clone().unwrap_or_else(|| "en".to_string());
+
+    let manifest: String = items
+        .iter()
+        .map(|i| {
+            format!(
+                "    <item id=\"{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                xml_escape(&i.id),
+                xml_escape(&i.href),
+                xml_escape(&i.media_type)
+            )
+        })
+        .collect();
+    let spine: String = documents
+        .iter()
+        .map(|d| format!("    <itemref idref=\"{}\"/>\n", xml_escape(&d.id)))
+        .collect();
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="BookId">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>
+    <dc:identifier id="BookId">{}</dc:identifier>
+  </metadata>
+  <manifest>
+    <item id="split-ncx" href="{}" media-type="application/x-dtbncx+xml"/>
+{}  </manifest>
+  <spine toc="split-ncx">
+{}  </spine>
+</package>
+"#,
+        xml_escape(book_title),
+        xml_escape(&language),
+        uid,
+        xml_escape(&ncx_name),
+        manifest,
+        spine
+    );
+    zip.start_file(package.opf_path.as_str(), deflated).map_err(err)?;
+    zip.write_all(opf.as_bytes()).map_err(io_err)?;
+
+    let ncx = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head>
+    <meta name="dtb:uid" content="{}"/>
+    <meta name="dtb:depth" content="1"/>
+    <meta name="dtb:totalPageCount" content="0"/>
+    <meta name="dtb:maxPageNumber" content="0"/>
+  </head>
+  <docTitle><text>{}</text></docTitle>
+  <navMap>
+    <navPoint id="navPoint-1" playOrder="1">
+      <navLabel><text>{}</text></navLabel>
+      <content src="{}"/>
+    </navPoint>
+  </navMap>
+</ncx>
+"#,
+        uid,
+        xml_escape(book_title),
+        xml_escape(chapter_title),
+        xml_escape(start_href)
+    );
+    zip.start_file(resolve(parent_dir(&package.opf_path), &ncx_name), deflated)
+        .map_err(err)?;
+    zip.write_all(ncx.as_bytes()).map_err(io_err)?;
+
+    zip.finish().map_err(err)?;
+    Ok(())
+}
+
+/// Words, non-whitespace characters and top-level TOC entries of an EPUB
+pub fn text_stats(path: &str) -> Result<(usize, usize, usize), String> {
+    let mut archive = open(path)?;
+    let package = read_package(&mut archive)?;
+    let mut words = 0;
+    let mut characters = 0;
+    for item in package.spine_items() {
+        let Ok(xhtml) = read_text(&mut archive, &item.path) else {
+            continue;
+        };
+        let text = plain_text(&xhtml);
+        words += text.split_whitespace().count();
+        characters += text.chars().filter(|c| !c.is_whitespace()).count();
+    }
+    let chapters = top_level_toc(&mut archive, &package).len();
+    Ok((words, characters, chapters))
+}
+
+/// Readable text of an XHTML document, leaving out head, scripts and styles
+pub fn plain_text(xhtml: &str) -> String {
+    let mut text = String::with_capacity(xhtml.len() / 2);
+    let mut skipping: Option<&str> = None;
+    let mut last = 0;
+    for tag in scan_tags(xhtml) {
+        if skipping.is_none() {
+            text.push_str(&xhtml[last..tag.start]);
+            text.push(' ');
+        }
+        last = tag.end;
+        match skipping {
+            Some(name) if tag.closing && tag.name == name => skipping = None,
+            None if !tag.closing && !tag.raw.ends_with('/') && matches!(tag.name, "head" | "script" | "style") => {
+                skipping = Some(tag.name)
+            }
+            _ => {}
+        }
+    }
+    if skipping.is_none() {
+        text.push_str(&xhtml[last..]);
+    }
+    xml_unescape(&text)
+}
+
+/// Structural problems that make readers or calibre choke: the OCF
+/// container, the package document, and files or links that point nowhere
+pub fn validate(path: &str) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut archive = match open(path) {
+        Ok(archive) => archive,
+        Err(e) => return vec![ValidationIssue::error(e)],
+    };
+    let names: HashSet<String> = archive.file_names().map(String::from).collect();
+
+    // mimetype must be the first entry, stored, with the exact media type
+    match archive.by_index(0) {
+        Ok(first) if first.name() == "mimetype" => {
+            if first.compression() != CompressionMethod::Stored {
+                issues.push(ValidationIssue::warning("mimetype entry is compressed"));
+            }
+        }
+        _ if names.contains("mimetype") => issues.push(ValidationIssue::warning("mimetype is not the first entry in the archive")),
+        _ => issues.push(ValidationIssue::error("Missing mimetype entry")),
+    }
+    if let Ok(mimetype) = read_text(&mut archive, "mimetype") {
+        if mimetype.trim() != "application/epub+zip" {
+            issues.push(ValidationIssue::error(format!("Unexpected mimetype: {}", mimetype.trim())));
+        }
+    }
+
+    if !names.contains("META-INF/container.xml") {
+        issues.push(ValidationIssue::error("Missing META-INF/container.xml"));
+        return issues;
+    }
+    let package = match read_package(&mut archive) {
+        Ok(package) => package,
+        Err(e) => {
+            issues.push(ValidationIssue::error(e));
+            return issues;
+        }
+    };
+
+    if package.title.as_deref().is_none_or(|t| t.trim().is_empty()) {
+        issues.push(ValidationIssue::warning("No title in metadata"));
+    }
+    if package.language.is_none() {
+        issues.push(ValidationIssue::warning("No language in metadata"));
+    }
+    if package.identifier.is_none() {
+        issues.push(ValidationIssue::warning("No identifier in metadata"));
+    }
+
+    let missing: Vec<&str> = package
+        .items
+        .iter()
+        .filter(|i| !names.contains(&i.path))
+        .map(|i| i.path.as_str())
+        .collect();
+    if !missing.is_empty() {
+        issues.push(ValidationIssue::error(format!(
+            "{} manifest file{} missing: {}",
+            missing.len(),
+            if missing.len() == 1 { "" } else { "s" },
+            examples(&missing)
+        )));
+    }
+
+    let unknown: Vec<&str> = package
+        .spine
+        .iter()
+        .filter(|id| package.item(id).is_none())
+        .map(|id| id.as_str())
+        .collect();
+    if package.spine.is_empty() {
+        issues.push(ValidationIssue::error("Spine is empty — there is nothing to read"));
+    } else if !unknown.is_empty() {
+        issues.push(ValidationIssue::error(format!("Spine refers to unknown items: {}", examples(&unknown))));
+    }
+
+    if top_level_toc(&mut archive, &package).is_empty() {
+        issues.push(ValidationIssue::warning("No table of contents"));
+    }
+
+    let has_cover = package
+        .items
+        .iter()
+        .any(|i| i.properties.split_whitespace().any(|p| p == "cover-image"))
+        || package.cover_id.as_deref().is_some_and(|id| package.item(id).is_some());
+    if !has_cover {
+        issues.push(ValidationIssue::warning("Missing cover"));
+    }
+
+    let mut broken = Vec::new();
+    for item in package.items.iter().filter(|i| i.media_type == "application/xhtml+xml" || i.media_type == "text/css") {
+        let Ok(content) = read_text(&mut archive, &item.path) else {
+            continue;
+        };
+        for reference in references(&content, parent_dir(&item.path)) {
+            if !names.contains(&reference) && !broken.contains(&reference) {
+                broken.push(reference);
+            }
+        }
+    }
+    if !broken.is_empty() {
+        let broken: Vec<&str> = broken.iter().map(String::as_str).collect();
+        issues.push(ValidationIssue::warning(format!(
+            "{} broken internal link{}: {}",
+            broken.len(),
+            if broken.len() == 1 { "" } else { "s" },
+            examples(&broken)
+        )));
+    }
+
+    issues
+}
+
+fn examples(list: &[&str]) -> String {
+    let mut text = list.iter().take(3).copied().collect::<Vec<_>>().join(", ");
+    if list.len() > 3 {
+        text.push_str(&format!(" and {} more", list.len() - 3));
+    }
+    text
+}
+
+// ─── Paths ───────────────────────────────────────────────
+
+pub fn parent_dir(path: &str) -> &str {
+    path.rfind('/').map_or("", |i| &path[..i])
+}
+
+/// Resolve an href against the directory of the file that contains it
+pub fn resolve(base: &str, href: &str) -> String {
+    let href = percent_decode(href);
+    let mut parts: Vec<&str> = if href.starts_with('/') {
+        Vec::new()
+    } else {
+        base.split('/').filter(|p| !p.is_empty()).collect()
+    };
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    cleaned.chars().take(120).collect()
+}
+
+// ─── XML scanning ────────────────────────────────────────
+
+pub struct Tag<'a> {
+    /// Local name, without any namespace prefix
+    pub name: &'a str,
+    /// Everything between `<` and `>`
+    pub raw: &'a str,
+    pub closing: bool,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub fn scan_tags(xml: &str) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = xml[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &xml[start..];
+        // Comments, CDATA, doctype and processing instructions carry no tags
+        let skip_to = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            Some("]]>")
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(terminator) = skip_to {
+            match rest.find(terminator) {
+                Some(end) => {
+                    pos = start + end + terminator.len();
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let Some(len) = rest.find('>') else {
+            break;
+        };
+        let raw = &xml[start + 1..start + len];
+        let closing = raw.starts_with('/');
+        let qualified = raw
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        tags.push(Tag {
+            name: qualified.rsplit(':').next().unwrap_or(qualified),
+            raw,
+            closing,
+            start,
+            end: start + len + 1,
+        });
+        pos = start + len + 1;
+    }
+    tags
+}
+
+pub fn attr(raw: &str, name: &str) -> Option<String> {
+    let mut pos = 0;
+    while let Some(offset) = raw[pos..].find(name) {
+        let start = pos + offset;
+        pos = start + name.len();
+        let preceded_by_space = raw[..start].chars().next_back().is_some_and(char::is_whitespace);
+        let rest = raw[pos..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value = rest[1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)?;
+        return Some(xml_unescape(&value[1..1 + end]));
+    }
+    None
+}
+
+/// Text content of the element opened at `tags[index]`, inner markup dropped
+pub fn inner_text(xml: &str, tags: &[Tag], index: usize) -> String {
+    let open = &tags[index];
+    if open.raw.ends_with('/') {
+        return String::new();
+    }
+    let end = tags[index + 1..]
+        .iter()
+        .find(|t| t.closing && t.name == open.name)
+        .map_or(xml.len(), |t| t.start);
+    let text = strip_tags(&xml[open.end..end]);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn strip_tags(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut last = 0;
+    for tag in scan_tags(markup) {
+        text.push_str(&markup[last..tag.start]);
+        text.push(' ');
+        last = tag.end;
+    }
+    text.push_str(&markup[last..]);
+    xml_unescape(&text)
+}
+
+pub fn xml_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';').filter(|&i| i <= 10) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Tom &amp; Jerry</dc:title>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="nav" href="Text/nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1b" href="Text/ch1b.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="css" href="Styles/book.css" media-type="text/css"/>
+    <item id="pic" href="Images/a%20b.png" media-type="image/png"/>
+  </manifest>
+  <spine>
+    <itemref idref="nav" linear="no"/>
+    <itemref idref="ch1"/>
+    <itemref idref="ch1b"/>
+    <itemref idref="ch2"/>
+  </spine>
+</package>"#;
+
+    const NAV: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops"><body>
+<nav epub:type="landmarks"><ol><li><a href="ch2.xhtml">Landmark</a></li></ol></nav>
+<nav epub:type="toc"><ol>
+  <li><a href="ch1.xhtml">Chapter <em>One</em></a>
+    <ol><li><a href="ch1b.xhtml">Nested</a></li></ol>
+  </li>
+  <li><a href="./ch2.xhtml#start">Chapter Two</a></li>
+</ol></nav>
+</body></html>"#;
+
+    fn chapter(body: &str) -> String {
+        format!(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><head><link rel="stylesheet" href="../Styles/book.css"/></head><body>{}</body></html>"#,
+            body
+        )
+    }
+
+    fn build_epub(files: &[(&str, String)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_scan_tags_skips_comments_and_strips_prefixes() {
+        let xml = r#"<?xml version="1.0"?><!-- <fake/> --><dc:title id="t">A</dc:title><![CDATA[<no>]]><br/>"#;
+        let tags = scan_tags(xml);
+        let names: Vec<(&str, bool)> = tags.iter().map(|t| (t.name, t.closing)).collect();
+        assert_eq!(names, [("title", false), ("title", true), ("br", false)]);
+        assert_eq!(tags[0].raw, r#"dc:title id="t""#);
+        assert_eq!(&xml[tags[2].start..tags[2].end], "<br/>");
+        assert_eq!(inner_text(xml, &tags, 0), "A");
+    }
+
+    #[test]
+    fn test_attr_unescapes_and_matches_whole_names() {
+        let raw = r#"a data-href="wrong" href='ch1.xhtml#s&amp;1' title="Tom &amp; Jerry &#x2014; &lt;1&gt;""#;
+        assert_eq!(attr(raw, "href").as_deref(), Some("ch1.xhtml#s&1"));
+        assert_eq!(attr(raw, "title").as_deref(), Some("Tom & Jerry \u{2014} <1>"));
+        assert_eq!(attr(raw, "data-href").as_deref(), Some("wrong"));
+        assert_eq!(attr(raw, "id"), None);
+        assert_eq!(attr(r#"image xlink:href="../a.png""#, "xlink:href").as_deref(), Some("../a.png"));
+    }
+
+    #[test]
+    fn test_resolve_relative_hrefs() {
+        assert_eq!(resolve("OEBPS/Text", "../Images/a%20b.png"), "OEBPS/Images/a b.png");
+        assert_eq!(resolve("OEBPS/Text", "./ch1.xhtml"), "OEBPS/Text/ch1.xhtml");
+        assert_eq!(resolve("OEBPS", "/META-INF/container.xml"), "META-INF/container.xml");
+        assert_eq!(resolve("", "../../x.css"), "x.css");
+        assert_eq!(parent_dir("OEBPS/content.opf"), "OEBPS");
+        assert_eq!(parent_dir("content.opf"), "");
+    }
+
+    #[test]
+    fn test_ncx_toc_top_level_only() {
+        let ncx = r#"<ncx><navMap>
+  <navPoint id="1"><navLabel><text>Part &amp; One</text></navLabel><content src="../Text/ch1.xhtml"/>
+    <navPoint id="1a"><navLabel><text>Inner</text></navLabel><content src="../Text/ch1b.xhtml"/></navPoint>
+  </navPoint>
+  <navPoint id="2"><navLabel><text>Two</text></navLabel><content src="../Text/ch2.xhtml#start"/></navPoint>
+</navMap></ncx>"#;
+        let toc = ncx_toc(ncx, "OEBPS/Toc");
+        let entries: Vec<(&str, &str, Option<&str>)> =
+            toc.iter().map(|e| (e.title.as_str(), e.path.as_str(), e.fragment.as_deref())).collect();
+        assert_eq!(
+            entries,
+            [("Part & One", "OEBPS/Text/ch1.xhtml", None), ("Two", "OEBPS/Text/ch2.xhtml", Some("start"))]
+        );
+    }
+
+    #[test]
+    fn test_nav_toc_prefers_toc_nav() {
+        let toc = nav_toc(NAV, "OEBPS/Text");
+        let entries: Vec<(&str, &str, Option<&str>)> =
+            toc.iter().map(|e| (e.title.as_str(), e.path.as_str(), e.fragment.as_deref())).collect();
+        assert_eq!(
+            entries,
+            [("Chapter One", "OEBPS/Text/ch1.xhtml", None), ("Chapter Two", "OEBPS/Text/ch2.xhtml", Some("start"))]
+        );
+    }
+
+    #[test]
+    fn test_split_by_chapter() {
+        let dir = std::env::temp_dir().join(format!("epub_split_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("book.epub");
+        let out = dir.join("out");
+        let epub = build_epub(&[
+            ("mimetype", "application/epub+zip".to_string()),
+            ("META-INF/container.xml", CONTAINER.to_string()),
+            ("OEBPS/content.opf", OPF.to_string()),
+            ("OEBPS/Text/nav.xhtml", NAV.to_string()),
+            ("OEBPS/Text/ch1.xhtml", chapter(r#"<p>One</p><a href="ch2.xhtml">next</a>"#)),
+            ("OEBPS/Text/ch1b.xhtml", chapter("<p>One, continued</p>")),
+            ("OEBPS/Text/ch2.xhtml", chapter(r#"<h1 id="start">Two</h1><img src="../Images/a%20b.png"/>"#)),
+            ("OEBPS/Styles/book.css", "body { margin: 0 }".to_string()),
+            ("OEBPS/Images/a b.png", "png".to_string()),
+        ]);
+        std::fs::write(&src, epub).unwrap();
+
+        let created = split_by_chapter(src.to_str().unwrap(), out.to_str().unwrap()).unwrap();
+        assert_eq!(created.len(), 2);
+        assert!(created[0].ends_with("01 - Chapter One.epub"), "{}", created[0]);
+
+        let mut first = open(&created[0]).unwrap();
+        let package = read_package(&mut first).unwrap();
+        let spine: Vec<&str> = package.spine_items().iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(spine, ["OEBPS/Text/ch1.xhtml", "OEBPS/Text/ch1b.xhtml"]);
+        assert_eq!(package.title.as_deref(), Some("Tom & Jerry — Chapter One"));
+        assert!(first.by_name("OEBPS/Styles/book.css").is_ok());
+        assert!(first.by_name("OEBPS/Images/a b.png").is_err());
+        // Linked, but belongs to the next chapter
+        assert!(first.by_name("OEBPS/Text/ch2.xhtml").is_err());
+
+        let mut second = open(&created[1]).unwrap();
+        let package = read_package(&mut second).unwrap();
+        assert!(second.by_name("OEBPS/Images/a b.png").is_ok());
+        let toc = top_level_toc(&mut second, &package);
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].path, "OEBPS/Text/ch2.xhtml");
+        assert_eq!(toc[0].fragment.as_deref(), Some("start"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,6 +1,13 @@
+mod drm;
+mod epub;
+
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tauri::{Manager, Emitter};
@@ -39,6 +46,78 @@ pub struct ConversionOptions {
     pub page_size: Option<String>,  // a4, letter, etc for PDF
     pub embed_font_family: Option<String>,
     pub no_images: Option<bool>,
+    /// CSS text appended to the book's styles (`--extra-css`)
+    pub extra_css: Option<String>,
+    /// XPath marking chapter starts (`--chapter`)
+    pub chapter_xpath: Option<String>,
+    pub remove_first_image: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: String, // "error" | "warning"
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { severity: "error".into(), message: message.into() }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self { severity: "warning".into(), message: message.into() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub path: String,
+    pub format: String,
+    /// False when any issue is an error; warnings alone still convert
+    pub valid: bool,
+    pub issues: Vec<ValidationIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmStatus {
+    pub has_drm: bool,
+    pub scheme: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub version: Option<String>,
+    pub install_hint: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookStats {
+    pub words: usize,
+    /// Not counting whitespace
+    pub characters: usize,
+    /// Top-level table of contents entries
+    pub chapters: usize,
+    pub wpm: u32,
+    pub reading_minutes: u32,
+}
+
+const DEFAULT_WPM: u32 = 250;
+
+// Words, characters and chapters per (path, mtime), so reopening an
+// unchanged book skips the work
+type StatsCache = HashMap<(String, SystemTime), (usize, usize, usize)>;
+static STATS_CACHE: Mutex<Option<StatsCache>> = Mutex::new(None);
+
+// Deleted when dropped, so every exit from a conversion cleans up
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +129,72 @@ pub struct ConversionProgress {
     pub message: Option<String>,
 }
 
+/// One file's outcome in a batch metadata read or write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchMetadataResult {
+    pub path: String,
+    pub metadata: Option<BookMetadata>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub done: usize,
+    pub total: usize,
+    pub path: String,
+    pub error: Option<String>,
+}
+
+// How many ebook-meta processes a batch runs at once
+const BATCH_CONCURRENCY: usize = 4;
+
+fn install_hint(tool: &str) -> String {
+    let hint = match tool {
+        // Both come with calibre
+        "ebook-convert" | "ebook-meta" => {
+            if cfg!(target_os = "macos") {
+                "brew install --cask calibre"
+            } else if cfg!(windows) {
+                "winget install calibre.calibre"
+            } else {
+                "sudo apt install calibre"
+            }
+        }
+        _ => return format!("Install {} and make sure it is on your PATH", tool),
+    };
+    format!("Install calibre with: {}", hint)
+}
+
+// A missing binary gets the install hint instead of a bare "not found"
+fn spawn_error(tool: &str, e: std::io::Error) -> String {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        format!("{} not found. {}", tool, install_hint(tool))
+    } else {
+        format!("Failed to run {}: {}", tool, e)
+    }
+}
+
+async fn tool_status(name: &str) -> ToolStatus {
+    let version = Command::new(name)
+        .arg("--version")
+        .output()
+        .await
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8_lossy(&o.stdout).lines().next().map(|l| l.trim().to_string()));
+    ToolStatus {
+        name: name.to_string(),
+        found: version.is_some(),
+        version,
+        install_hint: install_hint(name),
+    }
+}
+
+#[tauri::command]
+async fn check_all_tools() -> Vec<ToolStatus> {
+    vec![tool_status("ebook-convert").await, tool_status("ebook-meta").await]
+}
+
 #[tauri::command]
 async fn check_calibre() -> Result<bool, String> {
     let output = Command::new("ebook-convert")
@@ -61,11 +206,15 @@ async fn check_calibre() -> Result<bool, String> {
 
 #[tauri::command]
 async fn get_metadata(file_path: String) -> Result<BookMetadata, String> {
+    read_metadata(&file_path).await
+}
+
+async fn read_metadata(file_path: &str) -> Result<BookMetadata, String> {
     let output = Command::new("ebook-meta")
-        .arg(&file_path)
+        .arg(file_path)
         .output()
         .await
-        .map_err(|e| format!("Failed to run ebook-meta: {}", e))?;
+        .map_err(|e| spawn_error("ebook-meta", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let mut meta = BookMetadata {
@@ -100,7 +249,12 @@ async fn get_metadata(file_path: String) -> Result<BookMetadata, String> {
 
 #[tauri::command]
 async fn set_metadata(file_path: String, metadata: BookMetadata) -> Result<(), String> {
-    let mut args: Vec<String> = vec![file_path];
+    write_metadata(&file_path, &metadata).await
+}
+
+// Fields left as None are not passed to ebook-meta, so they keep their value
+async fn write_metadata(file_path: &str, metadata: &BookMetadata) -> Result<(), String> {
+    let mut args: Vec<String> = vec![file_path.to_string()];
 
     if let Some(ref t) = metadata.title { args.extend(["--title".into(), t.clone()]); }
     if let Some(ref a) = metadata.author { args.extend(["--authors".into(), a.clone()]); }
@@ -117,7 +271,7 @@ async fn set_metadata(file_path: String, metadata: BookMetadata) -> Result<(), S
         .args(&args)
         .output()
         .await
-        .map_err(|e| format!("Failed to run ebook-meta: {}", e))?;
+        .map_err(|e| spawn_error("ebook-meta", e))?;
 
     if output.status.success() {
         Ok(())
@@ -126,13 +280,75 @@ async fn set_metadata(file_path: String, metadata: BookMetadata) -> Result<(), S
     }
 }
 
+// Runs `job` for every item at most BATCH_CONCURRENCY at a time, emitting
+// "metadata-progress" as each finishes. Results keep the input order.
+async fn run_batch<T, F, Fut>(app: &tauri::AppHandle, items: Vec<(String, T)>, job: F) -> Vec<BatchMetadataResult>
+where
+    T: Send + 'static,
+    F: Fn(String, T) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<BookMetadata>, String>> + Send + 'static,
+{
+    let total = items.len();
+    let permits = Arc::new(tokio::sync::Semaphore::new(BATCH_CONCURRENCY));
+    let done = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = items
+        .into_iter()
+        .map(|(path, item)| {
+            let permits = Arc::clone(&permits);
+            let done = Arc::clone(&done);
+            let app = app.clone();
+            let work = job(path.clone(), item);
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = work.await;
+                let error = result.as_ref().err().cloned();
+                let _ = app.emit("metadata-progress", BatchProgress {
+                    done: done.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    path: path.clone(),
+                    error: error.clone(),
+                });
+                BatchMetadataResult {
+                    path,
+                    metadata: result.ok().flatten(),
+                    error,
+                }
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(total);
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+    results
+}
+
+#[tauri::command]
+async fn batch_get_metadata(app: tauri::AppHandle, paths: Vec<String>) -> Result<Vec<BatchMetadataResult>, String> {
+    let items = paths.into_iter().map(|p| (p, ())).collect();
+    Ok(run_batch(&app, items, |path, _| async move { read_metadata(&path).await.map(Some) }).await)
+}
+
+// Each update only touches the fields it sets; one failing file doesn't stop the rest
+#[tauri::command]
+async fn batch_set_metadata(app: tauri::AppHandle, updates: Vec<(String, BookMetadata)>) -> Result<Vec<BatchMetadataResult>, String> {
+    Ok(run_batch(&app, updates, |path, metadata| async move {
+        write_metadata(&path, &metadata).await.map(|_| None)
+    })
+    .await)
+}
+
 #[tauri::command]
 async fn extract_cover(file_path: String, output_path: String) -> Result<String, String> {
     let output = Command::new("ebook-meta")
         .args(&[&file_path, "--get-cover", &output_path])
         .output()
         .await
-        .map_err(|e| format!("Failed: {}", e))?;
+        .map_err(|e| spawn_error("ebook-meta", e))?;
 
     if output.status.success() {
         Ok(output_path)
@@ -150,7 +366,7 @@ async fn get_cover_base64(file_path: String) -> Result<Option<String>, String> {
         .args(&[&file_path, "--get-cover", &tmp_str])
         .output()
         .await
-        .map_err(|e| format!("Failed: {}", e))?;
+        .map_err(|e| spawn_error("ebook-meta", e))?;
 
     if output.status.success() && tmp.exists() {
         let data = tokio::fs::read(&tmp).await.map_err(|e| e.to_string())?;
@@ -173,6 +389,22 @@ async fn convert_ebook(
         .to_string_lossy()
         .to_string();
 
+    // calibre's error for a protected book says nothing about DRM
+    let drm_path = job.input_path.clone();
+    let drm = tokio::task::spawn_blocking(move || drm::detect(&drm_path))
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Ok(DrmStatus { has_drm: true, message, .. }) = drm {
+        let _ = app.emit("conversion-progress", ConversionProgress {
+            job_id: job.id.clone(),
+            file_name: file_stem.clone(),
+            progress: 0.0,
+            status: "error".into(),
+            message: Some(message.clone()),
+        });
+        return Err(message);
+    }
+
     let output_path = PathBuf::from(&job.output_dir)
         .join(format!("{}.{}", file_stem, job.output_format));
     let output_str = output_path.to_string_lossy().to_string();
@@ -192,6 +424,20 @@ async fn convert_ebook(
     if let Some(ref v) = opts.page_size { args.extend(["--paper-size".into(), v.clone()]); }
     if let Some(ref v) = opts.embed_font_family { args.extend(["--embed-font-family".into(), v.clone()]); }
     if opts.no_images == Some(true) { args.push("--no-images".into()); }
+    if let Some(ref v) = opts.chapter_xpath { args.extend(["--chapter".into(), v.clone()]); }
+    if opts.remove_first_image == Some(true) { args.push("--remove-first-image".into()); }
+
+    let _extra_css = match opts.extra_css.as_deref().filter(|css| !css.trim().is_empty()) {
+        Some(css) => {
+            let path = std::env::temp_dir().join(format!("ebook_extra_{}.css", uuid::Uuid::new_v4()));
+            tokio::fs::write(&path, css)
+                .await
+                .map_err(|e| format!("Failed to write extra CSS: {}", e))?;
+            args.extend(["--extra-css".into(), path.to_string_lossy().to_string()]);
+            Some(TempFile(path))
+        }
+        None => None,
+    };
 
     // Emit start
     let _ = app.emit("conversion-progress", ConversionProgress {
@@ -207,7 +453,7 @@ async fn convert_ebook(
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| format!("Failed to start ebook-convert: {}", e))?;
+        .map_err(|e| spawn_error("ebook-convert", e))?;
 
     // Read stderr for progress
     let stderr = child.stderr.take();
@@ -318,6 +564,115 @@ async fn get_toc(file_path: String) -> Result<String, String> {
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+// EPUBs are checked structurally; anything else only has to be readable by
+// calibre, which is what ebook-meta tries
+#[tauri::command]
+async fn validate_ebook(path: String) -> Result<ValidationReport, String> {
+    let format = std::path::Path::new(&path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let issues = match tokio::fs::metadata(&path).await {
+        Err(e) => vec![ValidationIssue::error(format!("Cannot read file: {}", e))],
+        Ok(meta) if meta.len() == 0 => vec![ValidationIssue::error("File is empty")],
+        Ok(_) if format == "epub" => {
+            let epub_path = path.clone();
+            tokio::task::spawn_blocking(move || epub::validate(&epub_path))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        Ok(_) => match Command::new("ebook-meta").arg(&path).output().await {
+            Err(e) => vec![ValidationIssue::warning(format!("Could not check the file: {}", spawn_error("ebook-meta", e)))],
+            Ok(output) if !output.status.success() => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+                vec![ValidationIssue::error(format!("Calibre can't read this file: {}", reason.trim()))]
+            }
+            Ok(_) => Vec::new(),
+        },
+    };
+
+    Ok(ValidationReport {
+        path,
+        format,
+        valid: !issues.iter().any(|i| i.severity == "error"),
+        issues,
+    })
+}
+
+#[tauri::command]
+async fn check_drm(path: String) -> Result<DrmStatus, String> {
+    tokio::task::spawn_blocking(move || drm::detect(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// EPUBs are read directly; other formats go through a temporary EPUB made by
+// ebook-convert so the text and TOC come out the same way
+#[tauri::command]
+async fn get_book_stats(path: String, wpm: Option<u32>) -> Result<BookStats, String> {
+    let mtime = tokio::fs::metadata(&path)
+        .await
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let key = (path.clone(), mtime);
+
+    let cached = STATS_CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(&key).copied());
+    let (words, characters, chapters) = match cached {
+        Some(counts) => counts,
+        None => {
+            let is_epub = path.to_lowercase().ends_with(".epub");
+            let (epub_path, _temp) = if is_epub {
+                (path.clone(), None)
+            } else {
+                let tmp = std::env::temp_dir().join(format!("ebook_stats_{}.epub", uuid::Uuid::new_v4()));
+                let temp = TempFile(tmp.clone());
+                let output = Command::new("ebook-convert")
+                    .arg(&path)
+                    .arg(&tmp)
+                    .output()
+                    .await
+                    .map_err(|e| spawn_error("ebook-convert", e))?;
+                if !output.status.success() {
+                    return Err(format!("Could not read the book's text: {}", String::from_utf8_lossy(&output.stderr).trim()));
+                }
+                (tmp.to_string_lossy().to_string(), Some(temp))
+            };
+            let counts = tokio::task::spawn_blocking(move || epub::text_stats(&epub_path))
+                .await
+                .map_err(|e| e.to_string())??;
+            STATS_CACHE
+                .lock()
+                .unwrap()
+                .get_or_insert_with(HashMap::new)
+                .insert(key, counts);
+            counts
+        }
+    };
+
+    let wpm = wpm.filter(|w| *w > 0).unwrap_or(DEFAULT_WPM);
+    Ok(BookStats {
+        words,
+        characters,
+        chapters,
+        wpm,
+        reading_minutes: (words as u32).div_ceil(wpm),
+    })
+}
+
+// Each top-level TOC section becomes its own EPUB; returns the files written
+#[tauri::command]
+async fn split_epub_by_chapter(path: String, output_dir: String) -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(move || epub::split_by_chapter(&path, &output_dir))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn get_supported_formats() -> Vec<String> {
     vec![
@@ -336,12 +691,19 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             check_calibre,
+            check_all_tools,
             get_metadata,
             set_metadata,
+            batch_get_metadata,
+            batch_set_metadata,
             extract_cover,
             get_cover_base64,
             convert_ebook,
             get_toc,
+            split_epub_by_chapter,
+            validate_ebook,
+            check_drm,
+            get_book_stats,
             get_supported_formats,
         ])
         .run(tauri::generate_context!())
@@ -20,6 +20,10 @@ document.addEventListener('DOMContentLoaded', async () => {
     const dot = document.getElementById('calibre-status');
     dot.classList.toggle('ok', ok);
     dot.title = ok ? 'Calibre detected' : 'Calibre not found — install calibre first';
+    const missing = (await invoke('check_all_tools')).filter(t => !t.found);
+    if (missing.length) {
+      dot.title = missing.map(t => `${t.name} not found. ${t.install_hint}`).join('\n');
+    }
   } catch { }
 
   // Tab buttons
@@ -35,8 +39,13 @@ document.addEventListener('DOMContentLoaded', async () => {
   document.getElementById('btn-pick-dir').addEventListener('click', pickOutputDir);
   document.getElementById('btn-convert').addEventListener('click', convertAll);
   document.getElementById('btn-save-meta').addEventListener('click', saveMetadata);
+  document.getElementById('btn-apply-all').addEventListener('click', applyMetadataToAll);
   document.getElementById('btn-extract-cover').addEventListener('click', extractCover);
   document.getElementById('btn-replace-cover').addEventListener('click', replaceCover);
+  document.getElementById('btn-split-chapters').addEventListener('click', splitChapters);
+  document.getElementById('stats-wpm').addEventListener('change', () => {
+    if (selectedBookPath) loadStats(selectedBookPath);
+  });
 
   // Drag & drop
   setupDragDrop();
@@ -45,6 +54,11 @@ document.addEventListener('DOMContentLoaded', async () => {
   await listen('conversion-progress', (event) => {
     updateProgress(event.payload);
   });
+
+  await listen('metadata-progress', (event) => {
+    const p = event.payload;
+    showMetaStatus(`Updating ${p.done}/${p.total}…`);
+  });
 });
 
 // ── Tabs ─────────────────────────────────────────────
@@ -97,8 +111,26 @@ async function addBook(filePath) {
     coverBase64 = await invoke('get_cover_base64', { filePath });
   } catch { }
 
-  books.push({ id, path: filePath, name, format: ext, coverBase64, selected: false });
+  books.push({ id, path: filePath, name, format: ext, coverBase64, selected: false, issues: [] });
   renderBooks();
+  validateBook(id);
+}
+
+// Problems show as a badge on the card before anything is converted
+async function validateBook(id) {
+  const book = books.find(b => b.id === id);
+  if (!book) return;
+  try {
+    const [report, drm] = await Promise.all([
+      invoke('validate_ebook', { path: book.path }),
+      invoke('check_drm', { path: book.path }).catch(() => null),
+    ]);
+    book.issues = report.issues;
+    if (drm?.has_drm) {
+      book.issues.unshift({ severity: 'error', message: drm.message });
+    }
+    renderBooks();
+  } catch { }
 }
 
 function removeBook(id) {
@@ -133,11 +165,20 @@ function renderBooks() {
       <div class="book-info">
         <div class="book-title" title="${b.name}">${b.name}</div>
         <div class="book-format">${b.format}</div>
+        ${issueBadge(b.issues)}
       </div>
     </div>
   `).join('');
 }
 
+function issueBadge(issues) {
+  if (!issues?.length) return '';
+  const hasError = issues.some(i => i.severity === 'error');
+  const title = issues.map(i => `${i.severity === 'error' ? '✗' : '⚠'} ${i.message}`).join('\n');
+  const label = issues.map(i => i.message).join(', ');
+  return `<div class="book-issues ${hasError ? 'error' : ''}" title="${escapeAttr(title)}">${hasError ? '✗' : '⚠'} ${escapeAttr(label)}</div>`;
+}
+
 function createEmptyState() {
   const div = document.createElement('div');
   div.className = 'empty-state';
@@ -238,6 +279,9 @@ async function convertAll() {
     page_size: strVal('opt-page-size'),
     embed_font_family: null,
     no_images: document.getElementById('opt-no-images').checked || null,
+    extra_css: strField('opt-extra-css'),
+    chapter_xpath: strField('opt-chapter-xpath'),
+    remove_first_image: document.getElementById('opt-remove-first-image').checked || null,
   };
 
   for (const book of books) {
@@ -336,6 +380,23 @@ async function loadMetadata(filePath) {
   } catch {
     document.getElementById('toc-preview').textContent = 'Could not extract TOC';
   }
+
+  loadStats(filePath);
+}
+
+// Counts are cached on the backend, so changing the WPM is instant
+async function loadStats(filePath) {
+  const el = document.getElementById('book-stats');
+  el.textContent = 'Counting…';
+  try {
+    const stats = await invoke('get_book_stats', { path: filePath, wpm: numVal('stats-wpm') });
+    const hours = Math.floor(stats.reading_minutes / 60);
+    const time = hours ? `${hours} h ${stats.reading_minutes % 60} min` : `${stats.reading_minutes} min`;
+    el.textContent = `${stats.words.toLocaleString()} words · ${stats.characters.toLocaleString()} characters · `
+      + `${stats.chapters} chapters · ~${time} at ${stats.wpm} wpm`;
+  } catch (e) {
+    el.textContent = 'Could not read statistics: ' + e;
+  }
 }
 
 async function saveMetadata() {
@@ -365,6 +426,47 @@ async function saveMetadata() {
   }
 }
 
+// Shared fields only — title, ISBN, description and series index stay per book.
+// Empty fields are sent as null so they don't wipe existing values.
+async function applyMetadataToAll() {
+  if (books.length === 0) {
+    showMetaStatus('Add some books first', true);
+    return;
+  }
+
+  const metadata = {
+    title: null,
+    author: strField('meta-author'),
+    language: strField('meta-language'),
+    publisher: strField('meta-publisher'),
+    description: null,
+    isbn: null,
+    tags: strField('meta-tags'),
+    series: strField('meta-series'),
+    series_index: null,
+    cover_path: null,
+  };
+  if (Object.values(metadata).every(v => v === null)) {
+    showMetaStatus('Fill in at least one shared field', true);
+    return;
+  }
+
+  try {
+    const results = await invoke('batch_set_metadata', {
+      updates: books.map(b => [b.path, metadata]),
+    });
+    const failed = results.filter(r => r.error);
+    if (failed.length) {
+      const names = failed.map(r => r.path.split('/').pop()).join(', ');
+      showMetaStatus(`Updated ${results.length - failed.length} books, failed: ${names}`, true);
+    } else {
+      showMetaStatus(`✓ Updated ${results.length} books`);
+    }
+  } catch (e) {
+    showMetaStatus('Error: ' + e, true);
+  }
+}
+
 async function extractCover() {
   if (!selectedBookPath) return;
   try {
@@ -398,6 +500,22 @@ async function replaceCover() {
   }
 }
 
+async function splitChapters() {
+  if (!selectedBookPath) {
+    showMetaStatus('Select a book first', true);
+    return;
+  }
+  try {
+    const outputDir = await dialogOpen({ directory: true });
+    if (!outputDir) return;
+    showMetaStatus('Splitting…');
+    const files = await invoke('split_epub_by_chapter', { path: selectedBookPath, outputDir });
+    showMetaStatus(`✓ Wrote ${files.length} chapter files`);
+  } catch (e) {
+    showMetaStatus('Error: ' + e, true);
+  }
+}
+
 function showMetaStatus(msg, isError = false) {
   const el = document.getElementById('meta-status');
   el.textContent = msg;
@@ -416,6 +534,10 @@ function strVal(id) {
   return v || null;
 }
 
+function escapeAttr(s) {
+  return s.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;').replace(/"/g, '&quot;');
+}
+
 function strField(id) {
   const v = document.getElementById(id)?.value?.trim();
   return v || null;
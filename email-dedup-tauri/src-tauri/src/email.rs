@@ -1,12 +1,15 @@
+use imap::types::{Fetch, NameAttribute};
 use imap::Session;
+use imap_proto::types::BodyStructure;
 use mailparse::parse_mail;
 use native_tls::{TlsConnector, TlsStream};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::io::Write;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{ErrorKind, Write};
 use std::net::TcpStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // ── Types ──────────────────────────────────────────────────────────────────
 
@@ -29,11 +32,14 @@ pub struct MailboxInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailHeader {
     pub uid: u32,
+    pub mailbox: String,
     pub message_id: String,
     pub subject: String,
     pub from: String,
     pub date: String,
     pub size: u32,
+    pub has_attachments: bool,
+    pub attachment_count: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +101,36 @@ pub fn connect(account: &ImapAccount) -> Result<Session<TlsStream<TcpStream>>, S
     Ok(session)
 }
 
+/// Like `connect`, but also hands back a clone of the socket so another
+/// thread can shut it down and unblock a pending read (e.g. during IDLE).
+pub fn connect_with_stream(
+    account: &ImapAccount,
+) -> Result<(Session<TlsStream<TcpStream>>, TcpStream), String> {
+    let tls = TlsConnector::builder()
+        .build()
+        .map_err(|e| format!("TLS error: {e}"))?;
+
+    let tcp = TcpStream::connect((account.host.as_str(), account.port))
+        .map_err(|e| format!("Connection error: {e}"))?;
+    let socket = tcp
+        .try_clone()
+        .map_err(|e| format!("Connection error: {e}"))?;
+    let stream = tls
+        .connect(&account.host, tcp)
+        .map_err(|e| format!("TLS error: {e}"))?;
+
+    let mut client = imap::Client::new(stream);
+    client
+        .read_greeting()
+        .map_err(|e| format!("Connection error: {e}"))?;
+
+    let session = client
+        .login(&account.username, &account.password)
+        .map_err(|e| format!("Login failed: {:?}", e.0))?;
+
+    Ok((session, socket))
+}
+
 // ── Mailbox Listing ────────────────────────────────────────────────────────
 
 pub fn list_mailboxes(session: &mut Session<TlsStream<TcpStream>>) -> Result<Vec<MailboxInfo>, String> {
@@ -118,8 +154,113 @@ pub fn list_mailboxes(session: &mut Session<TlsStream<TcpStream>>) -> Result<Vec
     Ok(mailboxes)
 }
 
+// Gmail exposes labels as folders, so these only hold copies of mail filed elsewhere
+const VIRTUAL_ATTRIBUTES: [&str; 3] = ["\\All", "\\Flagged", "\\Important"];
+const VIRTUAL_NAMES: [&str; 3] = ["[Gmail]/All Mail", "[Gmail]/Important", "[Gmail]/Starred"];
+
+/// Selectable mailboxes, and separately the virtual ones left out when
+/// `skip_virtual` is set.
+pub fn scannable_mailboxes(
+    session: &mut Session<TlsStream<TcpStream>>,
+    skip_virtual: bool,
+) -> Result<(Vec<String>, Vec<String>), String> {
+    let names = session
+        .list(None, Some("*"))
+        .map_err(|e| format!("List error: {e}"))?;
+
+    let mut scan = Vec::new();
+    let mut skipped = Vec::new();
+    for name in names.iter() {
+        let attributes = name.attributes();
+        if attributes
+            .iter()
+            .any(|a| matches!(a, NameAttribute::NoSelect))
+        {
+            continue;
+        }
+        let is_virtual = VIRTUAL_NAMES.contains(&name.name())
+            || attributes.iter().any(|a| {
+                matches!(a, NameAttribute::Custom(attr)
+                    if VIRTUAL_ATTRIBUTES.iter().any(|v| attr.eq_ignore_ascii_case(v)))
+            });
+        if skip_virtual && is_virtual {
+            skipped.push(name.name().to_string());
+        } else {
+            scan.push(name.name().to_string());
+        }
+    }
+
+    Ok((scan, skipped))
+}
+
 // ── Fetch Headers ──────────────────────────────────────────────────────────
 
+const HEADER_QUERY: &str =
+    "(UID RFC822.SIZE BODYSTRUCTURE BODY.PEEK[HEADER.FIELDS (MESSAGE-ID SUBJECT FROM DATE)])";
+
+// Leaf parts marked as attachments or carrying a file name; forwarded
+// messages count as one attachment and are not descended into.
+fn count_attachments(part: &BodyStructure) -> u32 {
+    let common = match part {
+        BodyStructure::Multipart { bodies, .. } => {
+            return bodies.iter().map(count_attachments).sum();
+        }
+        BodyStructure::Basic { common, .. }
+        | BodyStructure::Text { common, .. }
+        | BodyStructure::Message { common, .. } => common,
+    };
+
+    let disposition = common.disposition.as_ref();
+    if disposition.is_some_and(|d| d.ty.eq_ignore_ascii_case("attachment")) {
+        return 1;
+    }
+    if disposition.is_some_and(|d| d.ty.eq_ignore_ascii_case("inline")) {
+        return 0;
+    }
+    let named = common
+        .ty
+        .params
+        .iter()
+        .flatten()
+        .any(|(key, _)| key.eq_ignore_ascii_case("name"));
+    u32::from(named)
+}
+
+fn parse_header(msg: &Fetch, mailbox: &str) -> EmailHeader {
+    let uid = msg.uid.unwrap_or(0);
+    let size = msg.size.unwrap_or(0);
+    let attachment_count = msg.bodystructure().map(count_attachments).unwrap_or(0);
+    let header_bytes = msg
+        .header()
+        .or_else(|| msg.body())
+        .unwrap_or_default();
+
+    let parsed = parse_mail(header_bytes).unwrap_or_else(|_| {
+        parse_mail(b"").unwrap()
+    });
+
+    let get_hdr = |name: &str| -> String {
+        parsed
+            .headers
+            .iter()
+            .find(|h| h.get_key().eq_ignore_ascii_case(name))
+            .map(|h| h.get_value())
+            .unwrap_or_default()
+    };
+
+    EmailHeader {
+        uid,
+        mailbox: mailbox.to_string(),
+        message_id: get_hdr("Message-ID"),
+        subject: get_hdr("Subject"),
+        from: get_hdr("From"),
+        date: get_hdr("Date"),
+        size,
+        has_attachments: attachment_count > 0,
+        attachment_count,
+    }
+}
+
 pub fn fetch_headers(
     session: &mut Session<TlsStream<TcpStream>>,
     mailbox: &str,
@@ -134,42 +275,53 @@ pub fn fetch_headers(
 
     let range = format!("1:{}", mb.exists);
     let messages = session
-        .fetch(&range, "(UID RFC822.SIZE BODY.PEEK[HEADER.FIELDS (MESSAGE-ID SUBJECT FROM DATE)])")
+        .fetch(&range, HEADER_QUERY)
         .map_err(|e| format!("Fetch error: {e}"))?;
 
-    let mut headers = Vec::new();
-    for msg in messages.iter() {
-        let uid = msg.uid.unwrap_or(0);
-        let size = msg.size.unwrap_or(0);
-        let header_bytes = msg
-            .header()
-            .or_else(|| msg.body())
-            .unwrap_or_default();
-
-        let parsed = parse_mail(header_bytes).unwrap_or_else(|_| {
-            parse_mail(b"").unwrap()
-        });
+    Ok(messages.iter().map(|m| parse_header(m, mailbox)).collect())
+}
 
-        let get_hdr = |name: &str| -> String {
-            parsed
-                .headers
-                .iter()
-                .find(|h| h.get_key().eq_ignore_ascii_case(name))
-                .map(|h| h.get_value())
-                .unwrap_or_default()
-        };
+/// Headers of messages with a UID above `last_uid` in the already selected `mailbox`.
+pub fn fetch_headers_since(
+    session: &mut Session<TlsStream<TcpStream>>,
+    mailbox: &str,
+    last_uid: u32,
+) -> Result<Vec<EmailHeader>, String> {
+    // "n:*" always matches the newest message, even when its UID is below n
+    let uids: Vec<String> = session
+        .uid_search(format!("UID {}:*", last_uid + 1))
+        .map_err(|e| format!("Search error: {e}"))?
+        .into_iter()
+        .filter(|&uid| uid > last_uid)
+        .map(|uid| uid.to_string())
+        .collect();
 
-        headers.push(EmailHeader {
-            uid,
-            message_id: get_hdr("Message-ID"),
-            subject: get_hdr("Subject"),
-            from: get_hdr("From"),
-            date: get_hdr("Date"),
-            size,
-        });
+    if uids.is_empty() {
+        return Ok(Vec::new());
     }
 
-    Ok(headers)
+    let messages = session
+        .uid_fetch(uids.join(","), HEADER_QUERY)
+        .map_err(|e| format!("Fetch error: {e}"))?;
+
+    Ok(messages.iter().map(|m| parse_header(m, mailbox)).collect())
+}
+
+/// Blocks in IDLE until the mailbox changes or `timeout` passes.
+pub fn idle_wait(
+    session: &mut Session<TlsStream<TcpStream>>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let idle = session.idle().map_err(|e| format!("IDLE error: {e}"))?;
+    match idle.wait_with_timeout(timeout) {
+        Ok(_) => Ok(()),
+        Err(imap::Error::Io(e))
+            if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(format!("IDLE error: {e}")),
+    }
 }
 
 // ── Dedup Methods ──────────────────────────────────────────────────────────
@@ -181,7 +333,7 @@ pub enum DedupMethod {
     SizeSubject,
 }
 
-fn dedup_key(email: &EmailHeader, method: &DedupMethod) -> Option<String> {
+pub fn dedup_key(email: &EmailHeader, method: &DedupMethod) -> Option<String> {
     match method {
         DedupMethod::MessageId => {
             let mid = email.message_id.trim().to_string();
@@ -228,10 +380,14 @@ pub fn find_duplicates(headers: &[EmailHeader], method: DedupMethod) -> DedupRes
     let duplicate_groups: Vec<DuplicateGroup> = groups
         .into_iter()
         .filter(|(_, emails)| emails.len() > 1)
-        .map(|(key, emails)| DuplicateGroup {
-            key,
-            method: method_name.to_string(),
-            emails,
+        .map(|(key, mut emails)| {
+            // The first copy is kept, so put the one with its attachments intact there
+            emails.sort_by(|a, b| b.attachment_count.cmp(&a.attachment_count));
+            DuplicateGroup {
+                key,
+                method: method_name.to_string(),
+                emails,
+            }
         })
         .collect();
 
@@ -248,31 +404,160 @@ pub fn find_duplicates(headers: &[EmailHeader], method: DedupMethod) -> DedupRes
     }
 }
 
+// ── Account-wide Scan ──────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountScanProgress {
+    pub mailbox: String,
+    pub index: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountDedupResult {
+    pub mailboxes: BTreeMap<String, DedupResult>,
+    /// Duplicates that may live in different mailboxes; `None` unless requested
+    pub account_wide: Option<DedupResult>,
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+    pub total_scanned: usize,
+}
+
+/// Scans every mailbox over one connection. A mailbox that fails to fetch is
+/// reported in `errors` rather than aborting the whole scan.
+pub fn scan_account(
+    session: &mut Session<TlsStream<TcpStream>>,
+    method: DedupMethod,
+    skip_virtual: bool,
+    account_wide: bool,
+    mut on_progress: impl FnMut(AccountScanProgress),
+) -> Result<AccountDedupResult, String> {
+    let (mailboxes, skipped) = scannable_mailboxes(session, skip_virtual)?;
+
+    let mut results = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut all_headers = Vec::new();
+    for (index, mailbox) in mailboxes.iter().enumerate() {
+        on_progress(AccountScanProgress {
+            mailbox: mailbox.clone(),
+            index,
+            total: mailboxes.len(),
+        });
+        match fetch_headers(session, mailbox) {
+            Ok(headers) => {
+                results.insert(mailbox.clone(), find_duplicates(&headers, method.clone()));
+                if account_wide {
+                    all_headers.extend(headers);
+                }
+            }
+            Err(e) => errors.push(format!("{mailbox}: {e}")),
+        }
+    }
+
+    let total_scanned = results.values().map(|r| r.total_scanned).sum();
+    Ok(AccountDedupResult {
+        mailboxes: results,
+        account_wide: account_wide.then(|| find_duplicates(&all_headers, method)),
+        skipped,
+        errors,
+        total_scanned,
+    })
+}
+
 // ── Delete Duplicates ──────────────────────────────────────────────────────
 
-pub fn delete_duplicates(
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteResult {
+    pub count: usize,
+    pub dry_run: bool,
+    /// Issued by a dry run; the real deletion must present it
+    pub token: Option<String>,
+    pub export_path: Option<String>,
+    /// Full messages saved next to the header export, when requested
+    pub bodies_path: Option<String>,
+}
+
+/// Every copy but the first in each group.
+pub fn emails_to_delete(groups: &[DuplicateGroup]) -> Vec<EmailHeader> {
+    groups
+        .iter()
+        .flat_map(|g| g.emails.iter().skip(1).cloned())
+        .collect()
+}
+
+/// Writes the headers about to be deleted as JSON, so a purge leaves a trail.
+pub fn export_delete_headers(
+    mailbox: &str,
+    emails: &[EmailHeader],
+    export_path: &Path,
+) -> Result<(), String> {
+    let preview = serde_json::json!({
+        "mailbox": mailbox,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "emails": emails,
+    });
+    let json = serde_json::to_string_pretty(&preview).map_err(|e| e.to_string())?;
+    std::fs::write(export_path, json).map_err(|e| format!("Export write error: {e}"))
+}
+
+/// UIDs grouped by the mailbox each message was fetched from, since a UID
+/// only identifies a message within its own mailbox. Headers without a
+/// mailbox belong to `default`.
+pub fn uids_by_mailbox(emails: &[EmailHeader], default: &str) -> BTreeMap<String, Vec<u32>> {
+    let mut grouped: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for email in emails {
+        let mailbox = if email.mailbox.is_empty() { default } else { email.mailbox.as_str() };
+        grouped.entry(mailbox.to_string()).or_default().push(email.uid);
+    }
+    grouped
+}
+
+/// Where the message bodies go for a header export at `export_path`: a
+/// sibling "<name>-messages.mbox", so it can't overwrite the export itself.
+pub fn delete_bodies_path(export_path: &Path) -> PathBuf {
+    let stem = export_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "dedup-preview".to_string());
+    export_path.with_file_name(format!("{stem}-messages.mbox"))
+}
+
+/// Saves full copies of the messages about to be deleted to an .mbox, which
+/// can be re-imported to undo the purge. Each message is read from its own
+/// mailbox.
+pub fn export_delete_bodies(
     session: &mut Session<TlsStream<TcpStream>>,
     mailbox: &str,
-    groups: &[DuplicateGroup],
-    dry_run: bool,
+    emails: &[EmailHeader],
+    mbox_path: &Path,
 ) -> Result<usize, String> {
-    if dry_run {
-        let count: usize = groups.iter().map(|g| g.emails.len() - 1).sum();
-        return Ok(count);
+    let mut file =
+        std::fs::File::create(mbox_path).map_err(|e| format!("File create error: {e}"))?;
+    let mut count = 0;
+    for (mailbox, uids) in uids_by_mailbox(emails, mailbox) {
+        session
+            .select(&mailbox)
+            .map_err(|e| format!("Select error: {e}"))?;
+        let pending: HashMap<u32, Option<String>> = uids.into_iter().map(|uid| (uid, None)).collect();
+        count += write_mbox_messages(session, &pending, &mut file, &mut HashSet::new())?;
     }
+    Ok(count)
+}
 
+pub fn delete_duplicates(
+    session: &mut Session<TlsStream<TcpStream>>,
+    mailbox: &str,
+    uids: &[u32],
+) -> Result<usize, String> {
     session
         .select(mailbox)
         .map_err(|e| format!("Select error: {e}"))?;
 
     let mut deleted = 0;
-    for group in groups {
-        // Keep first, delete rest
-        for email in group.emails.iter().skip(1) {
-            let uid_str = format!("{}", email.uid);
-            if session.uid_store(&uid_str, "+FLAGS (\\Deleted)").is_ok() {
-                deleted += 1;
-            }
+    for uid in uids {
+        let uid_str = format!("{}", uid);
+        if session.uid_store(&uid_str, "+FLAGS (\\Deleted)").is_ok() {
+            deleted += 1;
         }
     }
 
@@ -337,39 +622,143 @@ pub fn transfer_emails(
 
 // ── Backup to .mbox ───────────────────────────────────────────────────────
 
-pub fn backup_to_mbox(
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupResult {
+    pub written: usize,
+    pub skipped: usize,
+}
+
+/// Sidecar file listing the messages already in an mbox: `<archive>.index.json`
+fn backup_index_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.as_os_str().to_owned();
+    name.push(".index.json");
+    PathBuf::from(name)
+}
+
+fn load_backup_index(index_path: &Path) -> HashSet<String> {
+    std::fs::read_to_string(index_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
+        .map(|keys| keys.into_iter().collect())
+        .unwrap_or_default()
+}
+
+fn save_backup_index(index_path: &Path, index: &HashSet<String>) -> Result<(), String> {
+    let mut keys: Vec<&String> = index.iter().collect();
+    keys.sort();
+    let json = serde_json::to_string_pretty(&keys).map_err(|e| e.to_string())?;
+    std::fs::write(index_path, json).map_err(|e| format!("Index write error: {e}"))
+}
+
+// Message-ID when present, otherwise the Subject+Date hash
+fn backup_key(email: &EmailHeader) -> Option<String> {
+    dedup_key(email, &DedupMethod::MessageId)
+        .or_else(|| dedup_key(email, &DedupMethod::SubjectDateHash))
+}
+
+// Bodies are fetched this many at a time, so neither the command line nor
+// memory grows with the mailbox
+const BODY_FETCH_CHUNK: usize = 100;
+
+// Sorted UIDs as an IMAP sequence set, runs collapsed: 1,2,3,7 → "1:3,7"
+fn uid_set(uids: &[u32]) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < uids.len() {
+        let start = uids[i];
+        while i + 1 < uids.len() && uids[i].checked_add(1) == Some(uids[i + 1]) {
+            i += 1;
+        }
+        parts.push(if uids[i] == start { start.to_string() } else { format!("{}:{}", start, uids[i]) });
+        i += 1;
+    }
+    parts.join(",")
+}
+
+// Downloads the pending UIDs and records each written message in the index
+fn write_mbox_messages(
     session: &mut Session<TlsStream<TcpStream>>,
-    mailbox: &str,
-    output_path: &PathBuf,
+    pending: &HashMap<u32, Option<String>>,
+    file: &mut std::fs::File,
+    index: &mut HashSet<String>,
 ) -> Result<usize, String> {
-    let mb = session
-        .select(mailbox)
-        .map_err(|e| format!("Select error: {e}"))?;
-
-    if mb.exists == 0 {
+    if pending.is_empty() {
         return Ok(0);
     }
 
-    let range = format!("1:{}", mb.exists);
-    let messages = session
-        .fetch(&range, "(UID RFC822)")
-        .map_err(|e| format!("Fetch error: {e}"))?;
-
-    let mut file = std::fs::File::create(output_path)
-        .map_err(|e| format!("File create error: {e}"))?;
+    let mut uids: Vec<u32> = pending.keys().copied().collect();
+    uids.sort_unstable();
 
     let mut count = 0;
-    for msg in messages.iter() {
-        if let Some(body) = msg.body() {
-            // mbox format: "From " line separator
-            writeln!(file, "From - {}", chrono::Utc::now().to_rfc2822())
-                .map_err(|e| format!("Write error: {e}"))?;
-            file.write_all(body)
-                .map_err(|e| format!("Write error: {e}"))?;
-            writeln!(file).map_err(|e| format!("Write error: {e}"))?;
-            count += 1;
+    for chunk in uids.chunks(BODY_FETCH_CHUNK) {
+        let messages = session
+            .uid_fetch(uid_set(chunk), "(UID RFC822)")
+            .map_err(|e| format!("Fetch error: {e}"))?;
+
+        for msg in messages.iter() {
+            if let Some(body) = msg.body() {
+                // mbox format: "From " line separator
+                writeln!(file, "From - {}", chrono::Utc::now().to_rfc2822())
+                    .map_err(|e| format!("Write error: {e}"))?;
+                file.write_all(body)
+                    .map_err(|e| format!("Write error: {e}"))?;
+                writeln!(file).map_err(|e| format!("Write error: {e}"))?;
+                count += 1;
+                if let Some(Some(key)) = msg.uid.and_then(|uid| pending.get(&uid)) {
+                    index.insert(key.clone());
+                }
+            }
         }
     }
 
     Ok(count)
 }
+
+pub fn backup_to_mbox(
+    session: &mut Session<TlsStream<TcpStream>>,
+    mailbox: &str,
+    output_path: &Path,
+    append: bool,
+) -> Result<BackupResult, String> {
+    let index_path = backup_index_path(output_path);
+    // A fresh archive starts a fresh index
+    let mut index = if append {
+        load_backup_index(&index_path)
+    } else {
+        HashSet::new()
+    };
+
+    // Headers first, so archived messages are skipped without downloading them
+    let headers = fetch_headers(session, mailbox)?;
+    let mut pending: HashMap<u32, Option<String>> = HashMap::new();
+    let mut queued = HashSet::new();
+    let mut skipped = 0;
+    for email in &headers {
+        let key = backup_key(email);
+        if let Some(k) = &key {
+            if index.contains(k) || !queued.insert(k.clone()) {
+                skipped += 1;
+                continue;
+            }
+        }
+        pending.insert(email.uid, key);
+    }
+
+    let mut file = if append {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(output_path)
+    } else {
+        std::fs::File::create(output_path)
+    }
+    .map_err(|e| format!("File open error: {e}"))?;
+
+    let result = write_mbox_messages(session, &pending, &mut file, &mut index);
+
+    // Persist whatever made it into the archive, even if the run failed midway
+    save_backup_index(&index_path, &index)?;
+    let written = result?;
+
+    Ok(BackupResult { written, skipped })
+}
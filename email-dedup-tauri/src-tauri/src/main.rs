@@ -2,12 +2,35 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod email;
+mod watch;
 
 use email::{
-    DedupMethod, DedupResult, DuplicateGroup, EmailHeader, ImapAccount, MailboxInfo,
-    TransferResult,
+    AccountDedupResult, BackupResult, DedupMethod, DedupResult, DeleteResult, DuplicateGroup,
+    EmailHeader, ImapAccount, MailboxInfo, TransferResult,
 };
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Emitter;
+use watch::WatchState;
+
+fn parse_method(method: &str) -> Result<DedupMethod, String> {
+    match method {
+        "message-id" => Ok(DedupMethod::MessageId),
+        "subject-date" => Ok(DedupMethod::SubjectDateHash),
+        "size-subject" => Ok(DedupMethod::SizeSubject),
+        _ => Err(format!("Unknown method: {method}")),
+    }
+}
+
+// "~/Desktop/x" as the UI suggests it; the OS won't expand the tilde
+fn expand_home(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (path.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
 
 // ── Tauri Commands ─────────────────────────────────────────────────────────
 
@@ -43,27 +66,124 @@ fn find_duplicates(
     let headers = email::fetch_headers(&mut session, &mailbox)?;
     let _ = session.logout();
 
-    let dedup_method = match method.as_str() {
-        "message-id" => DedupMethod::MessageId,
-        "subject-date" => DedupMethod::SubjectDateHash,
-        "size-subject" => DedupMethod::SizeSubject,
-        _ => return Err(format!("Unknown method: {method}")),
-    };
+    Ok(email::find_duplicates(&headers, parse_method(&method)?))
+}
 
-    Ok(email::find_duplicates(&headers, dedup_method))
+#[tauri::command]
+async fn scan_account(
+    app: tauri::AppHandle,
+    account: ImapAccount,
+    method: String,
+    skip_virtual: Option<bool>,
+    account_wide: bool,
+) -> Result<AccountDedupResult, String> {
+    let dedup_method = parse_method(&method)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = email::connect(&account)?;
+        let result = email::scan_account(
+            &mut session,
+            dedup_method,
+            skip_virtual.unwrap_or(true),
+            account_wide,
+            |progress| {
+                let _ = app.emit("scan-progress", progress);
+            },
+        );
+        let _ = session.logout();
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Deletions previewed by a dry run, keyed by the token it handed out
+#[derive(Default)]
+struct PendingDeletes(Mutex<HashMap<String, PendingDelete>>);
+
+struct PendingDelete {
+    host: String,
+    username: String,
+    mailbox: String,
+    /// Per mailbox the messages came from; account-wide groups span several
+    uids: BTreeMap<String, Vec<u32>>,
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn delete_duplicates(
+    pending: tauri::State<'_, PendingDeletes>,
     account: ImapAccount,
     mailbox: String,
     groups: Vec<DuplicateGroup>,
     dry_run: bool,
-) -> Result<usize, String> {
-    let mut session = email::connect(&account)?;
-    let result = email::delete_duplicates(&mut session, &mailbox, &groups, dry_run)?;
-    let _ = session.logout();
-    Ok(result)
+    export_path: Option<String>,
+    include_bodies: bool,
+    confirm_token: Option<String>,
+) -> Result<DeleteResult, String> {
+    if !dry_run {
+        // Only what a dry run previewed (and exported) can be deleted
+        let token = confirm_token.ok_or("Run a dry run first to get a confirmation token")?;
+        let plan = pending
+            .0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(&token)
+            .ok_or("Unknown or already used confirmation token")?;
+        if plan.host != account.host || plan.username != account.username || plan.mailbox != mailbox
+        {
+            return Err("Confirmation token was issued for a different mailbox".to_string());
+        }
+
+        let mut session = email::connect(&account)?;
+        let mut count = 0;
+        for (mailbox, uids) in &plan.uids {
+            count += email::delete_duplicates(&mut session, mailbox, uids)?;
+        }
+        let _ = session.logout();
+        return Ok(DeleteResult {
+            count,
+            dry_run,
+            token: None,
+            export_path: None,
+            bodies_path: None,
+        });
+    }
+
+    // No token without a trail: the headers are always exported first
+    let export_path = export_path
+        .filter(|p| !p.trim().is_empty())
+        .ok_or("Choose a file to export the preview to before the dry run")?;
+    let path = expand_home(&export_path);
+    let emails = email::emails_to_delete(&groups);
+    email::export_delete_headers(&mailbox, &emails, &path)?;
+    let mut bodies_path = None;
+    if include_bodies {
+        let mbox_path = email::delete_bodies_path(&path);
+        let mut session = email::connect(&account)?;
+        email::export_delete_bodies(&mut session, &mailbox, &emails, &mbox_path)?;
+        let _ = session.logout();
+        bodies_path = Some(mbox_path.to_string_lossy().into_owned());
+    }
+
+    let token = uuid::Uuid::new_v4().to_string();
+    let uids = email::uids_by_mailbox(&emails, &mailbox);
+    pending.0.lock().map_err(|e| e.to_string())?.insert(
+        token.clone(),
+        PendingDelete {
+            host: account.host,
+            username: account.username,
+            mailbox,
+            uids,
+        },
+    );
+
+    Ok(DeleteResult {
+        count: emails.len(),
+        dry_run,
+        token: Some(token),
+        export_path: Some(path.to_string_lossy().into_owned()),
+        bodies_path,
+    })
 }
 
 #[tauri::command]
@@ -91,26 +211,62 @@ fn backup_mbox(
     account: ImapAccount,
     mailbox: String,
     output_path: String,
-) -> Result<usize, String> {
+    append: bool,
+) -> Result<BackupResult, String> {
     let mut session = email::connect(&account)?;
     let path = PathBuf::from(output_path);
-    let count = email::backup_to_mbox(&mut session, &mailbox, &path)?;
+    let result = email::backup_to_mbox(&mut session, &mailbox, &path, append)?;
     let _ = session.logout();
-    Ok(count)
+    Ok(result)
+}
+
+#[tauri::command]
+fn watch_mailbox(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, WatchState>,
+    account: ImapAccount,
+    mailbox: String,
+    method: String,
+) -> Result<(), String> {
+    let dedup_method = parse_method(&method)?;
+    let mut handle = state.handle.lock().map_err(|e| e.to_string())?;
+    // Only one mailbox is watched at a time
+    if let Some(previous) = handle.take() {
+        previous.stop();
+    }
+    *handle = Some(watch::start(app, account, mailbox, dedup_method, method));
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_watching(state: tauri::State<'_, WatchState>) -> Result<bool, String> {
+    let mut handle = state.handle.lock().map_err(|e| e.to_string())?;
+    Ok(match handle.take() {
+        Some(h) => {
+            h.stop();
+            true
+        }
+        None => false,
+    })
 }
 
 // ── Main ───────────────────────────────────────────────────────────────────
 
 fn main() {
     tauri::Builder::default()
+        .manage(WatchState::default())
+        .manage(PendingDeletes::default())
         .invoke_handler(tauri::generate_handler![
             get_provider_defaults,
             test_connection,
             fetch_headers,
             find_duplicates,
+            scan_account,
             delete_duplicates,
             transfer_emails,
             backup_mbox,
+            watch_mailbox,
+            stop_watching,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,215 @@
+use crate::email::{self, DedupMethod, EmailHeader, ImapAccount};
+use imap::Session;
+use native_tls::TlsStream;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+// Servers may drop an IDLE after 30 minutes, so it is re-issued just before
+const IDLE_TIMEOUT: Duration = Duration::from_secs(29 * 60);
+// Seconds to wait before each reconnect attempt; the last one repeats
+const RECONNECT_DELAYS: [u64; 4] = [5, 15, 60, 300];
+
+// ── Events ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFound {
+    pub mailbox: String,
+    pub method: String,
+    pub email: EmailHeader,
+    pub original: EmailHeader,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    pub mailbox: String,
+    pub state: String,
+    pub message: String,
+}
+
+// ── State ──────────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct WatchState {
+    pub handle: Mutex<Option<WatchHandle>>,
+}
+
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    socket: Arc<Mutex<Option<TcpStream>>>,
+}
+
+impl WatchHandle {
+    /// Signals the watch thread and closes its socket so a pending IDLE
+    /// returns immediately instead of at the next timeout.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(socket) = self.socket.lock().unwrap().take() {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+struct Watcher {
+    app: AppHandle,
+    account: ImapAccount,
+    mailbox: String,
+    method: DedupMethod,
+    method_name: String,
+    stop: Arc<AtomicBool>,
+    socket: Arc<Mutex<Option<TcpStream>>>,
+    // First message seen for each dedup key
+    index: HashMap<String, EmailHeader>,
+    last_uid: Option<u32>,
+    // UIDVALIDITY that `last_uid` belongs to
+    uid_validity: Option<u32>,
+}
+
+pub fn start(
+    app: AppHandle,
+    account: ImapAccount,
+    mailbox: String,
+    method: DedupMethod,
+    method_name: String,
+) -> WatchHandle {
+    let stop = Arc::new(AtomicBool::new(false));
+    let socket = Arc::new(Mutex::new(None));
+    let mut watcher = Watcher {
+        app,
+        account,
+        mailbox,
+        method,
+        method_name,
+        stop: Arc::clone(&stop),
+        socket: Arc::clone(&socket),
+        index: HashMap::new(),
+        last_uid: None,
+        uid_validity: None,
+    };
+    std::thread::spawn(move || watcher.run());
+    WatchHandle { stop, socket }
+}
+
+// ── Watch Loop ─────────────────────────────────────────────────────────────
+
+impl Watcher {
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    fn status(&self, state: &str, message: String) {
+        let _ = self.app.emit(
+            "watch-status",
+            WatchStatus {
+                mailbox: self.mailbox.clone(),
+                state: state.to_string(),
+                message,
+            },
+        );
+    }
+
+    fn run(&mut self) {
+        let mut failures = 0;
+        while !self.stopped() {
+            match self.watch_session(&mut failures) {
+                Ok(()) => break,
+                Err(_) if self.stopped() => break,
+                Err(e) => {
+                    let delay = RECONNECT_DELAYS[failures.min(RECONNECT_DELAYS.len() - 1)];
+                    failures += 1;
+                    self.status("reconnecting", format!("{e} — retrying in {delay}s"));
+                    // Sleep in short steps so stop_watching isn't held up
+                    for _ in 0..delay {
+                        if self.stopped() {
+                            break;
+                        }
+                        std::thread::sleep(Duration::from_secs(1));
+                    }
+                }
+            }
+        }
+        self.status("stopped", "Stopped watching".to_string());
+    }
+
+    /// One connection's worth of watching. Returns `Ok` only when stopped.
+    fn watch_session(&mut self, failures: &mut usize) -> Result<(), String> {
+        let (mut session, socket) = email::connect_with_stream(&self.account)?;
+        *self.socket.lock().unwrap() = Some(socket);
+        if self.stopped() {
+            return Ok(());
+        }
+
+        let selected = session
+            .select(&self.mailbox)
+            .map_err(|e| format!("Select error: {e}"))?;
+        // UIDs only carry over while UIDVALIDITY stays the same. If the server
+        // renumbered the mailbox, old and new UIDs can't be told apart, so
+        // index it again from scratch.
+        if selected.uid_validity != self.uid_validity {
+            self.uid_validity = selected.uid_validity;
+            self.last_uid = None;
+            self.index.clear();
+        }
+
+        match self.last_uid {
+            None => {
+                let headers = email::fetch_headers(&mut session, &self.mailbox)?;
+                self.last_uid = Some(headers.iter().map(|h| h.uid).max().unwrap_or(0));
+                for header in headers {
+                    if let Some(key) = email::dedup_key(&header, &self.method) {
+                        self.index.entry(key).or_insert(header);
+                    }
+                }
+            }
+            // Reconnected: pick up whatever arrived while the connection was down
+            Some(_) => self.check_new(&mut session)?,
+        }
+        *failures = 0;
+        self.status(
+            "watching",
+            format!("Watching {} ({} indexed)", self.mailbox, self.index.len()),
+        );
+
+        while !self.stopped() {
+            email::idle_wait(&mut session, IDLE_TIMEOUT)?;
+            if self.stopped() {
+                break;
+            }
+            self.check_new(&mut session)?;
+        }
+
+        let _ = session.logout();
+        Ok(())
+    }
+
+    fn check_new(&mut self, session: &mut Session<TlsStream<TcpStream>>) -> Result<(), String> {
+        let since = self.last_uid.unwrap_or(0);
+        for header in email::fetch_headers_since(session, &self.mailbox, since)? {
+            self.last_uid = Some(self.last_uid.unwrap_or(0).max(header.uid));
+            let Some(key) = email::dedup_key(&header, &self.method) else {
+                continue;
+            };
+            match self.index.get(&key) {
+                Some(original) => {
+                    let _ = self.app.emit(
+                        "duplicate-found",
+                        DuplicateFound {
+                            mailbox: self.mailbox.clone(),
+                            method: self.method_name.clone(),
+                            email: header,
+                            original: original.clone(),
+                        },
+                    );
+                }
+                None => {
+                    self.index.insert(key, header);
+                }
+            }
+        }
+        Ok(())
+    }
+}
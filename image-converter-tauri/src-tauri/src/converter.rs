@@ -1,16 +1,24 @@
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::codecs::avif::AvifEncoder;
 use image::codecs::bmp::BmpEncoder;
 use image::codecs::gif::GifEncoder;
 use image::codecs::ico::IcoEncoder;
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
 use image::codecs::tiff::TiffEncoder;
-use image::{GenericImageView, ImageEncoder, ImageFormat};
+use image::metadata::Orientation;
+use image::{
+    DynamicImage, GenericImageView, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, RgbaImage,
+};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::heif;
+use crate::svg::{self, RenderSize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImageInfo {
@@ -20,6 +28,10 @@ pub struct ImageInfo {
     pub height: u32,
     pub format: String,
     pub size_bytes: u64,
+    /// Set when the file couldn't be decoded; it stays in the list so the
+    /// conversion reports it instead of it silently disappearing
+    #[serde(default)]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,9 +40,41 @@ pub struct ConvertOptions {
     pub output_dir: String,
     pub format: String,
     pub quality: u8,
+    /// "exact", "fit", "longest_edge" or "percent"; see `target_dimensions`.
+    /// Unset picks "longest_edge" when `longest_edge` is given, else "fit".
+    #[serde(default)]
+    pub resize_mode: Option<String>,
     pub resize_width: Option<u32>,
     pub resize_height: Option<u32>,
+    #[serde(default)]
+    pub resize_percent: Option<f64>,
+    /// Shrink so the biggest side is at most this many pixels
+    #[serde(default)]
+    pub longest_edge: Option<u32>,
     pub strip_metadata: bool,
+    /// Worker threads for this batch; rayon's global pool when unset
+    #[serde(default)]
+    pub max_threads: Option<usize>,
+    /// AVIF encoder speed, 1 (smallest files) to 10 (fastest)
+    #[serde(default)]
+    pub avif_speed: Option<u8>,
+    /// Rasterization size for SVG input, before any resize; see `RenderSize`
+    #[serde(default)]
+    pub render_width: Option<u32>,
+    #[serde(default)]
+    pub render_height: Option<u32>,
+    #[serde(default)]
+    pub render_scale: Option<f64>,
+}
+
+impl ConvertOptions {
+    fn render_size(&self) -> RenderSize {
+        RenderSize {
+            width: self.render_width,
+            height: self.render_height,
+            scale: self.render_scale,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,6 +85,44 @@ pub struct ConvertResult {
     pub error: Option<String>,
     pub original_size: u64,
     pub new_size: u64,
+    /// Never started because the batch was cancelled
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub completed: usize,
+    pub total: usize,
+    pub current_file: String,
+}
+
+/// Emitted once as `batch-done` after the last file. `bytes_saved` is negative
+/// when the converted files came out bigger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchDoneEvent {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub bytes_saved: i64,
+}
+
+impl BatchDoneEvent {
+    pub fn from_results(results: &[ConvertResult]) -> Self {
+        let succeeded: Vec<&ConvertResult> = results.iter().filter(|r| r.success).collect();
+        let cancelled = results.iter().filter(|r| r.cancelled).count();
+        BatchDoneEvent {
+            total: results.len(),
+            succeeded: succeeded.len(),
+            failed: results.len() - succeeded.len() - cancelled,
+            cancelled,
+            bytes_saved: succeeded
+                .iter()
+                .map(|r| r.original_size as i64 - r.new_size as i64)
+                .sum(),
+        }
+    }
 }
 
 pub fn get_image_info(paths: Vec<PathBuf>) -> Result<Vec<ImageInfo>, String> {
@@ -48,49 +130,165 @@ pub fn get_image_info(paths: Vec<PathBuf>) -> Result<Vec<ImageInfo>, String> {
         .par_iter()
         .filter_map(|p| {
             let meta = fs::metadata(p).ok()?;
-            let img = image::open(p).ok()?;
-            let (w, h) = img.dimensions();
-            let fmt = ImageFormat::from_path(p)
-                .map(|f| format!("{:?}", f))
-                .unwrap_or_else(|_| "Unknown".into());
+            let (width, height, error) = match open_image(p) {
+                Ok(img) => (img.width(), img.height(), None),
+                Err(e) => (0, 0, Some(e)),
+            };
+            let fmt = if heif::is_heif(p) {
+                "HEIF".into()
+            } else if svg::is_svg(p) {
+                "SVG".into()
+            } else {
+                ImageFormat::from_path(p)
+                    .map(|f| format!("{:?}", f))
+                    .unwrap_or_else(|_| "Unknown".into())
+            };
             Some(ImageInfo {
                 path: p.to_string_lossy().into(),
                 filename: p.file_name()?.to_string_lossy().into(),
-                width: w,
-                height: h,
+                width,
+                height,
                 format: fmt,
                 size_bytes: meta.len(),
+                error,
             })
         })
         .collect();
     Ok(results)
 }
 
-pub fn generate_thumbnail(path: &Path, max_size: u32) -> Result<String, String> {
-    let img = image::open(path).map_err(|e| e.to_string())?;
+// Decodes any supported input upright: HEIC/HEIF through an external
+// decoder, SVG rasterized at its own size, everything else with its EXIF
+// orientation applied
+pub fn open_image(path: &Path) -> Result<DynamicImage, String> {
+    if heif::is_heif(path) {
+        return heif::decode(path);
+    }
+    if svg::is_svg(path) {
+        return svg::render(path, RenderSize::default());
+    }
+    let mut decoder = ImageReader::open(path)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+const DEFAULT_THUMB_QUALITY: u8 = 75;
+
+/// PNG by default; "jpeg" and "webp" take `quality` (1–100) and come out
+/// much smaller for photo galleries
+pub fn generate_thumbnail(
+    path: &Path,
+    max_size: u32,
+    format: Option<&str>,
+    quality: Option<u8>,
+) -> Result<String, String> {
+    let img = open_image(path)?;
     let thumb = img.thumbnail(max_size, max_size);
+    let quality = quality.unwrap_or(DEFAULT_THUMB_QUALITY).clamp(1, 100);
     let mut buf = Cursor::new(Vec::new());
-    thumb
-        .write_to(&mut buf, ImageFormat::Png)
-        .map_err(|e| e.to_string())?;
+
+    let mime = match format.map(|f| f.to_lowercase()).as_deref() {
+        None | Some("png") => {
+            thumb
+                .write_to(&mut buf, ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            "image/png"
+        }
+        Some("jpeg") | Some("jpg") => {
+            let rgb = thumb.to_rgb8();
+            let enc = JpegEncoder::new_with_quality(&mut buf, quality);
+            enc.write_image(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+            .map_err(|e| e.to_string())?;
+            "image/jpeg"
+        }
+        Some("webp") => {
+            let rgba = thumb.to_rgba8();
+            let mem = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode(quality as f32);
+            buf.get_mut().extend_from_slice(&mem);
+            "image/webp"
+        }
+        Some(other) => return Err(format!("Unsupported thumbnail format: {}", other)),
+    };
+
     Ok(format!(
-        "data:image/png;base64,{}",
+        "data:{};base64,{}",
+        mime,
         BASE64.encode(buf.into_inner())
     ))
 }
 
-pub fn convert_images(options: ConvertOptions) -> Result<Vec<ConvertResult>, String> {
+/// Files still queued when `cancel` is set come back marked `cancelled`;
+/// `on_progress` is called as each file finishes
+pub fn convert_images(
+    options: ConvertOptions,
+    cancel: &AtomicBool,
+    on_progress: impl Fn(ProgressEvent) + Sync,
+) -> Result<Vec<ConvertResult>, String> {
     fs::create_dir_all(&options.output_dir).map_err(|e| e.to_string())?;
 
-    let results: Vec<ConvertResult> = options
-        .paths
-        .par_iter()
-        .map(|p| convert_single(p, &options))
-        .collect();
+    let total = options.paths.len();
+    let completed = AtomicUsize::new(0);
+    let run = || {
+        options
+            .paths
+            .par_iter()
+            .map(|p| {
+                let result = if cancel.load(Ordering::SeqCst) {
+                    cancelled_result(p)
+                } else {
+                    convert_single(p, &options)
+                };
+                on_progress(ProgressEvent {
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    current_file: Path::new(p)
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                });
+                result
+            })
+            .collect()
+    };
+
+    let results: Vec<ConvertResult> = match options.max_threads.filter(|&n| n > 0) {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| e.to_string())?
+            .install(run),
+        None => run(),
+    };
 
     Ok(results)
 }
 
+fn cancelled_result(path: &str) -> ConvertResult {
+    ConvertResult {
+        source: path.into(),
+        output: String::new(),
+        success: false,
+        error: Some("Cancelled".into()),
+        original_size: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        new_size: 0,
+        cancelled: true,
+    }
+}
+
 fn convert_single(path: &str, options: &ConvertOptions) -> ConvertResult {
     let source_path = PathBuf::from(path);
     let original_size = fs::metadata(&source_path).map(|m| m.len()).unwrap_or(0);
@@ -122,6 +320,7 @@ fn convert_single(path: &str, options: &ConvertOptions) -> ConvertResult {
                 error: None,
                 original_size,
                 new_size,
+                cancelled: false,
             }
         }
         Err(e) => ConvertResult {
@@ -131,24 +330,89 @@ fn convert_single(path: &str, options: &ConvertOptions) -> ConvertResult {
             error: Some(e),
             original_size,
             new_size: 0,
+            cancelled: false,
         },
     }
 }
 
+// Output size for the requested resize. Aspect ratio is kept everywhere
+// except "exact" with both sides given; anything missing or zero leaves the
+// image alone.
+//   exact         width × height; with one side, the other follows the ratio
+//   fit           largest size inside width × height (a missing side is unbounded)
+//   longest_edge  biggest side ≤ `longest_edge`, never enlarges
+//   percent       both sides scaled by `resize_percent`
+fn target_dimensions(width: u32, height: u32, opts: &ConvertOptions) -> (u32, u32) {
+    if width == 0 || height == 0 {
+        return (width, height);
+    }
+    let mode = opts
+        .resize_mode
+        .as_deref()
+        .unwrap_or(if opts.longest_edge.is_some() {
+            "longest_edge"
+        } else {
+            "fit"
+        });
+    let nw = opts.resize_width.filter(|&w| w > 0);
+    let nh = opts.resize_height.filter(|&h| h > 0);
+    let scaled = |ratio: f64| {
+        (
+            ((width as f64 * ratio).round() as u32).max(1),
+            ((height as f64 * ratio).round() as u32).max(1),
+        )
+    };
+
+    match mode {
+        "exact" => match (nw, nh) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (
+                w,
+                ((height as f64 * w as f64 / width as f64).round() as u32).max(1),
+            ),
+            (None, Some(h)) => (
+                ((width as f64 * h as f64 / height as f64).round() as u32).max(1),
+                h,
+            ),
+            (None, None) => (width, height),
+        },
+        "fit" => {
+            let ratio = match (nw, nh) {
+                (Some(w), Some(h)) => f64::min(w as f64 / width as f64, h as f64 / height as f64),
+                (Some(w), None) => w as f64 / width as f64,
+                (None, Some(h)) => h as f64 / height as f64,
+                (None, None) => return (width, height),
+            };
+            scaled(ratio)
+        }
+        "longest_edge" => match opts.longest_edge {
+            Some(edge) if edge > 0 && width.max(height) > edge => {
+                scaled(edge as f64 / width.max(height) as f64)
+            }
+            _ => (width, height),
+        },
+        "percent" => match opts.resize_percent {
+            Some(pct) if pct > 0.0 => scaled(pct / 100.0),
+            _ => (width, height),
+        },
+        _ => (width, height),
+    }
+}
+
+// cavif defaults to 4; a batch of photos at that speed takes noticeably long
+const DEFAULT_AVIF_SPEED: u8 = 6;
+
 fn do_convert(source: &Path, output: &Path, options: &ConvertOptions) -> Result<(), String> {
-    let mut img = image::open(source).map_err(|e| e.to_string())?;
+    let mut img = if svg::is_svg(source) {
+        svg::render(source, options.render_size())?
+    } else {
+        open_image(source)?
+    };
 
-    // Resize if requested
-    if let (Some(w), Some(h)) = (options.resize_width, options.resize_height) {
-        img = img.resize(w, h, image::imageops::FilterType::Lanczos3);
-    } else if let Some(w) = options.resize_width {
-        let (ow, oh) = img.dimensions();
-        let h = (oh as f64 * w as f64 / ow as f64) as u32;
-        img = img.resize_exact(w, h, image::imageops::FilterType::Lanczos3);
-    } else if let Some(h) = options.resize_height {
-        let (ow, oh) = img.dimensions();
-        let w = (ow as f64 * h as f64 / oh as f64) as u32;
-        img = img.resize_exact(w, h, image::imageops::FilterType::Lanczos3);
+    let (ow, oh) = img.dimensions();
+    let (nw, nh) = target_dimensions(ow, oh, options);
+    if (nw, nh) != (ow, oh) {
+        img = img.resize_exact(nw, nh, image::imageops::FilterType::Lanczos3);
     }
 
     // Strip metadata = re-encode from raw pixels (which we do anyway)
@@ -203,6 +467,16 @@ fn do_convert(source: &Path, output: &Path, options: &ConvertOptions) -> Result<
             enc.write_image(ico_rgba.as_raw(), iw, ih, image::ExtendedColorType::Rgba8)
                 .map_err(|e| e.to_string())?;
         }
+        "avif" => {
+            // ravif does the RGBA→YUV conversion and keeps alpha as an auxiliary image
+            let speed = options
+                .avif_speed
+                .unwrap_or(DEFAULT_AVIF_SPEED)
+                .clamp(1, 10);
+            let enc = AvifEncoder::new_with_speed_quality(&mut buf, speed, options.quality);
+            enc.write_image(raw, w, h, image::ExtendedColorType::Rgba8)
+                .map_err(|e| e.to_string())?;
+        }
         "gif" => {
             let mut enc = GifEncoder::new(&mut buf);
             enc.encode(raw, w, h, image::ExtendedColorType::Rgba8)
@@ -214,3 +488,354 @@ fn do_convert(source: &Path, output: &Path, options: &ConvertOptions) -> Result<
     fs::write(output, buf.into_inner()).map_err(|e| e.to_string())?;
     Ok(())
 }
+
+// Sizes packed into favicon.ico; browsers pick 16/32, Windows uses 48 and 256
+const FAVICON_ICO_SIZES: [u32; 4] = [16, 32, 48, 256];
+
+const FAVICON_PNGS: [(&str, u32); 5] = [
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("android-chrome-192x192.png", 192),
+    ("android-chrome-512x512.png", 512),
+];
+
+// Non-square sources are centred on a transparent square so nothing is cropped
+fn square_canvas(img: &DynamicImage) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    let side = w.max(h);
+    let mut canvas = RgbaImage::new(side, side);
+    image::imageops::overlay(
+        &mut canvas,
+        &img.to_rgba8(),
+        ((side - w) / 2) as i64,
+        ((side - h) / 2) as i64,
+    );
+    canvas
+}
+
+/// Writes a multi-resolution favicon.ico plus the usual PNG icons into
+/// `output_dir` and returns the paths written
+pub fn generate_favicon_set(path: &Path, output_dir: &Path) -> Result<Vec<String>, String> {
+    // Vector logos are rendered at the largest icon size rather than scaled up
+    let img = if svg::is_svg(path) {
+        svg::render(
+            path,
+            RenderSize {
+                width: Some(512),
+                height: Some(512),
+                scale: None,
+            },
+        )?
+    } else {
+        open_image(path)?
+    };
+    let square = DynamicImage::ImageRgba8(square_canvas(&img));
+    let sized = |size: u32| {
+        square
+            .resize_exact(size, size, image::imageops::FilterType::Lanczos3)
+            .to_rgba8()
+    };
+    fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+    let mut written = Vec::new();
+
+    let mut icon_dir = ico::IconDir::new(ico::ResourceType::Icon);
+    for size in FAVICON_ICO_SIZES {
+        let icon = ico::IconImage::from_rgba_data(size, size, sized(size).into_raw());
+        icon_dir.add_entry(ico::IconDirEntry::encode(&icon).map_err(|e| e.to_string())?);
+    }
+    let ico_path = output_dir.join("favicon.ico");
+    let file = fs::File::create(&ico_path).map_err(|e| e.to_string())?;
+    icon_dir.write(file).map_err(|e| e.to_string())?;
+    written.push(ico_path.to_string_lossy().into_owned());
+
+    for (name, size) in FAVICON_PNGS {
+        let png_path = output_dir.join(name);
+        sized(size).save(&png_path).map_err(|e| e.to_string())?;
+        written.push(png_path.to_string_lossy().into_owned());
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn avif_options(quality: u8) -> ConvertOptions {
+        ConvertOptions {
+            paths: Vec::new(),
+            output_dir: String::new(),
+            format: "avif".into(),
+            quality,
+            resize_mode: None,
+            resize_width: None,
+            resize_height: None,
+            resize_percent: None,
+            longest_edge: None,
+            strip_metadata: true,
+            max_threads: None,
+            avif_speed: Some(10),
+            render_width: None,
+            render_height: None,
+            render_scale: None,
+        }
+    }
+
+    // Translucent gradient, so both the colour and the alpha plane carry detail
+    fn write_sample(dir: &Path) -> PathBuf {
+        let img = RgbaImage::from_fn(48, 32, |x, y| {
+            Rgba([(x * 5) as u8, (y * 8) as u8, 128, (x * 4 + 40) as u8])
+        });
+        let path = dir.join("sample.png");
+        img.save(&path).unwrap();
+        path
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("image-converter-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn find_box<'a>(data: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+        let at = data.windows(4).position(|w| w == fourcc)?;
+        Some(&data[at + 4..])
+    }
+
+    #[test]
+    fn encodes_avif_with_dimensions_and_alpha() {
+        let dir = scratch_dir("avif");
+        let source = write_sample(&dir);
+        let output = dir.join("sample.avif");
+        do_convert(&source, &output, &avif_options(80)).unwrap();
+
+        let data = fs::read(&output).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(&data[4..12], b"ftypavif");
+        // ispe is a full box: 4 bytes of version/flags, then width and height
+        let ispe = find_box(&data, b"ispe").expect("no ispe box");
+        assert_eq!(u32::from_be_bytes(ispe[4..8].try_into().unwrap()), 48);
+        assert_eq!(u32::from_be_bytes(ispe[8..12].try_into().unwrap()), 32);
+        // The alpha plane is stored as an auxiliary image
+        assert!(find_box(&data, b"auxl").is_some(), "alpha was dropped");
+    }
+
+    #[test]
+    fn avif_quality_changes_output() {
+        let dir = scratch_dir("avif-quality");
+        let source = write_sample(&dir);
+        let low = dir.join("low.avif");
+        let high = dir.join("high.avif");
+        do_convert(&source, &low, &avif_options(20)).unwrap();
+        do_convert(&source, &high, &avif_options(95)).unwrap();
+
+        let (low, high) = (
+            fs::metadata(&low).unwrap().len(),
+            fs::metadata(&high).unwrap().len(),
+        );
+        let _ = fs::remove_dir_all(&dir);
+        assert!(
+            low < high,
+            "quality 20 gave {} bytes, quality 95 gave {}",
+            low,
+            high
+        );
+    }
+
+    fn resize_options(mode: &str, width: Option<u32>, height: Option<u32>) -> ConvertOptions {
+        ConvertOptions {
+            format: "png".into(),
+            resize_mode: Some(mode.into()),
+            resize_width: width,
+            resize_height: height,
+            ..avif_options(90)
+        }
+    }
+
+    #[test]
+    fn exact_resize() {
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("exact", Some(100), Some(100))),
+            (100, 100)
+        );
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("exact", Some(200), None)),
+            (200, 150)
+        );
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("exact", None, Some(60))),
+            (80, 60)
+        );
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("exact", None, None)),
+            (400, 300)
+        );
+    }
+
+    #[test]
+    fn fit_resize_keeps_aspect() {
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("fit", Some(100), Some(100))),
+            (100, 75)
+        );
+        assert_eq!(
+            target_dimensions(300, 400, &resize_options("fit", Some(100), Some(100))),
+            (75, 100)
+        );
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("fit", Some(800), None)),
+            (800, 600)
+        );
+        assert_eq!(
+            target_dimensions(400, 300, &resize_options("fit", None, Some(150))),
+            (200, 150)
+        );
+    }
+
+    #[test]
+    fn longest_edge_only_shrinks() {
+        let opts = ConvertOptions {
+            longest_edge: Some(200),
+            ..resize_options("longest_edge", None, None)
+        };
+        assert_eq!(target_dimensions(400, 300, &opts), (200, 150));
+        assert_eq!(target_dimensions(300, 400, &opts), (150, 200));
+        assert_eq!(target_dimensions(120, 80, &opts), (120, 80));
+    }
+
+    #[test]
+    fn percent_resize() {
+        let opts = ConvertOptions {
+            resize_percent: Some(25.0),
+            ..resize_options("percent", None, None)
+        };
+        assert_eq!(target_dimensions(400, 300, &opts), (100, 75));
+        assert_eq!(target_dimensions(2, 2, &opts), (1, 1));
+    }
+
+    #[test]
+    fn mode_defaults_from_fields() {
+        let mut opts = resize_options("fit", Some(100), Some(100));
+        opts.resize_mode = None;
+        assert_eq!(target_dimensions(400, 300, &opts), (100, 75));
+        opts.longest_edge = Some(300);
+        assert_eq!(target_dimensions(400, 300, &opts), (300, 225));
+    }
+
+    #[test]
+    fn convert_writes_resized_file() {
+        let dir = scratch_dir("resize");
+        let source = write_sample(&dir);
+        let output = dir.join("resized.png");
+        let opts = ConvertOptions {
+            longest_edge: Some(24),
+            ..resize_options("longest_edge", None, None)
+        };
+        do_convert(&source, &output, &opts).unwrap();
+
+        let dims = image::image_dimensions(&output).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(dims, (24, 16));
+    }
+
+    #[test]
+    fn unreadable_heic_is_reported_not_dropped() {
+        let dir = scratch_dir("heic");
+        let source = dir.join("IMG_0001.HEIC");
+        fs::write(&source, b"not really a heic").unwrap();
+
+        let infos = get_image_info(vec![source.clone()]).unwrap();
+        let mut opts = resize_options("fit", None, None);
+        opts.output_dir = dir.to_string_lossy().into();
+        let result = convert_single(&source.to_string_lossy(), &opts);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].format, "HEIF");
+        assert!(infos[0]
+            .error
+            .as_deref()
+            .is_some_and(|e| e.contains("HEIC")));
+        assert!(!result.success);
+        assert!(result.error.is_some_and(|e| e.contains("HEIC")));
+    }
+
+    #[test]
+    fn batch_done_counts_and_savings() {
+        let result = |success: bool, cancelled: bool, original_size: u64, new_size: u64| {
+            ConvertResult {
+                source: String::new(),
+                output: String::new(),
+                success,
+                error: None,
+                original_size,
+                new_size,
+                cancelled,
+            }
+        };
+        let results = vec![
+            result(true, false, 1000, 400),
+            result(true, false, 100, 300),
+            result(false, false, 500, 0),
+            result(false, true, 700, 0),
+        ];
+
+        assert_eq!(
+            BatchDoneEvent::from_results(&results),
+            BatchDoneEvent {
+                total: 4,
+                succeeded: 2,
+                failed: 1,
+                cancelled: 1,
+                bytes_saved: 400,
+            }
+        );
+    }
+
+    #[test]
+    fn svg_renders_at_requested_width() {
+        let dir = scratch_dir("svg");
+        let source = dir.join("logo.svg");
+        fs::write(
+            &source,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20">
+                <rect width="40" height="20" fill="#00ff88"/>
+            </svg>"##,
+        )
+        .unwrap();
+        let output = dir.join("logo.png");
+        let opts = ConvertOptions {
+            render_width: Some(200),
+            ..resize_options("fit", None, None)
+        };
+        do_convert(&source, &output, &opts).unwrap();
+
+        let img = image::open(&output).unwrap().to_rgba8();
+        let infos = get_image_info(vec![source]).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(img.dimensions(), (200, 100));
+        assert_eq!(img.get_pixel(100, 50).0, [0x00, 0xff, 0x88, 0xff]);
+        assert_eq!((infos[0].width, infos[0].height), (40, 20));
+        assert_eq!(infos[0].format, "SVG");
+    }
+
+    #[test]
+    fn favicon_set_packs_every_ico_size() {
+        let dir = scratch_dir("favicon");
+        let source = write_sample(&dir);
+        let out = dir.join("icons");
+
+        let written = generate_favicon_set(&source, &out).unwrap();
+        let icon_dir = ico::IconDir::read(fs::File::open(out.join("favicon.ico")).unwrap()).unwrap();
+        let touch = image::image_dimensions(out.join("apple-touch-icon.png")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(written.len(), 1 + FAVICON_PNGS.len());
+        let sizes: Vec<u32> = icon_dir.entries().iter().map(|e| e.width()).collect();
+        assert_eq!(sizes, FAVICON_ICO_SIZES);
+        assert_eq!(touch, (180, 180));
+    }
+}
@@ -0,0 +1,87 @@
+use image::DynamicImage;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// The image crate has no HEIF decoder, so HEIC/HEIF photos go through
+// whichever external decoder is installed. All of them apply the container's
+// rotation/mirror boxes, which is where iPhones store orientation.
+
+pub fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| matches!(e.as_str(), "heic" | "heif" | "hif"))
+}
+
+// libheif's decoder is heif-dec since 1.17, heif-convert before that
+fn decoder_commands(input: &Path, output: &Path) -> Vec<(&'static str, Command)> {
+    let mut commands = Vec::new();
+    for tool in ["heif-dec", "heif-convert"] {
+        let mut cmd = Command::new(tool);
+        cmd.arg(input).arg(output);
+        commands.push((tool, cmd));
+    }
+    let mut magick = Command::new("magick");
+    magick.arg(input).arg(output);
+    commands.push(("magick", magick));
+    if cfg!(target_os = "macos") {
+        let mut sips = Command::new("sips");
+        sips.args(["-s", "format", "png"])
+            .arg(input)
+            .arg("--out")
+            .arg(output);
+        commands.push(("sips", sips));
+    }
+    commands
+}
+
+pub fn decode(path: &Path) -> Result<DynamicImage, String> {
+    // Files are decoded in parallel, so every call gets its own temp name
+    let temp = std::env::temp_dir().join(format!(
+        "image-converter-heif-{}-{}.png",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut failures = Vec::new();
+    for (tool, mut cmd) in decoder_commands(path, &temp) {
+        match cmd.output() {
+            Ok(out) if out.status.success() && temp.exists() => {
+                let img = image::open(&temp).map_err(|e| e.to_string());
+                let _ = fs::remove_file(&temp);
+                return img;
+            }
+            Ok(out) => failures.push(format!(
+                "{}: {}",
+                tool,
+                String::from_utf8_lossy(&out.stderr).trim()
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => failures.push(format!("{}: {}", tool, e)),
+        }
+    }
+    let _ = fs::remove_file(&temp);
+
+    if failures.is_empty() {
+        Err(format!("HEIC/HEIF needs a decoder. {}", install_hint()))
+    } else {
+        Err(format!(
+            "Could not decode HEIC/HEIF ({})",
+            failures.join("; ")
+        ))
+    }
+}
+
+fn install_hint() -> String {
+    let hint = if cfg!(target_os = "macos") {
+        "brew install libheif"
+    } else if cfg!(windows) {
+        "choco install imagemagick"
+    } else {
+        "sudo apt install libheif-examples"
+    };
+    format!("Install it with: {}", hint)
+}
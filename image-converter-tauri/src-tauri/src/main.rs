@@ -2,9 +2,19 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod converter;
+mod heif;
+mod svg;
 
-use converter::{ConvertOptions, ConvertResult, ImageInfo};
+use converter::{BatchDoneEvent, ConvertOptions, ConvertResult, ImageInfo};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+#[derive(Default)]
+struct ConversionState {
+    cancel: Arc<AtomicBool>,
+}
 
 #[tauri::command]
 fn get_image_info(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
@@ -14,23 +24,62 @@ fn get_image_info(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
 }
 
 #[tauri::command]
-fn generate_thumbnail(path: String, max_size: u32) -> Result<String, String> {
-    converter::generate_thumbnail(&PathBuf::from(path), max_size)
+fn generate_thumbnail(
+    path: String,
+    max_size: u32,
+    thumb_format: Option<String>,
+    thumb_quality: Option<u8>,
+) -> Result<String, String> {
+    converter::generate_thumbnail(
+        &PathBuf::from(path),
+        max_size,
+        thumb_format.as_deref(),
+        thumb_quality,
+    )
+}
+
+#[tauri::command]
+fn generate_favicon_set(path: String, output_dir: String) -> Result<Vec<String>, String> {
+    converter::generate_favicon_set(&PathBuf::from(path), &PathBuf::from(output_dir))
+}
+
+// Async so cancel_conversion can be handled while the batch runs
+#[tauri::command]
+async fn convert_images(
+    app: AppHandle,
+    state: State<'_, ConversionState>,
+    options: ConvertOptions,
+) -> Result<Vec<ConvertResult>, String> {
+    let cancel = Arc::clone(&state.cancel);
+    cancel.store(false, Ordering::SeqCst);
+    let progress_app = app.clone();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        converter::convert_images(options, &cancel, |progress| {
+            let _ = progress_app.emit("convert-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let _ = app.emit("batch-done", BatchDoneEvent::from_results(&results));
+    Ok(results)
 }
 
 #[tauri::command]
-fn convert_images(options: ConvertOptions) -> Result<Vec<ConvertResult>, String> {
-    converter::convert_images(options)
+fn cancel_conversion(state: State<ConversionState>) {
+    state.cancel.store(true, Ordering::SeqCst);
 }
 
 fn main() {
     tauri::Builder::default()
+        .manage(ConversionState::default())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             get_image_info,
             generate_thumbnail,
             convert_images,
+            cancel_conversion,
+            generate_favicon_set,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,77 @@
+use image::{DynamicImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+// Scanning system fonts is slow, so it happens once, on the first SVG
+static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+/// Pixel size for vector input, which has none of its own. With neither
+/// `width` nor `height` the SVG's intrinsic size times `scale` is used.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderSize {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub scale: Option<f64>,
+}
+
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| e == "svg")
+}
+
+// One given side keeps the aspect ratio; both fit the drawing inside that box
+fn pixel_size(intrinsic: (f32, f32), size: RenderSize) -> (u32, u32) {
+    let (iw, ih) = (intrinsic.0 as f64, intrinsic.1 as f64);
+    let scale = match (size.width, size.height) {
+        (Some(w), Some(h)) => (w as f64 / iw).min(h as f64 / ih),
+        (Some(w), None) => w as f64 / iw,
+        (None, Some(h)) => h as f64 / ih,
+        (None, None) => size.scale.filter(|&s| s > 0.0).unwrap_or(1.0),
+    };
+    (
+        ((iw * scale).round() as u32).max(1),
+        ((ih * scale).round() as u32).max(1),
+    )
+}
+
+pub fn render(path: &Path, size: RenderSize) -> Result<DynamicImage, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let fontdb = FONTS.get_or_init(|| {
+        let mut db = usvg::fontdb::Database::new();
+        db.load_system_fonts();
+        Arc::new(db)
+    });
+    let options = usvg::Options {
+        // Relative <image href>s resolve next to the SVG
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb: Arc::clone(fontdb),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(&data, &options)
+        .map_err(|e| format!("Invalid SVG: {}", e))?;
+
+    let intrinsic = tree.size();
+    let (width, height) = pixel_size((intrinsic.width(), intrinsic.height()), size);
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("SVG render size {}×{} is too large", width, height))?;
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / intrinsic.width(),
+        height as f32 / intrinsic.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia keeps premultiplied alpha; the encoders expect straight alpha
+    let raw: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, raw)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| "SVG render produced a bad buffer".to_string())
+}
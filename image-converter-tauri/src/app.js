@@ -1,4 +1,5 @@
 const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
 
 let files = []; // { path, info, thumbnail }
 let outputDir = '';
@@ -7,6 +8,9 @@ let outputDir = '';
 const btnAdd = document.getElementById('btn-add');
 const btnClear = document.getElementById('btn-clear');
 const btnConvert = document.getElementById('btn-convert');
+const btnCancel = document.getElementById('btn-cancel');
+const maxThreads = document.getElementById('max-threads');
+const avifSpeed = document.getElementById('avif-speed');
 const btnOutput = document.getElementById('btn-output');
 const fileCount = document.getElementById('file-count');
 const formatSel = document.getElementById('format');
@@ -14,7 +18,12 @@ const qualitySlider = document.getElementById('quality');
 const qualityVal = document.getElementById('quality-val');
 const resizeW = document.getElementById('resize-w');
 const resizeH = document.getElementById('resize-h');
+const resizeMode = document.getElementById('resize-mode');
+const resizeValue = document.getElementById('resize-value');
 const stripMeta = document.getElementById('strip-meta');
+const renderW = document.getElementById('render-w');
+const renderH = document.getElementById('render-h');
+const renderScale = document.getElementById('render-scale');
 const outputPath = document.getElementById('output-path');
 const dropZone = document.getElementById('drop-zone');
 const imageGrid = document.getElementById('image-grid');
@@ -27,12 +36,25 @@ qualitySlider.addEventListener('input', () => {
   qualityVal.textContent = qualitySlider.value;
 });
 
+// Longest edge and percent take a single number instead of width × height
+resizeMode.addEventListener('change', () => {
+  const single = resizeMode.value === 'longest_edge' || resizeMode.value === 'percent';
+  document.getElementById('resize-box').classList.toggle('hidden', single);
+  document.getElementById('resize-single').classList.toggle('hidden', !single);
+  resizeValue.placeholder = resizeMode.value === 'percent' ? 'Percent' : 'Pixels';
+});
+
+// AVIF is the only format with an encoder speed knob
+formatSel.addEventListener('change', () => {
+  document.getElementById('avif-speed-row').classList.toggle('hidden', formatSel.value !== 'avif');
+});
+
 // --- Add images ---
 btnAdd.addEventListener('click', async () => {
   try {
     const selected = await window.__TAURI__.dialog.open({
       multiple: true,
-      filters: [{ name: 'Images', extensions: ['png','jpg','jpeg','webp','bmp','tiff','tif','gif','ico','avif'] }]
+      filters: [{ name: 'Images', extensions: ['png','jpg','jpeg','webp','bmp','tiff','tif','gif','ico','avif','heic','heif','svg'] }]
     });
     if (selected) {
       const paths = Array.isArray(selected) ? selected : [selected];
@@ -64,6 +86,7 @@ btnConvert.addEventListener('click', async () => {
   if (!files.length || !outputDir) return;
   btnConvert.disabled = true;
   btnConvert.textContent = '⏳ Converting…';
+  btnCancel.classList.remove('hidden');
   results.classList.add('hidden');
 
   try {
@@ -73,9 +96,17 @@ btnConvert.addEventListener('click', async () => {
         output_dir: outputDir,
         format: formatSel.value,
         quality: parseInt(qualitySlider.value),
+        resize_mode: resizeMode.value,
         resize_width: resizeW.value ? parseInt(resizeW.value) : null,
         resize_height: resizeH.value ? parseInt(resizeH.value) : null,
+        resize_percent: resizeMode.value === 'percent' && resizeValue.value ? parseFloat(resizeValue.value) : null,
+        longest_edge: resizeMode.value === 'longest_edge' && resizeValue.value ? parseInt(resizeValue.value) : null,
         strip_metadata: stripMeta.checked,
+        max_threads: maxThreads.value ? parseInt(maxThreads.value) : null,
+        avif_speed: avifSpeed.value ? parseInt(avifSpeed.value) : null,
+        render_width: renderW.value ? parseInt(renderW.value) : null,
+        render_height: renderH.value ? parseInt(renderH.value) : null,
+        render_scale: renderScale.value ? parseFloat(renderScale.value) : null,
       }
     });
     showResults(res);
@@ -85,6 +116,27 @@ btnConvert.addEventListener('click', async () => {
   }
   btnConvert.disabled = false;
   btnConvert.textContent = '🚀 Convert All';
+  btnCancel.classList.add('hidden');
+});
+
+// Files already converting finish; the rest come back marked cancelled
+btnCancel.addEventListener('click', () => {
+  btnCancel.classList.add('hidden');
+  invoke('cancel_conversion');
+});
+
+listen('convert-progress', (event) => {
+  const { completed, total, current_file } = event.payload;
+  btnConvert.textContent = `⏳ ${completed} / ${total} — ${current_file}`;
+});
+
+listen('batch-done', (event) => {
+  const { succeeded, failed, cancelled, bytes_saved } = event.payload;
+  const parts = [`${succeeded} converted`];
+  if (failed) parts.push(`${failed} failed`);
+  if (cancelled) parts.push(`${cancelled} cancelled`);
+  parts.push(bytes_saved >= 0 ? `${formatBytes(bytes_saved)} saved` : `${formatBytes(-bytes_saved)} larger`);
+  document.getElementById('results-summary').textContent = parts.join(' · ');
 });
 
 // --- Modal close ---
@@ -92,6 +144,18 @@ document.getElementById('modal-close').addEventListener('click', () => {
   previewModal.classList.add('hidden');
 });
 
+// favicon.ico (16–256) plus the PNG icons, written to the output folder
+document.getElementById('btn-favicon').addEventListener('click', async () => {
+  const status = document.getElementById('favicon-status');
+  if (!previewFile || !outputDir) return;
+  try {
+    const written = await invoke('generate_favicon_set', { path: previewFile.path, outputDir });
+    status.textContent = `${written.length} files written to ${outputDir}`;
+  } catch (e) {
+    status.textContent = 'Favicon set failed: ' + e;
+  }
+});
+
 // --- Drop zone ---
 dropZone.addEventListener('dragover', (e) => { e.preventDefault(); dropZone.classList.add('drag-over'); });
 dropZone.addEventListener('dragleave', () => { dropZone.classList.remove('drag-over'); });
@@ -106,7 +170,8 @@ async function addFiles(paths) {
   const infos = await invoke('get_image_info', { paths });
   for (const info of infos) {
     if (files.find(f => f.path === info.path)) continue;
-    const thumb = await invoke('generate_thumbnail', { path: info.path, maxSize: 200 });
+    // Files that failed to load stay listed with their error; convert reports them too
+    const thumb = info.error ? '' : await invoke('generate_thumbnail', { path: info.path, maxSize: 200, thumbFormat: 'webp', thumbQuality: 70 });
     files.push({ path: info.path, info, thumbnail: thumb });
   }
   render();
@@ -115,6 +180,8 @@ async function addFiles(paths) {
 function render() {
   fileCount.textContent = `${files.length} file${files.length !== 1 ? 's' : ''}`;
   dropZone.style.display = files.length ? 'none' : 'block';
+  // Vector input has no pixel size of its own, so only then ask for one
+  document.getElementById('svg-panel').classList.toggle('hidden', !files.some(f => f.info.format === 'SVG'));
   updateConvertBtn();
 
   imageGrid.innerHTML = files.map((f, i) => `
@@ -123,7 +190,9 @@ function render() {
       <img src="${f.thumbnail}" alt="${f.info.filename}">
       <div class="card-info">
         <div class="card-name" title="${f.info.filename}">${f.info.filename}</div>
-        <div class="card-meta">${f.info.width}×${f.info.height} · ${formatBytes(f.info.size_bytes)}</div>
+        ${f.info.error
+          ? `<div class="card-meta card-error" title="${f.info.error}">${f.info.error}</div>`
+          : `<div class="card-meta">${f.info.width}×${f.info.height} · ${formatBytes(f.info.size_bytes)}</div>`}
       </div>
     </div>
   `).join('');
@@ -146,7 +215,11 @@ function render() {
   });
 }
 
+let previewFile = null;
+
 function showPreview(f) {
+  previewFile = f;
+  document.getElementById('favicon-status').textContent = outputDir ? '' : 'Choose an output folder first';
   document.getElementById('preview-title').textContent = f.info.filename;
   document.getElementById('preview-before').src = f.thumbnail;
   document.getElementById('preview-before-info').textContent =
@@ -160,14 +233,16 @@ function showResults(res) {
   results.classList.remove('hidden');
   resultsList.innerHTML = res.map(r => {
     const saved = r.success ? ((1 - r.new_size / r.original_size) * 100).toFixed(1) : 0;
-    const cls = r.success ? 'success' : 'error';
+    const cls = r.success ? 'success' : r.cancelled ? 'cancelled' : 'error';
     const name = r.source.split('/').pop();
     return `<div class="result-item ${cls}">
       <span class="result-name">${name}</span>
       ${r.success
         ? `<span class="result-size">${formatBytes(r.original_size)} → ${formatBytes(r.new_size)}</span>
            <span class="result-saved">${saved > 0 ? '-' + saved + '%' : '+' + Math.abs(saved) + '%'}</span>`
-        : `<span style="color:var(--danger)">${r.error}</span>`
+        : r.cancelled
+          ? `<span style="color:var(--text-dim)">Cancelled</span>`
+          : `<span style="color:var(--danger)">${r.error}</span>`
       }
     </div>`;
   }).join('');
@@ -1,16 +1,24 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod svg;
+
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader};
+use image::{ColorType, DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use svg::RenderSize;
 use tauri::{Manager, Emitter};
 
 // ── Types ──────────────────────────────────────────────────────────────
@@ -24,6 +32,8 @@ pub struct ImageInfo {
     pub size_bytes: u64,
     pub format: String,
     pub thumbnail: String, // base64 data URI
+    pub bit_depth: u8,     // bits per channel
+    pub is_hdr: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,7 +46,34 @@ pub struct ConvertOptions {
     pub resize_percent: Option<f64>,
     pub strip_metadata: bool,
     pub output_dir: String,
-    pub filename_template: String, // {name}, {index}, {format}, {width}, {height}
+    pub filename_template: String, // see TEMPLATE_TOKENS
+    #[serde(default)]
+    pub flatten_background: Option<String>, // hex color, defaults to white
+    #[serde(default)]
+    pub tone_map: bool, // map HDR sources down to SDR instead of clipping
+    // Rasterization size for SVG input, before any resize; see RenderSize
+    #[serde(default)]
+    pub render_width: Option<u32>,
+    #[serde(default)]
+    pub render_height: Option<u32>,
+    #[serde(default)]
+    pub render_scale: Option<f64>,
+}
+
+impl ConvertOptions {
+    fn render_size(&self) -> RenderSize {
+        RenderSize {
+            width: self.render_width,
+            height: self.render_height,
+            scale: self.render_scale,
+        }
+    }
+
+    // ravif can only tag AVIF files as sRGB, so PQ/HLG code values written
+    // there untouched would be shown washed out; they're always tone-mapped
+    fn tone_map_for(&self, fmt: ImageFormat) -> bool {
+        self.tone_map || fmt == ImageFormat::Avif
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +90,27 @@ pub struct ConvertResult {
 pub struct SizeEstimate {
     pub estimated_bytes: u64,
     pub format: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateEntry {
+    pub path: String,
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+    pub thumbnail: String,
+    pub sha256: String,
+    pub distance: u32,            // Hamming distance to the group's first entry
+    pub exact_duplicate: bool,    // byte-identical to another entry in the group
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub kind: String, // "exact" when every entry is byte-identical, otherwise "similar"
+    pub entries: Vec<DuplicateEntry>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +120,33 @@ pub struct ProgressEvent {
     pub current_file: String,
 }
 
+// Same shape as image-converter-tauri's batch-done; this app has no cancel,
+// so `cancelled` is always 0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchDoneEvent {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub bytes_saved: i64,
+}
+
+impl BatchDoneEvent {
+    fn from_results(results: &[ConvertResult]) -> Self {
+        let succeeded: Vec<&ConvertResult> = results.iter().filter(|r| r.success).collect();
+        BatchDoneEvent {
+            total: results.len(),
+            succeeded: succeeded.len(),
+            failed: results.len() - succeeded.len(),
+            cancelled: 0,
+            bytes_saved: succeeded
+                .iter()
+                .map(|r| r.original_size as i64 - r.new_size as i64)
+                .sum(),
+        }
+    }
+}
+
 // ── Helpers ────────────────────────────────────────────────────────────
 
 fn detect_format(path: &Path) -> Option<ImageFormat> {
@@ -107,52 +192,320 @@ fn format_extension(fmt: ImageFormat) -> &'static str {
     }
 }
 
-fn make_thumbnail(img: &DynamicImage, max_size: u32) -> String {
+// JPEG at the encoder's default quality unless the caller asks otherwise;
+// WebP keeps the payload small when hundreds of thumbnails are on screen
+const DEFAULT_THUMB_QUALITY: u8 = 75;
+
+fn parse_thumb_format(format: Option<&str>) -> Result<ImageFormat, String> {
+    match format.map(|f| f.to_lowercase()).as_deref() {
+        None | Some("jpeg") | Some("jpg") => Ok(ImageFormat::Jpeg),
+        Some("png") => Ok(ImageFormat::Png),
+        Some("webp") => Ok(ImageFormat::WebP),
+        Some(other) => Err(format!("Unsupported thumbnail format: {}", other)),
+    }
+}
+
+fn make_thumbnail(img: &DynamicImage, max_size: u32, fmt: ImageFormat, quality: u8) -> String {
     let thumb = img.resize(max_size, max_size, FilterType::Triangle);
     let mut buf = Vec::new();
     let mut cursor = Cursor::new(&mut buf);
-    thumb
-        .write_to(&mut cursor, ImageFormat::Jpeg)
-        .unwrap_or_default();
-    format!("data:image/jpeg;base64,{}", BASE64.encode(&buf))
+    let mime = match fmt {
+        ImageFormat::WebP => {
+            // image's own WebP encoder is lossless only
+            let rgba = thumb.to_rgba8();
+            let encoder = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height());
+            buf = encoder.encode(quality as f32).to_vec();
+            "image/webp"
+        }
+        ImageFormat::Png => {
+            thumb.write_to(&mut cursor, ImageFormat::Png).unwrap_or_default();
+            "image/png"
+        }
+        _ => {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, quality);
+            DynamicImage::ImageRgb8(thumb.to_rgb8())
+                .write_with_encoder(encoder)
+                .unwrap_or_default();
+            "image/jpeg"
+        }
+    };
+    format!("data:{};base64,{}", mime, BASE64.encode(&buf))
 }
 
-fn apply_resize(img: DynamicImage, opts: &ConvertOptions) -> DynamicImage {
+// Output dimensions for the configured resize; mirrors the aspect-preserving
+// math of `DynamicImage::resize` for "fit".
+fn target_dimensions(width: u32, height: u32, opts: &ConvertOptions) -> (u32, u32) {
     match opts.resize_mode.as_str() {
         "percent" => {
             let pct = opts.resize_percent.unwrap_or(100.0) / 100.0;
-            let (w, h) = img.dimensions();
-            let nw = (w as f64 * pct).round() as u32;
-            let nh = (h as f64 * pct).round() as u32;
+            let nw = (width as f64 * pct).round() as u32;
+            let nh = (height as f64 * pct).round() as u32;
             if nw > 0 && nh > 0 {
-                img.resize_exact(nw, nh, FilterType::Lanczos3)
+                (nw, nh)
             } else {
-                img
+                (width, height)
             }
         }
         "pixels" => {
             let nw = opts.resize_width.unwrap_or(0);
             let nh = opts.resize_height.unwrap_or(0);
             if nw > 0 && nh > 0 {
-                img.resize_exact(nw, nh, FilterType::Lanczos3)
+                (nw, nh)
             } else {
-                img
+                (width, height)
             }
         }
         "fit" => {
             let nw = opts.resize_width.unwrap_or(0);
             let nh = opts.resize_height.unwrap_or(0);
-            if nw > 0 && nh > 0 {
-                img.resize(nw, nh, FilterType::Lanczos3)
+            if nw > 0 && nh > 0 && width > 0 && height > 0 {
+                let ratio = f64::min(nw as f64 / width as f64, nh as f64 / height as f64);
+                let fw = ((width as f64 * ratio).round() as u32).max(1);
+                let fh = ((height as f64 * ratio).round() as u32).max(1);
+                (fw, fh)
             } else {
-                img
+                (width, height)
             }
         }
-        _ => img,
+        _ => (width, height),
+    }
+}
+
+fn apply_resize(img: DynamicImage, opts: &ConvertOptions) -> DynamicImage {
+    let (w, h) = img.dimensions();
+    let (nw, nh) = target_dimensions(w, h, opts);
+    if (nw, nh) == (w, h) {
+        img
+    } else {
+        img.resize_exact(nw, nh, FilterType::Lanczos3)
+    }
+}
+
+fn format_supports_alpha(fmt: ImageFormat) -> bool {
+    !matches!(fmt, ImageFormat::Jpeg)
+}
+
+// Accepts "#rgb", "#rrggbb" or the same without the leading '#'
+fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid background color '{}', expected e.g. #ffffff", color);
+    // Checked up front so the byte slicing below can't split a character
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return Err(invalid()),
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i..i + 2], 16).map_err(|_| invalid());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// Composite over a solid color so transparent regions don't turn black
+fn flatten_onto(img: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let mut out = image::RgbImage::new(w, h);
+    for (src, dst) in rgba.pixels().zip(out.pixels_mut()) {
+        let a = src[3] as u32;
+        for c in 0..3 {
+            dst[c] = ((src[c] as u32 * a + background[c] as u32 * (255 - a) + 127) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(out)
+}
+
+// Ensure proper color space for the target format
+fn prepare_for_format(
+    img: DynamicImage,
+    fmt: ImageFormat,
+    background: Option<&str>,
+) -> Result<DynamicImage, String> {
+    let img = if format_supports_alpha(fmt) || !img.color().has_alpha() {
+        img
+    } else {
+        let background = match background.filter(|b| !b.trim().is_empty()) {
+            Some(color) => parse_hex_color(color)?,
+            None => [255, 255, 255],
+        };
+        flatten_onto(&img, background)
+    };
+
+    // Narrow 16-bit/float data explicitly for 8-bit-only encoders
+    if supports_high_bit_depth(fmt) || bit_depth(img.color()) <= 8 {
+        Ok(img)
+    } else if img.color().has_alpha() {
+        Ok(DynamicImage::ImageRgba8(img.to_rgba8()))
+    } else {
+        Ok(DynamicImage::ImageRgb8(img.to_rgb8()))
+    }
+}
+
+// ── Color / HDR ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transfer {
+    Srgb,
+    Linear, // float sources
+    Pq,     // SMPTE ST 2084
+    Hlg,    // ARIB STD-B67
+}
+
+impl Transfer {
+    fn is_hdr(self) -> bool {
+        self != Transfer::Srgb
+    }
+}
+
+fn bit_depth(color: ColorType) -> u8 {
+    (color.bytes_per_pixel() / color.channel_count()) * 8
+}
+
+fn supports_high_bit_depth(fmt: ImageFormat) -> bool {
+    matches!(fmt, ImageFormat::Png | ImageFormat::Tiff | ImageFormat::Avif)
+}
+
+// ICC descriptions are ASCII ('desc') or UTF-16BE ('mluc'), so look for both
+fn icc_mentions(icc: &[u8], needle: &str) -> bool {
+    let ascii = needle.as_bytes();
+    let utf16: Vec<u8> = needle.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+    icc.windows(ascii.len()).any(|w| w == ascii) || icc.windows(utf16.len()).any(|w| w == utf16.as_slice())
+}
+
+fn detect_transfer(img: &DynamicImage, icc: Option<&[u8]>) -> Transfer {
+    if matches!(img.color(), ColorType::Rgb32F | ColorType::Rgba32F) {
+        return Transfer::Linear;
+    }
+    match icc {
+        Some(icc) if ["2084", "2100 PQ", "P3 PQ"].iter().any(|n| icc_mentions(icc, n)) => Transfer::Pq,
+        Some(icc) if icc_mentions(icc, "HLG") => Transfer::Hlg,
+        _ => Transfer::Srgb,
+    }
+}
+
+// SVGs are rasterized at `render`; they have no ImageFormat and are always sRGB
+fn decode_image(
+    path: &Path,
+    render: RenderSize,
+) -> Result<(DynamicImage, Option<ImageFormat>, Transfer), String> {
+    if svg::is_svg(path) {
+        return Ok((svg::render(path, render)?, None, Transfer::Srgb));
+    }
+    let reader = ImageReader::open(path)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let format = reader.format();
+    let mut decoder = reader.into_decoder().map_err(|e| e.to_string())?;
+    let icc = decoder.icc_profile().ok().flatten();
+    let img = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    let transfer = detect_transfer(&img, icc.as_deref());
+    Ok((img, format, transfer))
+}
+
+// Signal value to linear light, scaled so SDR reference white is 1.0
+fn to_linear(v: f32, transfer: Transfer) -> f32 {
+    match transfer {
+        Transfer::Srgb | Transfer::Linear => v,
+        Transfer::Pq => {
+            let (m1, m2) = (0.159_301_76, 78.84375);
+            let (c1, c2, c3) = (0.835_937_5, 18.851_563, 18.6875);
+            let p = v.max(0.0).powf(1.0 / m2);
+            let nits = ((p - c1).max(0.0) / (c2 - c3 * p)).powf(1.0 / m1) * 10_000.0;
+            nits / 203.0
+        }
+        Transfer::Hlg => {
+            let (a, b, c) = (0.178_832_77, 0.284_668_92, 0.559_910_7);
+            let scene = if v <= 0.5 { v * v / 3.0 } else { (((v - c) / a).exp() + b) / 12.0 };
+            // 75% HLG signal is reference white
+            scene / 0.2642
+        }
     }
 }
 
+// Narkowicz's ACES filmic fit
+fn aces(x: f32) -> f32 {
+    let x = x * 0.6;
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+fn srgb_encode(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn tone_map_to_sdr(img: &DynamicImage, transfer: Transfer) -> DynamicImage {
+    let has_alpha = img.color().has_alpha();
+    let mut rgba = img.to_rgba32f();
+    rgba.pixels_mut().for_each(|p| {
+        for c in 0..3 {
+            p[c] = srgb_encode(aces(to_linear(p[c], transfer)));
+        }
+    });
+    let mapped = DynamicImage::ImageRgba32F(rgba);
+    if has_alpha {
+        DynamicImage::ImageRgba8(mapped.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(mapped.to_rgb8())
+    }
+}
+
+fn apply_tone_map(img: DynamicImage, transfer: Transfer, enabled: bool) -> DynamicImage {
+    if enabled && transfer.is_hdr() {
+        tone_map_to_sdr(&img, transfer)
+    } else {
+        img
+    }
+}
+
+// The image crate's AVIF encoder is 8-bit only; go through ravif directly so
+// high bit depth sources keep 10 bits. The file is tagged sRGB, which is why
+// HDR sources are tone-mapped before they get here (see tone_map_for).
+fn encode_avif_10bit(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    let rgba = img.to_rgba16();
+    let (w, h) = rgba.dimensions();
+    let to10 = |v: f32| (v * 1023.0).round().clamp(0.0, 1023.0) as u16;
+
+    // Full-range BT.601 YCbCr
+    let planes: Vec<[u16; 3]> = rgba
+        .pixels()
+        .map(|p| {
+            let [r, g, b] = [p[0], p[1], p[2]].map(|v| v as f32 / 65535.0);
+            let y = 0.299 * r + 0.587 * g + 0.114 * b;
+            [to10(y), to10((b - y) / 1.772 + 0.5), to10((r - y) / 1.402 + 0.5)]
+        })
+        .collect();
+    let alpha: Option<Vec<u16>> = img
+        .color()
+        .has_alpha()
+        .then(|| rgba.pixels().map(|p| to10(p[3] as f32 / 65535.0)).collect());
+
+    let quality = quality.clamp(1, 100) as f32;
+    ravif::Encoder::new()
+        .with_quality(quality)
+        .with_alpha_quality(quality)
+        .with_speed(4)
+        .encode_raw_planes_10_bit(
+            w as usize,
+            h as usize,
+            planes,
+            alpha,
+            ravif::PixelRange::Full,
+            ravif::MatrixCoefficients::BT601,
+        )
+        .map(|encoded| encoded.avif_file)
+        .map_err(|e| e.to_string())
+}
+
 fn encode_image(img: &DynamicImage, fmt: ImageFormat, quality: u8) -> Result<Vec<u8>, String> {
+    if fmt == ImageFormat::Avif && bit_depth(img.color()) > 8 {
+        return encode_avif_10bit(img, quality);
+    }
+
     let mut buf = Vec::new();
     let mut cursor = Cursor::new(&mut buf);
 
@@ -180,20 +533,88 @@ fn encode_image(img: &DynamicImage, fmt: ImageFormat, quality: u8) -> Result<Vec
     Ok(buf)
 }
 
+// ── Output Naming ──────────────────────────────────────────────────────
+
+const TEMPLATE_TOKENS: &[&str] = &[
+    "name", "index", "format", "ext", "width", "height", "date", "orig_format",
+];
+
+struct TemplateContext<'a> {
+    name: &'a str,
+    index: usize,
+    ext: &'a str,
+    width: u32,
+    height: u32,
+    date: &'a str,
+    orig_format: &'a str,
+}
+
+fn render_template(template: &str, ctx: &TemplateContext) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let token = &after[..end];
+        let value = match token {
+            "name" => ctx.name.to_string(),
+            "index" => format!("{:04}", ctx.index),
+            "format" | "ext" => ctx.ext.to_string(),
+            "width" => ctx.width.to_string(),
+            "height" => ctx.height.to_string(),
+            "date" => ctx.date.to_string(),
+            "orig_format" => ctx.orig_format.to_string(),
+            _ => {
+                let available: Vec<String> =
+                    TEMPLATE_TOKENS.iter().map(|t| format!("{{{}}}", t)).collect();
+                return Err(format!(
+                    "Unknown filename token '{{{}}}'. Available tokens: {}",
+                    token,
+                    available.join(", ")
+                ));
+            }
+        };
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+// Today's date as YYYY-MM-DD, in local time
+fn today_string() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
 fn build_output_path(
     source: &Path,
     index: usize,
     opts: &ConvertOptions,
     fmt: ImageFormat,
-) -> PathBuf {
+    dimensions: (u32, u32),
+    orig_format: &str,
+    date: &str,
+) -> Result<PathBuf, String> {
     let stem = source.file_stem().unwrap_or_default().to_string_lossy();
     let ext = format_extension(fmt);
-    let name = opts
-        .filename_template
-        .replace("{name}", &stem)
-        .replace("{index}", &format!("{:04}", index))
-        .replace("{format}", ext)
-        .replace("{ext}", ext);
+    let name = render_template(
+        &opts.filename_template,
+        &TemplateContext {
+            name: &stem,
+            index,
+            ext,
+            width: dimensions.0,
+            height: dimensions.1,
+            date,
+            orig_format,
+        },
+    )?;
 
     let filename = if name.contains('.') {
         name
@@ -201,24 +622,273 @@ fn build_output_path(
         format!("{}.{}", name, ext)
     };
 
-    Path::new(&opts.output_dir).join(filename)
+    Ok(Path::new(&opts.output_dir).join(filename))
+}
+
+// Append _2, _3, … until the path hasn't been claimed by another file in the
+// batch and, with `skip_existing`, isn't already on disk
+fn unique_output_path(path: PathBuf, taken: &mut HashSet<String>, skip_existing: bool) -> PathBuf {
+    let key = |p: &Path| p.to_string_lossy().to_lowercase();
+    let mut claim = |p: &Path| !(skip_existing && p.exists()) && taken.insert(key(p));
+    if claim(&path) {
+        return path;
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = path.extension().map(|e| e.to_string_lossy().into_owned());
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut counter = 2;
+    loop {
+        let filename = match &ext {
+            Some(ext) => format!("{}_{}.{}", stem, counter, ext),
+            None => format!("{}_{}", stem, counter),
+        };
+        let candidate = parent.join(filename);
+        if claim(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+// Source format and pre-resize dimensions, from the image header alone
+fn read_header(source: &Path, opts: &ConvertOptions) -> Result<(String, (u32, u32)), String> {
+    let (orig, dims) = if svg::is_svg(source) {
+        (None, svg::dimensions(source, opts.render_size())?)
+    } else {
+        let reader = ImageReader::open(source)
+            .map_err(|e| e.to_string())?
+            .with_guessed_format()
+            .map_err(|e| e.to_string())?;
+        let orig = reader.format();
+        (orig, reader.into_dimensions().map_err(|e| e.to_string())?)
+    };
+
+    let orig_format = match orig.or_else(|| detect_format(source)) {
+        Some(f) => format_extension(f).to_string(),
+        None => source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+    };
+    Ok((orig_format, dims))
+}
+
+// Resolve every output path before converting in parallel, reading only the
+// image headers for the post-resize dimensions.
+fn plan_output_paths(
+    paths: &[String],
+    opts: &ConvertOptions,
+    fmt: ImageFormat,
+) -> Result<Vec<Result<PathBuf, String>>, String> {
+    let date = today_string();
+    let mut taken = HashSet::new();
+    let mut planned = Vec::with_capacity(paths.len());
+
+    for (idx, p) in paths.iter().enumerate() {
+        let source = Path::new(p);
+        let (orig_format, (w, h)) = match read_header(source, opts) {
+            Ok(h) => h,
+            Err(e) => {
+                planned.push(Err(e));
+                continue;
+            }
+        };
+
+        let dimensions = target_dimensions(w, h, opts);
+        // Template errors apply to the whole batch
+        let path = build_output_path(source, idx + 1, opts, fmt, dimensions, &orig_format, &date)?;
+        planned.push(Ok(unique_output_path(path, &mut taken, false)));
+    }
+
+    Ok(planned)
+}
+
+fn convert_file(
+    p: &str,
+    output_path: Result<PathBuf, String>,
+    options: &ConvertOptions,
+    fmt: ImageFormat,
+) -> ConvertResult {
+    let source = Path::new(p);
+    let result = (|| -> Result<ConvertResult, String> {
+        let output_path = output_path?;
+        let (img, _, transfer) = decode_image(source, options.render_size())?;
+
+        let original_size = fs::metadata(source).map(|m| m.len()).unwrap_or(0);
+
+        // Apply resize
+        let img = apply_resize(img, options);
+        let img = apply_tone_map(img, transfer, options.tone_map_for(fmt));
+
+        // Flatten alpha for formats that can't store it
+        let img = prepare_for_format(img, fmt, options.flatten_background.as_deref())?;
+
+        // Encode
+        let buf = encode_image(&img, fmt, options.quality)?;
+        let new_size = buf.len() as u64;
+
+        // Write
+        fs::write(&output_path, &buf).map_err(|e| e.to_string())?;
+
+        Ok(ConvertResult {
+            source: p.to_string(),
+            output: output_path.to_string_lossy().into_owned(),
+            original_size,
+            new_size,
+            success: true,
+            error: None,
+        })
+    })();
+
+    match result {
+        Ok(r) => r,
+        Err(e) => ConvertResult {
+            source: p.to_string(),
+            output: String::new(),
+            original_size: 0,
+            new_size: 0,
+            success: false,
+            error: Some(e),
+        },
+    }
+}
+
+// ── Duplicates ─────────────────────────────────────────────────────────
+
+const DEFAULT_DUPLICATE_THRESHOLD: u32 = 6;
+
+// 64-bit difference hash: compares horizontally adjacent pixels of a 9×8
+// grayscale thumbnail, so it survives re-encoding and resizing
+fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+struct Fingerprint {
+    entry: DuplicateEntry,
+    hash: u64,
+}
+
+fn fingerprint(path: &str, thumb_format: ImageFormat, thumb_quality: u8) -> Option<Fingerprint> {
+    let bytes = fs::read(path).ok()?;
+    let sha256: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+    let img = image::load_from_memory(&bytes).ok()?;
+    let (width, height) = img.dimensions();
+
+    Some(Fingerprint {
+        hash: dhash(&img),
+        entry: DuplicateEntry {
+            path: path.to_string(),
+            name: Path::new(path).file_name()?.to_string_lossy().into_owned(),
+            width,
+            height,
+            size_bytes: bytes.len() as u64,
+            thumbnail: make_thumbnail(&img, 200, thumb_format, thumb_quality),
+            sha256,
+            distance: 0,
+            exact_duplicate: false,
+        },
+    })
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn group_duplicates(prints: Vec<Fingerprint>, threshold: u32) -> Vec<DuplicateGroup> {
+    let n = prints.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let same_bytes = prints[i].entry.sha256 == prints[j].entry.sha256;
+            if same_bytes || (prints[i].hash ^ prints[j].hash).count_ones() <= threshold {
+                let (a, b) = (find_root(&mut parent, i), find_root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..n {
+        let root = find_root(&mut parent, i);
+        clusters.entry(root).or_default().push(i);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = clusters
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|mut members| {
+            // Largest file first, it's usually the one worth keeping
+            members.sort_by_key(|&i| std::cmp::Reverse(prints[i].entry.size_bytes));
+            let first_hash = prints[members[0]].hash;
+            let mut sha_counts: HashMap<&str, usize> = HashMap::new();
+            for &i in &members {
+                *sha_counts.entry(prints[i].entry.sha256.as_str()).or_default() += 1;
+            }
+
+            let entries: Vec<DuplicateEntry> = members
+                .iter()
+                .map(|&i| DuplicateEntry {
+                    distance: (prints[i].hash ^ first_hash).count_ones(),
+                    exact_duplicate: sha_counts[prints[i].entry.sha256.as_str()] > 1,
+                    ..prints[i].entry.clone()
+                })
+                .collect();
+            let kind = if sha_counts.len() == 1 { "exact" } else { "similar" };
+
+            DuplicateGroup {
+                kind: kind.to_string(),
+                entries,
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| {
+        (a.kind != "exact")
+            .cmp(&(b.kind != "exact"))
+            .then(b.entries.len().cmp(&a.entries.len()))
+            .then_with(|| a.entries[0].path.cmp(&b.entries[0].path))
+    });
+    groups
 }
 
 // ── Tauri Commands ─────────────────────────────────────────────────────
 
 #[tauri::command]
-async fn load_images(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
+async fn load_images(
+    paths: Vec<String>,
+    thumb_format: Option<String>,
+    thumb_quality: Option<u8>,
+) -> Result<Vec<ImageInfo>, String> {
+    let thumb_format = parse_thumb_format(thumb_format.as_deref())?;
+    let thumb_quality = thumb_quality.unwrap_or(DEFAULT_THUMB_QUALITY).clamp(1, 100);
     let results: Vec<ImageInfo> = paths
         .par_iter()
         .filter_map(|p| {
             let path = Path::new(p);
-            let reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
-            let fmt = reader.format()?;
-            let img = reader.decode().ok()?;
+            let (img, fmt, transfer) = decode_image(path, RenderSize::default()).ok()?;
+            let fmt_str = if svg::is_svg(path) {
+                "SVG".to_string()
+            } else {
+                format!("{:?}", fmt?)
+            };
             let (w, h) = img.dimensions();
             let size = fs::metadata(path).ok()?.len();
-            let thumb = make_thumbnail(&img, 200);
-            let fmt_str = format!("{:?}", fmt);
+            let thumb = make_thumbnail(&img, 200, thumb_format, thumb_quality);
 
             Some(ImageInfo {
                 path: p.clone(),
@@ -228,6 +898,8 @@ async fn load_images(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
                 size_bytes: size,
                 format: fmt_str,
                 thumbnail: thumb,
+                bit_depth: bit_depth(img.color()),
+                is_hdr: transfer.is_hdr(),
             })
         })
         .collect();
@@ -235,37 +907,83 @@ async fn load_images(paths: Vec<String>) -> Result<Vec<ImageInfo>, String> {
     Ok(results)
 }
 
+// Outputs larger than this are estimated from a downscaled sample and
+// extrapolated by pixel count, which keeps the live estimate responsive.
+const ESTIMATE_SAMPLE_PIXELS: u64 = 1_000_000;
+
+// `threshold` is the max Hamming distance between 64-bit dHashes (0 = visually identical)
 #[tauri::command]
-async fn estimate_size(path: String, format: String, quality: u8) -> Result<SizeEstimate, String> {
-    let img_path = Path::new(&path);
-    let img = ImageReader::open(img_path)
-        .map_err(|e| e.to_string())?
-        .with_guessed_format()
-        .map_err(|e| e.to_string())?
-        .decode()
-        .map_err(|e| e.to_string())?;
+async fn find_duplicates(
+    paths: Vec<String>,
+    threshold: Option<u32>,
+    thumb_format: Option<String>,
+    thumb_quality: Option<u8>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_DUPLICATE_THRESHOLD).min(64);
+    let thumb_format = parse_thumb_format(thumb_format.as_deref())?;
+    let thumb_quality = thumb_quality.unwrap_or(DEFAULT_THUMB_QUALITY).clamp(1, 100);
+    let prints: Vec<Fingerprint> = paths
+        .par_iter()
+        .filter_map(|p| fingerprint(p, thumb_format, thumb_quality))
+        .collect();
+    Ok(group_duplicates(prints, threshold))
+}
 
-    let fmt = parse_output_format(&format);
-    let buf = encode_image(&img, fmt, quality)?;
+#[tauri::command]
+async fn estimate_size(path: String, options: ConvertOptions) -> Result<SizeEstimate, String> {
+    let (img, _, transfer) = decode_image(Path::new(&path), options.render_size())?;
+
+    let fmt = parse_output_format(&options.output_format);
+    let (w, h) = img.dimensions();
+    let (tw, th) = target_dimensions(w, h, &options);
+    let target_pixels = tw as u64 * th as u64;
+
+    let estimated_bytes = if target_pixels > ESTIMATE_SAMPLE_PIXELS {
+        let scale = (ESTIMATE_SAMPLE_PIXELS as f64 / target_pixels as f64).sqrt();
+        let sw = ((tw as f64 * scale).round() as u32).max(1);
+        let sh = ((th as f64 * scale).round() as u32).max(1);
+        let sample = apply_tone_map(img.thumbnail_exact(sw, sh), transfer, options.tone_map_for(fmt));
+        let sample = prepare_for_format(
+            sample,
+            fmt,
+            options.flatten_background.as_deref(),
+        )?;
+        let buf = encode_image(&sample, fmt, options.quality)?;
+        let factor = target_pixels as f64 / (sw as u64 * sh as u64) as f64;
+        (buf.len() as f64 * factor).round() as u64
+    } else {
+        let img = apply_tone_map(apply_resize(img, &options), transfer, options.tone_map_for(fmt));
+        let img = prepare_for_format(
+            img,
+            fmt,
+            options.flatten_background.as_deref(),
+        )?;
+        encode_image(&img, fmt, options.quality)?.len() as u64
+    };
 
     Ok(SizeEstimate {
-        estimated_bytes: buf.len() as u64,
-        format,
+        estimated_bytes,
+        format: options.output_format,
+        width: tw,
+        height: th,
     })
 }
 
 #[tauri::command]
-async fn get_preview(path: String, format: String, quality: u8, max_size: u32) -> Result<String, String> {
-    let img_path = Path::new(&path);
-    let img = ImageReader::open(img_path)
-        .map_err(|e| e.to_string())?
-        .with_guessed_format()
-        .map_err(|e| e.to_string())?
-        .decode()
-        .map_err(|e| e.to_string())?;
+async fn get_preview(
+    path: String,
+    format: String,
+    quality: u8,
+    max_size: u32,
+    flatten_background: Option<String>,
+    tone_map: Option<bool>,
+) -> Result<String, String> {
+    let (img, _, transfer) = decode_image(Path::new(&path), RenderSize::default())?;
 
-    let preview = img.resize(max_size, max_size, FilterType::Lanczos3);
     let fmt = parse_output_format(&format);
+    let preview = img.resize(max_size, max_size, FilterType::Lanczos3);
+    let preview = apply_tone_map(preview, transfer, tone_map.unwrap_or(false));
+    let preview = prepare_for_format(preview, fmt, flatten_background.as_deref())?;
     let buf = encode_image(&preview, fmt, quality)?;
 
     let mime = match fmt {
@@ -293,49 +1011,13 @@ async fn convert_images(
     let total = paths.len();
     let completed = Arc::new(AtomicUsize::new(0));
     let fmt = parse_output_format(&options.output_format);
+    let output_paths = plan_output_paths(&paths, &options, fmt)?;
 
     let results: Vec<ConvertResult> = paths
         .par_iter()
         .enumerate()
         .map(|(idx, p)| {
-            let source = Path::new(p);
-            let result = (|| -> Result<ConvertResult, String> {
-                let img = ImageReader::open(source)
-                    .map_err(|e| e.to_string())?
-                    .with_guessed_format()
-                    .map_err(|e| e.to_string())?
-                    .decode()
-                    .map_err(|e| e.to_string())?;
-
-                let original_size = fs::metadata(source).map(|m| m.len()).unwrap_or(0);
-
-                // Apply resize
-                let img = apply_resize(img, &options);
-
-                // Ensure proper color space for JPEG (no alpha)
-                let img = if matches!(fmt, ImageFormat::Jpeg) && img.color().has_alpha() {
-                    DynamicImage::ImageRgb8(img.to_rgb8())
-                } else {
-                    img
-                };
-
-                // Encode
-                let buf = encode_image(&img, fmt, options.quality)?;
-                let new_size = buf.len() as u64;
-
-                // Write
-                let output_path = build_output_path(source, idx + 1, &options, fmt);
-                fs::write(&output_path, &buf).map_err(|e| e.to_string())?;
-
-                Ok(ConvertResult {
-                    source: p.clone(),
-                    output: output_path.to_string_lossy().into_owned(),
-                    original_size,
-                    new_size,
-                    success: true,
-                    error: None,
-                })
-            })();
+            let result = convert_file(p, output_paths[idx].clone(), &options, fmt);
 
             let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
             let _ = app.emit(
@@ -343,7 +1025,7 @@ async fn convert_images(
                 ProgressEvent {
                     completed: done,
                     total,
-                    current_file: source
+                    current_file: Path::new(p)
                         .file_name()
                         .unwrap_or_default()
                         .to_string_lossy()
@@ -351,23 +1033,153 @@ async fn convert_images(
                 },
             );
 
-            match result {
-                Ok(r) => r,
-                Err(e) => ConvertResult {
-                    source: p.clone(),
-                    output: String::new(),
-                    original_size: 0,
-                    new_size: 0,
-                    success: false,
-                    error: Some(e),
-                },
-            }
+            result
         })
         .collect();
 
+    let _ = app.emit("batch-done", BatchDoneEvent::from_results(&results));
     Ok(results)
 }
 
+// ── Watch Folder ───────────────────────────────────────────────────────
+
+// Quiet period before a file counts as finished writing
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(1500);
+
+#[derive(Default)]
+struct WatchState {
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+}
+
+// What the watcher has done since it started. Events arrive one file at a
+// time, so numbering and name collisions are tracked here across them.
+#[derive(Default)]
+struct WatchProgress {
+    // Modification time of every file converted, so repeated events for an
+    // unchanged file don't convert it twice
+    converted: HashMap<PathBuf, SystemTime>,
+    // Output path handed to each source; its position gives {index}
+    outputs: HashMap<PathBuf, PathBuf>,
+    taken: HashSet<String>,
+}
+
+// A source seen before writes over its own earlier output. A new one gets the
+// next index and a name no other source, and no file already on disk, has.
+fn watch_output_path(
+    source: &Path,
+    options: &ConvertOptions,
+    fmt: ImageFormat,
+    progress: &mut WatchProgress,
+) -> Result<PathBuf, String> {
+    if let Some(output) = progress.outputs.get(source) {
+        return Ok(output.clone());
+    }
+    let (orig_format, (w, h)) = read_header(source, options)?;
+    let dimensions = target_dimensions(w, h, options);
+    let index = progress.outputs.len() + 1;
+    let path = build_output_path(source, index, options, fmt, dimensions, &orig_format, &today_string())?;
+    let path = unique_output_path(path, &mut progress.taken, true);
+    progress.outputs.insert(source.to_path_buf(), path.clone());
+    Ok(path)
+}
+
+fn handle_watch_event(
+    app: &tauri::AppHandle,
+    path: &Path,
+    options: &ConvertOptions,
+    output_dir: &Path,
+    progress: &mut WatchProgress,
+) {
+    if !path.is_file() || (detect_format(path).is_none() && !svg::is_svg(path)) {
+        return;
+    }
+    // Output written into the watched folder would otherwise be picked up again
+    if path.parent().and_then(|d| d.canonicalize().ok()).as_deref() == Some(output_dir) {
+        return;
+    }
+    let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) else {
+        return;
+    };
+    if progress.converted.get(path) == Some(&modified) {
+        return;
+    }
+
+    let p = path.to_string_lossy().into_owned();
+    let fmt = parse_output_format(&options.output_format);
+    let output_path = watch_output_path(path, options, fmt, progress);
+    let result = convert_file(&p, output_path, options, fmt);
+    // A file that failed (e.g. still being copied) is retried on its next change
+    if result.success {
+        progress.converted.insert(path.to_path_buf(), modified);
+    }
+    let _ = app.emit("watch-converted", result);
+}
+
+#[tauri::command]
+fn watch_folder(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, WatchState>,
+    input_dir: String,
+    options: ConvertOptions,
+) -> Result<(), String> {
+    let input = Path::new(&input_dir);
+    if !input.is_dir() {
+        return Err(format!("Not a folder: {}", input_dir));
+    }
+    fs::create_dir_all(&options.output_dir).map_err(|e| e.to_string())?;
+    let output_dir = Path::new(&options.output_dir)
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    // Catch template errors now rather than on the first dropped file
+    render_template(
+        &options.filename_template,
+        &TemplateContext {
+            name: "",
+            index: 1,
+            ext: "",
+            width: 0,
+            height: 0,
+            date: "",
+            orig_format: "",
+        },
+    )?;
+
+    let handler_app = app.clone();
+    let mut progress = WatchProgress::default();
+    let mut debouncer = new_debouncer(WATCH_DEBOUNCE, move |res: DebounceEventResult| match res {
+        Ok(events) => {
+            for event in events {
+                handle_watch_event(
+                    &handler_app,
+                    &event.path,
+                    &options,
+                    &output_dir,
+                    &mut progress,
+                );
+            }
+        }
+        Err(e) => {
+            let _ = handler_app.emit("watch-error", e.to_string());
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    debouncer
+        .watcher()
+        .watch(input, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    // Replacing the previous debouncer drops it, which stops that watch
+    *state.debouncer.lock().map_err(|e| e.to_string())? = Some(debouncer);
+    Ok(())
+}
+
+/// Returns whether a folder was being watched
+#[tauri::command]
+fn stop_watching(state: tauri::State<'_, WatchState>) -> Result<bool, String> {
+    let mut debouncer = state.debouncer.lock().map_err(|e| e.to_string())?;
+    Ok(debouncer.take().is_some())
+}
+
 #[tauri::command]
 async fn pick_folder() -> Result<Option<String>, String> {
     // We use the dialog plugin from frontend instead
@@ -378,13 +1190,17 @@ async fn pick_folder() -> Result<Option<String>, String> {
 
 fn main() {
     tauri::Builder::default()
+        .manage(WatchState::default())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             load_images,
+            find_duplicates,
             estimate_size,
             get_preview,
             convert_images,
+            watch_folder,
+            stop_watching,
             pick_folder,
         ])
         .run(tauri::generate_context!())
@@ -13,6 +13,11 @@ let selectedFormat = 'PNG';
 let outputDir = '';
 let estimateTimer = null;
 let selectedImageIndex = null;
+let watchedDir = null;
+let watchCount = 0;
+
+// Grid thumbnails as lossy WebP keep the IPC payload small for big folders
+const THUMB_OPTIONS = { thumbFormat: 'webp', thumbQuality: 70 };
 
 // ── DOM ────────────────────────────────────────────────────────────────
 
@@ -31,9 +36,15 @@ const resizeInputs = $('#resizeInputs');
 const resizePercent = $('#resizePercent');
 const resizePixels = $('#resizePixels');
 const stripMeta = $('#stripMeta');
+const flattenBg = $('#flattenBg');
+const toneMap = $('#toneMap');
+const findDupesBtn = $('#findDupesBtn');
+const dupesOverlay = $('#dupesOverlay');
 const outputFolder = $('#outputFolder');
 const folderPath = $('#folderPath');
 const filenameTemplate = $('#filenameTemplate');
+const watchBtn = $('#watchBtn');
+const watchStatus = $('#watchStatus');
 const convertBtn = $('#convertBtn');
 const progressBar = $('#progressBar');
 const progressFill = $('#progressFill');
@@ -69,6 +80,14 @@ resizeMode.addEventListener('change', () => {
   resizeInputs.classList.toggle('hidden', mode === 'none');
   resizePercent.classList.toggle('hidden', mode !== 'percent');
   resizePixels.classList.toggle('hidden', mode !== 'pixels' && mode !== 'fit');
+  scheduleEstimate();
+});
+
+flattenBg.addEventListener('input', scheduleEstimate);
+toneMap.addEventListener('change', scheduleEstimate);
+
+['#resizePct', '#resizeW', '#resizeH', '#renderW', '#renderH', '#renderScale'].forEach(sel => {
+  $(sel).addEventListener('input', scheduleEstimate);
 });
 
 // ── Output Folder ──────────────────────────────────────────────────────
@@ -87,6 +106,46 @@ outputFolder.addEventListener('click', async () => {
   }
 });
 
+// ── Watch Folder ───────────────────────────────────────────────────────
+
+// Anything dropped into the watched folder is converted with the options
+// set when watching started
+watchBtn.addEventListener('click', async () => {
+  if (watchedDir) {
+    await invoke('stop_watching');
+    watchedDir = null;
+    watchBtn.textContent = 'Watch a folder…';
+    watchStatus.classList.add('hidden');
+    return;
+  }
+  try {
+    const dir = await dialogOpen({ directory: true, title: 'Select Folder to Watch' });
+    if (!dir) return;
+    await invoke('watch_folder', { inputDir: dir, options: buildOptions() });
+    watchedDir = dir;
+    watchCount = 0;
+    watchBtn.textContent = 'Stop watching';
+    watchStatus.textContent = `Watching ${dir.split('/').slice(-2).join('/')}`;
+    watchStatus.classList.remove('hidden');
+  } catch (e) {
+    watchStatus.innerHTML = `<span class="watch-error">${e}</span>`;
+    watchStatus.classList.remove('hidden');
+  }
+});
+
+listen('watch-converted', (event) => {
+  if (!watchedDir) return;
+  const r = event.payload;
+  const name = r.source.split('/').pop();
+  if (r.success) watchCount++;
+  watchStatus.innerHTML = `Watching ${watchedDir.split('/').slice(-2).join('/')} — ${watchCount} converted<br>` +
+    (r.success ? `✓ ${name}` : `<span class="watch-error">✗ ${name}: ${r.error}</span>`);
+});
+
+listen('watch-error', (event) => {
+  watchStatus.innerHTML = `<span class="watch-error">${event.payload}</span>`;
+});
+
 // ── Drop Zone ──────────────────────────────────────────────────────────
 
 dropZone.addEventListener('click', () => fileInput.click());
@@ -125,7 +184,7 @@ async function loadFiles(files) {
   imageGrid.innerHTML = '<div class="loading" style="text-align:center;padding:40px;color:var(--fg-dim)">Loading images…</div>';
 
   try {
-    const newImages = await invoke('load_images', { paths: validPaths });
+    const newImages = await invoke('load_images', { paths: validPaths, ...THUMB_OPTIONS });
     images = [...images, ...newImages];
     renderGrid();
     updateStats();
@@ -143,6 +202,8 @@ async function loadFiles(files) {
 
 function renderGrid() {
   imageGrid.innerHTML = '';
+  // Vector input has no pixel size of its own, so only then ask for one
+  $('#svgPanel').classList.toggle('hidden', !images.some(i => i.format === 'SVG'));
   
   images.forEach((img, idx) => {
     const card = document.createElement('div');
@@ -156,6 +217,7 @@ function renderGrid() {
         <div class="card-name" title="${img.name}">${img.name}</div>
         <div class="card-meta">
           <span>${img.width}×${img.height}</span>
+          ${img.is_hdr || img.bit_depth > 8 ? `<span class="depth-badge" title="${img.is_hdr ? 'HDR source: enable tone mapping or keep PNG/TIFF output (AVIF is always tone-mapped)' : 'High bit depth: JPEG/WebP output is 8-bit'}">${img.is_hdr ? 'HDR' : img.bit_depth + '-bit'}</span>` : ''}
           <span>${humanSize(img.size_bytes)}</span>
         </div>
       </div>
@@ -197,7 +259,8 @@ async function showPreview(idx) {
   
   $('#previewTitle').textContent = img.name;
   $('#previewOriginal').src = img.thumbnail;
-  $('#previewOrigInfo').textContent = `${img.width}×${img.height} • ${humanSize(img.size_bytes)} • ${img.format}`;
+  $('#previewOrigInfo').textContent = `${img.width}×${img.height} • ${humanSize(img.size_bytes)} • ${img.format}` +
+    (img.is_hdr ? ' • HDR' : img.bit_depth > 8 ? ` • ${img.bit_depth}-bit` : '');
   
   // Load converted preview
   $('#previewConverted').src = '';
@@ -211,19 +274,20 @@ async function showPreview(idx) {
       path: img.path,
       format: selectedFormat,
       quality,
-      maxSize: 600
+      maxSize: 600,
+      flattenBackground: flattenBg.value,
+      toneMap: toneMap.checked
     });
     
     const estimate = await invoke('estimate_size', {
       path: img.path,
-      format: selectedFormat,
-      quality
+      options: buildOptions()
     });
     
     $('#previewConverted').src = preview;
     const savings = ((1 - estimate.estimated_bytes / img.size_bytes) * 100).toFixed(1);
     const savingsClass = savings > 0 ? 'positive' : 'negative';
-    $('#previewConvInfo').innerHTML = `${selectedFormat} • ${humanSize(estimate.estimated_bytes)} • <span class="${savingsClass}">${savings > 0 ? '-' : '+'}${Math.abs(savings)}%</span>`;
+    $('#previewConvInfo').innerHTML = `${selectedFormat} • ${estimate.width}×${estimate.height} • ${humanSize(estimate.estimated_bytes)} • <span class="${savingsClass}">${savings > 0 ? '-' : '+'}${Math.abs(savings)}%</span>`;
   } catch (e) {
     $('#previewConvInfo').textContent = 'Preview failed: ' + e;
   }
@@ -241,6 +305,61 @@ document.addEventListener('keydown', (e) => {
   if (e.key === 'Escape') previewOverlay.classList.add('hidden');
 });
 
+// ── Duplicates ─────────────────────────────────────────────────────────
+
+findDupesBtn.addEventListener('click', findDuplicates);
+$('#dupesClose').addEventListener('click', () => dupesOverlay.classList.add('hidden'));
+
+async function findDuplicates() {
+  findDupesBtn.disabled = true;
+  findDupesBtn.textContent = 'Scanning…';
+  try {
+    const groups = await invoke('find_duplicates', { paths: images.map(i => i.path), ...THUMB_OPTIONS });
+    renderDuplicates(groups);
+    dupesOverlay.classList.remove('hidden');
+  } catch (e) {
+    console.error('Duplicate scan error:', e);
+  } finally {
+    findDupesBtn.textContent = 'Find duplicates';
+    updateConvertBtn();
+  }
+}
+
+function renderDuplicates(groups) {
+  $('#dupesTitle').textContent = groups.length
+    ? `${groups.length} duplicate group${groups.length !== 1 ? 's' : ''}`
+    : 'No duplicates found';
+  $('#dupesList').innerHTML = groups.map(g => `
+    <div class="dupe-group">
+      <div class="dupe-kind ${g.kind}">${g.kind === 'exact' ? 'Identical files' : 'Visually similar'}</div>
+      <div class="dupe-entries">
+        ${g.entries.map(e => `
+          <div class="dupe-entry" data-path="${e.path}">
+            <img src="${e.thumbnail}" alt="${e.name}" />
+            <div class="card-name" title="${e.path}">${e.name}</div>
+            <div class="card-meta">
+              <span>${e.width}×${e.height} • ${humanSize(e.size_bytes)}</span>
+              <span>${e.exact_duplicate ? 'identical' : 'Δ' + e.distance}</span>
+            </div>
+            <button class="secondary-btn dupe-remove">Skip</button>
+          </div>
+        `).join('')}
+      </div>
+    </div>
+  `).join('');
+
+  $$('.dupe-remove').forEach(btn => btn.addEventListener('click', () => {
+    const entry = btn.closest('.dupe-entry');
+    const idx = images.findIndex(i => i.path === entry.dataset.path);
+    if (idx >= 0) images.splice(idx, 1);
+    entry.classList.add('skipped');
+    btn.disabled = true;
+    renderGrid();
+    updateStats();
+    updateConvertBtn();
+  }));
+}
+
 // ── Size Estimate ──────────────────────────────────────────────────────
 
 function scheduleEstimate() {
@@ -259,13 +378,15 @@ async function doEstimate() {
   try {
     const est = await invoke('estimate_size', {
       path: img.path,
-      format: selectedFormat,
-      quality: parseInt(qualitySlider.value)
+      options: buildOptions()
     });
     
     const savings = ((1 - est.estimated_bytes / img.size_bytes) * 100).toFixed(1);
     sizeEstimate.classList.remove('loading');
     sizeEstimate.innerHTML = `≈ ${humanSize(est.estimated_bytes)} <span style="color:${savings > 0 ? 'var(--success)' : 'var(--warning)'}">(${savings > 0 ? '-' : '+'}${Math.abs(savings)}%)</span>`;
+    if (est.width !== img.width || est.height !== img.height) {
+      sizeEstimate.innerHTML += ` · will become ${est.width}×${est.height}`;
+    }
   } catch (e) {
     sizeEstimate.classList.remove('loading');
     sizeEstimate.textContent = 'Estimate failed';
@@ -276,11 +397,9 @@ async function doEstimate() {
 
 convertBtn.addEventListener('click', startConversion);
 
-async function startConversion() {
-  if (!images.length || !outputDir) return;
-  
+function buildOptions() {
   const mode = resizeMode.value;
-  const options = {
+  return {
     output_format: selectedFormat,
     quality: parseInt(qualitySlider.value),
     resize_mode: mode,
@@ -288,9 +407,20 @@ async function startConversion() {
     resize_height: mode === 'pixels' || mode === 'fit' ? parseInt($('#resizeH').value) || null : null,
     resize_percent: mode === 'percent' ? parseFloat($('#resizePct').value) || null : null,
     strip_metadata: stripMeta.checked,
-    output_dir: outputDir,
+    flatten_background: flattenBg.value,
+    tone_map: toneMap.checked,
+    render_width: parseInt($('#renderW').value) || null,
+    render_height: parseInt($('#renderH').value) || null,
+    render_scale: parseFloat($('#renderScale').value) || null,
+    output_dir: outputDir || '',
     filename_template: filenameTemplate.value || '{name}',
   };
+}
+
+async function startConversion() {
+  if (!images.length || !outputDir) return;
+  
+  const options = buildOptions();
   
   // Show progress
   convertBtn.disabled = true;
@@ -397,6 +527,8 @@ function updateStats() {
 
 function updateConvertBtn() {
   convertBtn.disabled = !images.length || !outputDir;
+  findDupesBtn.disabled = images.length < 2;
+  watchBtn.disabled = !outputDir && !watchedDir;
 }
 
 // ── Drag & Drop from OS (Tauri file drop) ──────────────────────────────
@@ -1,6 +1,7 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::{Arc, Mutex};
 use tauri::State;
@@ -16,8 +17,14 @@ pub struct ConversionJob {
     pub format: String,
     pub quality: String,
     pub progress: f64,
-    pub status: String, // "pending", "running", "done", "error", "cancelled"
+    pub status: String, // "pending", "running", "done", "error", "cancelled", "interrupted"
     pub error: Option<String>,
+    // Input duration as reported by ffmpeg, used to tell a finished output from a partial one
+    #[serde(default)]
+    pub expected_duration: Option<f64>,
+    // Last LOG_TAIL_LINES lines of ffmpeg stderr, filled in when the job ends
+    #[serde(default)]
+    pub log_tail: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,11 +34,213 @@ pub struct ConvertRequest {
     pub quality: String, // "low", "medium", "high", "lossless"
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub version: Option<String>,
+    pub install_hint: String,
+}
+
 struct AppState {
     jobs: Arc<Mutex<HashMap<String, ConversionJob>>>,
     cancel_flags: Arc<Mutex<HashMap<String, bool>>>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    ffmpeg_path: Option<String>,
+}
+
+fn dirs_next() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join("Library/Application Support/com.core-tools.media-converter"))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var("APPDATA").ok().map(|a| PathBuf::from(a).join("com.core-tools.media-converter"))
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config/com.core-tools.media-converter"))
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs_next().map(|d| d.join("settings.json"))
+}
+
+fn load_settings() -> Settings {
+    settings_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) -> Result<(), String> {
+    let path = settings_path().ok_or("Cannot determine settings folder")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create settings folder: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+// ─── Job history ───
+//
+// Jobs are written to jobs.json on every status change so a crash mid-batch
+// can be detected and cleaned up on the next launch.
+
+fn jobs_path() -> Option<PathBuf> {
+    dirs_next().map(|d| d.join("jobs.json"))
+}
+
+fn load_jobs() -> HashMap<String, ConversionJob> {
+    jobs_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_jobs(jobs: &HashMap<String, ConversionJob>) {
+    let Some(path) = jobs_path() else { return };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(jobs) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+// ─── Job logs ───
+//
+// Full stderr is streamed to logs/<id>.log instead of memory; it is kept only
+// when the job fails.
+
+const LOG_TAIL_LINES: usize = 50;
+
+fn job_log_path(job_id: &str) -> Option<PathBuf> {
+    dirs_next().map(|d| d.join("logs").join(format!("{}.log", job_id)))
+}
+
+fn open_job_log(job_id: &str) -> Option<std::fs::File> {
+    let path = job_log_path(job_id)?;
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    std::fs::File::create(path).ok()
+}
+
+fn remove_job_log(job_id: &str) {
+    if let Some(path) = job_log_path(job_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn push_tail(tail: &mut VecDeque<String>, line: &str) {
+    // ffmpeg separates its stats updates with \r
+    for segment in line.split('\r').map(str::trim_end).filter(|l| !l.is_empty()) {
+        if tail.len() == LOG_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(segment.to_string());
+    }
+}
+
+fn probe_duration(path: &str) -> Option<f64> {
+    // ffmpeg exits non-zero without an output file but still prints the header
+    let output = std::process::Command::new(ffmpeg_bin())
+        .args(["-hide_banner", "-i", path])
+        .output()
+        .ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let re = Regex::new(r"Duration:\s*(\d+:\d+:\d+\.\d+)").ok()?;
+    parse_duration(&re.captures(&stderr)?[1])
+}
+
+fn output_complete(job: &ConversionJob) -> bool {
+    let Some(expected) = job.expected_duration else { return false };
+    if !Path::new(&job.output_path).exists() {
+        return false;
+    }
+    match probe_duration(&job.output_path) {
+        Some(actual) => (actual - expected).abs() <= (expected * 0.01).max(1.0),
+        None => false,
+    }
+}
+
+// Jobs still marked running were cut off by a crash or forced quit
+fn recover_jobs() -> HashMap<String, ConversionJob> {
+    let mut jobs = load_jobs();
+    let mut changed = false;
+
+    for job in jobs.values_mut() {
+        if job.status != "running" && job.status != "pending" {
+            continue;
+        }
+        changed = true;
+        if output_complete(job) {
+            job.status = "done".to_string();
+            job.progress = 100.0;
+        } else {
+            let _ = std::fs::remove_file(&job.output_path);
+            job.status = "interrupted".to_string();
+            job.progress = 0.0;
+            job.error = Some("Interrupted before finishing; partial output removed".to_string());
+        }
+    }
+
+    if changed {
+        save_jobs(&jobs);
+    }
+    jobs
+}
+
+// User-configured binary if it still exists, otherwise whatever is on PATH
+fn ffmpeg_bin() -> String {
+    load_settings()
+        .ffmpeg_path
+        .filter(|p| Path::new(p).exists())
+        .unwrap_or_else(|| "ffmpeg".to_string())
+}
+
+fn ffmpeg_version(bin: &str) -> Result<String, String> {
+    let output = std::process::Command::new(bin)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("Cannot run {}: {}", bin, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next().unwrap_or("");
+    if !output.status.success() || !first_line.starts_with("ffmpeg version") {
+        return Err(format!("{} is not an ffmpeg binary", bin));
+    }
+    Ok(first_line.to_string())
+}
+
+fn install_hint(tool: &str) -> String {
+    let hint = match tool {
+        "ffmpeg" => {
+            if cfg!(target_os = "macos") {
+                "brew install ffmpeg"
+            } else if cfg!(windows) {
+                "choco install ffmpeg"
+            } else {
+                "sudo apt install ffmpeg"
+            }
+        }
+        _ => return format!("Install {} and make sure it is on your PATH", tool),
+    };
+    format!("Install it with: {}", hint)
+}
+
+// A missing binary gets the install hint instead of a bare "not found"
+fn spawn_error(tool: &str, e: std::io::Error) -> String {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        format!("{} not found. {}", tool, install_hint(tool))
+    } else {
+        format!("Failed to start {}: {}", tool, e)
+    }
+}
+
 fn get_ffmpeg_args(input: &str, output: &str, format: &str, quality: &str) -> Vec<String> {
     let mut args = vec!["-i".to_string(), input.to_string(), "-y".to_string()];
 
@@ -109,11 +318,97 @@ fn parse_duration(s: &str) -> Option<f64> {
     Some(h * 3600.0 + m * 60.0 + s_val + cs / 100.0)
 }
 
+// Returns (has_video, has_audio); cover art is not counted as video
+async fn probe_streams(path: &str) -> Result<(bool, bool), String> {
+    let output = Command::new(ffmpeg_bin())
+        .args(["-hide_banner", "-i", path])
+        .output()
+        .await
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let streams: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("Stream #"))
+        .collect();
+    let has_video = streams
+        .iter()
+        .any(|l| l.contains("Video:") && !l.contains("(attached pic)"));
+    let has_audio = streams.iter().any(|l| l.contains("Audio:"));
+    Ok((has_video, has_audio))
+}
+
+// Catch impossible requests before a job is created instead of letting ffmpeg fail later
+async fn validate_request(request: &ConvertRequest) -> Result<(), String> {
+    let names = |kind: &str| {
+        SUPPORTED_FORMATS
+            .iter()
+            .filter(|(_, _, k)| kind.is_empty() || *k == kind)
+            .map(|(ext, _, _)| *ext)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let kind = SUPPORTED_FORMATS
+        .iter()
+        .find(|(ext, _, _)| *ext == request.output_format)
+        .map(|(_, _, kind)| *kind)
+        .ok_or_else(|| {
+            format!(
+                "Unsupported output format '{}'. Supported: {}",
+                request.output_format,
+                names("")
+            )
+        })?;
+
+    if !QUALITIES.contains(&request.quality.as_str()) {
+        return Err(format!(
+            "Unknown quality '{}'. Expected one of: {}",
+            request.quality,
+            QUALITIES.join(", ")
+        ));
+    }
+
+    if !Path::new(&request.input_path).is_file() {
+        return Err(format!("Input file not found: {}", request.input_path));
+    }
+
+    let name = Path::new(&request.input_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| request.input_path.clone());
+    let (has_video, has_audio) = probe_streams(&request.input_path).await?;
+
+    if !has_video && !has_audio {
+        return Err(format!("{} has no audio or video streams ffmpeg can read", name));
+    }
+    if kind == "video" && !has_video {
+        return Err(format!(
+            "{} is audio-only and can't be converted to {}. Choose an audio format: {}",
+            name,
+            request.output_format.to_uppercase(),
+            names("audio")
+        ));
+    }
+    if kind == "audio" && !has_audio {
+        return Err(format!(
+            "{} has no audio track to extract as {}",
+            name,
+            request.output_format.to_uppercase()
+        ));
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 async fn start_conversion(
     request: ConvertRequest,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
+    validate_request(&request).await?;
+
     let job_id = Uuid::new_v4().to_string();
 
     let ext = &request.output_format;
@@ -124,34 +419,63 @@ async fn start_conversion(
     let job = ConversionJob {
         id: job_id.clone(),
         input_path: request.input_path.clone(),
-        output_path: output_path.clone(),
+        output_path,
         format: request.output_format.clone(),
         quality: request.quality.clone(),
         progress: 0.0,
         status: "running".to_string(),
         error: None,
+        expected_duration: None,
+        log_tail: None,
     };
 
+    spawn_job(job, &state);
+    Ok(job_id)
+}
+
+// Re-queue an interrupted or failed job from scratch under the same id
+#[tauri::command]
+async fn resume(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let job = {
+        let jobs = state.jobs.lock().unwrap();
+        let job = jobs.get(&job_id).ok_or("Job not found")?;
+        if matches!(job.status.as_str(), "running" | "pending" | "done") {
+            return Err(format!("Job is {}, nothing to resume", job.status));
+        }
+        if !Path::new(&job.input_path).exists() {
+            return Err(format!("Input file no longer exists: {}", job.input_path));
+        }
+        ConversionJob {
+            progress: 0.0,
+            status: "running".to_string(),
+            error: None,
+            log_tail: None,
+            ..job.clone()
+        }
+    };
+
+    spawn_job(job, &state);
+    Ok(())
+}
+
+fn spawn_job(job: ConversionJob, state: &AppState) {
+    let args = get_ffmpeg_args(&job.input_path, &job.output_path, &job.format, &job.quality);
+    let jid = job.id.clone();
+    let output_path = job.output_path.clone();
+
     {
         let mut jobs = state.jobs.lock().unwrap();
-        jobs.insert(job_id.clone(), job);
+        jobs.insert(jid.clone(), job);
+        save_jobs(&jobs);
         let mut flags = state.cancel_flags.lock().unwrap();
-        flags.insert(job_id.clone(), false);
+        flags.insert(jid.clone(), false);
     }
 
-    let args = get_ffmpeg_args(
-        &request.input_path,
-        &output_path,
-        &request.output_format,
-        &request.quality,
-    );
-
-    let jid = job_id.clone();
     let jobs_ref = state.jobs.clone();
     let flags_ref = state.cancel_flags.clone();
 
     tokio::spawn(async move {
-        let result = Command::new("ffmpeg")
+        let result = Command::new(ffmpeg_bin())
             .args(&args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -163,8 +487,9 @@ async fn start_conversion(
                 let mut jobs = jobs_ref.lock().unwrap();
                 if let Some(job) = jobs.get_mut(&jid) {
                     job.status = "error".to_string();
-                    job.error = Some(format!("Failed to start ffmpeg: {}", e));
+                    job.error = Some(spawn_error("ffmpeg", e));
                 }
+                save_jobs(&jobs);
                 return;
             }
         };
@@ -172,6 +497,8 @@ async fn start_conversion(
         let stderr = child.stderr.take().unwrap();
         let reader = BufReader::new(stderr);
         let mut lines = reader.lines();
+        let mut log_file = open_job_log(&jid);
+        let mut tail = VecDeque::with_capacity(LOG_TAIL_LINES);
         let mut duration: Option<f64> = None;
         let time_re = Regex::new(r"time=(\d+:\d+:\d+\.\d+)").unwrap();
         let dur_re = Regex::new(r"Duration:\s*(\d+:\d+:\d+\.\d+)").unwrap();
@@ -184,18 +511,31 @@ async fn start_conversion(
             };
             if should_cancel {
                 let _ = child.kill().await;
+                let _ = std::fs::remove_file(&output_path);
+                remove_job_log(&jid);
                 let mut jobs = jobs_ref.lock().unwrap();
                 if let Some(job) = jobs.get_mut(&jid) {
                     job.status = "cancelled".to_string();
                 }
+                save_jobs(&jobs);
                 return;
             }
 
             match lines.next_line().await {
                 Ok(Some(line)) => {
+                    if let Some(file) = log_file.as_mut() {
+                        use std::io::Write;
+                        let _ = writeln!(file, "{}", line);
+                    }
+                    push_tail(&mut tail, &line);
                     if duration.is_none() {
                         if let Some(caps) = dur_re.captures(&line) {
                             duration = parse_duration(&caps[1]);
+                            let mut jobs = jobs_ref.lock().unwrap();
+                            if let Some(job) = jobs.get_mut(&jid) {
+                                job.expected_duration = duration;
+                            }
+                            save_jobs(&jobs);
                         }
                     }
                     if let Some(caps) = time_re.captures(&line) {
@@ -216,11 +556,13 @@ async fn start_conversion(
         }
 
         let status = child.wait().await;
+        drop(log_file);
         let mut jobs = jobs_ref.lock().unwrap();
         if let Some(job) = jobs.get_mut(&jid) {
             if job.status == "cancelled" {
                 return;
             }
+            job.log_tail = Some(Vec::from(tail).join("\n"));
             match status {
                 Ok(s) if s.success() => {
                     job.status = "done".to_string();
@@ -235,10 +577,15 @@ async fn start_conversion(
                     job.error = Some(format!("Process error: {}", e));
                 }
             }
+            // Don't leave a truncated file behind; keep the log for diagnosis
+            if job.status == "error" {
+                let _ = std::fs::remove_file(&output_path);
+            } else {
+                remove_job_log(&jid);
+            }
         }
+        save_jobs(&jobs);
     });
-
-    Ok(job_id)
 }
 
 #[tauri::command]
@@ -257,27 +604,85 @@ async fn cancel_job(job_id: String, state: State<'_, AppState>) -> Result<(), St
 #[tauri::command]
 async fn clear_completed(state: State<'_, AppState>) -> Result<(), String> {
     let mut jobs = state.jobs.lock().unwrap();
-    jobs.retain(|_, j| j.status == "running" || j.status == "pending");
+    jobs.retain(|id, j| {
+        let keep = j.status == "running" || j.status == "pending";
+        if !keep {
+            remove_job_log(id);
+        }
+        keep
+    });
+    save_jobs(&jobs);
     Ok(())
 }
 
+// Full stderr for failed jobs, or the captured tail when no log file is kept
+#[tauri::command]
+async fn get_job_log(id: String, state: State<'_, AppState>) -> Result<String, String> {
+    // Only ids of real jobs reach the filesystem
+    let log_tail = {
+        let jobs = state.jobs.lock().unwrap();
+        jobs.get(&id).ok_or("Job not found")?.log_tail.clone()
+    };
+    if let Some(log) = job_log_path(&id).and_then(|p| std::fs::read_to_string(p).ok()) {
+        return Ok(log);
+    }
+    log_tail.ok_or_else(|| "No log captured for this job".to_string())
+}
+
+#[tauri::command]
+async fn check_all_tools() -> Vec<ToolStatus> {
+    let version = ffmpeg_version(&ffmpeg_bin()).ok();
+    vec![ToolStatus {
+        name: "ffmpeg".to_string(),
+        found: version.is_some(),
+        version,
+        install_hint: install_hint("ffmpeg"),
+    }]
+}
+
+#[tauri::command]
+async fn get_ffmpeg_path() -> Result<String, String> {
+    Ok(ffmpeg_bin())
+}
+
+// An empty path clears the setting and falls back to auto-detection
+#[tauri::command]
+async fn set_ffmpeg_path(path: String) -> Result<String, String> {
+    let path = path.trim().to_string();
+    let mut settings = load_settings();
+    if path.is_empty() {
+        settings.ffmpeg_path = None;
+        save_settings(&settings)?;
+        return ffmpeg_version(&ffmpeg_bin());
+    }
+    let version = ffmpeg_version(&path)?;
+    settings.ffmpeg_path = Some(path);
+    save_settings(&settings)?;
+    Ok(version)
+}
+
+// (extension, label, kind)
+const SUPPORTED_FORMATS: &[(&str, &str, &str)] = &[
+    ("mp4", "MP4", "video"),
+    ("mkv", "MKV", "video"),
+    ("avi", "AVI", "video"),
+    ("mov", "MOV", "video"),
+    ("webm", "WebM", "video"),
+    ("mp3", "MP3", "audio"),
+    ("wav", "WAV", "audio"),
+    ("flac", "FLAC", "audio"),
+    ("aac", "AAC", "audio"),
+    ("ogg", "OGG", "audio"),
+];
+
+const QUALITIES: &[&str] = &["low", "medium", "high", "lossless"];
+
 #[tauri::command]
 fn get_supported_formats() -> Vec<serde_json::Value> {
-    serde_json::from_str(
-        r#"[
-        {"ext":"mp4","label":"MP4","type":"video"},
-        {"ext":"mkv","label":"MKV","type":"video"},
-        {"ext":"avi","label":"AVI","type":"video"},
-        {"ext":"mov","label":"MOV","type":"video"},
-        {"ext":"webm","label":"WebM","type":"video"},
-        {"ext":"mp3","label":"MP3","type":"audio"},
-        {"ext":"wav","label":"WAV","type":"audio"},
-        {"ext":"flac","label":"FLAC","type":"audio"},
-        {"ext":"aac","label":"AAC","type":"audio"},
-        {"ext":"ogg","label":"OGG","type":"audio"}
-    ]"#,
-    )
-    .unwrap()
+    SUPPORTED_FORMATS
+        .iter()
+        .map(|(ext, label, kind)| serde_json::json!({ "ext": ext, "label": label, "type": kind }))
+        .collect()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -285,15 +690,20 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(AppState {
-            jobs: Arc::new(Mutex::new(HashMap::new())),
+            jobs: Arc::new(Mutex::new(recover_jobs())),
             cancel_flags: Arc::new(Mutex::new(HashMap::new())),
         })
         .invoke_handler(tauri::generate_handler![
             start_conversion,
+            resume,
             get_jobs,
             cancel_job,
             clear_completed,
+            get_job_log,
             get_supported_formats,
+            get_ffmpeg_path,
+            set_ffmpeg_path,
+            check_all_tools,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -45,6 +45,7 @@ async function handleFiles(files) {
       });
     } catch (err) {
       console.error('Conversion error:', err);
+      alert(`${file.name}: ${err}`);
     }
   }
 }
@@ -57,6 +58,29 @@ async function cancelJob(id) {
   await invoke('cancel_job', { jobId: id });
 }
 
+async function resumeJob(id) {
+  try {
+    await invoke('resume', { jobId: id });
+  } catch (err) {
+    console.error('Resume error:', err);
+  }
+}
+
+const logPanel = document.getElementById('logPanel');
+
+async function showLog(id) {
+  try {
+    document.getElementById('logText').textContent = await invoke('get_job_log', { id });
+    logPanel.hidden = false;
+  } catch (err) {
+    console.error('Log error:', err);
+  }
+}
+
+document.getElementById('logClose').addEventListener('click', () => {
+  logPanel.hidden = true;
+});
+
 function renderJobs(jobs) {
   jobList.innerHTML = jobs.map(job => {
     const name = job.input_path.split('/').pop().split('\\').pop();
@@ -66,9 +90,18 @@ function renderJobs(jobs) {
       : job.status === 'done' ? '✓ Done'
       : job.status === 'error' ? '✗ Error'
       : job.status === 'cancelled' ? '⊘ Cancelled'
+      : job.status === 'interrupted' ? '⚠ Interrupted'
       : 'Pending';
     const cancelBtn = job.status === 'running'
       ? `<button class="btn-cancel" onclick="cancelJob('${job.id}')">Cancel</button>`
+      : ['interrupted', 'error', 'cancelled'].includes(job.status)
+      ? `<button class="btn-cancel" onclick="resumeJob('${job.id}')">Retry</button>`
+      : '';
+    const logBtn = job.log_tail
+      ? `<button class="btn-cancel" onclick="showLog('${job.id}')">Log</button>`
+      : '';
+    const errorLine = job.error
+      ? `<div class="job-error" title="${job.error}">${job.error}</div>`
       : '';
 
     return `
@@ -76,17 +109,33 @@ function renderJobs(jobs) {
         <div class="job-info">
           <div class="job-name">${name}</div>
           <div class="job-detail">→ ${job.format.toUpperCase()} · ${job.quality}</div>
+          ${errorLine}
         </div>
         <div class="progress-bar">
           <div class="progress-fill" style="width:${pct}%"></div>
         </div>
         <div class="job-status ${statusClass}">${statusText}</div>
         ${cancelBtn}
+        ${logBtn}
       </div>
     `;
   }).join('');
 }
 
+// Missing tools are named up front with their install command
+(async () => {
+  try {
+    const missing = (await invoke('check_all_tools')).filter(t => !t.found);
+    if (missing.length) {
+      const warning = document.getElementById('toolWarning');
+      warning.textContent = missing.map(t => `${t.name} not found. ${t.install_hint}`).join(' · ');
+      warning.hidden = false;
+    }
+  } catch (e) {
+    console.warn('Tool check failed:', e);
+  }
+})();
+
 // Poll jobs
 setInterval(async () => {
   try {
@@ -0,0 +1,157 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::process::Output;
+
+// How much of a failed tool's stderr makes it into the error
+const STDERR_TAIL_LINES: usize = 8;
+
+/// Error returned by the commands. Serializes to `{ kind, message, ... }` so
+/// the frontend can react per kind; `message` is the old plain-string error.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{tool} not found. {hint}")]
+    ToolNotFound { tool: String, hint: String },
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{tool} failed{}", exit_detail(*.code, .stderr_tail))]
+    ProcessFailed {
+        tool: String,
+        code: Option<i32>,
+        stderr_tail: String,
+    },
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Other(String),
+}
+
+fn exit_detail(code: Option<i32>, stderr_tail: &str) -> String {
+    let code = code.map(|c| format!(" with exit code {}", c)).unwrap_or_default();
+    match stderr_tail.lines().last() {
+        Some(last) => format!("{}: {}", code, last),
+        None => code,
+    }
+}
+
+impl AppError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        AppError::InvalidInput(message.into())
+    }
+
+    pub fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        AppError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn process_failed(tool: &str, output: &Output) -> Self {
+        AppError::ProcessFailed {
+            tool: tool.to_string(),
+            code: output.status.code(),
+            stderr_tail: stderr_tail(&output.stderr),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::ToolNotFound { .. } => "tool_not_found",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::ProcessFailed { .. } => "process_failed",
+            AppError::Io { .. } => "io",
+            AppError::Cancelled => "cancelled",
+            AppError::Other(_) => "other",
+        }
+    }
+}
+
+pub fn stderr_tail(stderr: &[u8]) -> String {
+    let text = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            AppError::ToolNotFound { tool, hint } => {
+                map.serialize_entry("tool", tool)?;
+                map.serialize_entry("hint", hint)?;
+            }
+            AppError::ProcessFailed {
+                tool,
+                code,
+                stderr_tail,
+            } => {
+                map.serialize_entry("tool", tool)?;
+                map.serialize_entry("code", code)?;
+                map.serialize_entry("stderr_tail", stderr_tail)?;
+            }
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_kind_and_message() {
+        let err = AppError::ToolNotFound {
+            tool: "ffmpeg".into(),
+            hint: "Install it with: brew install ffmpeg".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "kind": "tool_not_found",
+                "message": "ffmpeg not found. Install it with: brew install ffmpeg",
+                "tool": "ffmpeg",
+                "hint": "Install it with: brew install ffmpeg",
+            })
+        );
+
+        let err = AppError::invalid("Unsupported frame format: gif");
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["kind"], "invalid_input");
+        assert_eq!(value["message"], "Unsupported frame format: gif");
+    }
+
+    #[test]
+    fn process_failure_keeps_stderr_tail() {
+        let stderr: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let err = AppError::ProcessFailed {
+            tool: "ffmpeg".into(),
+            code: Some(1),
+            stderr_tail: stderr_tail(stderr.as_bytes()),
+        };
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["kind"], "process_failed");
+        assert_eq!(value["code"], 1);
+        assert_eq!(value["stderr_tail"].as_str().unwrap().lines().count(), STDERR_TAIL_LINES);
+        assert_eq!(value["message"], "ffmpeg failed with exit code 1: line 20");
+    }
+}
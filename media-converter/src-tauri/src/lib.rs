@@ -6,10 +6,13 @@ use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
 use uuid::Uuid;
 use regex::Regex;
 
+mod error;
+use error::AppError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConvertRequest {
     pub file_path: String,
@@ -20,6 +23,37 @@ pub struct ConvertRequest {
     pub bitrate: Option<String>,
     pub resolution: Option<String>,
     pub sample_rate: Option<String>,
+    /// Two ffmpeg passes for a more exact `bitrate` (video only)
+    #[serde(default)]
+    pub two_pass: Option<bool>,
+    /// "videotoolbox", "nvenc" or "none"; used for H.264 video without a codec override
+    #[serde(default)]
+    pub hw_accel: Option<String>,
+    /// Copy the source's tags (title, artist...) and chapters. Container
+    /// metadata is written by the muxer, so this works for stream copies and
+    /// re-encodes alike.
+    #[serde(default)]
+    pub keep_metadata: Option<bool>,
+    /// Passed to ffmpeg as-is, one argv entry each, just before the output
+    /// path. A `-vf` here replaces the resolution filter.
+    #[serde(default)]
+    pub extra_args: Option<Vec<String>>,
+    /// Convert only part of the input, in seconds. Either end may be left open.
+    #[serde(default)]
+    pub start_time: Option<f64>,
+    #[serde(default)]
+    pub end_time: Option<f64>,
+    /// Subtitle file (SRT, ASS...) to burn into video output
+    #[serde(default)]
+    pub subtitle_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub version: Option<String>,
+    pub install_hint: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -33,6 +67,23 @@ pub struct FileInfo {
     pub codec: String,
     pub resolution: String,
     pub bitrate: String,
+    pub streams: Vec<StreamInfo>,
+    pub is_hdr: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub codec_type: String, // "video", "audio", "subtitle", "data"...
+    pub codec_name: String,
+    pub language: Option<String>,
+    pub channels: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameExtraction {
+    pub frames: Vec<String>,
+    pub count: usize,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -40,32 +91,235 @@ pub struct ProgressEvent {
     pub job_id: String,
     pub file_name: String,
     pub progress: f64,
-    pub status: String, // "converting", "done", "error", "cancelled"
+    pub status: String, // "queued", "converting", "done", "error", "cancelled"
     pub message: String,
+    /// Encoding speed as a multiple of realtime; 0 when unknown
+    pub speed: f64,
+    pub eta_seconds: u64,
 }
 
+// Conversions allowed to run at once until a batch asks for something else
+const DEFAULT_MAX_PARALLEL: usize = 2;
+
 struct AppState {
     jobs: Mutex<HashMap<String, tokio::sync::watch::Sender<bool>>>,
+    // One permit per ffmpeg process allowed to run
+    slots: Arc<Semaphore>,
+    slot_limit: Mutex<SlotLimit>,
+    // Jobs waiting for a slot, oldest first, with their display names
+    queue: Mutex<Vec<(String, String)>>,
+    // `ffmpeg -encoders` for the binary it was probed with
+    encoders: Mutex<Option<(String, Vec<String>)>>,
 }
 
-#[tauri::command]
-async fn check_ffmpeg() -> Result<String, String> {
-    let output = std::process::Command::new("ffmpeg")
+struct SlotLimit {
+    max: usize,
+    // Permits still held by running jobs that must not be handed back
+    // after the limit was lowered
+    owed: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Settings {
+    ffmpeg_path: Option<String>,
+}
+
+fn dirs_next() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join("Library/Application Support/systems.core.media-converter"))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        std::env::var("APPDATA").ok().map(|a| PathBuf::from(a).join("CORE Media Converter"))
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        std::env::var("HOME").ok().map(|h| PathBuf::from(h).join(".config/core-media-converter"))
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs_next().map(|d| d.join("settings.json"))
+}
+
+fn load_settings() -> Settings {
+    settings_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) -> Result<(), AppError> {
+    let path = settings_path().ok_or("Cannot determine settings folder")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| AppError::io("Failed to create settings folder", e))?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| AppError::io("Failed to save settings", e))
+}
+
+// User-configured binary if it still exists, otherwise whatever is on PATH
+fn ffmpeg_bin() -> String {
+    load_settings()
+        .ffmpeg_path
+        .filter(|p| std::path::Path::new(p).exists())
+        .unwrap_or_else(|| "ffmpeg".to_string())
+}
+
+// ffprobe ships next to ffmpeg, so prefer the sibling of the configured binary
+fn ffprobe_bin() -> String {
+    let name = if cfg!(windows) { "ffprobe.exe" } else { "ffprobe" };
+    load_settings()
+        .ffmpeg_path
+        .map(|p| std::path::Path::new(&p).with_file_name(name))
+        .filter(|p| p.exists())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "ffprobe".to_string())
+}
+
+fn ffmpeg_version(bin: &str) -> Result<String, AppError> {
+    let output = std::process::Command::new(bin)
         .arg("-version")
-        .output();
-    match output {
-        Ok(o) => {
-            let version = String::from_utf8_lossy(&o.stdout);
-            let first_line = version.lines().next().unwrap_or("ffmpeg found");
-            Ok(first_line.to_string())
+        .output()
+        .map_err(|e| AppError::io(format!("Cannot run {}", bin), e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first_line = stdout.lines().next().unwrap_or("");
+    if !output.status.success() || !first_line.starts_with("ffmpeg version") {
+        return Err(AppError::invalid(format!("{} is not an ffmpeg binary", bin)));
+    }
+    Ok(first_line.to_string())
+}
+
+fn install_hint(tool: &str) -> String {
+    let hint = match tool {
+        "ffmpeg" | "ffprobe" => {
+            if cfg!(target_os = "macos") {
+                "brew install ffmpeg"
+            } else if cfg!(windows) {
+                "choco install ffmpeg"
+            } else {
+                "sudo apt install ffmpeg"
+            }
         }
-        Err(_) => Err("FFmpeg not found in PATH".to_string()),
+        _ => return format!("Install {} and make sure it is on your PATH", tool),
+    };
+    format!("Install it with: {}", hint)
+}
+
+// A missing binary gets the install hint instead of a bare "not found"
+fn spawn_error(tool: &str, e: std::io::Error) -> AppError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        AppError::ToolNotFound {
+            tool: tool.to_string(),
+            hint: install_hint(tool),
+        }
+    } else {
+        AppError::io(format!("Failed to start {}", tool), e)
     }
 }
 
+fn tool_status(name: &str, bin: &str) -> ToolStatus {
+    let version = std::process::Command::new(bin)
+        .arg("-version")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8_lossy(&o.stdout).lines().next().map(String::from));
+    ToolStatus {
+        name: name.to_string(),
+        found: version.is_some(),
+        version,
+        install_hint: install_hint(name),
+    }
+}
+
+#[tauri::command]
+async fn check_ffmpeg() -> Result<String, AppError> {
+    ffmpeg_version(&ffmpeg_bin()).map_err(|_| AppError::ToolNotFound {
+        tool: "FFmpeg".to_string(),
+        hint: install_hint("ffmpeg"),
+    })
+}
+
+#[tauri::command]
+async fn check_all_tools() -> Vec<ToolStatus> {
+    vec![tool_status("ffmpeg", &ffmpeg_bin()), tool_status("ffprobe", &ffprobe_bin())]
+}
+
+// Encoder name fragments that mean the work is done by a GPU or media engine
+const HW_ENCODER_MARKERS: [&str; 5] = ["videotoolbox", "nvenc", "qsv", "vaapi", "amf"];
+
+// Encoder names ffmpeg was built with. Probed once per binary; a failed
+// probe isn't cached so installing ffmpeg later still works.
+async fn ffmpeg_encoders(state: &AppState) -> Vec<String> {
+    let bin = ffmpeg_bin();
+    let mut cache = state.encoders.lock().await;
+    if let Some((probed, encoders)) = cache.as_ref() {
+        if *probed == bin {
+            return encoders.clone();
+        }
+    }
+
+    let output = match Command::new(&bin).args(["-hide_banner", "-encoders"]).output().await {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+    // Listing lines look like " V....D libx264   libx264 H.264 ..." after a "------" rule
+    let encoders: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip_while(|l| !l.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().nth(1))
+        .map(String::from)
+        .collect();
+    *cache = Some((bin, encoders.clone()));
+    encoders
+}
+
+#[tauri::command]
+async fn check_hw_accel(state: State<'_, AppState>) -> Result<Vec<String>, AppError> {
+    Ok(ffmpeg_encoders(&state)
+        .await
+        .into_iter()
+        .filter(|e| HW_ENCODER_MARKERS.iter().any(|m| e.contains(m)))
+        .collect())
+}
+
+// Hardware encoders have no CRF. NVENC's -cq uses about the same scale;
+// VideoToolbox takes a 1-100 quality where higher is better.
+fn video_quality_args(encoder: Option<&str>, crf: u32) -> [String; 2] {
+    match encoder {
+        Some("h264_nvenc") => ["-cq".to_string(), crf.to_string()],
+        Some("h264_videotoolbox") => ["-q:v".to_string(), (100 - crf * 2).to_string()],
+        _ => ["-crf".to_string(), crf.to_string()],
+    }
+}
+
+#[tauri::command]
+async fn get_ffmpeg_path() -> Result<String, AppError> {
+    Ok(ffmpeg_bin())
+}
+
+// An empty path clears the setting and falls back to auto-detection
+#[tauri::command]
+async fn set_ffmpeg_path(path: String) -> Result<String, AppError> {
+    let path = path.trim().to_string();
+    let mut settings = load_settings();
+    if path.is_empty() {
+        settings.ffmpeg_path = None;
+        save_settings(&settings)?;
+        return ffmpeg_version(&ffmpeg_bin());
+    }
+    let version = ffmpeg_version(&path)?;
+    settings.ffmpeg_path = Some(path);
+    save_settings(&settings)?;
+    Ok(version)
+}
+
 #[tauri::command]
-async fn probe_file(path: String) -> Result<FileInfo, String> {
-    let output = std::process::Command::new("ffprobe")
+async fn probe_file(path: String) -> Result<FileInfo, AppError> {
+    let output = std::process::Command::new(ffprobe_bin())
         .args([
             "-v", "quiet",
             "-print_format", "json",
@@ -74,13 +328,19 @@ async fn probe_file(path: String) -> Result<FileInfo, String> {
             &path,
         ])
         .output()
-        .map_err(|e| format!("ffprobe error: {}", e))?;
+        .map_err(|e| spawn_error("ffprobe", e))?;
+    // ffprobe is quiet, so a file it can't read only shows up as the exit code
+    if !output.status.success() {
+        return Err(AppError::process_failed("ffprobe", &output));
+    }
 
     let json: serde_json::Value =
         serde_json::from_slice(&output.stdout).map_err(|e| format!("Parse error: {}", e))?;
 
     let format_info = &json["format"];
-    let streams = json["streams"].as_array().ok_or("No streams")?;
+    let streams = json["streams"]
+        .as_array()
+        .ok_or_else(|| AppError::invalid("No streams"))?;
 
     let video_stream = streams.iter().find(|s| s["codec_type"] == "video");
     let audio_stream = streams.iter().find(|s| s["codec_type"] == "audio");
@@ -135,6 +395,9 @@ async fn probe_file(path: String) -> Result<FileInfo, String> {
         .unwrap_or("unknown")
         .to_string();
 
+    let is_hdr = streams.iter().any(is_hdr_stream);
+    let streams = streams.iter().map(stream_info).collect();
+
     Ok(FileInfo {
         path,
         name: file_name,
@@ -145,14 +408,37 @@ async fn probe_file(path: String) -> Result<FileInfo, String> {
         codec,
         resolution,
         bitrate,
+        streams,
+        is_hdr,
     })
 }
 
+fn stream_info(stream: &serde_json::Value) -> StreamInfo {
+    StreamInfo {
+        index: stream["index"].as_u64().unwrap_or(0) as u32,
+        codec_type: stream["codec_type"].as_str().unwrap_or("unknown").to_string(),
+        codec_name: stream["codec_name"].as_str().unwrap_or("unknown").to_string(),
+        // "und" is how containers spell "not set"
+        language: stream["tags"]["language"]
+            .as_str()
+            .filter(|l| *l != "und")
+            .map(String::from),
+        channels: stream["channels"].as_u64().map(|c| c as u32),
+    }
+}
+
+// PQ (HDR10, Dolby Vision) or HLG transfer, or BT.2020 primaries
+fn is_hdr_stream(stream: &serde_json::Value) -> bool {
+    stream["codec_type"] == "video"
+        && (matches!(stream["color_transfer"].as_str(), Some("smpte2084" | "arib-std-b67"))
+            || stream["color_primaries"] == "bt2020")
+}
+
 #[tauri::command]
-async fn select_output_dir() -> Result<String, String> {
+async fn select_output_dir() -> Result<String, AppError> {
     // Use rfd for native folder dialog
     let result = rfd_pick_folder().await;
-    result.ok_or("No folder selected".to_string())
+    result.ok_or(AppError::Cancelled)
 }
 
 async fn rfd_pick_folder() -> Option<String> {
@@ -166,33 +452,153 @@ async fn convert_file(
     app: AppHandle,
     state: State<'_, AppState>,
     request: ConvertRequest,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
+    validate_request(&request)?;
+    Ok(enqueue_conversion(&app, &state, request).await)
+}
+
+/// Queue several conversions at once, running at most `max_parallel` ffmpeg
+/// processes. The limit also applies to anything queued later.
+#[tauri::command]
+async fn convert_batch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    requests: Vec<ConvertRequest>,
+    max_parallel: usize,
+) -> Result<Vec<String>, AppError> {
+    for request in &requests {
+        validate_request(request)?;
+    }
+    set_max_parallel(&state, max_parallel).await;
+    let mut job_ids = Vec::with_capacity(requests.len());
+    for request in requests {
+        job_ids.push(enqueue_conversion(&app, &state, request).await);
+    }
+    Ok(job_ids)
+}
+
+// Flags run_conversion sets itself; a second copy would break progress or overwriting
+const RESERVED_ARGS: [&str; 3] = ["-i", "-y", "-progress"];
+
+// Checks that can fail before anything is queued
+fn validate_request(request: &ConvertRequest) -> Result<(), AppError> {
+    for arg in request.extra_args.iter().flatten() {
+        if RESERVED_ARGS.contains(&arg.trim()) {
+            return Err(AppError::invalid(format!("Extra arguments can't include {}", arg.trim())));
+        }
+    }
+    for time in [request.start_time, request.end_time].into_iter().flatten() {
+        if !time.is_finite() || time < 0.0 {
+            return Err(AppError::invalid(format!("Invalid trim time: {}", time)));
+        }
+    }
+    if let Some(path) = request.subtitle_path.as_deref().filter(|p| !p.is_empty()) {
+        if !std::path::Path::new(path).is_file() {
+            return Err(AppError::invalid(format!("Subtitle file not found: {}", path)));
+        }
+    }
+    if let (Some(start), Some(end)) = (request.start_time, request.end_time) {
+        if end <= start {
+            return Err(AppError::invalid(format!(
+                "Trim end ({:.2}s) must be after the start ({:.2}s)",
+                end, start
+            )));
+        }
+    }
+    Ok(())
+}
+
+async fn set_max_parallel(state: &AppState, max_parallel: usize) {
+    let wanted = max_parallel.max(1);
+    let mut limit = state.slot_limit.lock().await;
+    if wanted > limit.max {
+        let mut extra = wanted - limit.max;
+        let repaid = extra.min(limit.owed);
+        limit.owed -= repaid;
+        extra -= repaid;
+        state.slots.add_permits(extra);
+    } else if wanted < limit.max {
+        // Idle permits go now; busy ones when their job finishes
+        let excess = limit.max - wanted;
+        limit.owed += excess - state.slots.forget_permits(excess);
+    }
+    limit.max = wanted;
+}
+
+async fn enqueue_conversion(app: &AppHandle, state: &AppState, request: ConvertRequest) -> String {
     let job_id = Uuid::new_v4().to_string();
     let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+    state.jobs.lock().await.insert(job_id.clone(), cancel_tx);
 
-    {
-        let mut jobs = state.jobs.lock().await;
-        jobs.insert(job_id.clone(), cancel_tx);
-    }
+    let display_name = std::path::Path::new(&request.file_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or("file".to_string());
+    state.queue.lock().await.push((job_id.clone(), display_name));
+    emit_queue_positions(app, state).await;
 
-    let job_id_clone = job_id.clone();
     let app_clone = app.clone();
-
+    let job_id_clone = job_id.clone();
     tokio::spawn(async move {
-        run_conversion(app_clone, job_id_clone, request, cancel_rx).await;
+        run_queued(app_clone, job_id_clone, request, cancel_rx).await;
     });
+    job_id
+}
+
+// Wait for a free slot, then convert. Cancelling while queued just leaves the queue.
+async fn run_queued(
+    app: AppHandle,
+    job_id: String,
+    request: ConvertRequest,
+    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+) {
+    let state = app.state::<AppState>();
+    let permit = tokio::select! {
+        permit = Arc::clone(&state.slots).acquire_owned() => permit.ok(),
+        _ = cancel_rx.wait_for(|cancelled| *cancelled) => None,
+    };
 
-    Ok(job_id)
+    let display_name = {
+        let mut queue = state.queue.lock().await;
+        let position = queue.iter().position(|(id, _)| *id == job_id);
+        position.map(|i| queue.remove(i).1).unwrap_or_default()
+    };
+    emit_queue_positions(&app, &state).await;
+
+    match permit {
+        Some(permit) => {
+            run_conversion(app.clone(), job_id.clone(), request, cancel_rx).await;
+            let mut limit = state.slot_limit.lock().await;
+            if limit.owed > 0 {
+                limit.owed -= 1;
+                permit.forget();
+            }
+        }
+        None => emit_progress(&app, &job_id, &display_name, 0.0, "cancelled", "Cancelled"),
+    }
+    state.jobs.lock().await.remove(&job_id);
+}
+
+// Tell every waiting job where it stands in line
+async fn emit_queue_positions(app: &AppHandle, state: &AppState) {
+    let queue = state.queue.lock().await;
+    for (ahead, (job_id, display_name)) in queue.iter().enumerate() {
+        let message = match ahead {
+            0 => "Queued — next up".to_string(),
+            n => format!("Queued — {} ahead", n),
+        };
+        emit_progress(app, job_id, display_name, 0.0, "queued", &message);
+    }
 }
 
 #[tauri::command]
-async fn cancel_job(state: State<'_, AppState>, job_id: String) -> Result<(), String> {
+async fn cancel_job(state: State<'_, AppState>, job_id: String) -> Result<(), AppError> {
     let jobs = state.jobs.lock().await;
     if let Some(tx) = jobs.get(&job_id) {
         let _ = tx.send(true);
         Ok(())
     } else {
-        Err("Job not found".to_string())
+        Err(AppError::invalid("Job not found"))
     }
 }
 
@@ -216,41 +622,86 @@ async fn run_conversion(
         .join(format!("{}.{}", file_name, request.format.to_lowercase()));
 
     // Get duration for progress
-    let duration = get_duration(&request.file_path).await.unwrap_or(0.0);
+    let full_duration = get_duration(&request.file_path).await.unwrap_or(0.0);
+    let start = request.start_time.unwrap_or(0.0);
+    if full_duration > 0.0 && start >= full_duration {
+        let message = format!("Trim start {:.2}s is past the end of the file ({:.2}s)", start, full_duration);
+        emit_progress(&app, &job_id, &display_name, 0.0, "error", &message);
+        return;
+    }
+    // Progress counts output time, which only covers the trimmed range
+    let end = match request.end_time {
+        Some(end) if full_duration > 0.0 => end.min(full_duration),
+        Some(end) => end,
+        None => full_duration,
+    };
+    let duration = (end - start).max(0.0);
 
-    let mut args: Vec<String> = vec![
-        "-i".to_string(),
-        request.file_path.clone(),
-        "-y".to_string(),
-        "-progress".to_string(),
-        "pipe:1".to_string(),
-    ];
+    let mut args: Vec<String> = Vec::new();
+    // -ss before -i seeks the input quickly instead of decoding up to it
+    if start > 0.0 {
+        args.extend(["-ss".to_string(), format!("{:.3}", start)]);
+    }
+    args.extend(["-i".to_string(), request.file_path.clone()]);
+    // Input seeking restarts timestamps at zero, so the end is given as a
+    // length; an output -to would count from the new zero, not the source
+    if let Some(end) = request.end_time {
+        args.extend(["-t".to_string(), format!("{:.3}", end - start)]);
+    }
+    args.extend(["-y".to_string(), "-progress".to_string(), "pipe:1".to_string()]);
 
     let video_formats = ["mp4", "mkv", "avi", "mov", "webm"];
     let audio_formats = ["mp3", "wav", "flac", "aac", "ogg"];
     let fmt = request.format.to_lowercase();
     let is_video_output = video_formats.contains(&fmt.as_str());
     let _is_audio_output = audio_formats.contains(&fmt.as_str());
+    let has_bitrate = request.bitrate.as_deref().is_some_and(|b| !b.is_empty());
+    let two_pass = request.two_pass.unwrap_or(false) && is_video_output && has_bitrate;
+    let has_codec = request.codec.as_deref().is_some_and(|c| !c.is_empty());
 
-    // Quality presets
+    // Hardware H.264 encoder, unless WebM (VP9) or a codec override wins
+    let hw_encoder = match request.hw_accel.as_deref() {
+        _ if !is_video_output || has_codec || fmt == "webm" => None,
+        Some("videotoolbox") => Some("h264_videotoolbox"),
+        Some("nvenc") => Some("h264_nvenc"),
+        _ => None,
+    };
+    let mut hw_warning = None;
+    let hw_encoder = match hw_encoder {
+        Some(encoder) => {
+            let state = app.state::<AppState>();
+            if ffmpeg_encoders(&state).await.iter().any(|e| e == encoder) {
+                args.extend(["-c:v".to_string(), encoder.to_string()]);
+                Some(encoder)
+            } else {
+                args.extend(["-c:v".to_string(), "libx264".to_string()]);
+                hw_warning = Some(format!("{} isn't available in this FFmpeg, using libx264", encoder));
+                None
+            }
+        }
+        None => None,
+    };
+
+    // Quality presets. Two-pass encodes to the bitrate, so CRF is left out.
     match request.quality.as_str() {
+        _ if two_pass => {}
         "high" => {
             if is_video_output {
-                args.extend(["-crf".to_string(), "18".to_string()]);
+                args.extend(video_quality_args(hw_encoder, 18));
             } else {
                 args.extend(["-q:a".to_string(), "0".to_string()]);
             }
         }
         "medium" => {
             if is_video_output {
-                args.extend(["-crf".to_string(), "23".to_string()]);
+                args.extend(video_quality_args(hw_encoder, 23));
             } else {
                 args.extend(["-q:a".to_string(), "4".to_string()]);
             }
         }
         "low" => {
             if is_video_output {
-                args.extend(["-crf".to_string(), "28".to_string()]);
+                args.extend(video_quality_args(hw_encoder, 28));
             } else {
                 args.extend(["-q:a".to_string(), "8".to_string()]);
             }
@@ -280,11 +731,10 @@ async fn run_conversion(
         }
     }
 
-    // Resolution override
-    if let Some(res) = &request.resolution {
-        if !res.is_empty() && is_video_output {
-            args.extend(["-vf".to_string(), format!("scale={}", res.replace('x', ":"))]);
-        }
+    // Video filters (resolution etc.)
+    let filters = build_video_filters(&request, start);
+    if !filters.is_empty() && is_video_output {
+        args.extend(["-vf".to_string(), filters.join(",")]);
     }
 
     // Sample rate override (audio)
@@ -320,46 +770,144 @@ async fn run_conversion(
         args.extend(["-vn".to_string()]);
     }
 
-    args.push(out_path.to_string_lossy().to_string());
+    if request.keep_metadata.unwrap_or(false) {
+        args.extend(metadata_args(is_video_output));
+    }
 
-    emit_progress(&app, &job_id, &display_name, 0.0, "converting", "Starting...");
+    if let Some(extra) = &request.extra_args {
+        args.extend(extra.iter().cloned());
+    }
 
-    let mut child = match Command::new("ffmpeg")
-        .args(&args)
+    let starting = hw_warning.as_deref().unwrap_or("Starting...");
+    emit_progress(&app, &job_id, &display_name, 0.0, "converting", starting);
+
+    let output = out_path.to_string_lossy().to_string();
+    let outcome = if two_pass {
+        // Both passes share the stats file, kept per job so batches don't collide
+        let log_dir = std::env::temp_dir().join(format!("core_2pass_{}", job_id));
+        let log = log_dir.join("ffmpeg2pass").to_string_lossy().to_string();
+        let mut outcome = match std::fs::create_dir_all(&log_dir) {
+            Ok(()) => PassOutcome::Done,
+            Err(e) => PassOutcome::Failed(AppError::io("Failed to create pass log folder", e).to_string()),
+        };
+        if outcome == PassOutcome::Done {
+            let mut first = args.clone();
+            first.extend(["-pass", "1", "-passlogfile", &log, "-an", "-f", "null", "-"].map(String::from));
+            outcome = run_pass(&app, &job_id, &display_name, &first, duration, (0.0, 50.0), Some("Pass 1/2"), &mut cancel_rx).await;
+        }
+        if outcome == PassOutcome::Done {
+            let mut second = args;
+            second.extend(["-pass", "2", "-passlogfile", &log, &output].map(String::from));
+            outcome = run_pass(&app, &job_id, &display_name, &second, duration, (50.0, 100.0), Some("Pass 2/2"), &mut cancel_rx).await;
+        }
+        let _ = std::fs::remove_dir_all(&log_dir);
+        outcome
+    } else {
+        args.push(output);
+        run_pass(&app, &job_id, &display_name, &args, duration, (0.0, 100.0), None, &mut cancel_rx).await
+    };
+
+    match outcome {
+        PassOutcome::Done => {
+            emit_progress(&app, &job_id, &display_name, 100.0, "done", "Complete!");
+        }
+        PassOutcome::Cancelled => {
+            let _ = tokio::fs::remove_file(&out_path).await;
+            emit_progress(&app, &job_id, &display_name, 0.0, "cancelled", "Cancelled");
+        }
+        PassOutcome::Failed(message) => {
+            emit_progress(&app, &job_id, &display_name, 0.0, "error", &message);
+        }
+    }
+}
+
+// Audio formats mostly have nowhere to put chapters, so only tags are copied
+fn metadata_args(is_video_output: bool) -> Vec<String> {
+    let mut args = vec!["-map_metadata".to_string(), "0".to_string()];
+    if is_video_output {
+        args.extend(["-map_chapters".to_string(), "0".to_string()]);
+    }
+    args
+}
+
+#[derive(Debug, PartialEq)]
+enum PassOutcome {
+    Done,
+    Cancelled,
+    Failed(String),
+}
+
+/// Run one ffmpeg invocation, reporting its progress scaled into `range`
+/// (percent of the whole job) and killing it if the job is cancelled.
+#[allow(clippy::too_many_arguments)]
+async fn run_pass(
+    app: &AppHandle,
+    job_id: &str,
+    display_name: &str,
+    args: &[String],
+    duration: f64,
+    range: (f64, f64),
+    label: Option<&str>,
+    cancel_rx: &mut tokio::sync::watch::Receiver<bool>,
+) -> PassOutcome {
+    let mut child = match Command::new(ffmpeg_bin())
+        .args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
     {
         Ok(c) => c,
-        Err(e) => {
-            emit_progress(&app, &job_id, &display_name, 0.0, "error", &format!("Failed to start ffmpeg: {}", e));
-            return;
-        }
+        Err(e) => return PassOutcome::Failed(spawn_error("ffmpeg", e).to_string()),
     };
 
     let stdout = child.stdout.take().unwrap();
     let mut reader = BufReader::new(stdout).lines();
 
-    let time_re = Regex::new(r"out_time_us=(\d+)").unwrap();
+    let (start, end) = range;
+    let mut secs = 0.0;
+    let mut speed = 0.0;
 
     loop {
         tokio::select! {
             line = reader.next_line() => {
                 match line {
-                    Ok(Some(l)) => {
-                        if let Some(caps) = time_re.captures(&l) {
-                            if let Ok(us) = caps[1].parse::<f64>() {
-                                let secs = us / 1_000_000.0;
-                                let pct = if duration > 0.0 {
-                                    (secs / duration * 100.0).min(99.9)
-                                } else {
-                                    0.0
-                                };
-                                emit_progress(&app, &job_id, &display_name, pct, "converting",
-                                    &format!("{:.1}%", pct));
+                    // Each report is a block of key=value lines ending in progress=
+                    Ok(Some(l)) => match l.split_once('=') {
+                        Some(("out_time_us", us)) => {
+                            if let Ok(us) = us.trim().parse::<f64>() {
+                                secs = us / 1_000_000.0;
                             }
                         }
-                    }
+                        // "2.31x", or "N/A" before the first frame
+                        Some(("speed", value)) => {
+                            speed = value.trim().trim_end_matches('x').parse().unwrap_or(0.0);
+                        }
+                        Some(("progress", _)) => {
+                            let pct = if duration > 0.0 {
+                                start + (secs / duration).min(0.999) * (end - start)
+                            } else {
+                                start
+                            };
+                            let message = match label {
+                                Some(label) => format!("{} · {:.1}%", label, pct),
+                                None => format!("{:.1}%", pct),
+                            };
+                            // Input seconds left across the whole job, later passes included
+                            let remaining = (100.0 - pct) / (end - start) * duration;
+                            let eta_seconds = if speed > 0.0 { (remaining / speed).round() as u64 } else { 0 };
+                            let _ = app.emit("conversion-progress", ProgressEvent {
+                                job_id: job_id.to_string(),
+                                file_name: display_name.to_string(),
+                                progress: pct,
+                                status: "converting".to_string(),
+                                message,
+                                speed,
+                                eta_seconds,
+                            });
+                        }
+                        _ => {}
+                    },
                     Ok(None) => break,
                     Err(_) => break,
                 }
@@ -367,27 +915,48 @@ async fn run_conversion(
             _ = cancel_rx.changed() => {
                 if *cancel_rx.borrow() {
                     let _ = child.kill().await;
-                    let _ = tokio::fs::remove_file(&out_path).await;
-                    emit_progress(&app, &job_id, &display_name, 0.0, "cancelled", "Cancelled");
-                    return;
+                    return PassOutcome::Cancelled;
                 }
             }
         }
     }
 
-    let status = child.wait().await;
-    match status {
-        Ok(s) if s.success() => {
-            emit_progress(&app, &job_id, &display_name, 100.0, "done", "Complete!");
+    match child.wait().await {
+        Ok(s) if s.success() => PassOutcome::Done,
+        Ok(s) => PassOutcome::Failed(format!("FFmpeg exited with code {}", s.code().unwrap_or(-1))),
+        Err(e) => PassOutcome::Failed(format!("Error: {}", e)),
+    }
+}
+
+// Filter chain for video output. preview_segment runs the same chain so the
+// preview matches what the conversion will produce. `offset` is where an
+// input -ss seek started, which subtitle timing has to account for.
+fn build_video_filters(request: &ConvertRequest, offset: f64) -> Vec<String> {
+    let mut filters = Vec::new();
+    if let Some(res) = &request.resolution {
+        if !res.is_empty() {
+            filters.push(format!("scale={}", res.replace('x', ":")));
         }
-        Ok(s) => {
-            emit_progress(&app, &job_id, &display_name, 0.0, "error",
-                &format!("FFmpeg exited with code {}", s.code().unwrap_or(-1)));
+    }
+    if let Some(path) = request.subtitle_path.as_deref().filter(|p| !p.is_empty()) {
+        // Seeking restarts timestamps at zero; shift them back for libass
+        if offset > 0.0 {
+            filters.push(format!("setpts=PTS+{:.3}/TB", offset));
         }
-        Err(e) => {
-            emit_progress(&app, &job_id, &display_name, 0.0, "error", &format!("Error: {}", e));
+        filters.push(subtitles_filter(path));
+        if offset > 0.0 {
+            filters.push("setpts=PTS-STARTPTS".to_string());
         }
     }
+    filters
+}
+
+// The path is unescaped twice: first by the filtergraph parser, where it
+// sits in single quotes and a ' has to step outside them, then by the
+// filter's option parser, where \, : and ' are special.
+fn subtitles_filter(path: &str) -> String {
+    let option = path.replace('\\', "\\\\").replace(':', "\\:").replace('\'', "\\'");
+    format!("subtitles=filename='{}'", option.replace('\'', "'\\''"))
 }
 
 fn emit_progress(app: &AppHandle, job_id: &str, file_name: &str, progress: f64, status: &str, message: &str) {
@@ -397,11 +966,13 @@ fn emit_progress(app: &AppHandle, job_id: &str, file_name: &str, progress: f64,
         progress,
         status: status.to_string(),
         message: message.to_string(),
+        speed: 0.0,
+        eta_seconds: 0,
     });
 }
 
 async fn get_duration(path: &str) -> Option<f64> {
-    let output = std::process::Command::new("ffprobe")
+    let output = std::process::Command::new(ffprobe_bin())
         .args([
             "-v", "quiet",
             "-show_entries", "format=duration",
@@ -415,28 +986,155 @@ async fn get_duration(path: &str) -> Option<f64> {
 }
 
 #[tauri::command]
-async fn get_thumbnail(path: String) -> Result<String, String> {
-    let tmp = std::env::temp_dir().join(format!("core_thumb_{}.jpg", Uuid::new_v4()));
-    let status = std::process::Command::new("ffmpeg")
+async fn get_thumbnail(
+    path: String,
+    timestamp: f64,
+    thumb_format: Option<String>,
+    thumb_quality: Option<u8>,
+) -> Result<String, AppError> {
+    if !timestamp.is_finite() || timestamp < 0.0 {
+        return Err(AppError::invalid(format!("Invalid timestamp: {}", timestamp)));
+    }
+    // JPEG at ffmpeg's default quality unless asked otherwise
+    let format = thumb_format.map(|f| f.to_lowercase()).unwrap_or_else(|| "jpeg".to_string());
+    let (ext, mime) = match format.as_str() {
+        "jpeg" | "jpg" => ("jpg", "image/jpeg"),
+        "png" => ("png", "image/png"),
+        "webp" => ("webp", "image/webp"),
+        other => return Err(AppError::invalid(format!("Unsupported thumbnail format: {}", other))),
+    };
+    let quality_args: Vec<String> = match (ext, thumb_quality.map(|q| q.clamp(1, 100) as u32)) {
+        // -q:v runs 2 (best) to 31 (worst)
+        ("jpg", Some(q)) => vec!["-q:v".into(), (2 + (100 - q) * 29 / 99).to_string()],
+        ("webp", q) => vec!["-c:v".into(), "libwebp".into(), "-quality".into(), q.unwrap_or(75).to_string()],
+        _ => Vec::new(),
+    };
+
+    let tmp = std::env::temp_dir().join(format!("core_thumb_{}.{}", Uuid::new_v4(), ext));
+    let status = std::process::Command::new(ffmpeg_bin())
         .args([
+            "-ss", &format!("{:.3}", timestamp),
             "-i", &path,
-            "-ss", "00:00:01",
             "-vframes", "1",
             "-vf", "scale=200:-1",
-            "-y",
-            &tmp.to_string_lossy(),
         ])
+        .args(&quality_args)
+        .args(["-y", &tmp.to_string_lossy()])
         .output()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| spawn_error("ffmpeg", e))?;
 
     if !status.status.success() {
-        return Err("Failed to generate thumbnail".to_string());
+        let _ = std::fs::remove_file(&tmp);
+        return Err(AppError::process_failed("ffmpeg", &status));
     }
 
-    let bytes = std::fs::read(&tmp).map_err(|e| e.to_string())?;
+    let bytes = std::fs::read(&tmp).map_err(|e| AppError::io("Failed to read thumbnail", e))?;
     let _ = std::fs::remove_file(&tmp);
     let b64 = base64_encode(&bytes);
-    Ok(format!("data:image/jpeg;base64,{}", b64))
+    Ok(format!("data:{};base64,{}", mime, b64))
+}
+
+const GRID_MAX_FRAMES: u32 = 36;
+const GRID_FRAME_WIDTH: u32 = 240;
+// Past this length the grid only decodes keyframes; shorter clips may have
+// just one, which would fill every cell with the same picture
+const GRID_KEYFRAME_MIN_SECS: f64 = 120.0;
+
+/// `count` evenly spaced frames tiled into one roughly square JPEG sheet
+#[tauri::command]
+async fn get_thumbnail_grid(path: String, count: u32) -> Result<String, AppError> {
+    let count = count.clamp(1, GRID_MAX_FRAMES);
+    let duration = get_duration(&path)
+        .await
+        .filter(|d| *d > 0.0)
+        .ok_or_else(|| AppError::invalid("Can't read the video's duration"))?;
+    let cols = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(cols);
+
+    // One frame from the middle of each of `count` equal slices. For clips
+    // shorter than `count` seconds fps repeats frames rather than leaving gaps.
+    let interval = duration / count as f64;
+    let filters = format!(
+        "fps=1/{:.6},scale={}:-2,tile={}x{}",
+        interval, GRID_FRAME_WIDTH, cols, rows
+    );
+
+    let tmp = std::env::temp_dir().join(format!("core_grid_{}.jpg", Uuid::new_v4()));
+    let mut command = std::process::Command::new(ffmpeg_bin());
+    if duration >= GRID_KEYFRAME_MIN_SECS {
+        command.args(["-skip_frame", "nokey"]);
+    }
+    let output = command
+        .args([
+            "-ss", &format!("{:.3}", interval / 2.0),
+            "-i", &path,
+            "-vf", &filters,
+            "-frames:v", "1",
+            "-q:v", "4",
+            "-y", &tmp.to_string_lossy(),
+        ])
+        .output()
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(AppError::process_failed("ffmpeg", &output));
+    }
+
+    let bytes = std::fs::read(&tmp).map_err(|e| AppError::io("Failed to read thumbnail grid", e))?;
+    let _ = std::fs::remove_file(&tmp);
+    Ok(format!("data:image/jpeg;base64,{}", base64_encode(&bytes)))
+}
+
+// Preview limits — keep generation well under a couple of seconds
+const MAX_PREVIEW_SECS: f64 = 5.0;
+const PREVIEW_MAX_WIDTH: u32 = 480;
+const PREVIEW_FPS: u32 = 10;
+
+#[tauri::command]
+async fn preview_segment(request: ConvertRequest, start: f64, duration: f64) -> Result<String, AppError> {
+    let video_formats = ["mp4", "mkv", "avi", "mov", "webm"];
+    if !video_formats.contains(&request.format.to_lowercase().as_str()) {
+        return Err(AppError::invalid("Preview is only available for video formats"));
+    }
+    let start = if start.is_finite() { start.max(0.0) } else { 0.0 };
+    let duration = if duration.is_finite() && duration > 0.0 {
+        duration.min(MAX_PREVIEW_SECS)
+    } else {
+        MAX_PREVIEW_SECS
+    };
+
+    let mut filters = build_video_filters(&request, start);
+    filters.push(format!("fps={}", PREVIEW_FPS));
+    filters.push(format!("scale='min({},iw)':-2:flags=lanczos", PREVIEW_MAX_WIDTH));
+    let graph = format!(
+        "{},split[a][b];[a]palettegen[p];[b][p]paletteuse",
+        filters.join(",")
+    );
+
+    let tmp = std::env::temp_dir().join(format!("core_preview_{}.gif", Uuid::new_v4()));
+    let output = Command::new(ffmpeg_bin())
+        .args([
+            "-ss", &format!("{:.3}", start),
+            "-t", &format!("{:.3}", duration),
+            "-i", &request.file_path,
+            "-vf", &graph,
+            "-an",
+            "-y",
+            &tmp.to_string_lossy(),
+        ])
+        .output()
+        .await
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(AppError::process_failed("ffmpeg", &output));
+    }
+
+    let bytes = std::fs::read(&tmp).map_err(|e| AppError::io("Failed to read preview", e))?;
+    let _ = std::fs::remove_file(&tmp);
+    Ok(format!("data:image/gif;base64,{}", base64_encode(&bytes)))
 }
 
 fn base64_encode(data: &[u8]) -> String {
@@ -463,6 +1161,211 @@ fn base64_encode(data: &[u8]) -> String {
     result
 }
 
+// Frame extraction limits — anything above this is effectively "every frame"
+// for normal footage and only fills the disk.
+const MIN_EXTRACT_FPS: f64 = 0.01;
+const MAX_EXTRACT_FPS: f64 = 60.0;
+
+fn frame_extension(format: &str) -> Result<&'static str, AppError> {
+    match format.to_lowercase().as_str() {
+        "png" => Ok("png"),
+        "jpg" | "jpeg" => Ok("jpg"),
+        other => Err(AppError::invalid(format!("Unsupported frame format: {}", other))),
+    }
+}
+
+#[tauri::command]
+async fn extract_frames(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    output_dir: String,
+    fps: f64,
+    format: String,
+) -> Result<FrameExtraction, AppError> {
+    let ext = frame_extension(&format)?;
+    let fps = if fps.is_finite() {
+        fps.clamp(MIN_EXTRACT_FPS, MAX_EXTRACT_FPS)
+    } else {
+        1.0
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| AppError::io("Failed to create output folder", e))?;
+
+    let display_name = std::path::Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or("file".to_string());
+
+    // Expected frame count for progress
+    let duration = get_duration(&path).await.unwrap_or(0.0);
+    let expected_frames = (duration * fps).ceil();
+
+    let job_id = Uuid::new_v4().to_string();
+    let (cancel_tx, mut cancel_rx) = tokio::sync::watch::channel(false);
+    {
+        let mut jobs = state.jobs.lock().await;
+        jobs.insert(job_id.clone(), cancel_tx);
+    }
+
+    let out_dir = PathBuf::from(&output_dir);
+    let pattern = out_dir.join(format!("frame_%05d.{}", ext));
+    let mut args: Vec<String> = vec![
+        "-i".to_string(),
+        path.clone(),
+        "-vf".to_string(),
+        format!("fps={}", fps),
+        "-y".to_string(),
+        "-progress".to_string(),
+        "pipe:1".to_string(),
+    ];
+    if ext == "jpg" {
+        args.extend(["-q:v".to_string(), "2".to_string()]);
+    }
+    args.push(pattern.to_string_lossy().to_string());
+
+    emit_progress(&app, &job_id, &display_name, 0.0, "converting", "Extracting frames...");
+
+    let mut child = match Command::new(ffmpeg_bin())
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            state.jobs.lock().await.remove(&job_id);
+            let err = spawn_error("ffmpeg", e);
+            emit_progress(&app, &job_id, &display_name, 0.0, "error", &err.to_string());
+            return Err(err);
+        }
+    };
+
+    let stdout = child.stdout.take().unwrap();
+    let mut reader = BufReader::new(stdout).lines();
+    let frame_re = Regex::new(r"^frame=(\d+)").unwrap();
+    let mut last_frame: usize = 0;
+
+    loop {
+        tokio::select! {
+            line = reader.next_line() => {
+                match line {
+                    Ok(Some(l)) => {
+                        if let Some(caps) = frame_re.captures(&l) {
+                            if let Ok(n) = caps[1].parse::<usize>() {
+                                last_frame = n;
+                                let pct = if expected_frames > 0.0 {
+                                    (n as f64 / expected_frames * 100.0).min(99.9)
+                                } else {
+                                    0.0
+                                };
+                                emit_progress(&app, &job_id, &display_name, pct, "converting",
+                                    &format!("{} / {} frames", n, expected_frames as u64));
+                            }
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(_) => break,
+                }
+            }
+            _ = cancel_rx.changed() => {
+                if *cancel_rx.borrow() {
+                    let _ = child.kill().await;
+                    state.jobs.lock().await.remove(&job_id);
+                    emit_progress(&app, &job_id, &display_name, 0.0, "cancelled", "Cancelled");
+                    return Err(AppError::Cancelled);
+                }
+            }
+        }
+    }
+
+    let status = child.wait().await;
+    state.jobs.lock().await.remove(&job_id);
+    match status {
+        Ok(s) if s.success() => {}
+        Ok(s) => {
+            let msg = format!("FFmpeg exited with code {}", s.code().unwrap_or(-1));
+            emit_progress(&app, &job_id, &display_name, 0.0, "error", &msg);
+            return Err(AppError::ProcessFailed {
+                tool: "ffmpeg".to_string(),
+                code: s.code(),
+                stderr_tail: String::new(),
+            });
+        }
+        Err(e) => {
+            emit_progress(&app, &job_id, &display_name, 0.0, "error", &format!("Error: {}", e));
+            return Err(AppError::io("Failed to wait for ffmpeg", e));
+        }
+    }
+
+    // Only report frames written by this run, not leftovers already in the folder
+    let frames: Vec<String> = (1..=last_frame)
+        .map(|i| out_dir.join(format!("frame_{:05}.{}", i, ext)))
+        .filter(|p| p.exists())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    emit_progress(&app, &job_id, &display_name, 100.0, "done",
+        &format!("Extracted {} frames", frames.len()));
+
+    Ok(FrameExtraction {
+        count: frames.len(),
+        frames,
+    })
+}
+
+#[tauri::command]
+async fn extract_frame_at(
+    path: String,
+    output_dir: String,
+    timestamp: f64,
+    format: String,
+) -> Result<String, AppError> {
+    let ext = frame_extension(&format)?;
+    if !timestamp.is_finite() || timestamp < 0.0 {
+        return Err(AppError::invalid(format!("Invalid timestamp: {}", timestamp)));
+    }
+    if let Some(duration) = get_duration(&path).await {
+        if timestamp > duration {
+            return Err(AppError::invalid(format!(
+                "Timestamp {:.2}s is past the end of the file ({:.2}s)",
+                timestamp, duration
+            )));
+        }
+    }
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| AppError::io("Failed to create output folder", e))?;
+
+    let out_path = PathBuf::from(&output_dir)
+        .join(format!("frame_{}ms.{}", (timestamp * 1000.0).round() as u64, ext));
+
+    let mut args: Vec<String> = vec![
+        "-ss".to_string(),
+        format!("{:.3}", timestamp),
+        "-i".to_string(),
+        path,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-y".to_string(),
+    ];
+    if ext == "jpg" {
+        args.extend(["-q:v".to_string(), "2".to_string()]);
+    }
+    args.push(out_path.to_string_lossy().to_string());
+
+    let output = Command::new(ffmpeg_bin())
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| spawn_error("ffmpeg", e))?;
+
+    if !output.status.success() || !out_path.exists() {
+        return Err(AppError::process_failed("ffmpeg", &output));
+    }
+
+    Ok(out_path.to_string_lossy().to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -470,15 +1373,170 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .manage(AppState {
             jobs: Mutex::new(HashMap::new()),
+            slots: Arc::new(Semaphore::new(DEFAULT_MAX_PARALLEL)),
+            slot_limit: Mutex::new(SlotLimit { max: DEFAULT_MAX_PARALLEL, owed: 0 }),
+            queue: Mutex::new(Vec::new()),
+            encoders: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             check_ffmpeg,
+            check_all_tools,
+            check_hw_accel,
+            get_ffmpeg_path,
+            set_ffmpeg_path,
             probe_file,
             convert_file,
+            convert_batch,
             cancel_job,
             get_thumbnail,
+            get_thumbnail_grid,
+            preview_segment,
             select_output_dir,
+            extract_frames,
+            extract_frame_at,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn has_tool(name: &str) -> bool {
+        Command::new(name).arg("-version").output().is_ok_and(|o| o.status.success())
+    }
+
+    fn title_tag(path: &std::path::Path) -> String {
+        let output = Command::new("ffprobe")
+            .args(["-v", "quiet", "-show_entries", "format_tags=title", "-of", "default=nw=1:nk=1"])
+            .arg(path)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn request_with_extra(extra: &[&str]) -> ConvertRequest {
+        ConvertRequest {
+            file_path: "in.mp4".into(),
+            output_dir: "/tmp".into(),
+            format: "mp4".into(),
+            quality: "medium".into(),
+            codec: None,
+            bitrate: None,
+            resolution: None,
+            sample_rate: None,
+            two_pass: None,
+            hw_accel: None,
+            keep_metadata: None,
+            extra_args: Some(extra.iter().map(|a| a.to_string()).collect()),
+            start_time: None,
+            end_time: None,
+            subtitle_path: None,
+        }
+    }
+
+    #[test]
+    fn extra_args_reject_reserved_flags() {
+        assert!(validate_request(&request_with_extra(&["-vf", "hqdn3d"])).is_ok());
+        assert!(validate_request(&request_with_extra(&["-vf", "yadif", "-i", "other.mp4"])).is_err());
+        assert!(validate_request(&request_with_extra(&["-progress", "pipe:2"])).is_err());
+    }
+
+    #[test]
+    fn trim_end_must_follow_start() {
+        let mut request = request_with_extra(&[]);
+        request.start_time = Some(30.0);
+        assert!(validate_request(&request).is_ok());
+        request.end_time = Some(30.0);
+        assert!(validate_request(&request).is_err());
+        request.end_time = Some(45.5);
+        assert!(validate_request(&request).is_ok());
+    }
+
+    #[test]
+    fn subtitles_filter_escapes_path() {
+        assert_eq!(subtitles_filter("/subs/movie.srt"), "subtitles=filename='/subs/movie.srt'");
+        assert_eq!(
+            subtitles_filter("C:\\Subs\\it's.srt"),
+            "subtitles=filename='C\\:\\\\Subs\\\\it\\'\\''s.srt'"
+        );
+    }
+
+    #[test]
+    fn subtitles_chain_after_scale() {
+        let mut request = request_with_extra(&[]);
+        request.resolution = Some("1280x720".into());
+        request.subtitle_path = Some("/subs/a.srt".into());
+        assert_eq!(
+            build_video_filters(&request, 0.0),
+            ["scale=1280:720", "subtitles=filename='/subs/a.srt'"]
+        );
+        assert_eq!(build_video_filters(&request, 10.0).len(), 4);
+    }
+
+    #[test]
+    fn probe_streams_and_hdr() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"[
+                {"index": 0, "codec_type": "video", "codec_name": "hevc",
+                 "color_transfer": "smpte2084", "color_primaries": "bt2020"},
+                {"index": 1, "codec_type": "audio", "codec_name": "eac3", "channels": 6,
+                 "tags": {"language": "eng"}},
+                {"index": 2, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+                 "tags": {"language": "und"}}
+            ]"#,
+        )
+        .unwrap();
+        let streams = json.as_array().unwrap();
+        assert!(is_hdr_stream(&streams[0]));
+        assert!(!is_hdr_stream(&streams[1]));
+
+        let info = stream_info(&streams[1]);
+        assert_eq!((info.index, info.codec_type.as_str()), (1, "audio"));
+        assert_eq!((info.language.as_deref(), info.channels), (Some("eng"), Some(6)));
+        assert_eq!(stream_info(&streams[2]).language, None);
+    }
+
+    // Needs ffmpeg and ffprobe on PATH; skipped otherwise
+    #[test]
+    fn metadata_args_keep_title_tag() {
+        if !has_tool("ffmpeg") || !has_tool("ffprobe") {
+            eprintln!("ffmpeg/ffprobe not found, skipping");
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("core_metadata_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("src.mp4");
+        let status = Command::new("ffmpeg")
+            .args(["-v", "error", "-f", "lavfi", "-i", "testsrc=duration=1:size=64x64:rate=10"])
+            .args(["-f", "lavfi", "-i", "sine=duration=1"])
+            .args(["-metadata", "title=Metadata Test", "-shortest", "-y"])
+            .arg(&src)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // Stream copy, re-encode and audio-only output
+        for (name, codec_args, is_video) in [
+            ("copy.mkv", vec!["-c", "copy"], true),
+            ("encode.mkv", vec!["-c:v", "mpeg4"], true),
+            ("audio.m4a", vec!["-vn", "-c:a", "aac"], false),
+        ] {
+            let out = dir.join(name);
+            let status = Command::new("ffmpeg")
+                .args(["-v", "error", "-i"])
+                .arg(&src)
+                .args(codec_args)
+                .args(metadata_args(is_video))
+                .arg("-y")
+                .arg(&out)
+                .status()
+                .unwrap();
+            assert!(status.success(), "{}", name);
+            assert_eq!(title_tag(&out), "Metadata Test", "{}", name);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
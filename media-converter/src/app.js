@@ -45,14 +45,58 @@ document.addEventListener('DOMContentLoaded', async () => {
     const statusEl = document.getElementById('ffmpeg-status');
     statusEl.querySelector('.status-dot').classList.add('err');
     statusEl.querySelector('.status-text').textContent = 'FFmpeg not found';
-    document.getElementById('ffmpeg-warning').classList.remove('hidden');
+    showToolWarning(e);
   }
 
+  // Name every missing tool with its install command in the banner
+  try {
+    const missing = (await invoke('check_all_tools')).filter(t => !t.found);
+    if (missing.length) {
+      document.getElementById('ffmpeg-warning-text').textContent =
+        '⚠️ ' + missing.map(t => `${t.name} not found. ${t.install_hint}`).join(' · ');
+      document.getElementById('ffmpeg-warning').classList.remove('hidden');
+    }
+  } catch { }
+
+  // Offer only the hardware encoders this FFmpeg was built with
+  try {
+    const encoders = await invoke('check_hw_accel');
+    const select = document.getElementById('opt-hw-accel');
+    [['videotoolbox', 'VideoToolbox'], ['nvenc', 'NVENC']]
+      .filter(([accel]) => encoders.includes(`h264_${accel}`))
+      .forEach(([accel, label]) => select.add(new Option(label, accel)));
+  } catch { }
+
   renderFormats();
   setupEventListeners();
   setupProgressListener();
 });
 
+// Split on spaces, keeping "quoted parts" together.
+// Each piece goes to ffmpeg as its own argument; nothing is run through a shell.
+function splitArgs(text) {
+  const args = [...text.matchAll(/"([^"]*)"|'([^']*)'|(\S+)/g)].map(m => m[1] ?? m[2] ?? m[3]);
+  return args.length ? args : null;
+}
+
+function secondsOrNull(value) {
+  const seconds = parseFloat(value);
+  return Number.isFinite(seconds) ? seconds : null;
+}
+
+// Commands reject with { kind, message, ... }; older builds rejected with a string
+function errorMessage(e) {
+  if (!e || typeof e !== 'object') return String(e);
+  if (e.kind === 'process_failed' && e.stderr_tail) return `${e.message}\n\n${e.stderr_tail}`;
+  return e.message;
+}
+
+// A missing tool points at the install banner instead of a console line
+function showToolWarning(e) {
+  if (e?.message) document.getElementById('ffmpeg-warning-text').textContent = '⚠️ ' + e.message;
+  document.getElementById('ffmpeg-warning').classList.remove('hidden');
+}
+
 async function getDesktopPath() {
   // Simple heuristic
   const home = await getHomePath();
@@ -143,6 +187,7 @@ function setupEventListeners() {
   // Output dir
   document.getElementById('choose-dir-btn').addEventListener('click', chooseOutputDir);
   document.getElementById('output-path').addEventListener('click', chooseOutputDir);
+  document.getElementById('choose-subtitles-btn').addEventListener('click', chooseSubtitles);
 
   // Convert
   document.getElementById('convert-btn').addEventListener('click', startConversion);
@@ -172,14 +217,19 @@ async function addFile(path) {
     let thumbnail = null;
     if (info.is_video) {
       try {
-        thumbnail = await invoke('get_thumbnail', { path });
+        // A little way in, past the black frames most videos open with
+        const timestamp = info.duration ? Math.min(info.duration * 0.1, 10) : 0;
+        // Not every ffmpeg build has libwebp, so fall back to the default JPEG
+        thumbnail = await invoke('get_thumbnail', { path, timestamp, thumbFormat: 'webp', thumbQuality: 70 })
+          .catch(() => invoke('get_thumbnail', { path, timestamp }));
       } catch {}
     }
     files.push({ ...info, thumbnail });
     renderFileList();
     updateConvertButton();
   } catch (e) {
-    console.error('Failed to probe file:', e);
+    if (e?.kind === 'tool_not_found') showToolWarning(e);
+    else console.error('Failed to probe file:', errorMessage(e));
   }
 }
 
@@ -232,6 +282,8 @@ function renderFileList() {
           ${f.duration ? ' · ' + formatDuration(f.duration) : ''}
           ${f.resolution ? ' · ' + f.resolution : ''}
           ${f.codec ? ' · ' + f.codec : ''}
+          ${audioTrackCount(f) > 1 ? ` · ${audioTrackCount(f)} audio tracks` : ''}
+          ${f.is_hdr ? ' · HDR' : ''}
         </div>
       </div>
       <button class="file-remove" onclick="removeFile(${i})" title="Remove">✕</button>
@@ -239,6 +291,10 @@ function renderFileList() {
   `).join('');
 }
 
+function audioTrackCount(file) {
+  return (file.streams || []).filter(s => s.codec_type === 'audio').length;
+}
+
 // ---- Formats ----
 function renderFormats() {
   const grid = document.getElementById('format-grid');
@@ -278,6 +334,18 @@ async function chooseOutputDir() {
   }
 }
 
+async function chooseSubtitles() {
+  try {
+    const selected = await open({
+      multiple: false,
+      filters: [{ name: 'Subtitles', extensions: ['srt', 'ass', 'ssa', 'vtt'] }],
+    });
+    if (selected) document.getElementById('opt-subtitles').value = selected;
+  } catch (e) {
+    console.error('Subtitle selection error:', e);
+  }
+}
+
 // ---- Convert ----
 function updateConvertButton() {
   const btn = document.getElementById('convert-btn');
@@ -303,36 +371,48 @@ async function startConversion() {
   const progressItems = document.getElementById('progress-items');
   progressItems.innerHTML = '';
 
-  for (const file of files) {
-    const request = {
-      file_path: file.path,
-      output_dir: outputDir,
-      format: selectedFormat,
-      quality: selectedQuality,
-      codec: document.getElementById('opt-codec')?.value || null,
-      bitrate: document.getElementById('opt-bitrate')?.value || null,
-      resolution: document.getElementById('opt-resolution')?.value || null,
-      sample_rate: document.getElementById('opt-samplerate')?.value || null,
-    };
-
-    try {
-      const jobId = await invoke('convert_file', { request });
-      activeJobs[jobId] = { fileName: file.name, progress: 0, status: 'converting' };
+  const requests = files.map(file => ({
+    file_path: file.path,
+    output_dir: outputDir,
+    format: selectedFormat,
+    quality: selectedQuality,
+    codec: document.getElementById('opt-codec')?.value || null,
+    bitrate: document.getElementById('opt-bitrate')?.value || null,
+    resolution: document.getElementById('opt-resolution')?.value || null,
+    sample_rate: document.getElementById('opt-samplerate')?.value || null,
+    two_pass: document.getElementById('opt-two-pass')?.checked || null,
+    hw_accel: document.getElementById('opt-hw-accel')?.value || null,
+    keep_metadata: document.getElementById('opt-keep-metadata')?.checked || null,
+    extra_args: splitArgs(document.getElementById('opt-extra-args')?.value || ''),
+    start_time: secondsOrNull(document.getElementById('opt-start')?.value),
+    end_time: secondsOrNull(document.getElementById('opt-end')?.value),
+    subtitle_path: document.getElementById('opt-subtitles')?.value || null,
+  }));
+
+  try {
+    // Jobs beyond the parallel limit wait in line and report "queued"
+    const jobIds = await invoke('convert_batch', {
+      requests,
+      maxParallel: parseInt(document.getElementById('opt-parallel').value, 10) || 2,
+    });
+    jobIds.forEach((jobId, i) => {
+      const file = files[i];
+      activeJobs[jobId] = activeJobs[jobId] || { fileName: file.name, progress: 0, status: 'queued' };
 
       progressItems.innerHTML += `
         <div class="progress-item" id="job-${jobId}">
           <div class="progress-item-header">
             <span class="progress-item-name" title="${file.name}">${file.name}</span>
-            <span class="progress-item-status" id="status-${jobId}">Starting...</span>
+            <span class="progress-item-status" id="status-${jobId}">Queued</span>
           </div>
           <div class="progress-bar">
             <div class="progress-fill" id="fill-${jobId}" style="width: 0%"></div>
           </div>
         </div>
       `;
-    } catch (e) {
-      console.error('Failed to start conversion:', e);
-    }
+    });
+  } catch (e) {
+    console.error('Failed to start conversion:', errorMessage(e));
   }
 
   updateOverallProgress();
@@ -340,7 +420,7 @@ async function startConversion() {
 
 async function cancelAll() {
   for (const jobId of Object.keys(activeJobs)) {
-    if (activeJobs[jobId].status === 'converting') {
+    if (activeJobs[jobId].status === 'converting' || activeJobs[jobId].status === 'queued') {
       try {
         await invoke('cancel_job', { jobId });
       } catch {}
@@ -351,7 +431,7 @@ async function cancelAll() {
 // ---- Progress Listener ----
 function setupProgressListener() {
   listen('conversion-progress', (event) => {
-    const { job_id, file_name, progress, status, message } = event.payload;
+    const { job_id, file_name, progress, status, message, speed, eta_seconds } = event.payload;
 
     if (activeJobs[job_id]) {
       activeJobs[job_id].progress = progress;
@@ -367,7 +447,7 @@ function setupProgressListener() {
       if (status === 'error') fill.classList.add('error');
     }
     if (statusEl) {
-      statusEl.textContent = message;
+      statusEl.textContent = speed > 0 ? `${message} · ${speed.toFixed(1)}x, ~${formatEta(eta_seconds)} left` : message;
       statusEl.className = 'progress-item-status';
       if (status === 'done' || status === 'error' || status === 'cancelled') {
         statusEl.classList.add(status);
@@ -378,6 +458,13 @@ function setupProgressListener() {
   });
 }
 
+function formatEta(seconds) {
+  if (seconds < 60) return `${seconds}s`;
+  const minutes = Math.floor(seconds / 60);
+  if (minutes < 60) return `${minutes}m ${seconds % 60}s`;
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m`;
+}
+
 function updateOverallProgress() {
   const jobs = Object.values(activeJobs);
   if (jobs.length === 0) return;
@@ -1,18 +1,28 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use tauri::Manager;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tempfile::TempDir;
 
+mod error;
+use error::AppError;
+
 // ─── Types ───────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OcrResult {
     pub file: String,
+    /// Plain text, or the raw hOCR / ALTO / TSV document
     pub text: String,
     pub confidence: f64,
     pub language: String,
+    pub format: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +30,8 @@ pub struct ConversionResult {
     pub success: bool,
     pub output_path: String,
     pub message: String,
+    // Set by conversions that produce a PDF page by page
+    pub page_count: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +41,13 @@ pub struct BatchProgress {
     pub current_file: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub processed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
     pub path: String,
@@ -37,6 +56,146 @@ pub struct FileInfo {
     pub file_type: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub found: bool,
+    pub version: Option<String>,
+    pub install_hint: String,
+}
+
+/// Sent as "ocr-job-finished" when a job started by ocr_image, pdf_to_images
+/// or pdf_to_docx ends; `result` holds what the command used to return and
+/// `error` the serialized AppError
+#[derive(Debug, Clone, Serialize)]
+pub struct JobFinished {
+    pub job_id: String,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<serde_json::Value>,
+    pub cancelled: bool,
+}
+
+// ─── Jobs ────────────────────────────────────────────────────────────────────
+
+struct OcrJob {
+    // The tool currently running for this job, if any
+    child: Option<Child>,
+    temp_dirs: Vec<PathBuf>,
+    cancelled: bool,
+}
+
+type Jobs = Arc<Mutex<HashMap<String, OcrJob>>>;
+
+#[derive(Default)]
+struct OcrState {
+    jobs: Jobs,
+}
+
+// Runs `work` on its own thread and returns the job id straight away; the
+// outcome arrives as a JobFinished event
+fn start_job<T, F>(app: AppHandle, jobs: &Jobs, work: F) -> String
+where
+    T: Serialize,
+    F: FnOnce(&Jobs, &str) -> Result<T, AppError> + Send + 'static,
+{
+    let job_id = uuid::Uuid::new_v4().to_string();
+    jobs.lock().unwrap().insert(
+        job_id.clone(),
+        OcrJob {
+            child: None,
+            temp_dirs: Vec::new(),
+            cancelled: false,
+        },
+    );
+
+    let jobs = Arc::clone(jobs);
+    let id = job_id.clone();
+    thread::spawn(move || {
+        let result = work(&jobs, &id);
+        let cancelled = jobs.lock().unwrap().remove(&id).is_some_and(|job| job.cancelled);
+        let event = match result {
+            _ if cancelled => JobFinished {
+                job_id: id,
+                result: None,
+                error: serde_json::to_value(AppError::Cancelled).ok(),
+                cancelled,
+            },
+            Ok(value) => JobFinished {
+                job_id: id,
+                result: serde_json::to_value(value).ok(),
+                error: None,
+                cancelled,
+            },
+            Err(e) => JobFinished {
+                job_id: id,
+                result: None,
+                error: serde_json::to_value(e).ok(),
+                cancelled,
+            },
+        };
+        let _ = app.emit("ocr-job-finished", event);
+    });
+    job_id
+}
+
+// Removed by cancel_ocr; on success the job decides whether they stay
+fn track_temp_dir(jobs: &Jobs, job_id: &str, dir: &Path) {
+    if let Some(job) = jobs.lock().unwrap().get_mut(job_id) {
+        job.temp_dirs.push(dir.to_path_buf());
+    }
+}
+
+// `Command::output()`, except the child is parked in the job so cancel_ocr
+// can kill it while we poll for it to exit
+fn run_tracked(jobs: &Jobs, job_id: &str, tool: &str, cmd: &mut Command) -> Result<Output, AppError> {
+    let (stdout, stderr) = {
+        let mut map = jobs.lock().unwrap();
+        let job = map.get_mut(job_id).filter(|j| !j.cancelled).ok_or(AppError::Cancelled)?;
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| spawn_error(tool, e))?;
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+        job.child = Some(child);
+        (stdout, stderr)
+    };
+
+    let status = loop {
+        {
+            let mut map = jobs.lock().unwrap();
+            let job = map.get_mut(job_id).ok_or(AppError::Cancelled)?;
+            // cancel_ocr takes the child when it kills it
+            let Some(child) = job.child.as_mut() else {
+                return Err(AppError::Cancelled);
+            };
+            if let Some(status) = child.try_wait().map_err(|e| AppError::io(format!("{} failed", tool), e))? {
+                job.child = None;
+                break status;
+            }
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+// Drained on a thread so a chatty tool can't fill the pipe and stall
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
 // ─── Helpers ─────────────────────────────────────────────────────────────────
 
 fn detect_file_type(path: &str) -> String {
@@ -78,10 +237,85 @@ fn find_tool(name: &str) -> String {
     name.to_string()
 }
 
+// (macOS, Linux, Windows) install commands per binary
+fn install_hint(tool: &str) -> String {
+    let (mac, linux, windows) = match tool {
+        "tesseract" => ("brew install tesseract", "sudo apt install tesseract-ocr", "winget install UB-Mannheim.TesseractOCR"),
+        "pdftotext" | "pdftoppm" | "pdfinfo" | "pdfunite" => ("brew install poppler", "sudo apt install poppler-utils", "choco install poppler"),
+        "soffice" => (
+            "brew install --cask libreoffice",
+            "sudo apt install libreoffice",
+            "winget install TheDocumentFoundation.LibreOffice",
+        ),
+        "img2pdf" => ("brew install img2pdf", "sudo apt install img2pdf", "pip install img2pdf"),
+        "magick" => ("brew install imagemagick", "sudo apt install imagemagick", "winget install ImageMagick.ImageMagick"),
+        _ => return format!("Install {} and make sure it is on your PATH", tool),
+    };
+    let hint = if cfg!(target_os = "macos") {
+        mac
+    } else if cfg!(windows) {
+        windows
+    } else {
+        linux
+    };
+    format!("Install it with: {}", hint)
+}
+
+// A missing binary gets the install hint instead of a bare "not found"
+fn spawn_error(tool: &str, e: std::io::Error) -> AppError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        AppError::ToolNotFound {
+            tool: tool.to_string(),
+            hint: install_hint(tool),
+        }
+    } else {
+        AppError::io(format!("{} failed", tool), e)
+    }
+}
+
+// Found means it could be started at all; poppler prints its version to
+// stderr and exits non-zero on some releases, so the status isn't checked
+fn tool_status(name: &str, version_arg: &str) -> ToolStatus {
+    let output = Command::new(find_tool(name)).arg(version_arg).output();
+    let version = output.as_ref().ok().and_then(|o| {
+        let stdout = String::from_utf8_lossy(&o.stdout);
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        stdout
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .map(String::from)
+    });
+    ToolStatus {
+        name: name.to_string(),
+        found: output.is_ok(),
+        version,
+        install_hint: install_hint(name),
+    }
+}
+
 // ─── Commands ────────────────────────────────────────────────────────────────
 
 #[tauri::command]
-fn check_dependencies() -> Result<serde_json::Value, String> {
+fn check_all_tools() -> Vec<ToolStatus> {
+    [
+        ("tesseract", "--version"),
+        ("pdftotext", "-v"),
+        ("pdftoppm", "-v"),
+        ("pdfinfo", "-v"),
+        ("pdfunite", "-v"),
+        ("soffice", "--version"),
+        ("img2pdf", "--version"),
+        ("magick", "--version"),
+    ]
+    .iter()
+    .map(|(name, arg)| tool_status(name, arg))
+    .collect()
+}
+
+#[tauri::command]
+fn check_dependencies() -> Result<serde_json::Value, AppError> {
     let tesseract = Command::new(find_tesseract())
         .arg("--version")
         .output();
@@ -105,11 +339,11 @@ fn check_dependencies() -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-fn get_tesseract_languages() -> Result<Vec<String>, String> {
+fn get_tesseract_languages() -> Result<Vec<String>, AppError> {
     let output = Command::new(find_tesseract())
         .arg("--list-langs")
         .output()
-        .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+        .map_err(|e| spawn_error("tesseract", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let langs: Vec<String> = stdout
@@ -144,188 +378,692 @@ fn validate_files(paths: Vec<String>) -> Vec<FileInfo> {
         .collect()
 }
 
-#[tauri::command]
-fn ocr_image(path: String, language: String) -> Result<OcrResult, String> {
-    let tesseract = find_tesseract();
-    let tmp_dir = TempDir::new().map_err(|e| e.to_string())?;
-    let output_base = tmp_dir.path().join("ocr_output");
+/// Tesseract page segmentation and engine modes, plus whether to straighten
+/// the page first
+#[derive(Debug, Clone, Copy)]
+struct OcrOptions {
+    psm: u32,
+    oem: u32,
+    auto_rotate: bool,
+    format: OcrFormat,
+}
 
-    let output = Command::new(&tesseract)
-        .arg(&path)
-        .arg(output_base.to_str().unwrap())
-        .arg("-l")
-        .arg(&language)
-        .arg("--psm")
-        .arg("3")
-        .arg("--oem")
-        .arg("1")
-        .output()
-        .map_err(|e| format!("Tesseract failed: {}", e))?;
+impl Default for OcrOptions {
+    // Fully automatic segmentation on the LSTM engine, as plain text
+    fn default() -> Self {
+        OcrOptions { psm: 3, oem: 1, auto_rotate: false, format: OcrFormat::Txt }
+    }
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Tesseract error: {}", stderr));
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OcrFormat {
+    Txt,
+    Hocr,
+    Alto,
+    Tsv,
+}
+
+impl OcrFormat {
+    fn parse(format: &str) -> Result<Self, AppError> {
+        match format.to_lowercase().as_str() {
+            "txt" | "text" => Ok(OcrFormat::Txt),
+            "hocr" => Ok(OcrFormat::Hocr),
+            "alto" => Ok(OcrFormat::Alto),
+            "tsv" => Ok(OcrFormat::Tsv),
+            other => Err(AppError::invalid(format!("Unsupported OCR output format: {}", other))),
+        }
     }
 
-    let text_file = format!("{}.txt", output_base.to_str().unwrap());
-    let text = fs::read_to_string(&text_file)
-        .map_err(|e| format!("Failed to read OCR output: {}", e))?;
-
-    // Get confidence via tsv output
-    let tsv_output = Command::new(&tesseract)
-        .arg(&path)
-        .arg("stdout")
-        .arg("-l")
-        .arg(&language)
-        .arg("--psm")
-        .arg("3")
-        .arg("tsv")
-        .output();
+    // Tesseract's config name, which also picks the output renderer
+    fn config(self) -> &'static str {
+        match self {
+            OcrFormat::Txt => "txt",
+            OcrFormat::Hocr => "hocr",
+            OcrFormat::Alto => "alto",
+            OcrFormat::Tsv => "tsv",
+        }
+    }
 
-    let confidence = if let Ok(tsv) = tsv_output {
-        let tsv_text = String::from_utf8_lossy(&tsv.stdout);
-        let confs: Vec<f64> = tsv_text
-            .lines()
-            .skip(1)
-            .filter_map(|line| {
-                let cols: Vec<&str> = line.split('\t').collect();
-                if cols.len() >= 12 {
-                    cols[10].parse::<f64>().ok().filter(|&c| c >= 0.0)
-                } else {
-                    None
-                }
-            })
-            .collect();
-        if confs.is_empty() {
-            0.0
-        } else {
-            confs.iter().sum::<f64>() / confs.len() as f64
+    // Extension of the file that renderer writes
+    fn extension(self) -> &'static str {
+        match self {
+            OcrFormat::Alto => "xml",
+            other => other.config(),
         }
-    } else {
-        0.0
+    }
+}
+
+// `psm` 6 suits a single block of text, 7 a single line (receipts), 11 sparse
+// text; `oem` 0 is the legacy engine, 1 LSTM, 2 both and 3 whatever is installed
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn ocr_image(
+    app: AppHandle,
+    state: State<OcrState>,
+    path: String,
+    language: String,
+    psm: Option<u32>,
+    oem: Option<u32>,
+    auto_rotate: bool,
+    output_format: String,
+) -> Result<String, AppError> {
+    let defaults = OcrOptions::default();
+    let options = OcrOptions {
+        psm: psm.unwrap_or(defaults.psm),
+        oem: oem.unwrap_or(defaults.oem),
+        auto_rotate,
+        format: OcrFormat::parse(&output_format)?,
     };
+    if options.psm > 13 {
+        return Err(AppError::invalid(format!("Page segmentation mode must be 0–13, got {}", options.psm)));
+    }
+    if options.oem > 3 {
+        return Err(AppError::invalid(format!("OCR engine mode must be 0–3, got {}", options.oem)));
+    }
+    Ok(start_job(app, &state.jobs, move |jobs, job_id| {
+        ocr_file(jobs, job_id, &path, &language, options)
+    }))
+}
+
+// Degrees to turn the page clockwise so the text is upright, from Tesseract's
+// orientation detection. Pages it can't judge (too little text, no osd
+// language data) are left as they are.
+fn detect_rotation(jobs: &Jobs, job_id: &str, path: &str) -> Result<u32, AppError> {
+    let output = run_tracked(
+        jobs,
+        job_id,
+        "tesseract",
+        Command::new(find_tesseract()).arg(path).arg("-").arg("--psm").arg("0"),
+    )?;
+    if !output.status.success() {
+        log::warn!("Orientation detection failed for {}: {}", path, String::from_utf8_lossy(&output.stderr).trim());
+        return Ok(0);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix("Rotate:"))
+        .and_then(|r| r.trim().parse().ok())
+        .unwrap_or(0))
+}
+
+fn ocr_file(jobs: &Jobs, job_id: &str, path: &str, language: &str, options: OcrOptions) -> Result<OcrResult, AppError> {
+    let tesseract = find_tesseract();
+    let tmp_dir = TempDir::new().map_err(|e| AppError::io("Cannot create a temp folder", e))?;
+    track_temp_dir(jobs, job_id, tmp_dir.path());
+    let output_base = tmp_dir.path().join("ocr_output");
 
-    let file_name = Path::new(&path)
+    // Tesseract can't turn the page itself, so a rotated copy is OCR'd instead
+    let file_name = Path::new(path)
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown")
         .to_string();
+    let rotation = if options.auto_rotate { detect_rotation(jobs, job_id, path)? } else { 0 };
+    let rotated = tmp_dir.path().join("rotated.png");
+    let path = if rotation % 360 != 0 {
+        let output = run_tracked(
+            jobs,
+            job_id,
+            "magick",
+            Command::new(find_tool("magick"))
+                .arg(path)
+                .arg("-rotate")
+                .arg(rotation.to_string())
+                .arg(&rotated),
+        )?;
+        if !output.status.success() {
+            return Err(AppError::process_failed("ImageMagick", &output));
+        }
+        rotated.to_str().unwrap()
+    } else {
+        path
+    };
+
+    let output = run_tracked(
+        jobs,
+        job_id,
+        "tesseract",
+        Command::new(&tesseract)
+            .arg(path)
+            .arg(output_base.to_str().unwrap())
+            .arg("-l")
+            .arg(language)
+            .arg("--psm")
+            .arg(options.psm.to_string())
+            .arg("--oem")
+            .arg(options.oem.to_string())
+            .arg(options.format.config()),
+    )?;
+
+    if !output.status.success() {
+        return Err(AppError::process_failed("tesseract", &output));
+    }
+
+    let text_file = format!("{}.{}", output_base.to_str().unwrap(), options.format.extension());
+    let text = fs::read_to_string(&text_file)
+        .map_err(|e| AppError::io("Failed to read OCR output", e))?;
+
+    // Get confidence via tsv output, unless that is what was asked for
+    let confidence = if options.format == OcrFormat::Tsv {
+        mean_confidence(&text)
+    } else {
+        run_tracked(
+            jobs,
+            job_id,
+            "tesseract",
+            Command::new(&tesseract)
+                .arg(path)
+                .arg("stdout")
+                .arg("-l")
+                .arg(language)
+                .arg("--psm")
+                .arg(options.psm.to_string())
+                .arg("--oem")
+                .arg(options.oem.to_string())
+                .arg("tsv"),
+        )
+        .map(|tsv| mean_confidence(&String::from_utf8_lossy(&tsv.stdout)))
+        .unwrap_or(0.0)
+    };
 
     Ok(OcrResult {
         file: file_name,
         text,
         confidence,
-        language,
+        language: language.to_string(),
+        format: options.format.config().to_string(),
     })
 }
 
+// Average word confidence from Tesseract's TSV; -1 marks non-word rows
+fn mean_confidence(tsv: &str) -> f64 {
+    let confs: Vec<f64> = tsv
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() >= 12 {
+                cols[10].parse::<f64>().ok().filter(|&c| c >= 0.0)
+            } else {
+                None
+            }
+        })
+        .collect();
+    if confs.is_empty() {
+        0.0
+    } else {
+        confs.iter().sum::<f64>() / confs.len() as f64
+    }
+}
+
+// Without a page range the whole document is extracted; an open end runs
+// to the first or last page
 #[tauri::command]
-fn pdf_to_text(path: String) -> Result<String, String> {
-    let output = Command::new(find_tool("pdftotext"))
-        .arg("-layout")
-        .arg(&path)
-        .arg("-")
+fn pdf_to_text(path: String, first_page: Option<u32>, last_page: Option<u32>) -> Result<String, AppError> {
+    if first_page == Some(0) || last_page == Some(0) {
+        return Err(AppError::invalid("Page numbers start at 1"));
+    }
+    if let (Some(first), Some(last)) = (first_page, last_page) {
+        if first > last {
+            return Err(AppError::invalid(format!(
+                "First page ({}) is after the last page ({})",
+                first, last
+            )));
+        }
+    }
+    let output = pdftotext_command(&path, first_page, last_page)
         .output()
-        .map_err(|e| format!("pdftotext failed: {}", e))?;
+        .map_err(|e| spawn_error("pdftotext", e))?;
+    pdftotext_result(output)
+}
 
+fn pdftotext_command(path: &str, first_page: Option<u32>, last_page: Option<u32>) -> Command {
+    let mut cmd = Command::new(find_tool("pdftotext"));
+    cmd.arg("-layout");
+    if let Some(first) = first_page {
+        cmd.arg("-f").arg(first.to_string());
+    }
+    if let Some(last) = last_page {
+        cmd.arg("-l").arg(last.to_string());
+    }
+    cmd.arg(path).arg("-");
+    cmd
+}
+
+fn pdftotext_result(output: Output) -> Result<String, AppError> {
     if !output.status.success() {
-        return Err(format!(
-            "pdftotext error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+        return Err(AppError::process_failed("pdftotext", &output));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+const DEFAULT_DPI: u32 = 300;
+const DPI_RANGE: std::ops::RangeInclusive<u32> = 50..=1200;
+
+// `pages` takes "3", "1-5", "8-" (to the end) and "-4", comma-separated
 #[tauri::command]
-fn pdf_to_images(path: String) -> Result<Vec<String>, String> {
-    let tmp_dir = TempDir::new().map_err(|e| e.to_string())?;
+fn pdf_to_images(
+    app: AppHandle,
+    state: State<OcrState>,
+    path: String,
+    dpi: Option<u32>,
+    pages: Option<String>,
+) -> Result<String, AppError> {
+    let dpi = dpi.unwrap_or(DEFAULT_DPI);
+    if !DPI_RANGE.contains(&dpi) {
+        return Err(AppError::invalid(format!(
+            "DPI must be between {} and {}",
+            DPI_RANGE.start(),
+            DPI_RANGE.end()
+        )));
+    }
+    let pages = pages.filter(|p| !p.trim().is_empty());
+    Ok(start_job(app, &state.jobs, move |jobs, job_id| {
+        render_pages(jobs, job_id, &path, dpi, pages.as_deref())
+    }))
+}
+
+fn render_pages(
+    jobs: &Jobs,
+    job_id: &str,
+    path: &str,
+    dpi: u32,
+    pages: Option<&str>,
+) -> Result<Vec<String>, AppError> {
+    let tmp_dir = TempDir::new().map_err(|e| AppError::io("Cannot create a temp folder", e))?;
     let tmp_path = tmp_dir.into_path(); // persist so images remain
+    track_temp_dir(jobs, job_id, &tmp_path);
 
-    let output_prefix = tmp_path.join("page");
+    render_pages_into(jobs, job_id, path, dpi, pages, &tmp_path).inspect_err(|_| {
+        let _ = fs::remove_dir_all(&tmp_path);
+    })
+}
 
-    let output = Command::new(find_tool("pdftoppm"))
-        .arg("-png")
-        .arg("-r")
-        .arg("300")
-        .arg(&path)
-        .arg(output_prefix.to_str().unwrap())
-        .output()
-        .map_err(|e| format!("pdftoppm failed: {}", e))?;
+fn render_pages_into(
+    jobs: &Jobs,
+    job_id: &str,
+    path: &str,
+    dpi: u32,
+    pages: Option<&str>,
+    dir: &Path,
+) -> Result<Vec<String>, AppError> {
+    // No selection means one pdftoppm run over the whole document
+    let ranges = match pages {
+        Some(spec) => parse_page_ranges(spec, pdf_page_count(jobs, job_id, path)?)?
+            .into_iter()
+            .map(Some)
+            .collect(),
+        None => vec![None],
+    };
 
-    if !output.status.success() {
-        return Err(format!(
-            "pdftoppm error: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+    let output_prefix = dir.join("page");
+
+    for range in ranges {
+        let mut cmd = Command::new(find_tool("pdftoppm"));
+        cmd.arg("-png").arg("-r").arg(dpi.to_string());
+        if let Some((first, last)) = range {
+            cmd.arg("-f").arg(first.to_string()).arg("-l").arg(last.to_string());
+        }
+        cmd.arg(path).arg(output_prefix.to_str().unwrap());
+
+        let output = run_tracked(jobs, job_id, "pdftoppm", &mut cmd)?;
+
+        if !output.status.success() {
+            return Err(AppError::process_failed("pdftoppm", &output));
+        }
     }
 
-    let mut images: Vec<String> = fs::read_dir(&tmp_path)
-        .map_err(|e| e.to_string())?
+    let mut images: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| AppError::io("Cannot read the rendered pages", e))?
         .filter_map(|e| e.ok())
         .map(|e| e.path().to_string_lossy().to_string())
         .filter(|p| p.ends_with(".png"))
         .collect();
 
-    images.sort();
+    // pdftoppm zero-pads to the width of the page count, but go by the number
+    images.sort_by_key(|p| page_number(p));
     Ok(images)
 }
 
+fn pdf_page_count(jobs: &Jobs, job_id: &str, path: &str) -> Result<u32, AppError> {
+    let output = run_tracked(jobs, job_id, "pdfinfo", Command::new(find_tool("pdfinfo")).arg(path))?;
+    if !output.status.success() {
+        return Err(AppError::process_failed("pdfinfo", &output));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix("Pages:"))
+        .and_then(|n| n.trim().parse().ok())
+        .ok_or_else(|| AppError::invalid("Could not read the page count"))
+}
+
+// Sorted, merged (first, last) ranges, each checked against the page count
+fn parse_page_ranges(spec: &str, page_count: u32) -> Result<Vec<(u32, u32)>, AppError> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<u32>()
+            .map_err(|_| AppError::invalid(format!("Invalid page number '{}' in '{}'", n.trim(), spec)))
+    };
+
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((a, b)) => (
+                if a.trim().is_empty() { 1 } else { parse(a)? },
+                if b.trim().is_empty() { page_count } else { parse(b)? },
+            ),
+            None => (parse(part)?, parse(part)?),
+        };
+        if first == 0 || first > last {
+            return Err(AppError::invalid(format!("Invalid page range '{}'", part)));
+        }
+        if last > page_count {
+            return Err(AppError::invalid(format!(
+                "Page range '{}' is past the end of the document ({} pages)",
+                part, page_count
+            )));
+        }
+        ranges.push((first, last));
+    }
+    if ranges.is_empty() {
+        return Err(AppError::invalid(format!("No pages selected in '{}'", spec)));
+    }
+
+    ranges.sort();
+    let mut merged: Vec<(u32, u32)> = Vec::new();
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some(prev) if first <= prev.1 + 1 => prev.1 = prev.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    Ok(merged)
+}
+
+// "page-07.png" → 7
+fn page_number(image: &str) -> u32 {
+    image
+        .trim_end_matches(".png")
+        .rsplit('-')
+        .next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
 #[tauri::command]
-fn pdf_to_docx(pdf_path: String, output_path: String) -> Result<ConversionResult, String> {
+fn pdf_to_docx(app: AppHandle, state: State<OcrState>, pdf_path: String, output_path: String) -> String {
+    start_job(app, &state.jobs, move |jobs, job_id| {
+        convert_pdf_to_docx(jobs, job_id, &pdf_path, &output_path)
+    })
+}
+
+fn convert_pdf_to_docx(jobs: &Jobs, job_id: &str, pdf_path: &str, output_path: &str) -> Result<ConversionResult, AppError> {
     // Strategy: extract text with pdftotext, then create a simple DOCX
     // For image-based PDFs, we use OCR first
-    let text = pdf_to_text(pdf_path.clone()).unwrap_or_default();
+    let text = run_tracked(jobs, job_id, "pdftotext", &mut pdftotext_command(pdf_path, None, None))
+        .and_then(pdftotext_result)
+        .unwrap_or_default();
 
     if text.trim().is_empty() {
-        return Err("PDF appears to be image-based. Use OCR mode for this PDF.".to_string());
+        return Err(AppError::invalid("PDF appears to be image-based. Use OCR mode for this PDF."));
     }
 
     // Use LibreOffice for the conversion if available, or create simple text-based docx
     // First try: convert PDF directly with LibreOffice
-    let output_dir = Path::new(&output_path)
+    let output_dir = Path::new(output_path)
         .parent()
         .unwrap_or(Path::new("."))
         .to_string_lossy()
         .to_string();
 
-    let result = Command::new(find_tool("soffice"))
-        .arg("--headless")
-        .arg("--convert-to")
-        .arg("docx")
-        .arg("--outdir")
-        .arg(&output_dir)
-        .arg(&pdf_path)
-        .output();
+    let out = run_tracked(
+        jobs,
+        job_id,
+        "soffice",
+        Command::new(find_tool("soffice"))
+            .arg("--headless")
+            .arg("--convert-to")
+            .arg("docx")
+            .arg("--outdir")
+            .arg(&output_dir)
+            .arg(pdf_path),
+    )?;
 
-    match result {
-        Ok(out) if out.status.success() => {
-            // LibreOffice creates the file with same name but .docx extension
-            let pdf_stem = Path::new(&pdf_path)
-                .file_stem()
-                .unwrap()
-                .to_str()
-                .unwrap();
-            let created = format!("{}/{}.docx", output_dir, pdf_stem);
-
-            // Rename if needed
-            if created != output_path {
-                let _ = fs::rename(&created, &output_path);
+    if !out.status.success() {
+        return Err(AppError::process_failed("LibreOffice", &out));
+    }
+
+    // LibreOffice creates the file with same name but .docx extension
+    let pdf_stem = Path::new(pdf_path)
+        .file_stem()
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let created = format!("{}/{}.docx", output_dir, pdf_stem);
+
+    // Rename if needed
+    if created != output_path {
+        let _ = fs::rename(&created, output_path);
+    }
+
+    Ok(ConversionResult {
+        success: true,
+        output_path: output_path.to_string(),
+        message: "PDF converted to DOCX successfully".to_string(),
+        page_count: None,
+    })
+}
+
+/// An image or PDF → PDF with an invisible text layer over each page, so it
+/// can be searched and copied from. Runs as a job.
+#[tauri::command]
+fn ocr_to_searchable_pdf(
+    app: AppHandle,
+    state: State<OcrState>,
+    path: String,
+    language: String,
+    output_path: String,
+) -> String {
+    start_job(app, &state.jobs, move |jobs, job_id| {
+        searchable_pdf(jobs, job_id, &path, &language, &output_path)
+    })
+}
+
+fn searchable_pdf(jobs: &Jobs, job_id: &str, path: &str, language: &str, output_path: &str) -> Result<ConversionResult, AppError> {
+    let tmp_dir = TempDir::new().map_err(|e| AppError::io("Cannot create a temp folder", e))?;
+    track_temp_dir(jobs, job_id, tmp_dir.path());
+
+    // Tesseract reads images only, so PDFs are rendered and OCR'd page by
+    // page, then the page PDFs are joined
+    let images = if detect_file_type(path) == "pdf" {
+        render_pages_into(jobs, job_id, path, DEFAULT_DPI, None, tmp_dir.path())?
+    } else {
+        vec![path.to_string()]
+    };
+
+    let mut page_pdfs = Vec::new();
+    for (i, image) in images.iter().enumerate() {
+        let stem = tmp_dir.path().join(format!("ocr-{}", i + 1));
+        let output = run_tracked(
+            jobs,
+            job_id,
+            "tesseract",
+            Command::new(find_tesseract())
+                .arg(image)
+                .arg(&stem)
+                .arg("-l")
+                .arg(language)
+                .arg("pdf"),
+        )?;
+        if !output.status.success() {
+            return Err(AppError::process_failed("tesseract", &output));
+        }
+        page_pdfs.push(stem.with_extension("pdf"));
+    }
+
+    let merged = match page_pdfs.as_slice() {
+        [single] => single.clone(),
+        pages => {
+            let merged = tmp_dir.path().join("merged.pdf");
+            let output = run_tracked(
+                jobs,
+                job_id,
+                "pdfunite",
+                Command::new(find_tool("pdfunite")).args(pages).arg(&merged),
+            )?;
+            if !output.status.success() {
+                return Err(AppError::process_failed("pdfunite", &output));
+            }
+            merged
+        }
+    };
+
+    // A multi-page TIFF is one image but several pages
+    let page_count = match images.len() {
+        1 => pdf_page_count(jobs, job_id, &merged.to_string_lossy()).unwrap_or(1),
+        n => n as u32,
+    };
+
+    if let Some(parent) = Path::new(output_path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| AppError::io("Cannot create output folder", e))?;
+    }
+    // The temp folder may be on another disk, where rename fails
+    if fs::rename(&merged, output_path).is_err() {
+        fs::copy(&merged, output_path).map_err(|e| AppError::io("Cannot write the PDF", e))?;
+    }
+
+    Ok(ConversionResult {
+        success: true,
+        output_path: output_path.to_string(),
+        message: format!("Searchable PDF created ({} pages)", page_count),
+        page_count: Some(page_count),
+    })
+}
+
+// Mirrors `input_dir` into `output_dir`: <relative path>.txt per image or PDF,
+// keeping the full file name so scan.png and scan.pdf don't collide. Runs as a
+// job; files whose text is newer than the scan are skipped on re-runs.
+#[tauri::command]
+fn batch_ocr_to_files(
+    app: AppHandle,
+    state: State<OcrState>,
+    input_dir: String,
+    output_dir: String,
+    language: String,
+    recursive: bool,
+) -> Result<String, AppError> {
+    let input = PathBuf::from(&input_dir);
+    if !input.is_dir() {
+        return Err(AppError::invalid(format!("{} is not a folder", input_dir)));
+    }
+    let output = PathBuf::from(output_dir);
+    let events = app.clone();
+
+    Ok(start_job(app, &state.jobs, move |jobs, job_id| {
+        let scans = collect_scans(&input, &output, recursive)?;
+        let mut summary = BatchSummary::default();
+
+        for (i, scan) in scans.iter().enumerate() {
+            let relative = scan.strip_prefix(&input).unwrap_or(scan);
+            let _ = events.emit(
+                "batch-progress",
+                BatchProgress {
+                    current: i + 1,
+                    total: scans.len(),
+                    current_file: relative.to_string_lossy().to_string(),
+                },
+            );
+
+            let mut target = output.join(relative).into_os_string();
+            target.push(".txt");
+            let target = PathBuf::from(target);
+            if is_up_to_date(scan, &target) {
+                summary.skipped += 1;
+                continue;
             }
 
-            Ok(ConversionResult {
-                success: true,
-                output_path: output_path.clone(),
-                message: "PDF converted to DOCX successfully".to_string(),
-            })
+            let result = ocr_to_text(jobs, job_id, scan, &language).and_then(|text| {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).map_err(|e| AppError::io("Cannot create output folder", e))?;
+                }
+                fs::write(&target, text).map_err(|e| AppError::io("Cannot write text file", e))
+            });
+            match result {
+                Ok(()) => summary.processed += 1,
+                Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+                Err(e) => {
+                    log::warn!("OCR failed for {}: {}", scan.display(), e);
+                    summary.failed += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }))
+}
+
+// Images and PDFs under `dir`, sorted; the output folder is left out in case
+// it sits inside the input
+fn collect_scans(dir: &Path, output: &Path, recursive: bool) -> Result<Vec<PathBuf>, AppError> {
+    let mut scans = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| AppError::io(format!("Cannot read {}", dir.display()), e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if recursive && path != output {
+                scans.extend(collect_scans(&path, output, recursive)?);
+            }
+        } else if matches!(detect_file_type(&path.to_string_lossy()).as_str(), "image" | "pdf") {
+            scans.push(path);
         }
-        _ => Err("LibreOffice conversion failed. Ensure LibreOffice is installed.".to_string()),
     }
+    scans.sort();
+    Ok(scans)
+}
+
+fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => output >= input,
+        _ => false,
+    }
+}
+
+// PDFs are rendered page by page into a temp folder and the texts joined
+fn ocr_to_text(jobs: &Jobs, job_id: &str, path: &Path, language: &str) -> Result<String, AppError> {
+    let path = path.to_string_lossy();
+    if detect_file_type(&path) != "pdf" {
+        return ocr_file(jobs, job_id, &path, language, OcrOptions::default()).map(|r| r.text);
+    }
+
+    let tmp_dir = TempDir::new().map_err(|e| AppError::io("Cannot create a temp folder", e))?;
+    track_temp_dir(jobs, job_id, tmp_dir.path());
+    let pages = render_pages_into(jobs, job_id, &path, DEFAULT_DPI, None, tmp_dir.path())?;
+    let mut texts = Vec::new();
+    for page in pages {
+        texts.push(ocr_file(jobs, job_id, &page, language, OcrOptions::default())?.text);
+    }
+    Ok(texts.join("\n\n"))
+}
+
+// Kills whatever tool the job is running and deletes its temp files
+#[tauri::command]
+fn cancel_ocr(state: State<OcrState>, job_id: String) -> Result<(), AppError> {
+    let mut jobs = state.jobs.lock().unwrap();
+    let job = jobs.get_mut(&job_id).ok_or_else(|| AppError::invalid("No such job"))?;
+    job.cancelled = true;
+    if let Some(mut child) = job.child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    for dir in job.temp_dirs.drain(..) {
+        let _ = fs::remove_dir_all(dir);
+    }
+    Ok(())
 }
 
 #[tauri::command]
-fn docx_to_pdf(docx_path: String, output_path: String) -> Result<ConversionResult, String> {
+fn docx_to_pdf(docx_path: String, output_path: String) -> Result<ConversionResult, AppError> {
     let output_dir = Path::new(&output_path)
         .parent()
         .unwrap_or(Path::new("."))
@@ -340,13 +1078,10 @@ fn docx_to_pdf(docx_path: String, output_path: String) -> Result<ConversionResul
         .arg(&output_dir)
         .arg(&docx_path)
         .output()
-        .map_err(|e| format!("LibreOffice failed: {}", e))?;
+        .map_err(|e| spawn_error("soffice", e))?;
 
     if !result.status.success() {
-        return Err(format!(
-            "Conversion failed: {}",
-            String::from_utf8_lossy(&result.stderr)
-        ));
+        return Err(AppError::process_failed("LibreOffice", &result));
     }
 
     let docx_stem = Path::new(&docx_path)
@@ -364,11 +1099,12 @@ fn docx_to_pdf(docx_path: String, output_path: String) -> Result<ConversionResul
         success: true,
         output_path: output_path.clone(),
         message: "DOCX converted to PDF successfully".to_string(),
+        page_count: None,
     })
 }
 
 #[tauri::command]
-fn images_to_pdf(image_paths: Vec<String>, output_path: String) -> Result<ConversionResult, String> {
+fn images_to_pdf(image_paths: Vec<String>, output_path: String) -> Result<ConversionResult, AppError> {
     // Use ImageMagick convert or img2pdf
     let result = Command::new(find_tool("img2pdf"))
         .args(&image_paths)
@@ -381,6 +1117,7 @@ fn images_to_pdf(image_paths: Vec<String>, output_path: String) -> Result<Conver
             success: true,
             output_path: output_path.clone(),
             message: format!("{} images merged into PDF", image_paths.len()),
+            page_count: None,
         }),
         _ => {
             // Fallback: try ImageMagick
@@ -389,34 +1126,49 @@ fn images_to_pdf(image_paths: Vec<String>, output_path: String) -> Result<Conver
             let result2 = Command::new(find_tool("magick"))
                 .args(&args)
                 .output()
-                .map_err(|e| format!("Neither img2pdf nor ImageMagick available: {}", e))?;
+                .map_err(|e| {
+                    if e.kind() == std::io::ErrorKind::NotFound {
+                        AppError::ToolNotFound {
+                            tool: "img2pdf or ImageMagick".to_string(),
+                            hint: format!(
+                                "img2pdf: {} ImageMagick: {}",
+                                install_hint("img2pdf"),
+                                install_hint("magick")
+                            ),
+                        }
+                    } else {
+                        spawn_error("magick", e)
+                    }
+                })?;
 
             if result2.status.success() {
                 Ok(ConversionResult {
                     success: true,
                     output_path,
                     message: format!("{} images merged into PDF (ImageMagick)", image_paths.len()),
+                    page_count: None,
                 })
             } else {
-                Err("Failed to create PDF from images".to_string())
+                Err(AppError::process_failed("ImageMagick", &result2))
             }
         }
     }
 }
 
 #[tauri::command]
-fn save_text_to_file(text: String, output_path: String) -> Result<ConversionResult, String> {
-    fs::write(&output_path, &text).map_err(|e| e.to_string())?;
+fn save_text_to_file(text: String, output_path: String) -> Result<ConversionResult, AppError> {
+    fs::write(&output_path, &text).map_err(|e| AppError::io("Failed to save text", e))?;
     Ok(ConversionResult {
         success: true,
         output_path: output_path.clone(),
         message: "Text saved successfully".to_string(),
+        page_count: None,
     })
 }
 
 #[tauri::command]
-fn read_file_base64(path: String) -> Result<String, String> {
-    let data = fs::read(&path).map_err(|e| e.to_string())?;
+fn read_file_base64(path: String) -> Result<String, AppError> {
+    let data = fs::read(&path).map_err(|e| AppError::io(format!("Cannot read {}", path), e))?;
     Ok(base64::Engine::encode(
         &base64::engine::general_purpose::STANDARD,
         &data,
@@ -428,6 +1180,7 @@ fn read_file_base64(path: String) -> Result<String, String> {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(OcrState::default())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
@@ -443,12 +1196,16 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             check_dependencies,
+            check_all_tools,
             get_tesseract_languages,
             validate_files,
             ocr_image,
             pdf_to_text,
             pdf_to_images,
             pdf_to_docx,
+            ocr_to_searchable_pdf,
+            cancel_ocr,
+            batch_ocr_to_files,
             docx_to_pdf,
             images_to_pdf,
             save_text_to_file,
@@ -1,11 +1,15 @@
 // ─── CORE OCR Converter — Frontend ──────────────────────────────────────────
 const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
 const { open, save } = window.__TAURI__.dialog;
 
 // ─── State ───────────────────────────────────────────────────────────────────
 let files = [];
 let currentMode = 'ocr';
 let isProcessing = false;
+let cancelRequested = false;
+let currentJobId = null;
+const pendingJobs = new Map(); // job id → { resolve, reject }
 
 // ─── DOM Elements ────────────────────────────────────────────────────────────
 const $ = (s) => document.querySelector(s);
@@ -23,6 +27,35 @@ const progressText = $('#progressText');
 const statusText = $('#statusText');
 const statusRight = $('#statusRight');
 const resultContainer = $('#resultContainer');
+const btnCancel = $('#btnCancel');
+
+// ─── Jobs ────────────────────────────────────────────────────────────────────
+// Long commands hand back a job id right away and finish through an event
+listen('ocr-job-finished', (event) => {
+  const { job_id, result, error, cancelled } = event.payload;
+  const job = pendingJobs.get(job_id);
+  if (!job) return;
+  pendingJobs.delete(job_id);
+  if (job_id === currentJobId) currentJobId = null;
+  if (cancelled) job.reject('Cancelled');
+  else if (error) job.reject(error);
+  else job.resolve(result);
+});
+
+async function runJob(command, args) {
+  if (cancelRequested) throw 'Cancelled';
+  const jobId = await invoke(command, args);
+  currentJobId = jobId;
+  return new Promise((resolve, reject) => pendingJobs.set(jobId, { resolve, reject }));
+}
+
+async function cancelCurrentJob() {
+  cancelRequested = true;
+  setStatus('Cancelling…');
+  if (currentJobId) {
+    try { await invoke('cancel_ocr', { jobId: currentJobId }); } catch { }
+  }
+}
 
 // ─── Init ────────────────────────────────────────────────────────────────────
 document.addEventListener('DOMContentLoaded', async () => {
@@ -55,6 +88,10 @@ async function checkDependencies() {
       depLabel.textContent = 'No tools found';
     }
 
+    // Hover the label for install commands of whatever is missing
+    const missingTools = (await invoke('check_all_tools')).filter(t => !t.found);
+    depLabel.title = missingTools.map(t => `${t.name}: ${t.install_hint}`).join('\n');
+
     // Load available tesseract languages
     if (deps.tesseract) {
       try {
@@ -146,6 +183,8 @@ function setupEventListeners() {
   $('#btnAddFiles').addEventListener('click', addFiles);
   $('#btnClear').addEventListener('click', () => { files = []; updateUI(); setStatus('Cleared'); });
   btnConvert.addEventListener('click', runConversion);
+  btnCancel.addEventListener('click', cancelCurrentJob);
+  $('#btnOcrFolder').addEventListener('click', ocrFolder);
 
   // Mode tabs
   $$('.mode-tab').forEach(tab => {
@@ -158,6 +197,7 @@ function setupEventListeners() {
       $$('.mode-options').forEach(o => o.classList.add('hidden'));
       const optId = {
         'ocr': 'optOcr',
+        'ocrpdf': 'optOcr',
         'pdf2docx': 'optPdf2docx',
         'docx2pdf': 'optDocx2pdf',
         'pdf2text': 'optPdf2text',
@@ -238,6 +278,7 @@ function renderFileList() {
 function updateConvertButton() {
   const labels = {
     'ocr': '🔍 Run OCR',
+    'ocrpdf': '🔎 Make Searchable',
     'pdf2docx': '📝 Convert to Word',
     'docx2pdf': '📄 Convert to PDF',
     'pdf2text': '📋 Extract Text',
@@ -280,6 +321,7 @@ async function runConversion() {
   if (isProcessing || files.length === 0) return;
   
   isProcessing = true;
+  cancelRequested = false;
   updateConvertButton();
   
   // Switch to result tab
@@ -292,14 +334,15 @@ async function runConversion() {
   try {
     switch (currentMode) {
       case 'ocr': await runOCR(); break;
+      case 'ocrpdf': await runSearchablePdf(); break;
       case 'pdf2docx': await runPdf2Docx(); break;
       case 'docx2pdf': await runDocx2Pdf(); break;
       case 'pdf2text': await runPdf2Text(); break;
       case 'img2pdf': await runImg2Pdf(); break;
     }
   } catch (e) {
-    resultContainer.innerHTML = `<div class="empty-state"><div class="empty-icon">❌</div><p>Error: ${e}</p></div>`;
-    setStatus('Error: ' + e);
+    resultContainer.innerHTML = `<div class="empty-state"><div class="empty-icon">❌</div><p>${escapeHtml(errorText(e))}</p></div>`;
+    setStatus(errorText(e));
   }
 
   isProcessing = false;
@@ -318,7 +361,7 @@ async function runOCR() {
 
   resultContainer.innerHTML = '';
   
-  for (let i = 0; i < imageFiles.length; i++) {
+  for (let i = 0; i < imageFiles.length && !cancelRequested; i++) {
     const file = imageFiles[i];
     showProgress(i + 1, imageFiles.length, `OCR: ${file.name}`);
     setStatus(`Processing ${file.name}…`, `${i + 1}/${imageFiles.length}`);
@@ -328,14 +371,30 @@ async function runOCR() {
       
       if (file.file_type === 'pdf') {
         // Convert PDF pages to images first
-        const images = await invoke('pdf_to_images', { path: file.path });
-        filesToOcr = images.map((img, idx) => ({ path: img, name: `${file.name} (page ${idx + 1})` }));
+        const images = await runJob('pdf_to_images', {
+          path: file.path,
+          dpi: parseInt($('#ocrDpi').value, 10) || null,
+          pages: $('#ocrPages').value.trim() || null,
+        });
+        // Only the selected pages come back, so take the number from pdftoppm's name
+        filesToOcr = images.map((img) => {
+          const page = parseInt(img.match(/-(\d+)\.png$/)?.[1], 10);
+          return { path: img, name: `${file.name} (page ${page})` };
+        });
       } else {
         filesToOcr = [{ path: file.path, name: file.name }];
       }
 
       for (const f of filesToOcr) {
-        const result = await invoke('ocr_image', { path: f.path, language });
+        if (cancelRequested) break;
+        const result = await runJob('ocr_image', {
+          path: f.path,
+          language,
+          psm: parseInt($('#ocrPsm').value, 10),
+          oem: parseInt($('#ocrOem').value, 10),
+          autoRotate: $('#ocrAutoRotate').checked,
+          outputFormat: $('#ocrFormat').value,
+        });
         appendOcrResult({ ...result, file: f.name });
       }
     } catch (e) {
@@ -343,27 +402,90 @@ async function runOCR() {
     }
   }
   
-  setStatus('OCR complete', `${imageFiles.length} files processed`);
+  if (cancelRequested) setStatus('OCR cancelled');
+  else setStatus('OCR complete', `${imageFiles.length} files processed`);
+}
+
+// Whole folder tree → mirrored .txt files; re-runs only redo changed scans
+async function ocrFolder() {
+  if (isProcessing) return;
+  const inputDir = await open({ directory: true, title: 'Folder with scans' });
+  if (!inputDir) return;
+  const outputDir = await open({ directory: true, title: 'Where to write the text files' });
+  if (!outputDir) return;
+
+  isProcessing = true;
+  cancelRequested = false;
+  updateConvertButton();
+  showProgress(0, 1, 'Scanning folder…');
+  const unlisten = await listen('batch-progress', (event) => {
+    const p = event.payload;
+    showProgress(p.current, p.total, `OCR: ${p.current_file}`);
+    setStatus(`Processing ${p.current_file}…`, `${p.current}/${p.total}`);
+  });
+
+  try {
+    const summary = await runJob('batch_ocr_to_files', {
+      inputDir,
+      outputDir,
+      language: $('#ocrLang').value,
+      recursive: $('#ocrRecursive').checked,
+    });
+    setStatus('Folder OCR complete',
+      `${summary.processed} processed · ${summary.skipped} skipped · ${summary.failed} failed`);
+  } catch (e) {
+    setStatus(e === 'Cancelled' ? 'Folder OCR cancelled' : errorText(e));
+  }
+
+  unlisten();
+  isProcessing = false;
+  hideProgress();
+  updateConvertButton();
+}
+
+// Scans → PDFs with a text layer, written next to the originals
+async function runSearchablePdf() {
+  const scanFiles = files.filter(f => f.file_type === 'image' || f.file_type === 'pdf');
+  if (scanFiles.length === 0) { setStatus('No image or PDF files selected'); return; }
+
+  for (let i = 0; i < scanFiles.length && !cancelRequested; i++) {
+    const file = scanFiles[i];
+    showProgress(i + 1, scanFiles.length, `OCR: ${file.name}`);
+    setStatus(`Making ${file.name} searchable…`, `${i + 1}/${scanFiles.length}`);
+
+    try {
+      const outputPath = file.path.replace(/\.[^./\\]+$/, '') + '_searchable.pdf';
+      const result = await runJob('ocr_to_searchable_pdf', {
+        path: file.path,
+        language: $('#ocrLang').value,
+        outputPath,
+      });
+      appendConversionResult(file.name, result);
+    } catch (e) {
+      appendErrorResult(file.name, e);
+    }
+  }
+  setStatus(cancelRequested ? 'OCR cancelled' : 'Searchable PDFs created');
 }
 
 async function runPdf2Docx() {
   const pdfFiles = files.filter(f => f.file_type === 'pdf');
   if (pdfFiles.length === 0) { setStatus('No PDF files selected'); return; }
 
-  for (let i = 0; i < pdfFiles.length; i++) {
+  for (let i = 0; i < pdfFiles.length && !cancelRequested; i++) {
     const file = pdfFiles[i];
     showProgress(i + 1, pdfFiles.length, `Converting: ${file.name}`);
     setStatus(`Converting ${file.name}…`);
 
     try {
       const outputPath = file.path.replace(/\.pdf$/i, '.docx');
-      const result = await invoke('pdf_to_docx', { pdfPath: file.path, outputPath });
+      const result = await runJob('pdf_to_docx', { pdfPath: file.path, outputPath });
       appendConversionResult(file.name, result);
     } catch (e) {
       appendErrorResult(file.name, e);
     }
   }
-  setStatus('Conversion complete');
+  setStatus(cancelRequested ? 'Conversion cancelled' : 'Conversion complete');
 }
 
 async function runDocx2Pdf() {
@@ -396,7 +518,11 @@ async function runPdf2Text() {
     setStatus(`Extracting text from ${file.name}…`);
 
     try {
-      const text = await invoke('pdf_to_text', { path: file.path });
+      const text = await invoke('pdf_to_text', {
+        path: file.path,
+        firstPage: parseInt($('#textFirstPage').value, 10) || null,
+        lastPage: parseInt($('#textLastPage').value, 10) || null,
+      });
       appendOcrResult({ file: file.name, text, confidence: -1, language: 'N/A' });
     } catch (e) {
       appendErrorResult(file.name, e);
@@ -438,6 +564,7 @@ function appendOcrResult(result) {
   
   const block = document.createElement('div');
   block.className = 'result-block';
+  block.dataset.format = result.format || 'txt';
   block.innerHTML = `
     <div class="result-header">
       <span class="result-filename">${result.file}</span>
@@ -446,7 +573,7 @@ function appendOcrResult(result) {
     <div class="result-text">${escapeHtml(result.text)}</div>
     <div class="result-actions">
       <button class="btn" onclick="copyText(this)">📋 Copy</button>
-      <button class="btn" onclick="saveText(this)">💾 Save as TXT</button>
+      <button class="btn" onclick="saveText(this)">💾 Save as ${saveLabel(block.dataset.format)}</button>
     </div>
   `;
   resultContainer.appendChild(block);
@@ -466,7 +593,20 @@ function appendConversionResult(filename, result) {
   resultContainer.appendChild(block);
 }
 
+// Commands and jobs fail with { kind, message, ... }; the message already
+// carries the install command when a tool is missing
+function errorText(e) {
+  if (!e || typeof e !== 'object') return `Error: ${e}`;
+  if (e.kind === 'invalid_input') return e.message;
+  if (e.kind === 'tool_not_found') return `Missing tool — ${e.message}`;
+  return `Error: ${e.message}`;
+}
+
 function appendErrorResult(filename, error) {
+  // A failed tool's last stderr lines go in a collapsed details block
+  const details = error?.kind === 'process_failed' && error.stderr_tail
+    ? `<details><summary>${escapeHtml(error.tool)} output</summary><pre>${escapeHtml(error.stderr_tail)}</pre></details>`
+    : '';
   const block = document.createElement('div');
   block.className = 'conversion-success';
   block.style.borderColor = 'rgba(255, 68, 102, 0.3)';
@@ -475,7 +615,8 @@ function appendErrorResult(filename, error) {
     <span class="check">❌</span>
     <div class="info">
       <h4 style="color: var(--danger);">${filename}</h4>
-      <p>${error}</p>
+      <p>${escapeHtml(errorText(error))}</p>
+      ${details}
     </div>
   `;
   resultContainer.appendChild(block);
@@ -495,17 +636,27 @@ window.copyText = function(btn) {
   setTimeout(() => { btn.textContent = '📋 Copy'; }, 1500);
 };
 
+// File extension for each OCR output format
+const FORMAT_EXTENSIONS = { txt: 'txt', hocr: 'hocr', alto: 'xml', tsv: 'tsv' };
+
+function saveLabel(format) {
+  return format === 'alto' ? 'ALTO' : (FORMAT_EXTENSIONS[format] || 'txt').toUpperCase();
+}
+
 window.saveText = async function(btn) {
-  const text = btn.closest('.result-block').querySelector('.result-text').textContent;
+  const block = btn.closest('.result-block');
+  const text = block.querySelector('.result-text').textContent;
+  const format = block.dataset.format || 'txt';
+  const ext = FORMAT_EXTENSIONS[format] || 'txt';
   try {
     const path = await save({
-      defaultPath: 'output.txt',
-      filters: [{ name: 'Text', extensions: ['txt'] }],
+      defaultPath: `output.${ext}`,
+      filters: [{ name: saveLabel(format), extensions: [ext] }],
     });
     if (path) {
       await invoke('save_text_to_file', { text, outputPath: path });
       btn.textContent = '✅ Saved';
-      setTimeout(() => { btn.textContent = '💾 Save as TXT'; }, 1500);
+      setTimeout(() => { btn.textContent = `💾 Save as ${saveLabel(format)}`; }, 1500);
     }
   } catch (e) {
     console.error('Save failed:', e);
@@ -8,6 +8,8 @@ fn main() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             get_pdf_info,
+            get_metadata,
+            set_metadata,
             merge_pdfs,
             split_pdf,
             rotate_pdf,
@@ -20,6 +22,11 @@ fn main() {
             remove_protection,
             get_page_thumbnails,
             reorder_pages,
+            delete_pages,
+            insert_blank_pages,
+            stamp_pdf,
+            extract_pages,
+            redact_regions,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
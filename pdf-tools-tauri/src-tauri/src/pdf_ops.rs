@@ -1,4 +1,4 @@
-use lopdf::Document;
+use lopdf::{dictionary, Document};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -32,57 +32,176 @@ pub fn get_pdf_info(path: String) -> Result<PdfInfo, String> {
     })
 }
 
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PdfMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    /// ISO 8601, e.g. "2024-03-01T09:30:00+01:00"
+    pub creation_date: Option<String>,
+    pub has_xmp: bool,
+}
+
+impl PdfMetadata {
+    // (Info key, XMP property, value) for every field
+    fn entries(&mut self) -> [(&'static str, &'static str, &mut Option<String>); 7] {
+        [
+            ("Title", "dc:title", &mut self.title),
+            ("Author", "dc:creator", &mut self.author),
+            ("Subject", "dc:description", &mut self.subject),
+            ("Keywords", "pdf:Keywords", &mut self.keywords),
+            ("Creator", "xmp:CreatorTool", &mut self.creator),
+            ("Producer", "pdf:Producer", &mut self.producer),
+            ("CreationDate", "xmp:CreateDate", &mut self.creation_date),
+        ]
+    }
+}
+
+// Info dictionary values win; XMP fills in whatever Info doesn't have
+#[tauri::command]
+pub fn get_metadata(path: String) -> Result<PdfMetadata, String> {
+    let doc = Document::load(&path).map_err(|e| e.to_string())?;
+    let info = doc.trailer.get_deref(b"Info", &doc).and_then(|i| i.as_dict()).ok();
+    let xmp = read_xmp(&doc).map(|(_, xmp)| xmp);
+
+    let mut meta = PdfMetadata {
+        has_xmp: xmp.is_some(),
+        ..Default::default()
+    };
+    for (key, property, value) in meta.entries() {
+        let from_info = info
+            .and_then(|i| i.get_deref(key.as_bytes(), &doc).ok())
+            .and_then(decode_text)
+            .map(|v| if key == "CreationDate" { pdf_date_to_iso(&v) } else { v });
+        *value = from_info
+            .filter(|v| !v.trim().is_empty())
+            .or_else(|| xmp.as_deref().and_then(|x| xmp_property(x, property)));
+    }
+    Ok(meta)
+}
+
+// Fields left as None are untouched; an empty string removes the entry.
+// The Info dictionary is created if missing, and an existing XMP packet gets
+// the same values so readers that prefer XMP agree with the Info dict.
+#[tauri::command]
+pub fn set_metadata(path: String, mut fields: PdfMetadata, output: String) -> Result<String, String> {
+    let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
+
+    let info_id = match doc.trailer.get(b"Info") {
+        Ok(lopdf::Object::Reference(id)) if doc.get_dictionary(*id).is_ok() => *id,
+        Ok(lopdf::Object::Dictionary(dict)) => {
+            let dict = dict.clone();
+            doc.add_object(dict)
+        }
+        _ => doc.add_object(lopdf::Dictionary::new()),
+    };
+    doc.trailer.set("Info", info_id);
+    let mut xmp = read_xmp(&doc);
+    let mut xmp_updates = Vec::new();
+    let mut changed = 0;
+
+    for (key, property, value) in fields.entries() {
+        let Some(value) = value.as_deref().map(str::trim) else {
+            continue;
+        };
+        let encoded = match (key, value) {
+            (_, "") => None,
+            ("CreationDate", date) => Some(lopdf::Object::string_literal(iso_to_pdf_date(date)?)),
+            (_, text) => Some(text_string(text)),
+        };
+        let info = doc.get_dictionary_mut(info_id).map_err(|e| e.to_string())?;
+        match encoded {
+            Some(obj) => info.set(key, obj),
+            None => {
+                info.remove(key.as_bytes());
+            }
+        }
+        if let Some((_, ref mut packet)) = xmp {
+            remove_xmp_property(packet, property);
+            if !value.is_empty() {
+                let xmp_value = if key == "CreationDate" { pdf_date_to_iso(value) } else { value.to_string() };
+                xmp_updates.push(xmp_element(property, &xmp_value));
+            }
+        }
+        changed += 1;
+    }
+
+    if let Some((stream_id, mut packet)) = xmp {
+        if !xmp_updates.is_empty() {
+            let description = format!(
+                "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+                 xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n{}</rdf:Description>\n",
+                xmp_updates.concat()
+            );
+            if let Some(end) = packet.find("</rdf:RDF>") {
+                packet.insert_str(end, &description);
+            }
+        }
+        // XMP stays uncompressed so other tools can find it without parsing the PDF
+        if let Ok(lopdf::Object::Stream(stream)) = doc.get_object_mut(stream_id) {
+            stream.set_plain_content(packet.into_bytes());
+        }
+    }
+
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(format!("Updated {} metadata fields → {}", changed, output))
+}
+
 #[tauri::command]
 pub fn merge_pdfs(paths: Vec<String>, output: String) -> Result<String, String> {
     if paths.len() < 2 {
         return Err("Need at least 2 PDFs to merge".into());
     }
 
-    // Use lopdf's Document to manually merge by copying objects and pages
+    // Use lopdf's Document to manually merge by copying objects and pages.
+    // The first file is the base, so its /Info (Title etc.) carries over as-is.
     let mut base_doc = Document::load(&paths[0]).map_err(|e| e.to_string())?;
 
+    // Each source gets a top-level bookmark with its own outline nested below
+    let mut outline = vec![OutlineEntry {
+        title: text_string(&file_label(&paths[0])),
+        dest: base_doc.page_iter().next().map(fit_dest),
+        children: read_outline(&base_doc, &|id| Some(id)),
+    }];
+
+    let pages_id = base_doc
+        .catalog()
+        .and_then(|c| c.get(b"Pages"))
+        .and_then(|o| o.as_reference())
+        .map_err(|e| format!("{}: {}", paths[0], e))?;
+
     for path in &paths[1..] {
         let other_doc = Document::load(path).map_err(|e| e.to_string())?;
-        // Copy all objects from other doc, remapping IDs
+        // Copy each page and only the objects it uses, remapping every nested
+        // reference so fonts, images and content streams resolve in the output
         let mut id_map = std::collections::BTreeMap::new();
-        for (id, obj) in &other_doc.objects {
-            let new_id = base_doc.add_object(obj.clone());
-            id_map.insert(*id, new_id);
-        }
-        // Get page references from other doc and add to base catalog
-        let other_pages = other_doc.get_pages();
-        let base_catalog = base_doc.catalog().map_err(|e| e.to_string())?;
-        let pages_id = base_catalog
-            .get(b"Pages")
-            .ok()
-            .and_then(|o| match o {
-                lopdf::Object::Reference(r) => Some(*r),
-                _ => None,
-            });
+        let mut new_pages = Vec::new();
+        for page_id in other_doc.page_iter() {
+            new_pages.push(import_page(&mut base_doc, &other_doc, page_id, pages_id, &mut id_map)?);
+        }
 
-        if let Some(pages_id) = pages_id {
-            for (_page_num, page_id) in &other_pages {
-                let new_page_id = id_map.get(page_id).copied().unwrap_or(*page_id);
-                // Add new page ref to Kids array
-                if let Ok(pages_obj) = base_doc.get_object_mut(pages_id) {
-                    if let lopdf::Object::Dictionary(ref mut dict) = pages_obj {
-                        if let Ok(lopdf::Object::Array(ref mut kids)) = dict.get_mut(b"Kids") {
-                            kids.push(lopdf::Object::Reference(new_page_id));
-                        }
-                        // Update count
-                        if let Ok(lopdf::Object::Integer(ref mut count)) = dict.get_mut(b"Count") {
-                            *count += 1;
-                        }
-                    }
-                }
-                // Update Parent reference on the new page
-                if let Ok(page_obj) = base_doc.get_object_mut(new_page_id) {
-                    if let lopdf::Object::Dictionary(ref mut dict) = page_obj {
-                        dict.set("Parent", lopdf::Object::Reference(pages_id));
-                    }
-                }
-            }
+        let pages_dict = base_doc.get_dictionary_mut(pages_id).map_err(|e| e.to_string())?;
+        if let Ok(lopdf::Object::Array(ref mut kids)) = pages_dict.get_mut(b"Kids") {
+            kids.extend(new_pages.iter().map(|id| lopdf::Object::Reference(*id)));
         }
+        let count = pages_dict.get(b"Count").and_then(|c| c.as_i64()).unwrap_or(0);
+        pages_dict.set("Count", count + new_pages.len() as i64);
+
+        outline.push(OutlineEntry {
+            title: text_string(&file_label(path)),
+            dest: new_pages.first().copied().map(fit_dest),
+            children: read_outline(&other_doc, &|id| id_map.get(&id).copied()),
+        });
+    }
+
+    if let Some(outline_id) = write_outline(&mut base_doc, &outline) {
+        let catalog = base_doc.catalog_mut().map_err(|e| e.to_string())?;
+        catalog.set("Outlines", lopdf::Object::Reference(outline_id));
+        catalog.set("PageMode", lopdf::Object::Name(b"UseOutlines".to_vec()));
     }
 
     base_doc.save(&output).map_err(|e| e.to_string())?;
@@ -153,7 +272,8 @@ pub fn extract_text(path: String, pages: Option<Vec<u32>>) -> Result<String, Str
             let content_str = String::from_utf8_lossy(&content);
             for line in content_str.lines() {
                 let trimmed = line.trim();
-                if trimmed.starts_with('(') && trimmed.contains(")Tj") {
+                // lopdf writes rewritten content as "(text) Tj"
+                if trimmed.starts_with('(') && (trimmed.contains(")Tj") || trimmed.contains(") Tj")) {
                     if let Some(start) = trimmed.find('(') {
                         if let Some(end) = trimmed.rfind(')') {
                             text.push_str(&trimmed[start + 1..end]);
@@ -168,6 +288,75 @@ pub fn extract_text(path: String, pages: Option<Vec<u32>>) -> Result<String, Str
     Ok(text)
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RedactRegion {
+    pub page: u32,
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+// Removes every text-showing operator with a glyph inside one of the regions
+// (PDF points, origin bottom-left), including text drawn through form
+// XObjects, then paints the regions black. Images under a region are covered
+// but stay in the file.
+#[tauri::command]
+pub fn redact_regions(path: String, regions: Vec<RedactRegion>, output: String) -> Result<String, String> {
+    let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
+    let pages = doc.get_pages();
+    let total = pages.len() as u32;
+
+    let mut by_page: std::collections::BTreeMap<u32, Vec<[f64; 4]>> = std::collections::BTreeMap::new();
+    for r in &regions {
+        if r.page < 1 || r.page > total {
+            return Err(format!("Page {} out of bounds (1-{})", r.page, total));
+        }
+        if !(r.w > 0.0 && r.h > 0.0) {
+            return Err(format!("Region on page {} has no area", r.page));
+        }
+        by_page.entry(r.page).or_default().push([r.x, r.y, r.x + r.w, r.y + r.h]);
+    }
+
+    let mut removed = 0;
+    for (page_num, rects) in &by_page {
+        let page_id = pages[page_num];
+        let content = doc.get_page_content(page_id).map_err(|e| e.to_string())?;
+        let page = doc.get_dictionary(page_id).map_err(|e| e.to_string())?;
+        let resources = inherited_attr(&doc, page, b"Resources")
+            .and_then(|r| doc.dereference(r).ok())
+            .and_then(|(_, r)| r.as_dict().ok())
+            .cloned()
+            .unwrap_or_default();
+
+        let redacted = redact_content(&mut doc, &content, &resources, IDENTITY, rects, 0)
+            .map_err(|e| format!("Page {}: {}", page_num, e))?;
+        if let Some(redacted) = redacted {
+            let stream_id = doc.add_object(lopdf::Stream::new(dictionary! {}, redacted.content));
+            let page = doc.get_dictionary_mut(page_id).map_err(|e| e.to_string())?;
+            page.set("Contents", stream_id);
+            page.set("Resources", redacted.resources);
+            removed += redacted.removed;
+        }
+
+        let boxes: String = rects
+            .iter()
+            .map(|[x0, y0, x1, y1]| format!("{} {} {} {} re\n", x0, y0, x1 - x0, y1 - y0))
+            .collect();
+        overlay_page_content(&mut doc, page_id, format!("q 0 g\n{}f Q\n", boxes).into_bytes())?;
+    }
+
+    // The replaced content streams still hold the text; drop them from the file
+    doc.prune_objects();
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Removed {} text runs from {} pages → {}",
+        removed,
+        by_page.len(),
+        output
+    ))
+}
+
 #[tauri::command]
 pub fn add_watermark(path: String, watermark_text: String, output: String) -> Result<String, String> {
     let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
@@ -208,6 +397,74 @@ pub fn add_watermark(path: String, watermark_text: String, output: String) -> Re
     Ok(format!("Added watermark '{}' to {} pages", watermark_text, pages.len()))
 }
 
+// Draws the first page of `stamp_path` on top of the selected pages (all when
+// `pages` is empty), e.g. letterhead or a signature. `position` is "center",
+// "top", "bottom", "top-left", "top-right", "bottom-left" or "bottom-right".
+#[tauri::command]
+pub fn stamp_pdf(
+    base_path: String,
+    stamp_path: String,
+    output: String,
+    pages: Option<String>,
+    position: Option<String>,
+    scale: Option<f64>,
+) -> Result<String, String> {
+    let mut doc = Document::load(&base_path).map_err(|e| e.to_string())?;
+    let stamp_doc = Document::load(&stamp_path).map_err(|e| e.to_string())?;
+    let scale = scale.unwrap_or(1.0);
+    if !(scale > 0.0 && scale.is_finite()) {
+        return Err(format!("Invalid scale: {}", scale));
+    }
+
+    let all_pages = doc.get_pages();
+    let targets: Vec<u32> = match pages.as_deref().map(str::trim) {
+        None | Some("") => all_pages.keys().copied().collect(),
+        Some(range) => parse_page_range(range, all_pages.len() as u32)?,
+    };
+
+    // The stamp page becomes a Form XObject that keeps its own resources, so
+    // its font and image names can't clash with the base page's
+    let stamp_page_id = stamp_doc.page_iter().next().ok_or("Stamp PDF has no pages")?;
+    let stamp_page = stamp_doc.get_dictionary(stamp_page_id).map_err(|e| e.to_string())?;
+    let bbox = page_box(&stamp_doc, stamp_page);
+    let content = stamp_doc.get_page_content(stamp_page_id).map_err(|e| e.to_string())?;
+    let mut id_map = std::collections::BTreeMap::new();
+    let resources = match inherited_attr(&stamp_doc, stamp_page, b"Resources") {
+        Some(res) => remap_refs(&mut doc, &stamp_doc, res, &mut id_map),
+        None => lopdf::Object::Dictionary(dictionary! {}),
+    };
+    let form_id = doc.add_object(lopdf::Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => bbox.iter().map(|&v| lopdf::Object::Real(v as f32)).collect::<Vec<_>>(),
+            "Resources" => resources,
+        },
+        content,
+    ));
+
+    let (stamp_w, stamp_h) = ((bbox[2] - bbox[0]) * scale, (bbox[3] - bbox[1]) * scale);
+    for page_num in &targets {
+        let page_id = all_pages[page_num];
+        let page = doc.get_dictionary(page_id).map_err(|e| e.to_string())?;
+        let [x0, y0, x1, y1] = page_box(&doc, page);
+        let (x, y) = stamp_origin(position.as_deref().unwrap_or("center"), (x0, y0, x1, y1), (stamp_w, stamp_h))?;
+
+        let name = add_page_xobject(&mut doc, page_id, "Stamp", form_id)?;
+        let ops = format!(
+            "q {s} 0 0 {s} {tx:.2} {ty:.2} cm /{name} Do Q\n",
+            s = scale,
+            tx = x - bbox[0] * scale,
+            ty = y - bbox[1] * scale,
+            name = name
+        );
+        overlay_page_content(&mut doc, page_id, ops.into_bytes())?;
+    }
+
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(format!("Stamped {} pages → {}", targets.len(), output))
+}
+
 #[tauri::command]
 pub fn compress_pdf(path: String, output: String) -> Result<String, String> {
     let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
@@ -329,8 +586,1138 @@ pub fn reorder_pages(path: String, new_order: Vec<u32>, output: String) -> Resul
     Ok(format!("Reordered {} pages → {}", new_order.len(), output))
 }
 
+// Pages are kept in the order the range lists them, and may repeat ("3,3,5")
+#[tauri::command]
+pub fn extract_pages(path: String, range: String, output: String) -> Result<String, String> {
+    let source = Document::load(&path).map_err(|e| e.to_string())?;
+    let pages = source.get_pages();
+    let selected = parse_page_range(&range, pages.len() as u32)?;
+
+    // Start from an empty document and copy in only what the kept pages use
+    let mut doc = Document::with_version(source.version.clone());
+    let pages_id = doc.new_object_id();
+    let mut id_map = std::collections::BTreeMap::new();
+    let mut kids = Vec::new();
+    for page_num in &selected {
+        let page_id = import_page(&mut doc, &source, pages[page_num], pages_id, &mut id_map)?;
+        kids.push(lopdf::Object::Reference(page_id));
+    }
+    doc.objects.insert(
+        pages_id,
+        lopdf::Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    if let Ok(info) = source.trailer.get(b"Info") {
+        let info = remap_refs(&mut doc, &source, info, &mut id_map);
+        doc.trailer.set("Info", info);
+    }
+
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(format!("Extracted {} pages → {}", selected.len(), output))
+}
+
+#[tauri::command]
+pub fn delete_pages(path: String, pages: Vec<u32>, output: String) -> Result<u32, String> {
+    let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
+    let total = doc.get_pages().len() as u32;
+
+    for &p in &pages {
+        if p < 1 || p > total {
+            return Err(format!("Page {} out of bounds (1-{})", p, total));
+        }
+    }
+    let mut to_remove = pages;
+    to_remove.sort_unstable();
+    to_remove.dedup();
+    if to_remove.len() as u32 >= total {
+        return Err("Can't delete every page of a PDF".into());
+    }
+
+    // lopdf unlinks the pages from their Kids and fixes /Count up the tree;
+    // pruning then drops content and images only those pages used
+    doc.delete_pages(&to_remove);
+    doc.prune_objects();
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(total - to_remove.len() as u32)
+}
+
+#[tauri::command]
+pub fn insert_blank_pages(
+    path: String,
+    after_page: u32,
+    count: u32,
+    size: Option<String>,
+    output: String,
+) -> Result<u32, String> {
+    let mut doc = Document::load(&path).map_err(|e| e.to_string())?;
+    let pages = doc.get_pages();
+    let total = pages.len() as u32;
+
+    if after_page > total {
+        return Err(format!("Page {} out of bounds (0-{})", after_page, total));
+    }
+    if count == 0 {
+        return Err("Nothing to insert: count is 0".into());
+    }
+
+    // New pages go next to an existing one, in the same page tree node.
+    // after_page 0 means before the first page.
+    let anchor = pages.get(&after_page.max(1)).copied();
+    let media_box = match size.as_deref() {
+        None | Some("") | Some("match") => anchor
+            .and_then(|id| inherited_attr(&doc, doc.get_dictionary(id).ok()?, b"MediaBox").cloned())
+            .unwrap_or_else(|| paper_size("a4").unwrap()),
+        Some(name) => paper_size(name)?,
+    };
+    let (parent_id, index) = match anchor {
+        Some(page_id) => {
+            let (parent_id, pos) = kid_position(&doc, page_id)?;
+            (parent_id, if after_page == 0 { pos } else { pos + 1 })
+        }
+        None => {
+            let root = doc
+                .catalog()
+                .and_then(|c| c.get(b"Pages"))
+                .and_then(|o| o.as_reference())
+                .map_err(|e| e.to_string())?;
+            (root, 0)
+        }
+    };
+
+    let new_pages: Vec<lopdf::Object> = (0..count)
+        .map(|_| {
+            lopdf::Object::Reference(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => parent_id,
+                "MediaBox" => media_box.clone(),
+                "Resources" => dictionary! {},
+            }))
+        })
+        .collect();
+
+    let parent = doc.get_dictionary_mut(parent_id).map_err(|e| e.to_string())?;
+    match parent.get_mut(b"Kids") {
+        Ok(lopdf::Object::Array(ref mut kids)) => {
+            kids.splice(index..index, new_pages);
+        }
+        _ => return Err("Page tree node has no Kids array".into()),
+    }
+    // /Count on every ancestor includes the new leaves
+    let mut node = Some(parent_id);
+    while let Some(id) = node {
+        let dict = doc.get_dictionary_mut(id).map_err(|e| e.to_string())?;
+        let current = dict.get(b"Count").and_then(|c| c.as_i64()).unwrap_or(0);
+        dict.set("Count", current + count as i64);
+        node = dict.get(b"Parent").and_then(|p| p.as_reference()).ok();
+    }
+
+    doc.save(&output).map_err(|e| e.to_string())?;
+    Ok(total + count)
+}
+
 // --- Helpers ---
 
+// Page tree node holding `page_id` and the page's index in its Kids
+fn kid_position(doc: &Document, page_id: lopdf::ObjectId) -> Result<(lopdf::ObjectId, usize), String> {
+    let parent_id = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Parent"))
+        .and_then(|p| p.as_reference())
+        .map_err(|e| e.to_string())?;
+    let kids = doc
+        .get_dictionary(parent_id)
+        .and_then(|parent| parent.get(b"Kids"))
+        .and_then(|k| k.as_array())
+        .map_err(|e| e.to_string())?;
+    let pos = kids
+        .iter()
+        .position(|k| k.as_reference().ok() == Some(page_id))
+        .ok_or("Page tree is inconsistent: page missing from its parent's Kids")?;
+    Ok((parent_id, pos))
+}
+
+// A page's MediaBox as [x0, y0, x1, y1], following inheritance; A4 if missing
+fn page_box(doc: &Document, page: &lopdf::Dictionary) -> [f64; 4] {
+    let values: Vec<f64> = inherited_attr(doc, page, b"MediaBox")
+        .and_then(|b| doc.dereference(b).ok())
+        .and_then(|(_, b)| b.as_array().ok())
+        .map(|arr| arr.iter().filter_map(get_number).collect())
+        .unwrap_or_default();
+    match values[..] {
+        [x0, y0, x1, y1] => [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)],
+        _ => [0.0, 0.0, 595.0, 842.0],
+    }
+}
+
+// Lower-left corner for an overlay of `size` placed within `page`
+fn stamp_origin(position: &str, page: (f64, f64, f64, f64), size: (f64, f64)) -> Result<(f64, f64), String> {
+    let (x0, y0, x1, y1) = page;
+    let (w, h) = size;
+    let center = (x0 + (x1 - x0 - w) / 2.0, y0 + (y1 - y0 - h) / 2.0);
+    Ok(match position {
+        "center" => center,
+        "top" => (center.0, y1 - h),
+        "bottom" => (center.0, y0),
+        "top-left" => (x0, y1 - h),
+        "top-right" => (x1 - w, y1 - h),
+        "bottom-left" => (x0, y0),
+        "bottom-right" => (x1 - w, y0),
+        other => return Err(format!("Unknown position: {}", other)),
+    })
+}
+
+// Register an XObject in the page's own resources under a name that isn't
+// taken yet ("Stamp1", "Stamp2", …) and return that name. Inherited or shared
+// resource dictionaries are copied onto the page first.
+fn add_page_xobject(doc: &mut Document, page_id: lopdf::ObjectId, prefix: &str, xobject_id: lopdf::ObjectId) -> Result<String, String> {
+    let page = doc.get_dictionary(page_id).map_err(|e| e.to_string())?;
+    let mut resources = inherited_attr(doc, page, b"Resources")
+        .and_then(|r| doc.dereference(r).ok())
+        .and_then(|(_, r)| r.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let mut xobjects = resources
+        .get_deref(b"XObject", doc)
+        .and_then(|x| x.as_dict())
+        .cloned()
+        .unwrap_or_default();
+
+    let name = (1..)
+        .map(|n| format!("{}{}", prefix, n))
+        .find(|name| !xobjects.has(name.as_bytes()))
+        .unwrap();
+    xobjects.set(name.clone(), xobject_id);
+    resources.set("XObject", xobjects);
+    doc.get_dictionary_mut(page_id)
+        .map_err(|e| e.to_string())?
+        .set("Resources", resources);
+    Ok(name)
+}
+
+// Draw `content` over a page. The existing content is wrapped in q/Q so any
+// graphics state it leaves behind can't shift or recolour the overlay.
+fn overlay_page_content(doc: &mut Document, page_id: lopdf::ObjectId, content: Vec<u8>) -> Result<(), String> {
+    let existing = doc.get_page_contents(page_id);
+    let save_id = doc.add_object(lopdf::Stream::new(dictionary! {}, b"q\n".to_vec()));
+    let mut overlay = b"\nQ\n".to_vec();
+    overlay.extend(content);
+    let overlay_id = doc.add_object(lopdf::Stream::new(dictionary! {}, overlay));
+
+    let mut contents = vec![lopdf::Object::Reference(save_id)];
+    contents.extend(existing.into_iter().map(lopdf::Object::Reference));
+    contents.push(lopdf::Object::Reference(overlay_id));
+    doc.get_dictionary_mut(page_id)
+        .map_err(|e| e.to_string())?
+        .set("Contents", contents);
+    Ok(())
+}
+
+// The catalog's /Metadata stream as text, with its object id
+fn read_xmp(doc: &Document) -> Option<(lopdf::ObjectId, String)> {
+    let id = doc.catalog().ok()?.get(b"Metadata").ok()?.as_reference().ok()?;
+    let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+    let bytes = stream.get_plain_content().ok()?;
+    Some((id, String::from_utf8_lossy(&bytes).to_string()))
+}
+
+// Value of an XMP property written either as an element (language
+// alternatives and lists give their first item) or as an attribute
+fn xmp_property(xmp: &str, name: &str) -> Option<String> {
+    let value = if let Some((start, end)) = find_xmp_element(xmp, name) {
+        let element = &xmp[start..end];
+        let inner = &element[element.find('>')? + 1..];
+        let inner = inner.strip_suffix(&format!("</{}>", name)).unwrap_or("");
+        match inner.find("<rdf:li") {
+            Some(li) => {
+                let item = &inner[li..];
+                let text = &item[item.find('>')? + 1..];
+                text[..text.find("</rdf:li>")?].to_string()
+            }
+            None => inner.to_string(),
+        }
+    } else {
+        let (start, end) = find_xmp_attribute(xmp, name)?;
+        let attr = xmp[start..end].trim_start();
+        attr[name.len() + 2..attr.len() - 1].to_string()
+    };
+    let value = xml_unescape(value.trim());
+    (!value.is_empty()).then_some(value)
+}
+
+fn remove_xmp_property(xmp: &mut String, name: &str) {
+    while let Some((start, end)) = find_xmp_element(xmp, name).or_else(|| find_xmp_attribute(xmp, name)) {
+        xmp.replace_range(start..end, "");
+    }
+}
+
+// Byte range of `<name …>…</name>` or `<name …/>`
+fn find_xmp_element(xmp: &str, name: &str) -> Option<(usize, usize)> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    while let Some(offset) = xmp[from..].find(&open) {
+        let start = from + offset;
+        let rest = &xmp[start + open.len()..];
+        if rest.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            let tag_end = start + open.len() + rest.find('>')? + 1;
+            if xmp[..tag_end].ends_with("/>") {
+                return Some((start, tag_end));
+            }
+            let close = format!("</{}>", name);
+            let end = tag_end + xmp[tag_end..].find(&close)? + close.len();
+            return Some((start, end));
+        }
+        from = start + open.len();
+    }
+    None
+}
+
+// Byte range of ` name="…"` inside a tag, including the leading whitespace
+fn find_xmp_attribute(xmp: &str, name: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    while let Some(offset) = xmp[from..].find(name) {
+        let start = from + offset;
+        let after = &xmp[start + name.len()..];
+        let preceded_by_space = xmp[..start].ends_with(|c: char| c.is_whitespace());
+        if preceded_by_space && (after.starts_with("=\"") || after.starts_with("='")) {
+            let quote = &after[1..2];
+            let value_end = start + name.len() + 2 + after[2..].find(quote)? + 1;
+            let ws_start = xmp[..start].trim_end().len();
+            return Some((ws_start, value_end));
+        }
+        from = start + name.len();
+    }
+    None
+}
+
+fn xmp_element(name: &str, value: &str) -> String {
+    let value = xml_escape(value);
+    match name {
+        "dc:title" | "dc:description" => format!(
+            "  <{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>\n",
+            name, value
+        ),
+        "dc:creator" => format!("  <{0}><rdf:Seq><rdf:li>{1}</rdf:li></rdf:Seq></{0}>\n", name, value),
+        _ => format!("  <{0}>{1}</{0}>\n", name, value),
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// "D:20240301093000+01'00'" → "2024-03-01T09:30:00+01:00". Missing parts
+// default as the PDF spec says (month/day 01, time 00); junk is returned as-is.
+fn pdf_date_to_iso(date: &str) -> String {
+    let raw = date.trim().trim_start_matches("D:");
+    let digits: String = raw.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 4 {
+        return date.to_string();
+    }
+    let part = |range: std::ops::Range<usize>, default: &str| digits.get(range).unwrap_or(default).to_string();
+    let mut iso = format!(
+        "{}-{}-{}T{}:{}:{}",
+        &digits[..4],
+        part(4..6, "01"),
+        part(6..8, "01"),
+        part(8..10, "00"),
+        part(10..12, "00"),
+        part(12..14, "00")
+    );
+    let zone = raw[digits.len()..].replace('\'', "");
+    match zone.chars().next() {
+        Some('Z') => iso.push('Z'),
+        // Metadata is untrusted: a malformed offset is dropped rather than sliced
+        Some(sign @ ('+' | '-')) => {
+            let digits_at = |range| zone.get(range).filter(|s: &&str| s.bytes().all(|b| b.is_ascii_digit()));
+            if let Some(hours) = digits_at(1..3) {
+                let mins = digits_at(3..5).unwrap_or("00");
+                iso.push_str(&format!("{}{}:{}", sign, hours, mins));
+            }
+        }
+        _ => {}
+    }
+    iso
+}
+
+// "2024-03-01T09:30:00+01:00" or "2024-03-01" → "D:20240301093000+01'00'"
+fn iso_to_pdf_date(date: &str) -> Result<String, String> {
+    if date.starts_with("D:") {
+        return Ok(date.to_string());
+    }
+    let (date_part, time_part) = date.split_once('T').unwrap_or((date, ""));
+    let ymd: String = date_part.chars().filter(|c| c.is_ascii_digit()).collect();
+    if ymd.len() != 8 {
+        return Err(format!("Invalid date: {} (expected YYYY-MM-DD[THH:MM:SS])", date));
+    }
+    let zone_at = time_part.find(['Z', '+', '-']).unwrap_or(time_part.len());
+    let hms: String = time_part[..zone_at].chars().filter(|c| c.is_ascii_digit()).take(6).collect();
+    let zone = match &time_part[zone_at..] {
+        "" => String::new(),
+        "Z" => "Z".to_string(),
+        offset => {
+            let digits: String = offset.chars().filter(|c| c.is_ascii_digit()).collect();
+            if digits.len() != 4 {
+                return Err(format!("Invalid time zone in date: {}", date));
+            }
+            format!("{}{}'{}'", &offset[..1], &digits[..2], &digits[2..])
+        }
+    };
+    Ok(format!("D:{}{}{}", ymd, hms, zone))
+}
+
+// --- Redaction ---
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+// a × b in PDF's row-vector convention: apply a, then b
+fn mul(a: Matrix, b: Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn apply(m: Matrix, x: f64, y: f64) -> (f64, f64) {
+    (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5])
+}
+
+fn translate(tx: f64, ty: f64) -> Matrix {
+    [1.0, 0.0, 0.0, 1.0, tx, ty]
+}
+
+fn matrix_from(values: &[f64]) -> Option<Matrix> {
+    values.try_into().ok()
+}
+
+// Glyph widths in thousandths of an em, enough to place glyphs on the page
+struct FontMetrics {
+    two_byte: bool,
+    first_char: u32,
+    widths: Vec<f64>,
+    cid_widths: std::collections::HashMap<u32, f64>,
+    default_width: f64,
+}
+
+impl FontMetrics {
+    fn width(&self, code: u32) -> f64 {
+        let width = if self.two_byte {
+            self.cid_widths.get(&code).copied()
+        } else {
+            code.checked_sub(self.first_char).and_then(|i| self.widths.get(i as usize)).copied()
+        };
+        width.unwrap_or(self.default_width)
+    }
+}
+
+fn font_metrics(doc: &Document, resources: &lopdf::Dictionary) -> std::collections::HashMap<Vec<u8>, FontMetrics> {
+    let number = |obj: &lopdf::Object| doc.dereference(obj).ok().and_then(|(_, o)| get_number(o));
+    let Ok(fonts) = resources.get_deref(b"Font", doc).and_then(|f| f.as_dict()) else {
+        return Default::default();
+    };
+
+    fonts
+        .iter()
+        .filter_map(|(name, font)| {
+            let font = doc.dereference(font).ok()?.1.as_dict().ok()?;
+            let metrics = if font.get(b"Subtype").and_then(|s| s.as_name()).ok() == Some(b"Type0".as_slice()) {
+                let descendant = font.get_deref(b"DescendantFonts", doc).and_then(|d| d.as_array()).ok()?.first()?;
+                let cid_font = doc.dereference(descendant).ok()?.1.as_dict().ok()?;
+                FontMetrics {
+                    two_byte: true,
+                    first_char: 0,
+                    widths: Vec::new(),
+                    cid_widths: cid_widths(doc, cid_font),
+                    default_width: cid_font.get(b"DW").ok().and_then(number).unwrap_or(1000.0),
+                }
+            } else {
+                // The standard 14 fonts often come without /Widths; 500 is a
+                // fair average and errs towards covering more glyphs
+                FontMetrics {
+                    two_byte: false,
+                    first_char: font.get(b"FirstChar").ok().and_then(number).unwrap_or(0.0) as u32,
+                    widths: font
+                        .get_deref(b"Widths", doc)
+                        .and_then(|w| w.as_array())
+                        .map(|w| w.iter().map(|v| number(v).unwrap_or(0.0)).collect())
+                        .unwrap_or_default(),
+                    cid_widths: Default::default(),
+                    default_width: 500.0,
+                }
+            };
+            Some((name.clone(), metrics))
+        })
+        .collect()
+}
+
+// CID font /W array: `c [w1 w2 …]` or `c_first c_last w`
+fn cid_widths(doc: &Document, cid_font: &lopdf::Dictionary) -> std::collections::HashMap<u32, f64> {
+    let number = |obj: &lopdf::Object| doc.dereference(obj).ok().and_then(|(_, o)| get_number(o));
+    let mut widths = std::collections::HashMap::new();
+    let Ok(w) = cid_font.get_deref(b"W", doc).and_then(|w| w.as_array()) else {
+        return widths;
+    };
+
+    let mut i = 0;
+    while let Some(first) = w.get(i).and_then(number) {
+        match w.get(i + 1).and_then(|o| doc.dereference(o).ok()).map(|(_, o)| o) {
+            Some(lopdf::Object::Array(list)) => {
+                for (k, v) in list.iter().enumerate() {
+                    if let Some(v) = number(v) {
+                        widths.insert(first as u32 + k as u32, v);
+                    }
+                }
+                i += 2;
+            }
+            Some(last) => {
+                let (Some(last), Some(width)) = (get_number(last), w.get(i + 2).and_then(number)) else {
+                    break;
+                };
+                for code in (first as u32..=last as u32).take(0x10000) {
+                    widths.insert(code, width);
+                }
+                i += 3;
+            }
+            None => break,
+        }
+    }
+    widths
+}
+
+// The parts of the graphics state that decide where text lands
+#[derive(Clone)]
+struct TextGraphics {
+    ctm: Matrix,
+    font: Option<Vec<u8>>,
+    size: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    h_scale: f64,
+    leading: f64,
+    rise: f64,
+}
+
+struct Redacted {
+    content: Vec<u8>,
+    resources: lopdf::Dictionary,
+    removed: usize,
+}
+
+// Rewrite a content stream without the text-showing operators that touch
+// `rects`. Returns None when nothing had to change.
+fn redact_content(
+    doc: &mut Document,
+    content: &[u8],
+    resources: &lopdf::Dictionary,
+    ctm: Matrix,
+    rects: &[[f64; 4]],
+    depth: u32,
+) -> Result<Option<Redacted>, String> {
+    use lopdf::content::{Content, Operation};
+    use lopdf::Object;
+
+    let operations = Content::decode(content)
+        .map_err(|e| format!("can't parse content stream: {}", e))?
+        .operations;
+    let fonts = font_metrics(doc, resources);
+    let mut xobjects = resources
+        .get_deref(b"XObject", doc)
+        .and_then(|x| x.as_dict())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut gs = TextGraphics {
+        ctm,
+        font: None,
+        size: 0.0,
+        char_spacing: 0.0,
+        word_spacing: 0.0,
+        h_scale: 1.0,
+        leading: 0.0,
+        rise: 0.0,
+    };
+    let mut stack = Vec::new();
+    let (mut tm, mut tlm) = (IDENTITY, IDENTITY);
+    let mut out = Vec::with_capacity(operations.len());
+    let mut removed = 0;
+    let mut replaced = Vec::new();
+
+    for mut op in operations {
+        let nums: Vec<f64> = op.operands.iter().filter_map(get_number).collect();
+        match op.operator.as_str() {
+            "q" => stack.push(gs.clone()),
+            "Q" => gs = stack.pop().unwrap_or(gs),
+            "cm" => {
+                if let Some(m) = matrix_from(&nums) {
+                    gs.ctm = mul(m, gs.ctm);
+                }
+            }
+            "BT" => {
+                tm = IDENTITY;
+                tlm = IDENTITY;
+            }
+            "Tf" => {
+                gs.font = op.operands.first().and_then(|o| o.as_name().ok()).map(<[u8]>::to_vec);
+                gs.size = nums.last().copied().unwrap_or(0.0);
+            }
+            "Tc" => gs.char_spacing = nums.first().copied().unwrap_or(0.0),
+            "Tw" => gs.word_spacing = nums.first().copied().unwrap_or(0.0),
+            "Tz" => gs.h_scale = nums.first().copied().unwrap_or(100.0) / 100.0,
+            "TL" => gs.leading = nums.first().copied().unwrap_or(0.0),
+            "Ts" => gs.rise = nums.first().copied().unwrap_or(0.0),
+            "Td" | "TD" => {
+                if let [tx, ty] = nums[..] {
+                    if op.operator == "TD" {
+                        gs.leading = -ty;
+                    }
+                    tlm = mul(translate(tx, ty), tlm);
+                    tm = tlm;
+                }
+            }
+            "Tm" => {
+                if let Some(m) = matrix_from(&nums) {
+                    tlm = m;
+                    tm = m;
+                }
+            }
+            "T*" => {
+                tlm = mul(translate(0.0, -gs.leading), tlm);
+                tm = tlm;
+            }
+            "Tj" | "TJ" | "'" | "\"" => {
+                if op.operator == "\"" && nums.len() >= 2 {
+                    gs.word_spacing = nums[0];
+                    gs.char_spacing = nums[1];
+                }
+                if op.operator == "'" || op.operator == "\"" {
+                    tlm = mul(translate(0.0, -gs.leading), tlm);
+                    tm = tlm;
+                }
+                let items: Vec<Object> = match op.operands.last() {
+                    Some(Object::Array(items)) if op.operator == "TJ" => items.clone(),
+                    Some(text @ Object::String(..)) => vec![text.clone()],
+                    _ => Vec::new(),
+                };
+                let metrics = gs.font.as_ref().and_then(|f| fonts.get(f));
+                let (advance, hit) = text_run(&gs, metrics, &items, mul(tm, gs.ctm), rects);
+                tm = mul(translate(advance, 0.0), tm);
+
+                if hit {
+                    removed += 1;
+                    // Keep the pen where the text would have left it, so the
+                    // rest of the line doesn't shift
+                    match op.operator.as_str() {
+                        "'" => out.push(Operation::new("T*", vec![])),
+                        "\"" => {
+                            out.push(Operation::new("Tw", vec![op.operands[0].clone()]));
+                            out.push(Operation::new("Tc", vec![op.operands[1].clone()]));
+                            out.push(Operation::new("T*", vec![]));
+                        }
+                        _ => {}
+                    }
+                    let scale = gs.size * gs.h_scale;
+                    let shift = if scale != 0.0 { -advance / scale * 1000.0 } else { 0.0 };
+                    out.push(Operation::new("TJ", vec![Object::Array(vec![Object::Real(shift as f32)])]));
+                    continue;
+                }
+            }
+            "Do" if depth < 8 => {
+                let name = op.operands.first().and_then(|o| o.as_name().ok()).map(<[u8]>::to_vec);
+                if let Some(name) = name {
+                    if let Some((form_id, count)) = redact_form(doc, &xobjects, &name, resources, gs.ctm, rects, depth)? {
+                        // A fresh name per redacted draw: the same form may be
+                        // painted elsewhere on the page where nothing was hit
+                        let new_name = (1..)
+                            .map(|n| [name.as_slice(), format!("_r{}", n).as_bytes()].concat())
+                            .find(|n| !xobjects.has(n))
+                            .unwrap();
+                        xobjects.set(new_name.clone(), form_id);
+                        op.operands[0] = Object::Name(new_name);
+                        replaced.push(name);
+                        removed += count;
+                    }
+                }
+            }
+            _ => {}
+        }
+        out.push(op);
+    }
+
+    if removed == 0 {
+        return Ok(None);
+    }
+    let mut resources = resources.clone();
+    if !replaced.is_empty() {
+        // Unredacted forms must not stay reachable from these resources, or
+        // they'd be saved along with the text we just removed
+        for name in replaced {
+            let still_drawn = out.iter().any(|op| {
+                op.operator == "Do" && op.operands.first().and_then(|o| o.as_name().ok()) == Some(name.as_slice())
+            });
+            if !still_drawn {
+                xobjects.remove(&name);
+            }
+        }
+        resources.set("XObject", xobjects);
+    }
+    let content = Content { operations: out }.encode().map_err(|e| e.to_string())?;
+    Ok(Some(Redacted {
+        content,
+        resources,
+        removed,
+    }))
+}
+
+// Horizontal advance of a Tj/TJ run in text space, and whether any glyph's
+// box (mapped through `trm`) overlaps one of `rects`
+fn text_run(
+    gs: &TextGraphics,
+    metrics: Option<&FontMetrics>,
+    items: &[lopdf::Object],
+    trm: Matrix,
+    rects: &[[f64; 4]],
+) -> (f64, bool) {
+    let (two_byte, width_of) = match metrics {
+        Some(m) => (m.two_byte, Box::new(|code| m.width(code)) as Box<dyn Fn(u32) -> f64>),
+        None => (false, Box::new(|_| 500.0) as Box<dyn Fn(u32) -> f64>),
+    };
+    // Rough ascent/descent, as font descriptors are often missing or wrong
+    let (bottom, top) = (gs.rise - 0.25 * gs.size, gs.rise + gs.size);
+    let mut x = 0.0;
+    let mut hit = false;
+
+    for item in items {
+        match item {
+            lopdf::Object::String(bytes, _) => {
+                let codes: Vec<u32> = if two_byte {
+                    bytes.chunks(2).map(|c| c.iter().fold(0, |acc, &b| (acc << 8) | b as u32)).collect()
+                } else {
+                    bytes.iter().map(|&b| b as u32).collect()
+                };
+                for code in codes {
+                    let glyph = width_of(code) / 1000.0 * gs.size * gs.h_scale;
+                    if !hit {
+                        let corners = [(x, bottom), (x + glyph, bottom), (x, top), (x + glyph, top)].map(|(cx, cy)| apply(trm, cx, cy));
+                        let (xs, ys) = (corners.map(|c| c.0), corners.map(|c| c.1));
+                        let min = |v: [f64; 4]| v.into_iter().fold(f64::INFINITY, f64::min);
+                        let max = |v: [f64; 4]| v.into_iter().fold(f64::NEG_INFINITY, f64::max);
+                        hit = rects.iter().any(|r| min(xs) <= r[2] && max(xs) >= r[0] && min(ys) <= r[3] && max(ys) >= r[1]);
+                    }
+                    let word = if !two_byte && code == 32 { gs.word_spacing } else { 0.0 };
+                    x += glyph + (gs.char_spacing + word) * gs.h_scale;
+                }
+            }
+            other => {
+                if let Some(n) = get_number(other) {
+                    x -= n / 1000.0 * gs.size * gs.h_scale;
+                }
+            }
+        }
+    }
+    (x, hit)
+}
+
+// Redacted copy of the form XObject `name`, if any of its text was hit
+fn redact_form(
+    doc: &mut Document,
+    xobjects: &lopdf::Dictionary,
+    name: &[u8],
+    parent_resources: &lopdf::Dictionary,
+    ctm: Matrix,
+    rects: &[[f64; 4]],
+    depth: u32,
+) -> Result<Option<(lopdf::ObjectId, usize)>, String> {
+    let Some(id) = xobjects.get(name).ok().and_then(|o| o.as_reference().ok()) else {
+        return Ok(None);
+    };
+    let Ok(stream) = doc.get_object(id).and_then(|o| o.as_stream()) else {
+        return Ok(None);
+    };
+    if stream.dict.get(b"Subtype").and_then(|s| s.as_name()).ok() != Some(b"Form".as_slice()) {
+        return Ok(None);
+    }
+
+    let matrix = stream
+        .dict
+        .get(b"Matrix")
+        .and_then(|m| m.as_array())
+        .ok()
+        .and_then(|m| matrix_from(&m.iter().filter_map(get_number).collect::<Vec<_>>()))
+        .unwrap_or(IDENTITY);
+    // Forms without their own /Resources use the page's
+    let resources = stream
+        .dict
+        .get_deref(b"Resources", doc)
+        .and_then(|r| r.as_dict())
+        .cloned()
+        .unwrap_or_else(|_| parent_resources.clone());
+    let content = stream.get_plain_content().map_err(|e| e.to_string())?;
+    let mut dict = stream.dict.clone();
+
+    let Some(redacted) = redact_content(doc, &content, &resources, mul(matrix, ctm), rects, depth + 1)? else {
+        return Ok(None);
+    };
+    dict.set("Resources", redacted.resources);
+    let mut copy = lopdf::Stream::new(dict, Vec::new());
+    copy.set_plain_content(redacted.content);
+    Ok(Some((doc.add_object(copy), redacted.removed)))
+}
+
+// MediaBox for a named paper size, in points
+fn paper_size(name: &str) -> Result<lopdf::Object, String> {
+    let (w, h) = match name.to_lowercase().as_str() {
+        "a3" => (842, 1191),
+        "a4" => (595, 842),
+        "a5" => (420, 595),
+        "letter" => (612, 792),
+        "legal" => (612, 1008),
+        other => return Err(format!("Unknown page size: {}", other)),
+    };
+    Ok(lopdf::Object::Array(vec![0.into(), 0.into(), w.into(), h.into()]))
+}
+
+// Copy one page and everything it draws with from `source` into `target`,
+// hanging it under `parent`. `id_map` carries source → target ids so objects
+// shared between pages (fonts, images) are only copied once.
+fn import_page(
+    target: &mut Document,
+    source: &Document,
+    page_id: lopdf::ObjectId,
+    parent: lopdf::ObjectId,
+    id_map: &mut std::collections::BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> Result<lopdf::ObjectId, String> {
+    let page = source.get_dictionary(page_id).map_err(|e| e.to_string())?;
+    let new_id = target.new_object_id();
+    id_map.entry(page_id).or_insert(new_id);
+
+    let mut dict = lopdf::Dictionary::new();
+    for (key, value) in page.iter() {
+        if key.as_slice() != b"Parent" {
+            dict.set(key.clone(), remap_refs(target, source, value, id_map));
+        }
+    }
+    // The source page tree doesn't come along, so inherited attributes have to
+    // be written onto the page itself
+    for key in [b"Resources".as_slice(), b"MediaBox", b"CropBox", b"Rotate"] {
+        if !dict.has(key) {
+            if let Some(value) = inherited_attr(source, page, key) {
+                dict.set(key.to_vec(), remap_refs(target, source, value, id_map));
+            }
+        }
+    }
+    dict.set("Parent", parent);
+    target.objects.insert(new_id, lopdf::Object::Dictionary(dict));
+    Ok(new_id)
+}
+
+fn inherited_attr<'a>(doc: &'a Document, page: &'a lopdf::Dictionary, key: &[u8]) -> Option<&'a lopdf::Object> {
+    let mut node = page;
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        node = node.get_deref(b"Parent", doc).ok()?.as_dict().ok()?;
+    }
+    None
+}
+
+// Deep-copy an object, copying whatever it references on first sight
+fn remap_refs(
+    target: &mut Document,
+    source: &Document,
+    obj: &lopdf::Object,
+    id_map: &mut std::collections::BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> lopdf::Object {
+    match obj {
+        lopdf::Object::Reference(id) => copy_object(target, source, *id, id_map),
+        lopdf::Object::Array(items) => {
+            lopdf::Object::Array(items.iter().map(|o| remap_refs(target, source, o, id_map)).collect())
+        }
+        lopdf::Object::Dictionary(dict) => lopdf::Object::Dictionary(remap_dict(target, source, dict, id_map)),
+        lopdf::Object::Stream(stream) => {
+            let mut copy = stream.clone();
+            copy.dict = remap_dict(target, source, &stream.dict, id_map);
+            lopdf::Object::Stream(copy)
+        }
+        other => other.clone(),
+    }
+}
+
+fn remap_dict(
+    target: &mut Document,
+    source: &Document,
+    dict: &lopdf::Dictionary,
+    id_map: &mut std::collections::BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> lopdf::Dictionary {
+    let mut copy = lopdf::Dictionary::new();
+    for (key, value) in dict.iter() {
+        copy.set(key.clone(), remap_refs(target, source, value, id_map));
+    }
+    copy
+}
+
+fn copy_object(
+    target: &mut Document,
+    source: &Document,
+    id: lopdf::ObjectId,
+    id_map: &mut std::collections::BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> lopdf::Object {
+    if let Some(new_id) = id_map.get(&id) {
+        return lopdf::Object::Reference(*new_id);
+    }
+    let Ok(obj) = source.get_object(id) else {
+        return lopdf::Object::Null;
+    };
+    // Pages only come across through import_page; a link or annotation
+    // pointing at a page that isn't copied would otherwise drag in the whole
+    // source page tree
+    if let lopdf::Object::Dictionary(dict) = obj {
+        if dict.type_is(b"Page") || dict.type_is(b"Pages") {
+            return lopdf::Object::Null;
+        }
+    }
+    // Reserve the id before recursing so reference cycles terminate
+    let new_id = target.new_object_id();
+    id_map.insert(id, new_id);
+    let copy = remap_refs(target, source, obj, id_map);
+    target.objects.insert(new_id, copy);
+    lopdf::Object::Reference(new_id)
+}
+
+// One bookmark, with its destination already pointing into the output document
+struct OutlineEntry {
+    title: lopdf::Object,
+    dest: Option<lopdf::Object>,
+    children: Vec<OutlineEntry>,
+}
+
+fn file_label(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn fit_dest(page_id: lopdf::ObjectId) -> lopdf::Object {
+    lopdf::Object::Array(vec![
+        lopdf::Object::Reference(page_id),
+        lopdf::Object::Name(b"Fit".to_vec()),
+    ])
+}
+
+// PDF text strings: PDFDocEncoding when every character has a byte there
+// (printable ASCII and Latin-1), otherwise UTF-16BE with a BOM
+fn text_string(text: &str) -> lopdf::Object {
+    // From 0xA1 up PDFDocEncoding matches Latin-1, except 0xAD which is undefined
+    let in_pdf_doc = |c: char| matches!(c, ' '..='~' | '\u{a1}'..='\u{ac}' | '\u{ae}'..='\u{ff}');
+    if text.chars().all(in_pdf_doc) {
+        return lopdf::Object::String(text.chars().map(|c| c as u8).collect(), lopdf::StringFormat::Literal);
+    }
+    lopdf::Object::String(lopdf::encode_utf16_be(text), lopdf::StringFormat::Hexadecimal)
+}
+
+// Decodes PDFDocEncoding, UTF-16BE and UTF-8 (PDF 2.0) text strings
+fn decode_text(obj: &lopdf::Object) -> Option<String> {
+    let text = lopdf::decode_text_string(obj).ok()?;
+    Some(text.trim_start_matches('\u{feff}').to_string())
+}
+
+// Read a document's bookmarks, mapping destination pages through `remap`.
+// Bookmarks whose page can't be mapped keep their title but lose the link.
+fn read_outline(doc: &Document, remap: &dyn Fn(lopdf::ObjectId) -> Option<lopdf::ObjectId>) -> Vec<OutlineEntry> {
+    let first = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get_deref(b"Outlines", doc).ok())
+        .and_then(|o| o.as_dict().ok())
+        .and_then(|d| d.get(b"First").ok())
+        .and_then(|o| o.as_reference().ok());
+    read_outline_items(doc, first, remap, &mut std::collections::BTreeSet::new())
+}
+
+fn read_outline_items(
+    doc: &Document,
+    mut next: Option<lopdf::ObjectId>,
+    remap: &dyn Fn(lopdf::ObjectId) -> Option<lopdf::ObjectId>,
+    seen: &mut std::collections::BTreeSet<lopdf::ObjectId>,
+) -> Vec<OutlineEntry> {
+    let mut items = Vec::new();
+    // `seen` stops malformed outlines whose Next/First links loop back
+    while let Some(id) = next.filter(|id| seen.insert(*id)) {
+        let Ok(node) = doc.get_dictionary(id) else {
+            break;
+        };
+        let title = node
+            .get_deref(b"Title", doc)
+            .cloned()
+            .unwrap_or_else(|_| lopdf::Object::string_literal(""));
+        let dest = outline_dest(doc, node).and_then(|mut dest| {
+            let page = dest.first()?.as_reference().ok()?;
+            dest[0] = lopdf::Object::Reference(remap(page)?);
+            Some(lopdf::Object::Array(dest))
+        });
+        let first_child = node.get(b"First").and_then(|o| o.as_reference()).ok();
+        items.push(OutlineEntry {
+            title,
+            dest,
+            children: read_outline_items(doc, first_child, remap, seen),
+        });
+        next = node.get(b"Next").and_then(|o| o.as_reference()).ok();
+    }
+    items
+}
+
+// Explicit destination array of a bookmark, from /Dest or a GoTo action
+fn outline_dest(doc: &Document, node: &lopdf::Dictionary) -> Option<Vec<lopdf::Object>> {
+    let dest = match node.get_deref(b"Dest", doc) {
+        Ok(dest) => dest,
+        Err(_) => {
+            let action = node.get_deref(b"A", doc).ok()?.as_dict().ok()?;
+            if action.get(b"S").and_then(|s| s.as_name()).ok() != Some(b"GoTo".as_slice()) {
+                return None;
+            }
+            action.get_deref(b"D", doc).ok()?
+        }
+    };
+    match dest {
+        lopdf::Object::Name(name) | lopdf::Object::String(name, _) => dest_array(doc, named_dest(doc, name)?),
+        other => dest_array(doc, other),
+    }
+}
+
+fn dest_array(doc: &Document, dest: &lopdf::Object) -> Option<Vec<lopdf::Object>> {
+    match doc.dereference(dest).ok()?.1 {
+        lopdf::Object::Array(arr) => Some(arr.clone()),
+        // Named destinations may be wrapped as << /D [...] >>
+        lopdf::Object::Dictionary(dict) => dict.get_deref(b"D", doc).ok()?.as_array().ok().cloned(),
+        _ => None,
+    }
+}
+
+// Look a named destination up in /Dests (PDF 1.1) or the /Names name tree
+fn named_dest<'a>(doc: &'a Document, name: &[u8]) -> Option<&'a lopdf::Object> {
+    let catalog = doc.catalog().ok()?;
+    if let Some(dest) = catalog
+        .get_deref(b"Dests", doc)
+        .and_then(|d| d.as_dict())
+        .ok()
+        .and_then(|dests| dests.get(name).ok())
+    {
+        return Some(dest);
+    }
+    let tree = catalog
+        .get_deref(b"Names", doc)
+        .and_then(|n| n.as_dict())
+        .ok()?
+        .get_deref(b"Dests", doc)
+        .and_then(|d| d.as_dict())
+        .ok()?;
+    name_tree_lookup(doc, tree, name, 0)
+}
+
+fn name_tree_lookup<'a>(doc: &'a Document, node: &'a lopdf::Dictionary, key: &[u8], depth: u32) -> Option<&'a lopdf::Object> {
+    if depth > 32 {
+        return None;
+    }
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(|n| n.as_array()) {
+        for pair in names.chunks(2) {
+            if let [lopdf::Object::String(k, _), value] = pair {
+                if k.as_slice() == key {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    node.get_deref(b"Kids", doc)
+        .and_then(|k| k.as_array())
+        .ok()?
+        .iter()
+        .filter_map(|kid| doc.dereference(kid).ok()?.1.as_dict().ok())
+        .find_map(|kid| name_tree_lookup(doc, kid, key, depth + 1))
+}
+
+// Write a fresh /Outlines tree and return its id. Top-level entries start
+// expanded, everything below them collapsed.
+fn write_outline(doc: &mut Document, entries: &[OutlineEntry]) -> Option<lopdf::ObjectId> {
+    if entries.is_empty() {
+        return None;
+    }
+    let root_id = doc.new_object_id();
+    let (first, last, visible) = write_outline_items(doc, root_id, entries, true);
+    doc.objects.insert(
+        root_id,
+        lopdf::Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => first,
+            "Last" => last,
+            "Count" => visible,
+        }),
+    );
+    Some(root_id)
+}
+
+// Returns (first, last, visible item count) for one sibling list
+fn write_outline_items(
+    doc: &mut Document,
+    parent: lopdf::ObjectId,
+    entries: &[OutlineEntry],
+    open: bool,
+) -> (lopdf::ObjectId, lopdf::ObjectId, i64) {
+    let ids: Vec<lopdf::ObjectId> = entries.iter().map(|_| doc.new_object_id()).collect();
+    let mut visible = entries.len() as i64;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let mut dict = dictionary! {
+            "Title" => entry.title.clone(),
+            "Parent" => parent,
+        };
+        if i > 0 {
+            dict.set("Prev", ids[i - 1]);
+        }
+        if i + 1 < ids.len() {
+            dict.set("Next", ids[i + 1]);
+        }
+        if let Some(ref dest) = entry.dest {
+            dict.set("Dest", dest.clone());
+        }
+        if !entry.children.is_empty() {
+            let (first, last, child_visible) = write_outline_items(doc, ids[i], &entry.children, false);
+            dict.set("First", first);
+            dict.set("Last", last);
+            // Negative Count means the entry is collapsed
+            if open {
+                dict.set("Count", child_visible);
+                visible += child_visible;
+            } else {
+                dict.set("Count", -(entry.children.len() as i64));
+            }
+        }
+        doc.objects.insert(ids[i], lopdf::Object::Dictionary(dict));
+    }
+    (ids[0], ids[ids.len() - 1], visible)
+}
+
 fn parse_page_range(range: &str, total: u32) -> Result<Vec<u32>, String> {
     let mut pages = Vec::new();
     for part in range.split(',') {
@@ -374,3 +1761,353 @@ fn get_number(obj: &lopdf::Object) -> Option<f64> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Object, Stream};
+
+    // A minimal PDF with one "Page N" line per page, a bookmark per page and a Title
+    fn sample_pdf(path: &std::path::Path, title: &str, pages: u32) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let mut kids = Vec::new();
+        for n in 1..=pages {
+            let content = format!("BT /F1 24 Tf 72 720 Td\n(Page {})Tj\nET", n);
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+                "Contents" => content_id,
+            });
+            kids.push(Object::Reference(page_id));
+            doc.add_bookmark(lopdf::Bookmark::new(format!("Chapter {}", n), [0.0; 3], 0, page_id), None);
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => pages }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        if let Some(outline_id) = doc.build_outline() {
+            doc.catalog_mut().unwrap().set("Outlines", outline_id);
+        }
+        let info_id = doc.add_object(dictionary! { "Title" => Object::string_literal(title) });
+        doc.trailer.set("Info", info_id);
+        doc.save(path).unwrap();
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pdf-tools-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn outline_children(doc: &Document, node: &lopdf::Dictionary) -> Vec<lopdf::Dictionary> {
+        let mut items = Vec::new();
+        let mut next = node.get(b"First").and_then(Object::as_reference).ok();
+        while let Some(id) = next {
+            let item = doc.get_dictionary(id).unwrap().clone();
+            next = item.get(b"Next").and_then(Object::as_reference).ok();
+            items.push(item);
+        }
+        items
+    }
+
+    fn title_of(item: &lopdf::Dictionary) -> String {
+        String::from_utf8_lossy(item.get(b"Title").unwrap().as_str().unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_merge_keeps_outlines_and_title() {
+        let dir = temp_dir("merge-outline");
+        let (a, b, out) = (dir.join("intro.pdf"), dir.join("appendix.pdf"), dir.join("merged.pdf"));
+        sample_pdf(&a, "Handbook", 2);
+        sample_pdf(&b, "Appendix", 3);
+
+        merge_pdfs(
+            vec![a.to_string_lossy().to_string(), b.to_string_lossy().to_string()],
+            out.to_string_lossy().to_string(),
+        )
+        .unwrap();
+
+        let merged = Document::load(&out).unwrap();
+        let pages: Vec<lopdf::ObjectId> = merged.get_pages().values().copied().collect();
+        assert_eq!(pages.len(), 5);
+
+        let info = merged.trailer.get_deref(b"Info", &merged).unwrap().as_dict().unwrap();
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Handbook");
+
+        let root = merged.catalog().unwrap().get_deref(b"Outlines", &merged).unwrap().as_dict().unwrap();
+        let top = outline_children(&merged, root);
+        let top_titles: Vec<String> = top.iter().map(title_of).collect();
+        assert_eq!(top_titles, ["intro.pdf", "appendix.pdf"]);
+
+        // The second file's bookmarks point at its pages' new ids in the merged doc
+        let nested = outline_children(&merged, &top[1]);
+        assert_eq!(nested.iter().map(title_of).collect::<Vec<_>>(), ["Chapter 1", "Chapter 2", "Chapter 3"]);
+        for (item, page_id) in nested.iter().zip(&pages[2..]) {
+            let dest = item.get(b"Dest").unwrap().as_array().unwrap();
+            assert_eq!(dest[0].as_reference().unwrap(), *page_id);
+        }
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_merge_copies_page_resources() {
+        let dir = temp_dir("merge-deep");
+        let (a, b, out) = (dir.join("a.pdf"), dir.join("b.pdf"), dir.join("merged.pdf"));
+        sample_pdf(&a, "A", 2);
+        sample_pdf(&b, "B", 3);
+
+        merge_pdfs(
+            vec![a.to_string_lossy().to_string(), b.to_string_lossy().to_string()],
+            out.to_string_lossy().to_string(),
+        )
+        .unwrap();
+
+        // Both sources use the same object numbers, so any reference that
+        // wasn't remapped would show the first file's text or a missing font
+        let merged = Document::load(&out).unwrap();
+        for (_, page_id) in merged.get_pages() {
+            let fonts = merged.get_page_fonts(page_id).unwrap();
+            assert_eq!(fonts[b"F1".as_slice()].get(b"BaseFont").unwrap().as_name().unwrap(), b"Helvetica");
+            let parent = merged.get_dictionary(page_id).unwrap().get(b"Parent").unwrap();
+            assert_eq!(parent, merged.catalog().unwrap().get(b"Pages").unwrap());
+        }
+        let text = extract_text(out.to_string_lossy().to_string(), None).unwrap();
+        let lines: Vec<&str> = text.lines().filter(|l| l.starts_with("Page")).collect();
+        assert_eq!(lines, ["Page 1", "Page 2", "Page 1", "Page 2", "Page 3"]);
+
+        // Only what the pages use is copied, not the second file's catalog
+        let catalogs = merged.objects.values().filter(|o| o.as_dict().is_ok_and(|d| d.type_is(b"Catalog"))).count();
+        assert_eq!(catalogs, 1);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_delete_and_insert_pages() {
+        let dir = temp_dir("pages");
+        let (src, trimmed, padded) = (dir.join("src.pdf"), dir.join("trimmed.pdf"), dir.join("padded.pdf"));
+        sample_pdf(&src, "Pages", 4);
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+
+        assert!(delete_pages(path(&src), vec![5], path(&trimmed)).is_err());
+        assert!(delete_pages(path(&src), vec![1, 2, 3, 4], path(&trimmed)).is_err());
+        assert_eq!(delete_pages(path(&src), vec![2, 3, 2], path(&trimmed)).unwrap(), 2);
+        let text = extract_text(path(&trimmed), None).unwrap();
+        assert_eq!(text.lines().filter(|l| l.starts_with("Page")).collect::<Vec<_>>(), ["Page 1", "Page 4"]);
+
+        assert!(insert_blank_pages(path(&trimmed), 3, 1, None, path(&padded)).is_err());
+        assert_eq!(insert_blank_pages(path(&trimmed), 1, 2, Some("Letter".into()), path(&padded)).unwrap(), 4);
+        let doc = Document::load(&padded).unwrap();
+        let root = doc.catalog().unwrap().get_deref(b"Pages", &doc).unwrap().as_dict().unwrap();
+        assert_eq!(root.get(b"Count").unwrap().as_i64().unwrap(), 4);
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 4);
+        let blank = doc.get_dictionary(pages[&2]).unwrap();
+        assert_eq!(blank.get(b"MediaBox").unwrap().as_array().unwrap()[3].as_i64().unwrap(), 792);
+        assert!(doc.get_page_content(pages[&2]).unwrap().is_empty());
+        assert!(!doc.get_page_content(pages[&4]).unwrap().is_empty());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_stamp_pdf() {
+        let dir = temp_dir("stamp");
+        let (base, stamp, out) = (dir.join("base.pdf"), dir.join("stamp.pdf"), dir.join("stamped.pdf"));
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+        sample_pdf(&base, "Base", 3);
+        sample_pdf(&stamp, "Stamp", 1);
+
+        assert!(stamp_pdf(path(&base), path(&stamp), path(&out), None, Some("middle".into()), None).is_err());
+        stamp_pdf(path(&base), path(&stamp), path(&out), Some("1,3".into()), Some("bottom-right".into()), Some(0.5))
+            .unwrap();
+
+        let doc = Document::load(&out).unwrap();
+        let pages = doc.get_pages();
+        let (_, res_ids) = doc.get_page_resources(pages[&1]).unwrap();
+        assert!(res_ids.is_empty(), "resources should live on the page itself");
+        let res = doc.get_dictionary(pages[&1]).unwrap().get(b"Resources").unwrap().as_dict().unwrap();
+        // The page keeps its own font next to the stamp
+        assert!(res.get_deref(b"Font", &doc).unwrap().as_dict().unwrap().has(b"F1"));
+        let form_id = res.get(b"XObject").unwrap().as_dict().unwrap().get(b"Stamp1").unwrap().as_reference().unwrap();
+        let form = doc.get_object(form_id).unwrap().as_stream().unwrap();
+        assert_eq!(form.dict.get(b"Subtype").unwrap().as_name().unwrap(), b"Form");
+        let form_fonts = form.dict.get(b"Resources").unwrap().as_dict().unwrap().get(b"Font").unwrap();
+        assert!(form_fonts.as_dict().unwrap().has(b"F1"));
+
+        let content = String::from_utf8(doc.get_page_content(pages[&1]).unwrap()).unwrap();
+        assert!(content.starts_with("q\n") && content.contains("Q\nq 0.5 0 0 0.5 297.50 0.00 cm /Stamp1 Do Q"));
+        let untouched = String::from_utf8(doc.get_page_content(pages[&2]).unwrap()).unwrap();
+        assert!(!untouched.contains("Do"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_extract_pages_allows_duplicates() {
+        let dir = temp_dir("extract");
+        let (src, out) = (dir.join("src.pdf"), dir.join("subset.pdf"));
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+        sample_pdf(&src, "Report", 4);
+
+        assert!(extract_pages(path(&src), "2-5".into(), path(&out)).is_err());
+        extract_pages(path(&src), "3,3,1".into(), path(&out)).unwrap();
+
+        let doc = Document::load(&out).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 3);
+        assert_ne!(pages[&1], pages[&2], "a repeated page needs its own page object");
+        for page_id in pages.values() {
+            assert!(doc.get_page_fonts(*page_id).unwrap().contains_key(b"F1".as_slice()));
+        }
+        let text = extract_text(path(&out), None).unwrap();
+        assert_eq!(text.lines().filter(|l| l.starts_with("Page")).collect::<Vec<_>>(), ["Page 3", "Page 3", "Page 1"]);
+        let info = doc.trailer.get_deref(b"Info", &doc).unwrap().as_dict().unwrap();
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Report");
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let dir = temp_dir("metadata");
+        let (src, out) = (dir.join("src.pdf"), dir.join("tagged.pdf"));
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+        sample_pdf(&src, "Old title", 1);
+
+        // Add an XMP packet using both element and attribute style properties
+        let mut doc = Document::load(&src).unwrap();
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:pdf="http://ns.adobe.com/pdf/1.3/" pdf:Producer="Scanner 2000">
+  <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Old title</rdf:li></rdf:Alt></dc:title>
+</rdf:Description>
+</rdf:RDF></x:xmpmeta>"#;
+        let xmp_id = doc.add_object(Stream::new(dictionary! { "Type" => "Metadata", "Subtype" => "XML" }, xmp.into()));
+        doc.catalog_mut().unwrap().set("Metadata", xmp_id);
+        doc.save(&src).unwrap();
+
+        let before = get_metadata(path(&src)).unwrap();
+        assert!(before.has_xmp);
+        assert_eq!(before.title.as_deref(), Some("Old title"));
+        assert_eq!(before.producer.as_deref(), Some("Scanner 2000"));
+
+        let fields = PdfMetadata {
+            title: Some("Café menu".into()),
+            author: Some("Мария".into()),
+            keywords: Some("food, <draft>".into()),
+            producer: Some("".into()),
+            creation_date: Some("2024-03-01T09:30:00+01:00".into()),
+            ..Default::default()
+        };
+        set_metadata(path(&src), fields, path(&out)).unwrap();
+
+        let doc = Document::load(&out).unwrap();
+        let info = doc.trailer.get_deref(b"Info", &doc).unwrap().as_dict().unwrap();
+        // Latin-1 fits PDFDocEncoding, Cyrillic needs UTF-16BE
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Caf\xe9 menu");
+        assert!(info.get(b"Author").unwrap().as_str().unwrap().starts_with(b"\xfe\xff"));
+        assert_eq!(info.get(b"CreationDate").unwrap().as_str().unwrap(), b"D:20240301093000+01'00'");
+
+        let after = get_metadata(path(&out)).unwrap();
+        assert_eq!(after.title.as_deref(), Some("Café menu"));
+        assert_eq!(after.author.as_deref(), Some("Мария"));
+        assert_eq!(after.keywords.as_deref(), Some("food, <draft>"));
+        assert_eq!(after.producer, None);
+        assert_eq!(after.creation_date.as_deref(), Some("2024-03-01T09:30:00+01:00"));
+
+        let (_, packet) = read_xmp(&doc).unwrap();
+        assert!(!packet.contains("Old title") && !packet.contains("Scanner 2000"));
+        assert_eq!(xmp_property(&packet, "dc:title").as_deref(), Some("Café menu"));
+        assert_eq!(xmp_property(&packet, "pdf:Keywords").as_deref(), Some("food, <draft>"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_pdf_date_to_iso_drops_malformed_offset() {
+        assert_eq!(pdf_date_to_iso("D:20240301093000+01'00'"), "2024-03-01T09:30:00+01:00");
+        assert_eq!(pdf_date_to_iso("D:20240301093000-05"), "2024-03-01T09:30:00-05:00");
+        assert_eq!(pdf_date_to_iso("D:20240301093000+€1"), "2024-03-01T09:30:00");
+        assert_eq!(pdf_date_to_iso("D:20240301093000+x"), "2024-03-01T09:30:00");
+    }
+
+    #[test]
+    fn test_redact_regions_removes_text() {
+        let dir = temp_dir("redact");
+        let (src, out) = (dir.join("src.pdf"), dir.join("redacted.pdf"));
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+        sample_pdf(&src, "Statement", 2);
+
+        // Two lines on page 1: the account number at y=700, a note at y=680,
+        // plus a form XObject that repeats the number lower down
+        let mut doc = Document::load(&src).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let font_ref = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Resources")
+            .unwrap()
+            .as_dict()
+            .unwrap()
+            .get(b"Font")
+            .unwrap()
+            .clone();
+        let form_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 300.into(), 50.into()],
+                "Resources" => dictionary! { "Font" => font_ref.clone() },
+            },
+            b"BT /F1 12 Tf 0 10 Td\n(Account 1234-5678)Tj\nET".to_vec(),
+        ));
+        let content = b"BT /F1 12 Tf 72 700 Td\n(Account 1234-5678)Tj\n0 -20 Td\n(Public note)Tj\nET\nq 1 0 0 1 72 400 cm /Fm1 Do Q";
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+        let page = doc.get_dictionary_mut(page_id).unwrap();
+        page.set("Contents", content_id);
+        page.set(
+            "Resources",
+            dictionary! { "Font" => font_ref, "XObject" => dictionary! { "Fm1" => form_id } },
+        );
+        doc.save(&src).unwrap();
+
+        let regions = vec![
+            RedactRegion { page: 1, x: 60.0, y: 695.0, w: 300.0, h: 20.0 },
+            RedactRegion { page: 1, x: 60.0, y: 405.0, w: 300.0, h: 20.0 },
+        ];
+        assert!(redact_regions(path(&src), vec![RedactRegion { page: 3, x: 0.0, y: 0.0, w: 1.0, h: 1.0 }], path(&out)).is_err());
+        redact_regions(path(&src), regions, path(&out)).unwrap();
+
+        let text = extract_text(path(&out), None).unwrap();
+        assert!(!text.contains("1234-5678"), "redacted text still extractable: {}", text);
+        assert!(text.contains("Public note"));
+        assert!(text.contains("Page 2"));
+
+        // Nothing left anywhere in the file, including the form's stream
+        let mut redacted = Document::load(&out).unwrap();
+        redacted.decompress();
+        let raw = fs::read(&out).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("1234-5678"));
+        for obj in redacted.objects.values() {
+            if let Ok(stream) = obj.as_stream() {
+                assert!(!String::from_utf8_lossy(&stream.content).contains("1234-5678"));
+            }
+        }
+        let page_content = String::from_utf8(redacted.get_page_content(redacted.get_pages()[&1]).unwrap()).unwrap();
+        assert!(page_content.contains("60 695 300 20 re"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
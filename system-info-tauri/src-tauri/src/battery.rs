@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+// ── Battery ──────────────────────────────────────────────────────
+//
+// Read straight from the platform: /sys/class/power_supply on Linux,
+// pmset + ioreg on macOS and Win32_Battery on Windows. Desktops simply
+// report no battery.
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatteryInfo {
+    pub percent: f32,
+    pub state: String, // "charging", "discharging", "full", "unknown"
+    pub on_ac_power: bool,
+    pub time_to_empty_secs: Option<u64>,
+    pub time_to_full_secs: Option<u64>,
+    pub cycle_count: Option<u32>,
+    pub health_percent: Option<f32>,
+}
+
+#[tauri::command]
+pub fn get_battery_info() -> Option<BatteryInfo> {
+    read_battery()
+}
+
+#[cfg(target_os = "linux")]
+fn read_battery() -> Option<BatteryInfo> {
+    use std::fs;
+    use std::path::Path;
+
+    let read = |dir: &Path, name: &str| -> Option<String> {
+        fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+    };
+    let read_num = |dir: &Path, name: &str| -> Option<f64> {
+        read(dir, name).and_then(|s| s.parse().ok())
+    };
+
+    let supplies: Vec<_> = fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+
+    let on_ac_power = supplies.iter().any(|dir| {
+        read(dir, "type").as_deref() == Some("Mains") && read(dir, "online").as_deref() == Some("1")
+    });
+
+    // Peripheral batteries (mice, headsets) report scope=Device
+    let dir = supplies.iter().find(|dir| {
+        read(dir, "type").as_deref() == Some("Battery")
+            && read(dir, "scope").as_deref() != Some("Device")
+    })?;
+
+    // Energy in µWh / power in µW, or charge in µAh / current in µA
+    let now = read_num(dir, "energy_now").or_else(|| read_num(dir, "charge_now"));
+    let full = read_num(dir, "energy_full").or_else(|| read_num(dir, "charge_full"));
+    let design = read_num(dir, "energy_full_design").or_else(|| read_num(dir, "charge_full_design"));
+    let rate = read_num(dir, "power_now")
+        .or_else(|| read_num(dir, "current_now"))
+        .map(f64::abs)
+        .filter(|r| *r > 0.0);
+
+    let percent = read_num(dir, "capacity")
+        .or_else(|| match (now, full) {
+            (Some(n), Some(f)) if f > 0.0 => Some(n / f * 100.0),
+            _ => None,
+        })
+        .unwrap_or(0.0) as f32;
+
+    let state = match read(dir, "status").as_deref() {
+        Some("Charging") => "charging",
+        Some("Discharging") => "discharging",
+        Some("Full") => "full",
+        Some("Not charging") if percent >= 99.0 => "full",
+        _ => "unknown",
+    }
+    .to_string();
+
+    let hours_to_secs = |h: f64| (h * 3600.0).round() as u64;
+    let time_to_empty_secs = match (state.as_str(), now, rate) {
+        ("discharging", Some(n), Some(r)) => Some(hours_to_secs(n / r)),
+        _ => None,
+    };
+    let time_to_full_secs = match (state.as_str(), now, full, rate) {
+        ("charging", Some(n), Some(f), Some(r)) => Some(hours_to_secs((f - n).max(0.0) / r)),
+        _ => None,
+    };
+
+    Some(BatteryInfo {
+        percent,
+        on_ac_power: on_ac_power || state == "charging",
+        state,
+        time_to_empty_secs,
+        time_to_full_secs,
+        cycle_count: read_num(dir, "cycle_count").map(|c| c as u32).filter(|c| *c > 0),
+        health_percent: match (full, design) {
+            (Some(f), Some(d)) if d > 0.0 => Some((f / d * 100.0) as f32),
+            _ => None,
+        },
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn read_battery() -> Option<BatteryInfo> {
+    use std::process::Command;
+
+    // Now drawing from 'AC Power'
+    //  -InternalBattery-0 (id=1234)	85%; charging; 1:23 remaining present: true
+    let out = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    let on_ac_power = text.contains("'AC Power'");
+    let line = text.lines().find(|l| l.contains("InternalBattery"))?;
+    let fields: Vec<&str> = line.split('\t').nth(1)?.split(';').map(str::trim).collect();
+
+    let percent: f32 = fields.first()?.trim_end_matches('%').parse().ok()?;
+    let state = match fields.get(1).copied().unwrap_or("") {
+        "charging" | "finishing charge" => "charging",
+        "discharging" => "discharging",
+        "charged" => "full",
+        _ => "unknown",
+    }
+    .to_string();
+
+    let remaining = fields.get(2).and_then(|f| {
+        let (h, m) = f.split_whitespace().next()?.split_once(':')?;
+        Some(h.parse::<u64>().ok()? * 3600 + m.parse::<u64>().ok()? * 60)
+    });
+
+    // "CycleCount" = 123
+    let ioreg = Command::new("ioreg")
+        .args(["-r", "-c", "AppleSmartBattery"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default();
+    let ioreg_num = |key: &str| -> Option<f64> {
+        let needle = format!("\"{}\" = ", key);
+        ioreg
+            .lines()
+            .find_map(|l| l.trim().strip_prefix(needle.as_str()))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    // Apple Silicon reports MaxCapacity as a percentage; the raw value is in mAh
+    let max = ioreg_num("AppleRawMaxCapacity").or_else(|| ioreg_num("MaxCapacity"));
+    let design = ioreg_num("DesignCapacity");
+
+    Some(BatteryInfo {
+        percent,
+        on_ac_power,
+        time_to_empty_secs: if state == "discharging" { remaining } else { None },
+        time_to_full_secs: if state == "charging" { remaining } else { None },
+        state,
+        cycle_count: ioreg_num("CycleCount").map(|c| c as u32),
+        health_percent: match (max, design) {
+            (Some(m), Some(d)) if d > 0.0 => Some((m / d * 100.0) as f32),
+            _ => None,
+        },
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn read_battery() -> Option<BatteryInfo> {
+    use std::process::Command;
+
+    let out = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance -ClassName Win32_Battery | Select-Object -First 1 EstimatedChargeRemaining,BatteryStatus,EstimatedRunTime | ConvertTo-Json",
+        ])
+        .output()
+        .ok()?;
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).ok()?;
+
+    let percent = json["EstimatedChargeRemaining"].as_f64()? as f32;
+    // https://learn.microsoft.com/windows/win32/cimwin32prov/win32-battery
+    let status = json["BatteryStatus"].as_u64().unwrap_or(0);
+    let state = match status {
+        1 => "discharging",
+        3 => "full",
+        6..=9 => "charging",
+        _ => "unknown",
+    }
+    .to_string();
+    // 71582788 minutes is reported while on AC
+    let run_time = json["EstimatedRunTime"]
+        .as_u64()
+        .filter(|m| *m < 71_582_788)
+        .map(|m| m * 60);
+
+    Some(BatteryInfo {
+        percent,
+        on_ac_power: status != 1,
+        time_to_empty_secs: if state == "discharging" { run_time } else { None },
+        time_to_full_secs: None,
+        state,
+        cycle_count: None,
+        health_percent: None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_battery() -> Option<BatteryInfo> {
+    None
+}
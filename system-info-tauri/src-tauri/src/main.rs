@@ -1,21 +1,29 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod battery;
+mod sampler;
 mod system;
 
 use system::*;
 
 fn main() {
     tauri::Builder::default()
+        .manage(sampler::Sampler::start())
         .invoke_handler(tauri::generate_handler![
             get_overview,
             get_cpu_info,
             get_memory_info,
             get_disk_info,
+            get_disk_io,
+            get_largest_dirs,
             get_network_info,
+            get_network_rates,
             get_process_list,
+            battery::get_battery_info,
             export_report_json,
             export_report_html,
+            diff_reports,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
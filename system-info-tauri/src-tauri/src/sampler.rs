@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Disks, Networks};
+
+// ── Background sampler ───────────────────────────────────────────
+//
+// Rates need two readings of the cumulative counters, so a single thread
+// keeps persistent sysinfo handles alive and refreshes them on an interval.
+// Commands only read the latest computed rates and never block on a sample.
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InterfaceRate {
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiskRate {
+    pub mount_point: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub total_read_bytes: u64,
+    pub total_written_bytes: u64,
+}
+
+#[derive(Default)]
+struct Samples {
+    network: HashMap<String, InterfaceRate>,
+    disks: HashMap<String, DiskRate>,
+}
+
+pub struct Sampler {
+    samples: Arc<Mutex<Samples>>,
+}
+
+impl Sampler {
+    pub fn start() -> Self {
+        let samples = Arc::new(Mutex::new(Samples::default()));
+        let shared = Arc::clone(&samples);
+
+        thread::spawn(move || {
+            let mut networks = Networks::new_with_refreshed_list();
+            let mut last_net: HashMap<String, (u64, u64)> = networks
+                .iter()
+                .map(|(name, data)| (name.clone(), (data.total_received(), data.total_transmitted())))
+                .collect();
+            // Disk counters come from /proc/diskstats, IOKit or IOCTL_DISK_PERFORMANCE
+            let mut disks = Disks::new_with_refreshed_list();
+            let mut last_disk = disk_totals(&disks);
+            let mut last_tick = Instant::now();
+
+            loop {
+                thread::sleep(SAMPLE_INTERVAL);
+                let secs = last_tick.elapsed().as_secs_f64().max(0.001);
+                last_tick = Instant::now();
+
+                networks.refresh(true);
+                let mut rates = HashMap::new();
+                let mut totals = HashMap::new();
+                for (name, data) in networks.iter() {
+                    let rx = data.total_received();
+                    let tx = data.total_transmitted();
+                    // A counter that went backwards means the interface was reset
+                    let rate = match last_net.get(name) {
+                        Some(&(prev_rx, prev_tx)) => InterfaceRate {
+                            rx_bytes_per_sec: rx.saturating_sub(prev_rx) as f64 / secs,
+                            tx_bytes_per_sec: tx.saturating_sub(prev_tx) as f64 / secs,
+                        },
+                        None => InterfaceRate::default(),
+                    };
+                    rates.insert(name.clone(), rate);
+                    totals.insert(name.clone(), (rx, tx));
+                }
+                last_net = totals;
+
+                disks.refresh(true);
+                let mut disk_rates = HashMap::new();
+                let totals = disk_totals(&disks);
+                for (name, (mount_point, read, written)) in &totals {
+                    let (read_rate, write_rate) = match last_disk.get(name) {
+                        Some((_, prev_read, prev_written)) => (
+                            read.saturating_sub(*prev_read) as f64 / secs,
+                            written.saturating_sub(*prev_written) as f64 / secs,
+                        ),
+                        None => (0.0, 0.0),
+                    };
+                    disk_rates.insert(
+                        name.clone(),
+                        DiskRate {
+                            mount_point: mount_point.clone(),
+                            read_bytes_per_sec: read_rate,
+                            write_bytes_per_sec: write_rate,
+                            total_read_bytes: *read,
+                            total_written_bytes: *written,
+                        },
+                    );
+                }
+                last_disk = totals;
+
+                if let Ok(mut s) = shared.lock() {
+                    s.network = rates;
+                    s.disks = disk_rates;
+                }
+            }
+        });
+
+        Sampler { samples }
+    }
+
+    pub fn network_rate(&self, interface: &str) -> InterfaceRate {
+        self.samples
+            .lock()
+            .ok()
+            .and_then(|s| s.network.get(interface).copied())
+            .unwrap_or_default()
+    }
+
+    pub fn disk_rates(&self) -> HashMap<String, DiskRate> {
+        self.samples
+            .lock()
+            .map(|s| s.disks.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn network_rates(&self) -> HashMap<String, InterfaceRate> {
+        self.samples
+            .lock()
+            .map(|s| s.network.clone())
+            .unwrap_or_default()
+    }
+}
+
+// Keyed by device name; a device mounted more than once is only counted once
+fn disk_totals(disks: &Disks) -> HashMap<String, (String, u64, u64)> {
+    let mut totals = HashMap::new();
+    for disk in disks.list() {
+        let usage = disk.usage();
+        totals
+            .entry(disk.name().to_string_lossy().to_string())
+            .or_insert_with(|| {
+                (
+                    disk.mount_point().to_string_lossy().to_string(),
+                    usage.total_read_bytes,
+                    usage.total_written_bytes,
+                )
+            });
+    }
+    totals
+}
+
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1_073_741_824.0 {
+        format!("{:.2} GB/s", bytes_per_sec / 1_073_741_824.0)
+    } else if bytes_per_sec >= 1_048_576.0 {
+        format!("{:.1} MB/s", bytes_per_sec / 1_048_576.0)
+    } else if bytes_per_sec >= 1024.0 {
+        format!("{:.1} KB/s", bytes_per_sec / 1024.0)
+    } else {
+        format!("{:.0} B/s", bytes_per_sec)
+    }
+}
@@ -1,5 +1,11 @@
+use crate::battery::get_battery_info;
+use crate::sampler::{format_rate, Sampler};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use sysinfo::{Components, Disks, Networks, System};
+use tauri::{AppHandle, Emitter, State};
 // (removed unused imports)
 
 // ── Data structures ──────────────────────────────────────────────
@@ -68,6 +74,35 @@ pub struct DiskEntry {
     pub is_removable: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskIo {
+    pub name: String,
+    pub mount_point: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub read_rate: String,
+    pub write_rate: String,
+    pub total_read_bytes: u64,
+    pub total_written_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DirSize {
+    pub path: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LargestDirs {
+    pub root: String,
+    pub dirs: Vec<DirSize>,
+    pub scanned_entries: u64,
+    pub truncated: bool,
+    pub done: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NetworkInterface {
     pub name: String,
@@ -75,6 +110,24 @@ pub struct NetworkInterface {
     pub transmitted_bytes: u64,
     pub received_packets: u64,
     pub transmitted_packets: u64,
+    // Defaulted so reports exported before rates existed still parse
+    #[serde(default)]
+    pub rx_bytes_per_sec: f64,
+    #[serde(default)]
+    pub tx_bytes_per_sec: f64,
+    #[serde(default)]
+    pub rx_rate: String,
+    #[serde(default)]
+    pub tx_rate: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkRate {
+    pub name: String,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_rate: String,
+    pub tx_rate: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -86,6 +139,321 @@ pub struct ProcessEntry {
     pub status: String,
 }
 
+// ── Sorting ──────────────────────────────────────────────────────
+
+// Case-insensitive compare where digit runs compare by value ("disk9" < "disk10")
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut ai = a.chars().peekable();
+    let mut bi = b.chars().peekable();
+
+    loop {
+        match (ai.peek().copied(), bi.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let mut na = String::new();
+                while let Some(c) = ai.peek().copied().filter(char::is_ascii_digit) {
+                    na.push(c);
+                    ai.next();
+                }
+                let mut nb = String::new();
+                while let Some(c) = bi.peek().copied().filter(char::is_ascii_digit) {
+                    nb.push(c);
+                    bi.next();
+                }
+                let ta = na.trim_start_matches('0');
+                let tb = nb.trim_start_matches('0');
+                let ord = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                ai.next();
+                bi.next();
+            }
+        }
+    }
+}
+
+fn desc_f64(a: f64, b: f64) -> Ordering {
+    b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+}
+
+// sort_by: "name" (default), "usage", "size", "free"
+fn sort_disks(disks: &mut [DiskEntry], sort_by: Option<&str>) -> Result<(), String> {
+    match sort_by.unwrap_or("name") {
+        "usage" => disks.sort_by(|a, b| desc_f64(a.usage_percent, b.usage_percent)),
+        "size" => disks.sort_by(|a, b| desc_f64(a.total_gb, b.total_gb)),
+        "free" => disks.sort_by(|a, b| desc_f64(a.available_gb, b.available_gb)),
+        "name" => disks.sort_by(|a, b| {
+            natural_cmp(&a.name, &b.name).then_with(|| natural_cmp(&a.mount_point, &b.mount_point))
+        }),
+        other => return Err(format!("Unknown disk sort: {}", other)),
+    }
+    Ok(())
+}
+
+// sort_by: "name" (default), "throughput"
+fn sort_networks(nets: &mut [NetworkInterface], sort_by: Option<&str>) -> Result<(), String> {
+    match sort_by.unwrap_or("name") {
+        "throughput" => nets.sort_by(|a, b| {
+            desc_f64(
+                a.rx_bytes_per_sec + a.tx_bytes_per_sec,
+                b.rx_bytes_per_sec + b.tx_bytes_per_sec,
+            )
+            .then_with(|| natural_cmp(&a.name, &b.name))
+        }),
+        "name" => nets.sort_by(|a, b| natural_cmp(&a.name, &b.name)),
+        other => return Err(format!("Unknown network sort: {}", other)),
+    }
+    Ok(())
+}
+
+const MAX_PROCESSES: usize = 50;
+
+// sort_by: "cpu" (default), "memory", "name", "pid"
+fn sort_processes(procs: &mut [ProcessEntry], sort_by: &str) -> Result<(), String> {
+    match sort_by {
+        "cpu" => procs.sort_by(|a, b| b.cpu_percent.partial_cmp(&a.cpu_percent).unwrap_or(Ordering::Equal)),
+        "memory" => procs.sort_by_key(|p| std::cmp::Reverse(p.memory_mb)),
+        "name" => procs.sort_by(|a, b| natural_cmp(&a.name, &b.name).then(a.pid.cmp(&b.pid))),
+        "pid" => procs.sort_by_key(|p| p.pid),
+        other => return Err(format!("Unknown process sort: {}", other)),
+    }
+    Ok(())
+}
+
+// The busiest processes — by memory when sorting on it, by CPU otherwise —
+// ordered by `sort_by` for display
+fn top_processes(mut procs: Vec<ProcessEntry>, sort_by: Option<&str>) -> Result<Vec<ProcessEntry>, String> {
+    let sort_by = sort_by.unwrap_or("cpu");
+    sort_processes(&mut procs, if sort_by == "memory" { "memory" } else { "cpu" })?;
+    procs.truncate(MAX_PROCESSES);
+    sort_processes(&mut procs, sort_by)?;
+    Ok(procs)
+}
+
+// ── Directory sizes ──────────────────────────────────────────────
+
+// Hard caps so scanning something like / can't hang the command
+const MAX_SCAN_ENTRIES: u64 = 2_000_000;
+const MAX_SCAN_TIME: Duration = Duration::from_secs(60);
+
+struct ScanBudget {
+    started: Instant,
+    entries: u64,
+}
+
+impl ScanBudget {
+    fn exhausted(&self) -> bool {
+        self.entries >= MAX_SCAN_ENTRIES || self.started.elapsed() >= MAX_SCAN_TIME
+    }
+}
+
+// Total size of a tree without following symlinks; returns (bytes, files)
+fn dir_size(root: &Path, budget: &mut ScanBudget) -> (u64, u64) {
+    let mut size = 0;
+    let mut files = 0;
+    let mut stack = vec![root.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            if budget.exhausted() {
+                return (size, files);
+            }
+            budget.entries += 1;
+            let Ok(meta) = entry.path().symlink_metadata() else { continue };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else if meta.is_file() {
+                size += meta.len();
+                files += 1;
+            }
+        }
+    }
+
+    (size, files)
+}
+
+// ── Report diff ──────────────────────────────────────────────────
+
+// The subset of an export_report_json document needed for diffing
+#[derive(Debug, Deserialize)]
+struct Report {
+    timestamp: String,
+    overview: Overview,
+    memory: MemoryInfo,
+    disks: Vec<DiskEntry>,
+    network: Vec<NetworkInterface>,
+    processes: Vec<ProcessEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricDelta {
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,
+}
+
+impl MetricDelta {
+    fn new(before: f64, after: f64) -> Self {
+        MetricDelta { before, after, delta: after - before }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiskDelta {
+    pub mount_point: String,
+    pub free_gb: Option<MetricDelta>, // None when the disk is only in one report
+    pub present_before: bool,
+    pub present_after: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NetworkDelta {
+    pub name: String,
+    pub received_bytes: u64,
+    pub transmitted_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProcessDelta {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_percent: MetricDelta,
+    pub memory_mb: MetricDelta,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportDiff {
+    pub before_timestamp: String,
+    pub after_timestamp: String,
+    pub elapsed_seconds: Option<i64>,
+    pub memory_used_mb: MetricDelta,
+    pub swap_used_mb: MetricDelta,
+    pub cpu_usage_percent: MetricDelta,
+    pub disks: Vec<DiskDelta>,
+    pub network: Vec<NetworkDelta>,
+    // Reports only hold the top processes, so these are relative to that sample
+    pub new_processes: Vec<ProcessEntry>,
+    pub exited_processes: Vec<ProcessEntry>,
+    pub changed_processes: Vec<ProcessDelta>,
+}
+
+fn parse_report(json: &str, which: &str) -> Result<Report, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid {} report: {}", which, e))
+}
+
+// Bytes moved between two cumulative readings; a smaller second reading
+// means the counter was reset, so everything since the reset counts
+fn counter_delta(before: u64, after: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else {
+        after
+    }
+}
+
+fn diff(before: Report, after: Report) -> ReportDiff {
+    let elapsed_seconds = chrono::DateTime::parse_from_rfc3339(&before.timestamp)
+        .and_then(|b| chrono::DateTime::parse_from_rfc3339(&after.timestamp).map(|a| (a - b).num_seconds()))
+        .ok();
+
+    let mut disks: Vec<DiskDelta> = before
+        .disks
+        .iter()
+        .map(|b| {
+            let a = after.disks.iter().find(|a| a.mount_point == b.mount_point);
+            DiskDelta {
+                mount_point: b.mount_point.clone(),
+                free_gb: a.map(|a| MetricDelta::new(b.available_gb, a.available_gb)),
+                present_before: true,
+                present_after: a.is_some(),
+            }
+        })
+        .collect();
+    disks.extend(
+        after
+            .disks
+            .iter()
+            .filter(|a| !before.disks.iter().any(|b| b.mount_point == a.mount_point))
+            .map(|a| DiskDelta {
+                mount_point: a.mount_point.clone(),
+                free_gb: None,
+                present_before: false,
+                present_after: true,
+            }),
+    );
+    disks.sort_by(|a, b| natural_cmp(&a.mount_point, &b.mount_point));
+
+    let mut network: Vec<NetworkDelta> = after
+        .network
+        .iter()
+        .filter_map(|a| {
+            let b = before.network.iter().find(|b| b.name == a.name)?;
+            Some(NetworkDelta {
+                name: a.name.clone(),
+                received_bytes: counter_delta(b.received_bytes, a.received_bytes),
+                transmitted_bytes: counter_delta(b.transmitted_bytes, a.transmitted_bytes),
+            })
+        })
+        .collect();
+    network.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+
+    // PIDs get reused, so a process is the same only if the name matches too
+    let same = |a: &ProcessEntry, b: &ProcessEntry| a.pid == b.pid && a.name == b.name;
+    let new_processes: Vec<ProcessEntry> = after
+        .processes
+        .iter()
+        .filter(|a| !before.processes.iter().any(|b| same(a, b)))
+        .cloned()
+        .collect();
+    let exited_processes: Vec<ProcessEntry> = before
+        .processes
+        .iter()
+        .filter(|b| !after.processes.iter().any(|a| same(a, b)))
+        .cloned()
+        .collect();
+    let mut changed_processes: Vec<ProcessDelta> = after
+        .processes
+        .iter()
+        .filter_map(|a| {
+            let b = before.processes.iter().find(|b| same(a, b))?;
+            Some(ProcessDelta {
+                pid: a.pid,
+                name: a.name.clone(),
+                cpu_percent: MetricDelta::new(b.cpu_percent as f64, a.cpu_percent as f64),
+                memory_mb: MetricDelta::new(b.memory_mb as f64, a.memory_mb as f64),
+            })
+        })
+        .collect();
+    changed_processes.sort_by(|a, b| desc_f64(a.memory_mb.delta.abs(), b.memory_mb.delta.abs()));
+
+    ReportDiff {
+        before_timestamp: before.timestamp,
+        after_timestamp: after.timestamp,
+        elapsed_seconds,
+        memory_used_mb: MetricDelta::new(before.memory.used_mb as f64, after.memory.used_mb as f64),
+        swap_used_mb: MetricDelta::new(before.memory.swap_used_mb as f64, after.memory.swap_used_mb as f64),
+        cpu_usage_percent: MetricDelta::new(
+            before.overview.cpu_usage_percent as f64,
+            after.overview.cpu_usage_percent as f64,
+        ),
+        disks,
+        network,
+        new_processes,
+        exited_processes,
+        changed_processes,
+    }
+}
+
 // ── Tauri Commands ───────────────────────────────────────────────
 
 #[tauri::command]
@@ -171,10 +539,10 @@ pub fn get_memory_info() -> MemoryInfo {
 }
 
 #[tauri::command]
-pub fn get_disk_info() -> Vec<DiskEntry> {
+pub fn get_disk_info(sort_by: Option<String>) -> Result<Vec<DiskEntry>, String> {
     let disks = Disks::new_with_refreshed_list();
 
-    disks
+    let mut entries: Vec<DiskEntry> = disks
         .iter()
         .map(|d| {
             let total = d.total_space();
@@ -191,33 +559,152 @@ pub fn get_disk_info() -> Vec<DiskEntry> {
                 is_removable: d.is_removable(),
             }
         })
-        .collect()
+        .collect();
+
+    sort_disks(&mut entries, sort_by.as_deref())?;
+    Ok(entries)
 }
 
 #[tauri::command]
-pub fn get_network_info() -> Vec<NetworkInterface> {
+pub fn get_disk_io(sampler: State<'_, Sampler>) -> Vec<DiskIo> {
+    let mut io: Vec<DiskIo> = sampler
+        .disk_rates()
+        .into_iter()
+        .map(|(name, rate)| DiskIo {
+            name,
+            mount_point: rate.mount_point,
+            read_bytes_per_sec: rate.read_bytes_per_sec,
+            write_bytes_per_sec: rate.write_bytes_per_sec,
+            read_rate: format_rate(rate.read_bytes_per_sec),
+            write_rate: format_rate(rate.write_bytes_per_sec),
+            total_read_bytes: rate.total_read_bytes,
+            total_written_bytes: rate.total_written_bytes,
+        })
+        .collect();
+
+    io.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    io
+}
+
+// Sizes the immediate subdirectories of `path`. Partial results are emitted as
+// "largest-dirs-progress" after each subdirectory so the UI can fill in while
+// the walk continues.
+#[tauri::command]
+pub async fn get_largest_dirs(
+    app: AppHandle,
+    path: String,
+    top_n: Option<usize>,
+) -> Result<LargestDirs, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+    let top_n = top_n.unwrap_or(10).max(1);
+
+    // The walk can run for up to MAX_SCAN_TIME, so keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || scan_largest_dirs(&app, &root, path, top_n))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn scan_largest_dirs(app: &AppHandle, root: &Path, path: String, top_n: usize) -> Result<LargestDirs, String> {
+    let children: Vec<PathBuf> = std::fs::read_dir(root)
+        .map_err(|e| format!("Cannot read {}: {}", path, e))?
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .map(|e| e.path())
+        .collect();
+
+    let mut budget = ScanBudget { started: Instant::now(), entries: 0 };
+    let mut dirs: Vec<DirSize> = Vec::new();
+
+    for child in children {
+        if budget.exhausted() {
+            break;
+        }
+        let (size_bytes, file_count) = dir_size(&child, &mut budget);
+        dirs.push(DirSize {
+            path: child.to_string_lossy().to_string(),
+            name: child.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            size_bytes,
+            file_count,
+        });
+        dirs.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes).then_with(|| natural_cmp(&a.name, &b.name)));
+        dirs.truncate(top_n);
+
+        let _ = app.emit(
+            "largest-dirs-progress",
+            LargestDirs {
+                root: path.clone(),
+                dirs: dirs.clone(),
+                scanned_entries: budget.entries,
+                truncated: false,
+                done: false,
+            },
+        );
+    }
+
+    Ok(LargestDirs {
+        root: path,
+        dirs,
+        scanned_entries: budget.entries,
+        truncated: budget.exhausted(),
+        done: true,
+    })
+}
+
+#[tauri::command]
+pub fn get_network_info(sort_by: Option<String>, sampler: State<'_, Sampler>) -> Result<Vec<NetworkInterface>, String> {
     let networks = Networks::new_with_refreshed_list();
 
-    networks
+    let mut interfaces: Vec<NetworkInterface> = networks
         .iter()
-        .map(|(name, data)| NetworkInterface {
-            name: name.clone(),
-            received_bytes: data.total_received(),
-            transmitted_bytes: data.total_transmitted(),
-            received_packets: data.total_packets_received(),
-            transmitted_packets: data.total_packets_transmitted(),
+        .map(|(name, data)| {
+            let rate = sampler.network_rate(name);
+            NetworkInterface {
+                name: name.clone(),
+                received_bytes: data.total_received(),
+                transmitted_bytes: data.total_transmitted(),
+                received_packets: data.total_packets_received(),
+                transmitted_packets: data.total_packets_transmitted(),
+                rx_bytes_per_sec: rate.rx_bytes_per_sec,
+                tx_bytes_per_sec: rate.tx_bytes_per_sec,
+                rx_rate: format_rate(rate.rx_bytes_per_sec),
+                tx_rate: format_rate(rate.tx_bytes_per_sec),
+            }
+        })
+        .collect();
+
+    sort_networks(&mut interfaces, sort_by.as_deref())?;
+    Ok(interfaces)
+}
+
+#[tauri::command]
+pub fn get_network_rates(sampler: State<'_, Sampler>) -> Vec<NetworkRate> {
+    let mut rates: Vec<NetworkRate> = sampler
+        .network_rates()
+        .into_iter()
+        .map(|(name, rate)| NetworkRate {
+            name,
+            rx_bytes_per_sec: rate.rx_bytes_per_sec,
+            tx_bytes_per_sec: rate.tx_bytes_per_sec,
+            rx_rate: format_rate(rate.rx_bytes_per_sec),
+            tx_rate: format_rate(rate.tx_bytes_per_sec),
         })
-        .collect()
+        .collect();
+
+    rates.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    rates
 }
 
 #[tauri::command]
-pub fn get_process_list() -> Vec<ProcessEntry> {
+pub fn get_process_list(sort_by: Option<String>) -> Result<Vec<ProcessEntry>, String> {
     let mut sys = System::new_all();
     sys.refresh_all();
     std::thread::sleep(std::time::Duration::from_millis(200));
     sys.refresh_all();
 
-    let mut procs: Vec<ProcessEntry> = sys
+    let procs: Vec<ProcessEntry> = sys
         .processes()
         .iter()
         .map(|(pid, proc_)| ProcessEntry {
@@ -229,19 +716,18 @@ pub fn get_process_list() -> Vec<ProcessEntry> {
         })
         .collect();
 
-    procs.sort_by(|a, b| b.cpu_percent.partial_cmp(&a.cpu_percent).unwrap_or(std::cmp::Ordering::Equal));
-    procs.truncate(50);
-    procs
+    top_processes(procs, sort_by.as_deref())
 }
 
 #[tauri::command]
-pub fn export_report_json() -> Result<String, String> {
+pub fn export_report_json(sampler: State<'_, Sampler>) -> Result<String, String> {
     let overview = get_overview();
     let cpu = get_cpu_info();
     let memory = get_memory_info();
-    let disks = get_disk_info();
-    let network = get_network_info();
-    let processes = get_process_list();
+    let disks = get_disk_info(None)?;
+    let network = get_network_info(None, sampler)?;
+    let processes = get_process_list(None)?;
+    let battery = get_battery_info();
 
     let report = serde_json::json!({
         "timestamp": chrono::Local::now().to_rfc3339(),
@@ -251,6 +737,7 @@ pub fn export_report_json() -> Result<String, String> {
         "disks": disks,
         "network": network,
         "processes": processes,
+        "battery": battery,
     });
 
     serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
@@ -261,7 +748,30 @@ pub fn export_report_html() -> Result<String, String> {
     let overview = get_overview();
     let _cpu = get_cpu_info();
     let memory = get_memory_info();
-    let disks = get_disk_info();
+    let disks = get_disk_info(None)?;
+    let battery_section = match get_battery_info() {
+        Some(b) => {
+            let remaining = match (b.time_to_empty_secs, b.time_to_full_secs) {
+                (Some(s), _) => format!("{}h {}m to empty", s / 3600, (s % 3600) / 60),
+                (None, Some(s)) => format!("{}h {}m to full", s / 3600, (s % 3600) / 60),
+                _ => "—".to_string(),
+            };
+            format!(
+                "<h2>Battery ({:.0}%)</h2>\n<table>\n\
+                 <tr><th>State</th><td>{}{}</td></tr>\n\
+                 <tr><th>Remaining</th><td>{}</td></tr>\n\
+                 <tr><th>Cycle count</th><td>{}</td></tr>\n\
+                 <tr><th>Health</th><td>{}</td></tr>\n</table>",
+                b.percent,
+                b.state,
+                if b.on_ac_power { " (AC power)" } else { "" },
+                remaining,
+                b.cycle_count.map(|c| c.to_string()).unwrap_or_else(|| "—".to_string()),
+                b.health_percent.map(|h| format!("{:.0}%", h)).unwrap_or_else(|| "—".to_string()),
+            )
+        }
+        None => String::new(),
+    };
 
     let html = format!(r#"<!DOCTYPE html>
 <html lang="en">
@@ -302,6 +812,7 @@ th {{ background: #16213e; color: #00ff88; }}
 <tr><th>Mount</th><th>Total GB</th><th>Used GB</th><th>Usage</th></tr>
 {disk_rows}
 </table>
+{battery_section}
 </body></html>"#,
         timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
         hostname = overview.hostname,
@@ -318,7 +829,15 @@ th {{ background: #16213e; color: #00ff88; }}
             "<tr><td>{}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}%</td></tr>",
             d.mount_point, d.total_gb, d.used_gb, d.usage_percent
         )).collect::<Vec<_>>().join("\n"),
+        battery_section = battery_section,
     );
 
     Ok(html)
 }
+
+#[tauri::command]
+pub fn diff_reports(before_json: String, after_json: String) -> Result<ReportDiff, String> {
+    let before = parse_report(&before_json, "before")?;
+    let after = parse_report(&after_json, "after")?;
+    Ok(diff(before, after))
+}